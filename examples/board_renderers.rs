@@ -0,0 +1,79 @@
+//! Frame time of the two board renderers: nine boards, as many cells as a 60x60 board,
+//! each fully repainted every frame. Runs with sprites, pass `texture` for the
+//! [`BoardRenderer::Texture`] one, and logs the frame time every second.
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    prelude::*,
+};
+use blocks::{
+    board::{BOARD_HEIGHT, BOARD_WIDTH},
+    prelude::*,
+};
+
+/// Boards on each side of the grid of boards.
+const GRID: usize = 3;
+
+fn main() {
+    let renderer = match std::env::args().nth(1).as_deref() {
+        Some("texture") => BoardRenderer::Texture,
+        _ => BoardRenderer::Sprites,
+    };
+    let plugin = BlocksPlugin::builder()
+        .renderer(renderer)
+        .build()
+        .expect("valid configuration");
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            plugin,
+            FrameTimeDiagnosticsPlugin,
+            LogDiagnosticsPlugin::default(),
+        ))
+        .add_systems(PostStartup, spawn_boards)
+        .add_systems(Update, repaint_everything)
+        .run();
+}
+
+/// Fills the screen with the main board and the others around it, scaled down to fit.
+fn spawn_boards(
+    mut commands: Commands,
+    renderer: Res<BoardRenderer>,
+    mut images: ResMut<Assets<Image>>,
+    mut q_main_board: Query<&mut Transform, With<MainBoard>>,
+) {
+    let scale = TILE_SIZE / GRID as f32;
+    let spacing = BOARD_WIDTH as f32 * scale;
+    let position = |i: usize| (i as f32 - (GRID - 1) as f32 / 2.) * spacing;
+    q_main_board.single_mut().scale = Vec3::splat(scale);
+    for i in 0..GRID * GRID {
+        let (x, y) = (i % GRID, i / GRID);
+        // The main board is in the middle
+        if (x, y) == (GRID / 2, GRID / 2) {
+            continue;
+        }
+        let mut board = match *renderer {
+            BoardRenderer::Sprites => spawn_board(&mut commands, true),
+            BoardRenderer::Texture => spawn_textured_board(&mut commands, &mut images),
+        };
+        board.insert(
+            Transform::from_xyz(position(x), position(y), 0.).with_scale(Vec3::splat(scale)),
+        );
+    }
+}
+
+fn repaint_everything(
+    q_boards: Query<Entity, (With<Board>, Without<OverlayBoard>)>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+) {
+    let cells = (0..BOARD_HEIGHT)
+        .flat_map(|y| (0..BOARD_WIDTH).map(move |x| (x, y)))
+        .collect::<Vec<_>>();
+    for board in q_boards.iter() {
+        board_changed.send(BoardChangedEvent {
+            board,
+            revision: 0,
+            cells: cells.iter().copied().collect(),
+        });
+    }
+}
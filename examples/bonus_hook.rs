@@ -0,0 +1,41 @@
+//! A scripting hook: every 5th clear turns an empty cell of the board into a bonus
+//! cell doubling the points of the clear removing it.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use bevy::prelude::*;
+use blocks::prelude::*;
+use rand::seq::IteratorRandom;
+
+/// Clears between two bonus cells.
+const EVERY_CLEARS: u32 = 5;
+
+fn main() {
+    let clears = AtomicU32::new(0);
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .insert_resource(BlocksHooks::default().on_clear(move |_, board| {
+            if !(clears.fetch_add(1, Ordering::Relaxed) + 1).is_multiple_of(EVERY_CLEARS) {
+                return;
+            }
+            let grid = board.grid();
+            let empty = grid
+                .cells
+                .0
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    row.iter()
+                        .enumerate()
+                        .filter(|(_, cell)| cell.is_none())
+                        .map(move |(x, _)| (x, y))
+                })
+                .choose(&mut rand::thread_rng());
+            if let Some(cell) = empty {
+                board.set_bonus(cell, 2);
+                board.toast("Bonus cell!");
+            }
+        }))
+        .add_plugins(BlocksPlugin::default())
+        .run();
+}
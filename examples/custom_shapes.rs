@@ -0,0 +1,59 @@
+//! A shape pool of its own: shapes from the `shapes!` macro plus more read from RON.
+//!
+//! F5 swaps the pool for the shapes in `shapes.ron`, in the same format as
+//! `EXTRA_SHAPES`, without restarting the game.
+
+use bevy::prelude::*;
+use blocks::prelude::*;
+
+/// `(width, height, pattern, name)` of every extra shape, as for `Shape::from_pattern`.
+/// Shapes without a name are named after their size.
+const EXTRA_SHAPES: &str = r##"[
+    (3, 3, "#.#.#.#.#", Some("X")),
+    (3, 3, ".#.###.#.", Some("Plus")),
+    (2, 2, "#..#", None),
+]"##;
+
+/// Shapes read from RON, `None` if it is malformed.
+fn parse_shapes(ron: &str) -> Option<Vec<NamedShape>> {
+    let shapes: Vec<(usize, usize, String, Option<String>)> = ron::from_str(ron).ok()?;
+    Some(
+        shapes
+            .into_iter()
+            .map(|(width, height, pattern, name)| NamedShape {
+                name,
+                shape: Shape::from_pattern(width, height, &pattern),
+            })
+            .collect(),
+    )
+}
+
+fn main() {
+    // The tutorial hands out the first three default shapes, keep them in front
+    let mut shapes = shapes! {
+        (2,2) "####" as "Square";
+        (4,1) "####" as "Line 4";
+        (3,1) "###" as "Line 3";
+    };
+    shapes.extend(parse_shapes(EXTRA_SHAPES).expect("EXTRA_SHAPES is valid RON"));
+
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .insert_resource(ShapeRegistry::new(shapes))
+        .add_plugins(BlocksPlugin::default())
+        .add_systems(Update, reload_shapes)
+        .run();
+}
+
+fn reload_shapes(input_keys: Res<Input<KeyCode>>, mut pool_changes: EventWriter<ShapePoolChanged>) {
+    if !input_keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let shapes = std::fs::read_to_string("shapes.ron")
+        .ok()
+        .and_then(|ron| parse_shapes(&ron));
+    match shapes {
+        Some(shapes) => pool_changes.send(ShapePoolChanged(shapes)),
+        None => warn!("shapes.ron is missing or malformed"),
+    }
+}
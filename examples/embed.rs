@@ -0,0 +1,43 @@
+//! Runs the game with a smaller shape pool, reporting placements and clears, using
+//! nothing but the public API.
+
+use bevy::prelude::*;
+use blocks::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        // The tutorial hands out the first three default shapes
+        .insert_resource(ShapeRegistry::new(shapes! {
+            (2,2) "####";
+            (4,1) "####";
+            (3,1) "###";
+            (1,1) "#";
+        }))
+        .add_plugins(BlocksPlugin::default())
+        .add_systems(Update, report)
+        .run();
+}
+
+fn report(
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    q_main_board: Query<Entity, With<MainBoard>>,
+) {
+    let Ok(main_board) = q_main_board.get_single() else {
+        return;
+    };
+    for event in placements.read().filter(|event| event.board == main_board) {
+        info!("placed shape {} at {:?}", event.shape.id.0, event.anchor);
+    }
+    for event in lines_cleared
+        .read()
+        .filter(|event| event.board == main_board)
+    {
+        info!(
+            "cleared {} rows and {} columns",
+            event.lines.rows.len(),
+            event.lines.columns.len()
+        );
+    }
+}
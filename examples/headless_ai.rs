@@ -0,0 +1,31 @@
+//! No window, no app: the greedy AI plays 200 moves on a bare board model, which is
+//! printed at the end. Pass a number to change the seed.
+
+use blocks::{ai, board::Board, default_shapes, prelude::ShapeRegistry};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const MOVES: usize = 200;
+
+fn main() {
+    let seed = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(0);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let registry = ShapeRegistry::new(default_shapes());
+    let weights = registry.default_weights();
+    let mut board = Board::default();
+    let mut lines = 0;
+    for turn in 0..MOVES {
+        let (id, rotation) = registry.random_weighted(&mut rng, &weights);
+        let shape = registry.get(id, rotation);
+        let Some((anchor, _)) = ai::best_placement(&board, shape) else {
+            println!("no room for shape {} after {turn} moves", id.0);
+            break;
+        };
+        board.try_place(shape, anchor, rng.gen());
+        lines += board.clear_full_lines().len();
+    }
+    println!("{board}");
+    println!("{lines} lines cleared");
+}
@@ -0,0 +1,10 @@
+//! The game with every default: add the plugin next to bevy's.
+
+use bevy::prelude::*;
+use blocks::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, BlocksPlugin::default()))
+        .run();
+}
@@ -0,0 +1,38 @@
+//! Ordering a system of one's own against the game's [`BlocksSystems`]: right after a
+//! placement, before its lines clear, the board already holds the placed shape, so
+//! full lines can be counted before they're gone.
+
+use bevy::prelude::*;
+use blocks::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, BlocksPlugin::default()))
+        .add_systems(
+            Update,
+            log_placements
+                .after(BlocksSystems::Placement)
+                .before(BlocksSystems::Resolution),
+        )
+        .run();
+}
+
+fn log_placements(mut placements: EventReader<PlacementEvent>, q_board: Query<&Board>) {
+    for event in placements.read() {
+        let Ok(board) = q_board.get(event.board) else {
+            continue;
+        };
+        let grid = board.grid();
+        let filled = event
+            .cells
+            .iter()
+            .all(|&(x, y)| grid.cells.0[y][x].is_some());
+        let lines = grid.full_lines();
+        info!(
+            "placed {} cells at {:?}, on the board: {filled}, completing {} lines",
+            event.cells.len(),
+            event.anchor,
+            lines.rows.len() + lines.columns.len()
+        );
+    }
+}
@@ -0,0 +1,49 @@
+//! Times superimposing every default shape at every anchor of an empty and a half
+//! filled board and visiting the cells it covers, what the preview does each frame.
+//! Run with `--release`.
+
+use std::time::Instant;
+
+use blocks::{
+    board::{Board, SuperimpositionState, BOARD_HEIGHT, BOARD_WIDTH},
+    default_shapes,
+    prelude::{ShapeRegistry, TileColor},
+};
+
+const ROUNDS: usize = 200;
+
+fn main() {
+    let registry = ShapeRegistry::new(default_shapes());
+    let mut half_filled = Board::default();
+    // Line 4, lying flat
+    let (line, _) = registry.iter().nth(1).unwrap();
+    for y in 0..BOARD_HEIGHT as isize / 2 {
+        for x in (0..BOARD_WIDTH as isize).step_by(4) {
+            half_filled.try_place(registry.get(line, 0), (x, y), TileColor::Red);
+        }
+    }
+    for (name, board) in [("empty", Board::default()), ("half filled", half_filled)] {
+        let start = Instant::now();
+        let mut fits = 0;
+        for _ in 0..ROUNDS {
+            for (_, rotations) in registry.iter() {
+                let shape = &rotations[0];
+                for y in -8..24 {
+                    for x in -8..24 {
+                        let superimposition = board.superimpose_at(shape, (x, y));
+                        fits += superimposition
+                            .cells
+                            .iter()
+                            .filter(|&&(_, _, state)| state == SuperimpositionState::Fits)
+                            .count();
+                    }
+                }
+            }
+        }
+        let calls = ROUNDS * registry.iter().count() * 32 * 32;
+        println!(
+            "{name}: {:.0} ns per superimposition ({fits} fitting cells)",
+            start.elapsed().as_nanos() as f64 / calls as f64
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! A second board spawned next to the main one, Tab moves play over to the other.
+//!
+//! Whichever board carries [`MainBoard`] receives the preview and placements, so
+//! routing input is moving the marker, along with the overlay board showing the preview.
+
+use bevy::prelude::*;
+use blocks::prelude::*;
+
+/// Horizontal distance of each board from the center of the screen.
+const BOARD_OFFSET: f32 = 310.;
+
+/// Either of the two boards the player can switch between.
+#[derive(Component)]
+struct Playable;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, BlocksPlugin::default()))
+        .add_systems(PostStartup, spawn_second_board)
+        .add_systems(
+            Update,
+            (switch_boards, show_second_board).run_if(
+                state_changed::<GameState>().or_else(|input_keys: Res<Input<KeyCode>>| {
+                    input_keys.just_pressed(KeyCode::Tab)
+                }),
+            ),
+        )
+        .run();
+}
+
+fn spawn_second_board(
+    mut commands: Commands,
+    mut q_main_board: Query<(Entity, &mut Transform), With<MainBoard>>,
+) {
+    let (main_board, mut transform) = q_main_board.single_mut();
+    transform.translation.x = -BOARD_OFFSET;
+    commands.entity(main_board).insert(Playable);
+    spawn_board(&mut commands, true).insert((
+        Playable,
+        Transform::from_xyz(BOARD_OFFSET, 0., 0.).with_scale(Vec3::splat(TILE_SIZE)),
+    ));
+}
+
+fn switch_boards(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    q_boards: Query<(Entity, Has<MainBoard>), With<Playable>>,
+    q_overlay_board: Query<Entity, With<OverlayBoard>>,
+) {
+    if !input_keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let Some((next, _)) = q_boards.iter().find(|(_, main)| !main) else {
+        return;
+    };
+    for (board, main) in q_boards.iter() {
+        if main {
+            commands.entity(board).remove::<MainBoard>();
+        }
+    }
+    commands.entity(next).insert(MainBoard);
+    for overlay in q_overlay_board.iter() {
+        commands.entity(overlay).set_parent(next);
+    }
+}
+
+/// The game only shows its main board outside the menu, the second one follows.
+fn show_second_board(
+    state: Res<State<GameState>>,
+    mut q_boards: Query<&mut Visibility, (With<Playable>, Without<MainBoard>)>,
+) {
+    for mut visibility in q_boards.iter_mut() {
+        *visibility = if *state.get() == GameState::Menu {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
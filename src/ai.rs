@@ -0,0 +1,153 @@
+//! Greedy placement search: tries every anchor of a shape and keeps the best scoring.
+
+use crate::{
+    board::{anchors, Board, TileColor},
+    budget::FrameBudget,
+    shape::Shape,
+};
+
+/// Score of every line a placement completes, outweighing any amount of contact.
+const LINE_SCORE: f32 = 100.;
+
+/// Scores placing the shape at `anchor`: completed lines, then how snugly the shape
+/// touches filled cells and walls. `None` if it doesn't fit.
+pub fn evaluate(board: &Board, shape: &Shape, anchor: (isize, isize)) -> Option<f32> {
+    let mut board = *board;
+    let placed = board.try_place(shape, anchor, TileColor::Gray)?;
+    let filled = |x: isize, y: isize| {
+        x < 0
+            || y < 0
+            || !board.contains(x as usize, y as usize)
+            || board.cells.0[y as usize][x as usize].is_some()
+    };
+    let contact = placed
+        .iter()
+        .flat_map(|&(x, y)| {
+            let (x, y) = (x as isize, y as isize);
+            [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+        })
+        .filter(|&(x, y)| filled(x, y))
+        .count();
+    Some(board.full_lines().len() as f32 * LINE_SCORE + contact as f32)
+}
+
+/// Best anchor for the shape along with its score, the first one scanned on ties.
+pub fn best_placement(board: &Board, shape: &Shape) -> Option<((isize, isize), f32)> {
+    ranked_placements(board, shape, 1).first().copied()
+}
+
+/// The `count` best anchors for the shape with their scores, best first and in scan
+/// order on ties.
+pub fn ranked_placements(board: &Board, shape: &Shape, count: usize) -> Vec<((isize, isize), f32)> {
+    let mut search = PlacementSearch::ranked(count);
+    search.resume(board, shape, &FrameBudget::unlimited());
+    search.ranked
+}
+
+/// [`best_placement`] spread over as many calls as the budgets require, all of them
+/// for the same board and shape. Keeps the runners-up too when made with
+/// [`PlacementSearch::ranked`].
+pub struct PlacementSearch {
+    /// Anchors evaluated so far, in scan order.
+    scanned: usize,
+    /// Best anchors so far, best first.
+    ranked: Vec<((isize, isize), f32)>,
+    /// Anchors `ranked` keeps.
+    keep: usize,
+}
+impl Default for PlacementSearch {
+    fn default() -> Self {
+        Self::ranked(1)
+    }
+}
+impl PlacementSearch {
+    /// A search keeping the `count` best anchors, see [`ranked_placements`].
+    pub fn ranked(count: usize) -> Self {
+        Self {
+            scanned: 0,
+            ranked: Vec::with_capacity(count),
+            keep: count.max(1),
+        }
+    }
+
+    /// Evaluates anchors until all were or the budget ran out, `Some` with the result
+    /// of [`best_placement`] in the former case.
+    pub fn resume(
+        &mut self,
+        board: &Board,
+        shape: &Shape,
+        budget: &FrameBudget,
+    ) -> Option<Option<((isize, isize), f32)>> {
+        for (x, y) in anchors(shape).skip(self.scanned) {
+            if !budget.check() {
+                return None;
+            }
+            self.scanned += 1;
+            let anchor = (x as isize, y as isize);
+            let Some(score) = evaluate(board, shape, anchor) else {
+                continue;
+            };
+            // Below the anchors scoring the same, which were scanned first
+            let place = self.ranked.partition_point(|&(_, ranked)| ranked >= score);
+            if place < self.keep {
+                self.ranked.insert(place, (anchor, score));
+                self.ranked.truncate(self.keep);
+            }
+        }
+        Some(self.ranked.first().copied())
+    }
+
+    /// Best anchors found so far with their scores, best first.
+    pub fn ranked_so_far(&self) -> &[((isize, isize), f32)] {
+        &self.ranked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BOARD_HEIGHT, BOARD_WIDTH};
+
+    /// A board filled in a scattered pattern, with a few lines nearly complete.
+    fn fixture() -> Board {
+        let mut board = Board::default();
+        for y in 0..BOARD_HEIGHT {
+            for x in 0..BOARD_WIDTH {
+                if (x * 7 + y * 3) % 5 == 0 || (y < 3 && x != 9) {
+                    board.cells.0[y][x] = Some(TileColor::Stone);
+                }
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn a_search_spread_over_frames_finds_the_same_placements() {
+        let board = fixture();
+        let shape = Shape::from_pattern(2, 3, "#.#.##");
+        let mut search = PlacementSearch::ranked(5);
+        let mut frames = 0;
+        let best = loop {
+            frames += 1;
+            assert!(frames < 100_000, "the search made no progress");
+            if let Some(best) = search.resume(&board, &shape, &FrameBudget::new(10)) {
+                break best;
+            }
+        };
+
+        let ranked = ranked_placements(&board, &shape, 5);
+        assert_eq!(ranked.len(), 5);
+        assert_eq!(search.ranked_so_far(), ranked.as_slice());
+        assert_eq!(best, best_placement(&board, &shape));
+    }
+
+    #[test]
+    fn completed_lines_outweigh_contact() {
+        let board = fixture();
+        let dot = Shape::from_pattern(1, 1, "#");
+        let ((x, y), score) = best_placement(&board, &dot).unwrap();
+        assert_eq!(x, 9);
+        assert!(y < 3);
+        assert!(score >= LINE_SCORE);
+    }
+}
@@ -0,0 +1,241 @@
+//! Accessibility mode: game events described in words on a caption strip, and passed to
+//! screen readers as a live region with the `accesskit` feature.
+
+#[cfg(feature = "accesskit")]
+use bevy::a11y::accesskit::{Live, NodeBuilder, Role};
+use bevy::{a11y::AccessibilityNode, prelude::*};
+
+use crate::{
+    menu::GameState,
+    overtime::SuddenDeath,
+    settings::Settings,
+    shape::ShapeRegistry,
+    shape_slot::{PlacementsLeft, FEW_PLACEMENTS},
+    BlocksSystems, GameOverEvent, LinesClearedEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+/// Announcements kept on the caption strip, the oldest scrolling off.
+const CAPTION_LINES: usize = 3;
+
+/// A game event described for players who can't follow the board.
+#[derive(Event)]
+pub struct Announcement(pub String);
+
+#[derive(Component)]
+struct CaptionStrip;
+
+pub struct AnnouncePlugin;
+impl Plugin for AnnouncePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Announcement>()
+            .add_systems(Startup, spawn_caption_strip)
+            .add_systems(
+                Update,
+                (
+                    announce_game_events.after(BlocksSystems::Resolution),
+                    announce_placements_left.run_if(
+                        in_state(GameState::Playing).and_then(resource_changed::<PlacementsLeft>()),
+                    ),
+                    show_announcements,
+                )
+                    .chain()
+                    .run_if(|settings: Res<Settings>| settings.announcements),
+            );
+    }
+}
+
+fn plural(count: usize, word: &str) -> String {
+    if count == 1 {
+        format!("1 {word}")
+    } else {
+        format!("{count} {word}s")
+    }
+}
+
+/// Describes placements, clears, overtime and the game's end on the main board. Columns and rows count
+/// from 1 at the bottom left, the placement's being its lowest, leftmost cell.
+fn announce_game_events(
+    registry: Res<ShapeRegistry>,
+    sudden_death: Res<SuddenDeath>,
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    mut game_over: EventReader<GameOverEvent>,
+    mut announcements: EventWriter<Announcement>,
+    mut previous_sudden_death: Local<SuddenDeath>,
+    q_main_board: Query<(), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    for event in placements.read() {
+        if !q_main_board.contains(event.board) {
+            continue;
+        }
+        let Some(&(x, y)) = event.cells.iter().min_by_key(|&&(x, y)| (y, x)) else {
+            continue;
+        };
+        announcements.send(Announcement(format!(
+            "Placed {} at column {}, row {}",
+            registry.name(event.shape.id),
+            x + 1,
+            y + 1
+        )));
+    }
+    for event in lines_cleared.read() {
+        if !q_main_board.contains(event.board) {
+            continue;
+        }
+        let lines = &event.lines;
+        let message = match (lines.rows.len(), lines.columns.len()) {
+            (rows, 0) => format!("Cleared {}", plural(rows, "row")),
+            (0, columns) => format!("Cleared {}", plural(columns, "column")),
+            (rows, columns) => format!(
+                "Cleared {} and {}",
+                plural(rows, "row"),
+                plural(columns, "column")
+            ),
+        };
+        announcements.send(Announcement(message));
+    }
+    if *sudden_death != *previous_sudden_death {
+        let message = match (*previous_sudden_death, *sudden_death) {
+            (SuddenDeath::Calm, SuddenDeath::Overtime(remaining)) => Some(format!(
+                "Overtime, {} seconds to clear the board",
+                remaining.ceil()
+            )),
+            (SuddenDeath::Overtime(_), SuddenDeath::Calm) => Some("Overtime canceled".into()),
+            _ => None,
+        };
+        if let Some(message) = message {
+            announcements.send(Announcement(message));
+        }
+    }
+    if game_over.read().count() > 0 {
+        announcements.send(Announcement("Game over".into()));
+    }
+    // Countdown ticks don't count as changes
+    if std::mem::discriminant(&*sudden_death) != std::mem::discriminant(&*previous_sudden_death) {
+        *previous_sudden_death = *sudden_death;
+    }
+}
+
+/// Warns once few placements are left for the selected shape, the game ending when
+/// none are.
+fn announce_placements_left(
+    left: Res<PlacementsLeft>,
+    mut announced: Local<Option<usize>>,
+    mut announcements: EventWriter<Announcement>,
+) {
+    let few = match left.0 {
+        Some((placements, false)) if placements < FEW_PLACEMENTS => Some(placements),
+        _ => None,
+    };
+    if few == *announced {
+        return;
+    }
+    *announced = few;
+    if let Some(placements) = few {
+        announcements.send(Announcement(format!(
+            "{} remaining before game over",
+            plural(placements, "placement")
+        )));
+    }
+}
+
+fn spawn_caption_strip(mut commands: Commands) {
+    let mut strip = commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 18.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.),
+                left: Val::Percent(30.),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.6).into(),
+            // Until the first announcement
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CaptionStrip,
+    ));
+    #[cfg(feature = "accesskit")]
+    {
+        let mut node = NodeBuilder::new(Role::Status);
+        node.set_live(Live::Polite);
+        strip.insert(AccessibilityNode::from(node));
+    }
+    #[cfg(not(feature = "accesskit"))]
+    let _ = &mut strip;
+}
+
+/// Logs announcements and keeps the latest ones on the caption strip.
+fn show_announcements(
+    mut announcements: EventReader<Announcement>,
+    mut lines: Local<Vec<String>>,
+    mut q_strip: Query<
+        (&mut Text, &mut Visibility, Option<&mut AccessibilityNode>),
+        With<CaptionStrip>,
+    >,
+) {
+    let mut latest = None;
+    for Announcement(message) in announcements.read() {
+        info!("{message}");
+        lines.push(message.clone());
+        latest = Some(message);
+    }
+    let Some(latest) = latest else {
+        return;
+    };
+    let excess = lines.len().saturating_sub(CAPTION_LINES);
+    lines.drain(..excess);
+    let Ok((mut text, mut visibility, node)) = q_strip.get_single_mut() else {
+        return;
+    };
+    text.sections[0].value = lines.join("\n");
+    *visibility = Visibility::Inherited;
+    // Screen readers only need the news, not the whole strip
+    if let Some(mut node) = node {
+        node.set_name(latest.as_str());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announced(world: &mut World) -> Vec<String> {
+        world
+            .resource_mut::<Events<Announcement>>()
+            .drain()
+            .map(|Announcement(message)| message)
+            .collect()
+    }
+
+    #[test]
+    fn few_placements_are_announced_once() {
+        let mut world = World::new();
+        world.init_resource::<Events<Announcement>>();
+        let mut system = IntoSystem::into_system(announce_placements_left);
+        system.initialize(&mut world);
+        let mut run = |world: &mut World, left| {
+            world.insert_resource(PlacementsLeft(left));
+            system.run((), world);
+            announced(world)
+        };
+
+        assert!(run(&mut world, Some((20, false))).is_empty());
+        assert_eq!(
+            run(&mut world, Some((3, false))),
+            ["3 placements remaining before game over"]
+        );
+        assert!(run(&mut world, Some((3, false))).is_empty());
+        assert_eq!(
+            run(&mut world, Some((1, false))),
+            ["1 placement remaining before game over"]
+        );
+    }
+}
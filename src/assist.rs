@@ -0,0 +1,427 @@
+//! Adaptive assist, turned on with the `adaptive_assist` setting: the shape randomizer
+//! leans toward small shapes while the player struggles and toward large ones while
+//! they cruise, judging by the last [`WINDOW`] placements.
+//!
+//! Struggling is a board over [`STRUGGLE_FILL`] full without a clear in the last
+//! [`STRUGGLE_MOVES`] placements, cruising at least [`CRUISE_CLEARS`] clearing placements
+//! in the window on a board under [`CRUISE_FILL`] full. The bias moves a [`STEP`] toward
+//! either per placement and scales no weight by more than [`MAX_FACTOR`] either way.
+//!
+//! Only placements and the board feed it, so a seed and the same moves give the same
+//! shapes again. It stays off in the daily game, races, puzzles and versus, and games it
+//! biased don't enter the leaderboard. An icon on the HUD shows while it biases draws.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    menu::GameMode, restart::RestartEvent, settings::Settings, shape::ShapeRegistry,
+    strings::Strings, BlocksSystems, Board, LinesClearedEvent, MainBoard, OverlayBoard,
+    PlacementEvent,
+};
+
+/// Placements the assist judges the player by.
+const WINDOW: usize = 15;
+const STRUGGLE_FILL: f32 = 0.7;
+const STRUGGLE_MOVES: usize = 8;
+const CRUISE_FILL: f32 = 0.4;
+const CRUISE_CLEARS: usize = 5;
+/// Change of the bias per placement.
+const STEP: f32 = 0.25;
+/// Most the bias scales a shape's weight by, up or down.
+const MAX_FACTOR: f32 = 2.;
+
+/// A placement as the assist remembers it.
+#[derive(Clone, Copy)]
+struct Move {
+    lines: usize,
+    /// Share of the board's cells filled once the placement's lines cleared.
+    fill: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct Assist {
+    /// The last [`WINDOW`] placements at most, latest first.
+    recent: VecDeque<Move>,
+    /// From -1, leaning toward large shapes, to 1, leaning toward small ones.
+    bias: f32,
+    /// Whether the setting is on and the mode allows it.
+    enabled: bool,
+    /// Whether the bias leaned any draw of the current game.
+    pub used: bool,
+}
+impl Assist {
+    /// Whether draws are biased.
+    pub fn active(&self) -> bool {
+        self.enabled && self.bias != 0.
+    }
+
+    /// The weights, indexed by [`crate::shape::ShapeId`], leaning toward small shapes
+    /// for a positive bias and large ones for a negative bias. Every weight is scaled by
+    /// between `1 / MAX_FACTOR` and `MAX_FACTOR`.
+    pub fn apply(&self, weights: &[f32], registry: &ShapeRegistry) -> Vec<f32> {
+        if !self.active() {
+            return weights.to_vec();
+        }
+        let cells: Vec<u32> = registry
+            .iter()
+            .map(|(_, rotations)| rotations[0].bits().count_ones())
+            .collect();
+        let pool_cells = registry.pool().map(|(id, _)| cells[id.0 as usize] as f32);
+        let (min, max) = pool_cells.fold((f32::MAX, f32::MIN), |(min, max), cells| {
+            (min.min(cells), max.max(cells))
+        });
+        if max <= min {
+            return weights.to_vec();
+        }
+        weights
+            .iter()
+            .zip(&cells)
+            .map(|(weight, &cells)| {
+                // -1 for the smallest shapes of the pool, 1 for the largest
+                let size = ((2. * (cells as f32 - min) / (max - min)) - 1.).clamp(-1., 1.);
+                weight * MAX_FACTOR.powf(-self.bias * size)
+            })
+            .collect()
+    }
+
+    /// The bias the recent placements call for.
+    fn target(&self) -> f32 {
+        let Some(latest) = self.recent.front() else {
+            return 0.;
+        };
+        let stuck = self.recent.len() >= STRUGGLE_MOVES
+            && self
+                .recent
+                .iter()
+                .take(STRUGGLE_MOVES)
+                .all(|placement| placement.lines == 0);
+        let clears = self
+            .recent
+            .iter()
+            .filter(|placement| placement.lines > 0)
+            .count();
+        if latest.fill > STRUGGLE_FILL && stuck {
+            1.
+        } else if latest.fill < CRUISE_FILL && clears >= CRUISE_CLEARS {
+            -1.
+        } else {
+            0.
+        }
+    }
+
+    fn record(&mut self, placement: Move) {
+        if self.recent.len() == WINDOW {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(placement);
+        let target = self.target();
+        self.bias = if (target - self.bias).abs() <= STEP {
+            target
+        } else {
+            self.bias + STEP * (target - self.bias).signum()
+        };
+    }
+}
+
+/// Whether the mode may use the assist, keeping shared and timed results fair.
+fn allowed(mode: GameMode) -> bool {
+    !matches!(
+        mode,
+        GameMode::Daily | GameMode::Sprint | GameMode::Dig | GameMode::Puzzle | GameMode::Versus
+    )
+}
+
+#[derive(Component)]
+struct AssistIcon;
+
+pub struct AssistPlugin;
+impl Plugin for AssistPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Assist>()
+            .add_systems(Startup, spawn_assist_icon)
+            .add_systems(
+                Update,
+                (
+                    (reset_assist, enable_assist)
+                        .chain()
+                        .before(BlocksSystems::Preview),
+                    track_placements.in_set(BlocksSystems::Resolution),
+                    show_assist_icon.in_set(BlocksSystems::Presentation),
+                ),
+            );
+    }
+}
+
+fn reset_assist(mut restarts: EventReader<RestartEvent>, mut assist: ResMut<Assist>) {
+    if restarts.read().count() > 0 {
+        *assist = Assist::default();
+    }
+}
+
+fn enable_assist(settings: Res<Settings>, mode: Res<GameMode>, mut assist: ResMut<Assist>) {
+    let enabled = settings.adaptive_assist && allowed(*mode);
+    if assist.enabled != enabled {
+        assist.enabled = enabled;
+    }
+}
+
+/// Records the frame's placement on the main board once its lines cleared.
+fn track_placements(
+    mut assist: ResMut<Assist>,
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    q_main_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let placed = placements
+        .read()
+        .filter(|event| q_main_board.contains(event.board))
+        .count()
+        > 0;
+    let lines: usize = lines_cleared
+        .read()
+        .filter(|event| q_main_board.contains(event.board))
+        .map(|event| event.lines.rows.len() + event.lines.columns.len())
+        .sum();
+    let Ok(board) = q_main_board.get_single() else {
+        return;
+    };
+    if !placed || !assist.enabled {
+        return;
+    }
+    let cells = board.grid.cells.0.iter().flatten();
+    let total = cells.clone().count();
+    let filled = cells.filter(|cell| cell.is_some()).count();
+    assist.record(Move {
+        lines,
+        fill: filled as f32 / total as f32,
+    });
+    if assist.active() {
+        assist.used = true;
+    }
+}
+
+fn spawn_assist_icon(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                right: Val::Px(8.),
+                padding: UiRect::axes(Val::Px(6.), Val::Px(2.)),
+                ..default()
+            },
+            background_color: Color::rgb(0.2, 0.5, 0.3).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        AssistIcon,
+    ));
+}
+
+fn show_assist_icon(
+    strings: Res<Strings>,
+    assist: Res<Assist>,
+    mut q_icon: Query<(&mut Visibility, &mut Text), With<AssistIcon>>,
+) {
+    let shown = assist.active();
+    for (mut visibility, mut text) in q_icon.iter_mut() {
+        let wanted = if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(wanted);
+        if strings.is_changed() {
+            text.sections[0].value = strings.get("hud.assist").to_owned();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::default_shapes;
+
+    fn assist(bias: f32) -> Assist {
+        Assist {
+            bias,
+            enabled: true,
+            ..default()
+        }
+    }
+
+    fn record_all(assist: &mut Assist, placement: Move, times: usize) {
+        for _ in 0..times {
+            assist.record(placement);
+        }
+    }
+
+    /// Factor each pool shape's weight is scaled by, by its cells.
+    fn factors(assist: &Assist, registry: &ShapeRegistry) -> Vec<(u32, f32)> {
+        let weights = vec![1.; registry.iter().count()];
+        registry
+            .iter()
+            .zip(assist.apply(&weights, registry))
+            .map(|((_, rotations), weight)| (rotations[0].bits().count_ones(), weight))
+            .collect()
+    }
+
+    #[test]
+    fn no_weight_is_scaled_past_the_max_factor() {
+        let registry = ShapeRegistry::new(default_shapes());
+        for step in -4..=4 {
+            let assist = assist(step as f32 * STEP);
+            for (cells, factor) in factors(&assist, &registry) {
+                assert!(
+                    (1. / MAX_FACTOR - 1e-4..=MAX_FACTOR + 1e-4).contains(&factor),
+                    "a {cells} cell shape scaled by {factor} at bias {}",
+                    assist.bias
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn the_bias_stays_bounded_and_moves_a_step_at_a_time() {
+        let mut rng = StdRng::seed_from_u64(202);
+        let mut assist = assist(0.);
+        for _ in 0..2000 {
+            let before = assist.bias;
+            assist.record(Move {
+                lines: if rng.gen_bool(0.3) {
+                    rng.gen_range(1..4)
+                } else {
+                    0
+                },
+                fill: rng.gen(),
+            });
+            assert!((-1. ..=1.).contains(&assist.bias), "bias {}", assist.bias);
+            assert!((assist.bias - before).abs() <= STEP + 1e-6);
+        }
+    }
+
+    #[test]
+    fn struggling_leans_toward_small_shapes() {
+        let registry = ShapeRegistry::new(default_shapes());
+        let mut assist = assist(0.);
+        let stuck = Move {
+            lines: 0,
+            fill: 0.8,
+        };
+        record_all(&mut assist, stuck, STRUGGLE_MOVES - 1);
+        assert_eq!(assist.bias, 0.);
+
+        record_all(&mut assist, stuck, 4);
+        assert_eq!(assist.bias, 1.);
+        let factors = factors(&assist, &registry);
+        let smallest = factors.iter().min_by_key(|(cells, _)| *cells).unwrap();
+        let largest = factors.iter().max_by_key(|(cells, _)| *cells).unwrap();
+        assert_eq!((smallest.1, largest.1), (MAX_FACTOR, 1. / MAX_FACTOR));
+    }
+
+    #[test]
+    fn cruising_leans_toward_large_shapes() {
+        let registry = ShapeRegistry::new(default_shapes());
+        let mut assist = assist(0.);
+        let clearing = Move {
+            lines: 1,
+            fill: 0.2,
+        };
+        record_all(&mut assist, clearing, CRUISE_CLEARS + 3);
+        assert_eq!(assist.bias, -1.);
+        let factors = factors(&assist, &registry);
+        let largest = factors.iter().max_by_key(|(cells, _)| *cells).unwrap();
+        assert_eq!(largest.1, MAX_FACTOR);
+
+        // Back to a fuller board it eases off again, a step a placement
+        assist.record(Move {
+            lines: 0,
+            fill: 0.5,
+        });
+        assert_eq!(assist.bias, -1. + STEP);
+    }
+
+    #[test]
+    fn placements_past_the_window_are_forgotten() {
+        let mut assist = assist(0.);
+        let clearing = Move {
+            lines: 2,
+            fill: 0.2,
+        };
+        let quiet = Move {
+            lines: 0,
+            fill: 0.2,
+        };
+        record_all(&mut assist, clearing, CRUISE_CLEARS);
+        assert_eq!(assist.target(), -1.);
+
+        // The clears stay in the window until as many later placements pushed them out
+        record_all(&mut assist, quiet, WINDOW - CRUISE_CLEARS);
+        assert_eq!(assist.target(), -1.);
+        assist.record(quiet);
+        assert_eq!(assist.recent.len(), WINDOW);
+        assert_eq!(assist.target(), 0.);
+
+        record_all(&mut assist, quiet, WINDOW);
+        assert_eq!(assist.recent.len(), WINDOW);
+        assert!(assist.recent.iter().all(|placement| placement.lines == 0));
+    }
+
+    #[test]
+    fn the_same_moves_give_the_same_bias() {
+        let moves: Vec<Move> = (0..40)
+            .map(|i| Move {
+                lines: usize::from(i % 3 == 0),
+                fill: (i % 10) as f32 / 10.,
+            })
+            .collect();
+        let biases = || {
+            let mut assist = assist(0.);
+            moves
+                .iter()
+                .map(|&placement| {
+                    assist.record(placement);
+                    assist.bias
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(biases(), biases());
+    }
+
+    #[test]
+    fn a_disabled_assist_leaves_the_weights_alone() {
+        let registry = ShapeRegistry::new(default_shapes());
+        let weights: Vec<f32> = (0..registry.iter().count()).map(|i| i as f32).collect();
+        let assist = Assist {
+            enabled: false,
+            ..assist(1.)
+        };
+        assert!(!assist.active());
+        assert_eq!(assist.apply(&weights, &registry), weights);
+    }
+
+    #[test]
+    fn fair_modes_keep_the_assist_off() {
+        for mode in [
+            GameMode::Daily,
+            GameMode::Sprint,
+            GameMode::Dig,
+            GameMode::Puzzle,
+            GameMode::Versus,
+        ] {
+            assert!(!allowed(mode));
+        }
+        assert!(allowed(GameMode::Endless));
+    }
+}
@@ -0,0 +1,254 @@
+//! Attract mode: after [`ATTRACT_DELAY`] seconds without input on the menu, the menu
+//! gives way to the demo game played at [`ATTRACT_SPEED`] times its pace, with its score
+//! showing and tips fading in and out over it. Any input brings the menu back.
+//!
+//! The demo keeps its [`Score`] and [`Level`] on its own board, so nothing of it reaches
+//! the game's board and a game started afterwards starts clean.
+
+use bevy::{
+    input::{mouse::MouseMotion, InputSystem},
+    prelude::*,
+};
+
+use crate::{
+    menu::{Demo, DemoBoard, GameState, MenuRoot},
+    scoring::{Combo, Level, Score},
+    strings::{t, Strings},
+    tween::Tween,
+};
+
+/// Seconds without input on the menu before the attract mode starts.
+const ATTRACT_DELAY: f32 = 30.;
+/// Pace of the demo in the attract mode, relative to its pace behind the menu.
+const ATTRACT_SPEED: f32 = 2.;
+/// Seconds each tip shows, fades included.
+const CARD_SECONDS: f32 = 6.;
+const FADE_SECONDS: f32 = 0.8;
+/// Keys in [`Strings`] of the tips, shown in turn.
+const CARDS: [&str; 3] = ["attract.clear_lines", "attract.rotate", "attract.bonus"];
+
+#[derive(Resource, Default)]
+struct Attract {
+    /// Seconds since the last input on the menu.
+    idle: f32,
+    active: bool,
+    /// Index into [`CARDS`] of the tip showing.
+    card: usize,
+    /// Seconds the tip has been showing.
+    card_time: f32,
+}
+
+#[derive(Component)]
+struct AttractRoot;
+
+#[derive(Component)]
+struct AttractScore;
+
+#[derive(Component)]
+struct AttractCard;
+
+pub struct AttractPlugin;
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Attract>()
+            .add_systems(OnExit(GameState::Menu), leave_attract)
+            // Before the menu sees the input ending the attract mode
+            .add_systems(
+                PreUpdate,
+                watch_idle
+                    .after(InputSystem)
+                    .run_if(in_state(GameState::Menu)),
+            )
+            .add_systems(
+                Update,
+                (update_attract_score, cycle_cards)
+                    .run_if(in_state(GameState::Menu))
+                    .run_if(|attract: Res<Attract>| attract.active),
+            );
+    }
+}
+
+/// Starts the attract mode once the menu was idle long enough and ends it on any input,
+/// which the menu then doesn't see.
+fn watch_idle(
+    mut commands: Commands,
+    time: Res<Time>,
+    strings: Res<Strings>,
+    (mut input_keys, mut input_mb, input_gamepad): (
+        ResMut<Input<KeyCode>>,
+        ResMut<Input<MouseButton>>,
+        Res<Input<GamepadButton>>,
+    ),
+    mut mouse_motion: EventReader<MouseMotion>,
+    (mut attract, mut demo): (ResMut<Attract>, ResMut<Demo>),
+    mut q_menu: Query<&mut Visibility, With<MenuRoot>>,
+    q_attract: Query<Entity, With<AttractRoot>>,
+    mut q_demo_score: Query<(&mut Score, &mut Level, &mut Combo), With<DemoBoard>>,
+) {
+    let input = input_keys.get_just_pressed().next().is_some()
+        || input_mb.get_just_pressed().next().is_some()
+        || input_gamepad.get_just_pressed().next().is_some()
+        || mouse_motion.read().count() > 0;
+    if input {
+        attract.idle = 0.;
+        if attract.active {
+            end_attract(&mut commands, &mut attract, &mut demo, &q_attract);
+            for mut visibility in q_menu.iter_mut() {
+                *visibility = Visibility::Inherited;
+            }
+            input_keys.clear();
+            input_mb.clear();
+        }
+        return;
+    }
+    attract.idle += time.delta_seconds();
+    if attract.active || attract.idle < ATTRACT_DELAY {
+        return;
+    }
+
+    *attract = Attract {
+        active: true,
+        ..default()
+    };
+    demo.speed = ATTRACT_SPEED;
+    for (mut score, mut level, mut combo) in q_demo_score.iter_mut() {
+        *score = Score::default();
+        *level = Level::default();
+        *combo = Combo::default();
+    }
+    for mut visibility in q_menu.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::all(Val::Px(24.)),
+                    ..default()
+                },
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            AttractRoot,
+        ))
+        .with_children(|commands| {
+            commands.spawn((
+                TextBundle::from_section(
+                    t!(strings, "attract.score", 0, 0),
+                    TextStyle {
+                        font_size: 32.,
+                        color: Color::WHITE.with_a(0.),
+                        ..default()
+                    },
+                ),
+                Tween::new(FADE_SECONDS).with_alpha(0., 1.),
+                AttractScore,
+            ));
+            spawn_card(commands, &strings, CARDS[0]);
+            commands.spawn((
+                TextBundle::from_section(
+                    strings.get("attract.press_any_key"),
+                    TextStyle {
+                        font_size: 24.,
+                        color: Color::WHITE.with_a(0.),
+                        ..default()
+                    },
+                ),
+                Tween::new(FADE_SECONDS).with_alpha(0., 1.),
+            ));
+        });
+}
+
+fn spawn_card(commands: &mut ChildBuilder, strings: &Strings, key: &str) {
+    commands.spawn((
+        TextBundle::from_section(
+            strings.get(key),
+            TextStyle {
+                font_size: 40.,
+                color: Color::WHITE.with_a(0.),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(45.),
+            ..default()
+        }),
+        Tween::new(FADE_SECONDS).with_alpha(0., 1.),
+        AttractCard,
+    ));
+}
+
+fn end_attract(
+    commands: &mut Commands,
+    attract: &mut Attract,
+    demo: &mut Demo,
+    q_attract: &Query<Entity, With<AttractRoot>>,
+) {
+    attract.active = false;
+    demo.speed = 1.;
+    for entity in q_attract.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Starting a game from the attract mode, e.g. through the console, leaves nothing of
+/// it behind.
+fn leave_attract(
+    mut commands: Commands,
+    (mut attract, mut demo): (ResMut<Attract>, ResMut<Demo>),
+    q_attract: Query<Entity, With<AttractRoot>>,
+) {
+    attract.idle = 0.;
+    end_attract(&mut commands, &mut attract, &mut demo, &q_attract);
+}
+
+fn update_attract_score(
+    strings: Res<Strings>,
+    q_demo_score: Query<(&Score, &Level), With<DemoBoard>>,
+    mut q_text: Query<&mut Text, With<AttractScore>>,
+) {
+    let (Ok((score, level)), Ok(mut text)) = (q_demo_score.get_single(), q_text.get_single_mut())
+    else {
+        return;
+    };
+    let value = t!(strings, "attract.score", score.0, level.lines);
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+/// Cross-fades to the next tip every [`CARD_SECONDS`].
+fn cycle_cards(
+    mut commands: Commands,
+    time: Res<Time>,
+    strings: Res<Strings>,
+    mut attract: ResMut<Attract>,
+    q_attract: Query<Entity, With<AttractRoot>>,
+    q_cards: Query<Entity, (With<AttractCard>, Without<Tween>)>,
+) {
+    attract.card_time += time.delta_seconds();
+    if attract.card_time < CARD_SECONDS {
+        return;
+    }
+    let Ok(root) = q_attract.get_single() else {
+        return;
+    };
+    attract.card_time = 0.;
+    attract.card = (attract.card + 1) % CARDS.len();
+    for card in q_cards.iter() {
+        commands.entity(card).insert(
+            Tween::new(FADE_SECONDS)
+                .with_alpha(1., 0.)
+                .despawn_on_finish(),
+        );
+    }
+    commands
+        .entity(root)
+        .with_children(|commands| spawn_card(commands, &strings, CARDS[attract.card]));
+}
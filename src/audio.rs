@@ -0,0 +1,127 @@
+//! Sound effects, played from [`SfxEvent`]s. With dynamic audio on, the placement sound
+//! rises in pitch along a major scale with the combo, the placements in a row that
+//! cleared lines, and a low thud under it grows louder the nearer the shape landed to
+//! the bottom of the board, or to the top of the settled cells with gravity.
+//!
+//! The sounds are `sounds/place.ogg` and `sounds/thud.ogg` in the assets directory,
+//! the game stays silent without them.
+
+use bevy::{audio::Volume, prelude::*};
+
+use crate::{
+    board::BOARD_HEIGHT,
+    scoring::{Combo, ScoreBreakdownEvent},
+    settings::Settings,
+    systems::Gravity,
+    Board, MainBoard, OverlayBoard,
+};
+
+/// Semitones above the base pitch of each combo, the last one held for longer combos.
+const COMBO_SCALE: [f32; 8] = [0., 2., 4., 5., 7., 9., 11., 12.];
+/// Volume of the thud right at the bottom, fading out further up.
+const THUD_VOLUME: f32 = 0.6;
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum SfxEvent {
+    /// A shape was placed on the main board.
+    Placement {
+        /// Placements in a row that cleared lines, this one included, 0 if it didn't.
+        combo: u32,
+        /// Lowest row the shape covers.
+        row: usize,
+    },
+}
+
+#[derive(Resource)]
+struct SfxAssets {
+    place: Handle<AudioSource>,
+    thud: Handle<AudioSource>,
+}
+
+pub struct SfxPlugin;
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SfxEvent>()
+            .add_systems(Startup, load_sfx)
+            // Placements are only scored by the end of the frame
+            .add_systems(PostUpdate, (send_placement_sfx, play_sfx).chain());
+    }
+}
+
+fn load_sfx(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SfxAssets {
+        place: asset_server.load("sounds/place.ogg"),
+        thud: asset_server.load("sounds/thud.ogg"),
+    });
+}
+
+fn send_placement_sfx(
+    mut breakdowns: EventReader<ScoreBreakdownEvent>,
+    mut sfx: EventWriter<SfxEvent>,
+    q_main_board: Query<&Combo, With<MainBoard>>,
+) {
+    let combo = q_main_board.get_single().map_or(0, |combo| combo.0);
+    for breakdown in breakdowns.read() {
+        let row = breakdown
+            .placement_cells
+            .iter()
+            .map(|&(_, y)| y)
+            .min()
+            .unwrap_or(0);
+        sfx.send(SfxEvent::Placement { combo, row });
+    }
+}
+
+/// Playback speed of the placement sound, rising a scale step per combo.
+fn combo_speed(combo: u32) -> f32 {
+    let step = (combo as usize).min(COMBO_SCALE.len() - 1);
+    2f32.powf(COMBO_SCALE[step] / 12.)
+}
+
+/// Lowest row with an empty cell, where cells settle with gravity.
+fn fill_frontier(board: &Board) -> usize {
+    let grid = &board.grid;
+    (0..grid.height())
+        .find(|&y| (0..grid.width()).any(|x| grid.cells.0[y][x].is_none()))
+        .unwrap_or(grid.height())
+}
+
+fn play_sfx(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    gravity: Res<Gravity>,
+    assets: Res<SfxAssets>,
+    sources: Res<Assets<AudioSource>>,
+    mut sfx: EventReader<SfxEvent>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    for event in sfx.read() {
+        let SfxEvent::Placement { combo, row } = *event;
+        // Sounds that failed to load would wait for their asset forever
+        if sources.contains(&assets.place) {
+            let speed = if settings.dynamic_audio {
+                combo_speed(combo)
+            } else {
+                1.
+            };
+            commands.spawn(AudioBundle {
+                source: assets.place.clone(),
+                settings: PlaybackSettings::DESPAWN.with_speed(speed),
+            });
+        }
+        if !settings.dynamic_audio || !sources.contains(&assets.thud) {
+            continue;
+        }
+        let (floor, height) = match q_board.get_single() {
+            Ok(board) if gravity.0 => (fill_frontier(board), board.grid.height()),
+            Ok(board) => (0, board.grid.height()),
+            _ => (0, BOARD_HEIGHT),
+        };
+        let nearness = 1. - row.saturating_sub(floor) as f32 / height as f32;
+        commands.spawn(AudioBundle {
+            source: assets.thud.clone(),
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new_relative(THUD_VOLUME * nearness * nearness)),
+        });
+    }
+}
@@ -0,0 +1,221 @@
+//! The greedy AI playing the main board in place of the player, for watching and
+//! tuning it. Turned on with `--autoplay` or F8.
+//!
+//! Its step mode, toggled with F9, pauses before every move with the top
+//! [`CANDIDATES`] placements on the overlay, the chosen one in green and the runners-up
+//! in yellow, each labeled with its rank and evaluation. N makes the move, as does
+//! waiting for the delay given with `--autoplay-step-delay <seconds>`.
+
+use bevy::prelude::*;
+
+use crate::{
+    ai::PlacementSearch,
+    board::TileColor,
+    budget::{FrameBudget, ScanBudget},
+    highlight::{HighlightLayer, Highlights},
+    menu::GameState,
+    render_order,
+    shape::{ShapeInstance, ShapeRegistry},
+    strings::Strings,
+    toast::spawn_toast,
+    BlocksSystems, Board, MainBoard, OverlayBoard, SelectedShape,
+};
+
+/// Placements the step mode shows.
+const CANDIDATES: usize = 5;
+/// Seconds between moves outside the step mode, to be able to follow them.
+const MOVE_INTERVAL: f32 = 0.4;
+const BEST_COLOR: Color = Color::rgba(0.2, 0.9, 0.3, 0.6);
+const CANDIDATE_COLOR: Color = Color::rgba(0.95, 0.85, 0.2, 0.45);
+const LABEL_FONT_SIZE: f32 = 16.;
+
+#[derive(Resource, Default)]
+pub struct Autoplay {
+    pub enabled: bool,
+    /// Whether to pause before every move, showing the candidates.
+    pub step: bool,
+    /// Seconds after which the step mode moves on by itself, `None` to wait for N.
+    pub step_delay: Option<f32>,
+    /// Board revision and shape the search is for.
+    key: Option<(u64, ShapeInstance)>,
+    search: PlacementSearch,
+    /// Seconds since the search finished.
+    waited: f32,
+    /// Whether the candidates are on screen.
+    shown: bool,
+    /// Board and anchor to place the selected shape at this frame.
+    chosen: Option<(Entity, (isize, isize))>,
+}
+impl Autoplay {
+    /// The move the AI decided on, for the placement system to make in place of a click.
+    pub fn take_move(&mut self) -> Option<(Entity, (isize, isize))> {
+        self.chosen.take()
+    }
+}
+
+/// Rank and evaluation of a shown candidate, floating over its cells.
+#[derive(Component)]
+struct CandidateLabel;
+
+pub struct AutoplayPlugin;
+impl Plugin for AutoplayPlugin {
+    fn build(&self, app: &mut App) {
+        let args: Vec<String> = std::env::args().collect();
+        let step_delay = args
+            .iter()
+            .position(|arg| arg == "--autoplay-step-delay")
+            .and_then(|position| args.get(position + 1)?.parse().ok());
+        app.insert_resource(Autoplay {
+            enabled: args.iter().any(|arg| arg == "--autoplay"),
+            step_delay,
+            ..default()
+        })
+        .add_systems(
+            Update,
+            (
+                toggle_autoplay.before(BlocksSystems::Preview),
+                think
+                    .after(BlocksSystems::Preview)
+                    .before(BlocksSystems::Placement)
+                    .run_if(in_state(GameState::Playing)),
+            ),
+        );
+    }
+}
+
+/// F8 turns the autoplay on and off, F9 its step mode.
+fn toggle_autoplay(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    strings: Res<Strings>,
+    mut autoplay: ResMut<Autoplay>,
+) {
+    if input_keys.just_pressed(KeyCode::F8) {
+        autoplay.enabled = !autoplay.enabled;
+        let key = if autoplay.enabled {
+            "autoplay.on"
+        } else {
+            "autoplay.off"
+        };
+        spawn_toast(&mut commands, strings.get(key));
+    }
+    if input_keys.just_pressed(KeyCode::F9) {
+        autoplay.step = !autoplay.step;
+        let key = if autoplay.step {
+            "autoplay.step_on"
+        } else {
+            "autoplay.step_off"
+        };
+        spawn_toast(&mut commands, strings.get(key));
+    }
+}
+
+/// Searches the placements of the selected shape within the [`ScanBudget`], then
+/// decides on the best once it's due.
+fn think(
+    mut commands: Commands,
+    time: Res<Time>,
+    input_keys: Res<Input<KeyCode>>,
+    registry: Res<ShapeRegistry>,
+    scan_budget: Res<ScanBudget>,
+    mut autoplay: ResMut<Autoplay>,
+    mut highlights: ResMut<Highlights>,
+    q_board: Query<(&Board, &GlobalTransform, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+    q_labels: Query<Entity, With<CandidateLabel>>,
+) {
+    let autoplay = &mut *autoplay;
+    let showing = autoplay.enabled && autoplay.step;
+    if autoplay.shown && !showing {
+        hide_candidates(&mut commands, autoplay, &mut highlights, &q_labels);
+    }
+    if !autoplay.enabled {
+        autoplay.key = None;
+        autoplay.chosen = None;
+        return;
+    }
+    let (Ok((board, transform, entity)), Ok(&shape)) =
+        (q_board.get_single(), q_selected_shape.get_single())
+    else {
+        return;
+    };
+    let key = (board.grid.revision(), shape);
+    if autoplay.key != Some(key) {
+        hide_candidates(&mut commands, autoplay, &mut highlights, &q_labels);
+        autoplay.key = Some(key);
+        autoplay.search = PlacementSearch::ranked(CANDIDATES);
+        autoplay.waited = 0.;
+        autoplay.chosen = None;
+    }
+    let resolved = shape.resolve(&registry);
+    let budget = FrameBudget::new(scan_budget.0);
+    // Nothing fitting ends the game on its own
+    let Some(Some((anchor, _))) = autoplay.search.resume(&board.grid, resolved, &budget) else {
+        return;
+    };
+    autoplay.waited += time.delta_seconds();
+
+    let due = if showing {
+        if !autoplay.shown {
+            autoplay.shown = true;
+            // The runners-up first, for the chosen placement to cover them
+            for (rank, &(anchor, score)) in autoplay.search.ranked_so_far().iter().enumerate().rev()
+            {
+                let mut grid = board.grid;
+                let cells = grid
+                    .try_place(resolved, anchor, TileColor::Gray)
+                    .unwrap_or_default();
+                let color = if rank == 0 {
+                    BEST_COLOR
+                } else {
+                    CANDIDATE_COLOR
+                };
+                for &cell in cells.iter() {
+                    highlights.set(HighlightLayer::Autoplay, cell, color);
+                }
+                let center = cells
+                    .iter()
+                    .map(|&cell| board.cell_to_world(transform, cell))
+                    .sum::<Vec2>()
+                    / cells.len().max(1) as f32;
+                commands.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            format!("#{} {score:.0}", rank + 1),
+                            TextStyle {
+                                font_size: LABEL_FONT_SIZE,
+                                color: color.with_a(1.),
+                                ..default()
+                            },
+                        ),
+                        transform: Transform::from_translation(center.extend(render_order::POPUPS)),
+                        ..default()
+                    },
+                    CandidateLabel,
+                ));
+            }
+        }
+        input_keys.just_pressed(KeyCode::N)
+            || autoplay
+                .step_delay
+                .is_some_and(|delay| autoplay.waited >= delay)
+    } else {
+        autoplay.waited >= MOVE_INTERVAL
+    };
+    if due {
+        autoplay.chosen = Some((entity, anchor));
+    }
+}
+
+fn hide_candidates(
+    commands: &mut Commands,
+    autoplay: &mut Autoplay,
+    highlights: &mut Highlights,
+    q_labels: &Query<Entity, With<CandidateLabel>>,
+) {
+    highlights.clear(HighlightLayer::Autoplay);
+    for entity in q_labels.iter() {
+        commands.entity(entity).despawn();
+    }
+    autoplay.shown = false;
+}
@@ -3,9 +3,17 @@ use rand::{
     distributions::{Distribution, Standard},
     Rng,
 };
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use smallvec::SmallVec;
 use std::fmt;
 
-#[derive(PartialEq, Eq, Clone, Copy, Default)]
+use crate::shape::Shape;
+
+/// Cells of a board, inline for up to a shape's worth so placements, and the events and
+/// commands about them, don't allocate.
+pub type CellList = SmallVec<[(usize, usize); 64]>;
+
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub enum TileColor {
     #[default]
     Gray,
@@ -13,6 +21,84 @@ pub enum TileColor {
     Green,
     Blue,
     Transparent,
+    /// Obstacle cell, downgraded to [`TileColor::Cracked`] by the first clear.
+    Stone,
+    /// Obstacle cell removed by the next clear.
+    Cracked,
+    /// Entry of the [`crate::palette::ColorPalette`] added on top of the built-in colors.
+    Custom(CustomColor),
+}
+/// Custom colors are written as lowercase letters, limiting their number.
+pub const MAX_CUSTOM_COLORS: u8 = 26;
+
+/// Index of a custom color, below [`MAX_CUSTOM_COLORS`] so it has a letter.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub struct CustomColor(u8);
+impl CustomColor {
+    /// `None` past the last custom color.
+    pub fn new(index: u8) -> Option<Self> {
+        (index < MAX_CUSTOM_COLORS).then_some(Self(index))
+    }
+
+    pub fn index(self) -> u8 {
+        self.0
+    }
+}
+impl From<CustomColor> for u8 {
+    fn from(color: CustomColor) -> Self {
+        color.0
+    }
+}
+impl TryFrom<u8> for CustomColor {
+    type Error = String;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        Self::new(index).ok_or_else(|| format!("no custom color {index}"))
+    }
+}
+impl TileColor {
+    /// Single character naming the color in text formats.
+    pub fn to_char(self) -> char {
+        match self {
+            TileColor::Gray => '#',
+            TileColor::Red => 'R',
+            TileColor::Green => 'G',
+            TileColor::Blue => 'B',
+            TileColor::Transparent => '.',
+            TileColor::Stone => 'S',
+            TileColor::Cracked => 'C',
+            TileColor::Custom(custom) => (b'a' + custom.0) as char,
+        }
+    }
+
+    /// Whether a cell can hold the color. [`TileColor::Transparent`] can't, such a cell
+    /// would block placements without showing.
+    pub fn is_placeable(self) -> bool {
+        self != TileColor::Transparent
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '#' => Some(TileColor::Gray),
+            'R' => Some(TileColor::Red),
+            'G' => Some(TileColor::Green),
+            'B' => Some(TileColor::Blue),
+            '.' => Some(TileColor::Transparent),
+            'S' => Some(TileColor::Stone),
+            'C' => Some(TileColor::Cracked),
+            'a'..='z' => Some(TileColor::Custom(CustomColor(c as u8 - b'a'))),
+            _ => None,
+        }
+    }
+
+    /// What is left of the cell once a line containing it cleared.
+    pub fn after_clear(self) -> Option<Self> {
+        match self {
+            TileColor::Stone => Some(TileColor::Cracked),
+            _ => None,
+        }
+    }
 }
 impl Distribution<TileColor> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> TileColor {
@@ -32,156 +118,105 @@ impl From<TileColor> for Color {
             TileColor::Blue => Color::rgb(0.0, 0.0, 1.0),
             TileColor::Gray => Color::rgb(0.3, 0.3, 0.3),
             TileColor::Transparent => Color::rgba(0.0, 0.0, 0.0, 0.0),
+            TileColor::Stone => Color::rgb(0.1, 0.1, 0.1),
+            TileColor::Cracked => Color::rgb(0.18, 0.18, 0.18),
+            // Only the palette knows, see `ColorPalette::color`
+            TileColor::Custom(_) => Color::FUCHSIA,
         }
     }
 }
-impl TileColor {
-    const DEFAULT: TileColor = TileColor::Gray;
-}
-
-#[derive(PartialEq, Eq, Clone, Copy, Component)]
-pub struct Shape {
-    pub color: TileColor,
-    pub fields: [[bool; 8]; 8],
-}
-impl Shape {
-    pub fn bounds(&self) -> (usize, usize) {
-        self.fields
-            .iter()
-            .enumerate()
-            .fold((0, 0), |acc, (i, row)| {
-                let max_x_in_row = row.iter().enumerate().fold(0, |max_x, (j, &val)| {
-                    if val {
-                        usize::max(max_x, j + 1)
-                    } else {
-                        max_x
-                    }
-                });
-                (
-                    usize::max(acc.0, max_x_in_row),
-                    usize::max(acc.1, if max_x_in_row > 0 { i + 1 } else { acc.1 }),
-                )
-            })
-    }
-    pub fn rotate_90(&self) -> Shape {
-        let mut new_fields = [[false; 8]; 8];
-        let (width, height) = self.bounds();
+pub struct Grid<T, const W: usize, const H: usize>(pub [[T; W]; H]);
 
-        for i in 0..height {
-            for j in 0..width {
-                new_fields[j][height - i - 1] = self.fields[i][j];
-            }
-        }
+/// Most cells across a board, the size boards start at.
+pub const BOARD_WIDTH: usize = 20;
+/// Most cells up a board, the size boards start at.
+pub const BOARD_HEIGHT: usize = 20;
+/// Fewest cells across or up a board.
+pub const MIN_BOARD_SIZE: usize = 4;
+/// Most a single clear is multiplied by, however many bonus cells it removes.
+pub const MAX_BONUS_MULTIPLIER: u32 = 16;
 
-        Shape {
-            fields: new_fields,
-            ..*self
-        }
+/// Extra state of a cell, following its content through clears and gravity.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub struct CellMeta {
+    /// Multiplies the points of the clear removing the cell, 0 for none.
+    pub bonus: u8,
+    /// The cell can neither be placed on nor cleared before this turn.
+    pub frozen_until: u32,
+    /// Goal of a puzzle, to be cleared.
+    pub target: bool,
+    /// Part of a garbage row, see [`Board::insert_garbage_rows`].
+    pub garbage: bool,
+}
+impl CellMeta {
+    pub fn is_frozen(&self, turn: u32) -> bool {
+        turn < self.frozen_until
     }
 
-    pub fn equivalents(&self) -> Vec<Shape> {
-        let mut shapes = vec![*self];
-        let rot90 = self.rotate_90();
-        if !shapes.contains(&rot90) {
-            shapes.push(rot90);
-        }
-        let rot180 = rot90.rotate_90();
-        if !shapes.contains(&rot180) {
-            shapes.push(rot180);
-        }
-        let rot270 = rot180.rotate_90();
-        if !shapes.contains(&rot270) {
-            shapes.push(rot270);
-        }
-        shapes
+    /// Placements left until the cell thaws.
+    pub fn frozen_for(&self, turn: u32) -> u32 {
+        self.frozen_until.saturating_sub(turn)
     }
+}
 
-    pub fn from_pattern(w: usize, h: usize, pat: &str) -> Self {
-        if pat.len() != w * h {
-            panic!("Pattern length does not match given dimensions");
-        }
-
-        let mut fields = [[false; 8]; 8];
-        for (i, c) in pat.chars().enumerate() {
-            let x = i % w; // x-coordinate (column)
-            let y = i / w; // y-coordinate (row)
+/// The board has no room left for what was asked of it.
+#[derive(Debug)]
+pub struct BoardFull;
 
-            // Ensure we don't go out of bounds if the shape's w or h is greater than 8
-            if x >= 8 || y >= 8 {
-                panic!("Pattern dimensions out of bounds");
-            }
+/// Only square boards can be turned onto themselves.
+#[derive(Debug)]
+pub struct NotSquare;
 
-            fields[y][x] = match c {
-                '#' => true,
-                '.' => false,
-                _ => panic!("Invalid character in pattern: {}", c),
-            };
-        }
+/// Boards are between [`MIN_BOARD_SIZE`] and [`BOARD_WIDTH`] by [`BOARD_HEIGHT`] cells.
+#[derive(Debug)]
+pub struct UnsupportedSize;
 
-        // Initialize with a default color for this demonstration; adjust as needed
-        Self {
-            color: TileColor::DEFAULT,
-            fields,
-        }
-    }
+/// The cells of a board, the bottom left `width` by `height` of which are played on.
+/// Those beyond stay empty and can't be placed on.
+#[derive(Clone, Copy)]
+pub struct Board {
+    pub cells: Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>,
+    pub meta: Grid<CellMeta, BOARD_WIDTH, BOARD_HEIGHT>,
+    /// Bumped every time a cell changes, lets consumers cache work per board state.
+    revision: u64,
+    /// Number of shapes placed so far.
+    turn: u32,
+    width: usize,
+    height: usize,
+    /// Side of the square regions clearing once full, 0 for none.
+    region_size: usize,
 }
-
-impl fmt::Display for Shape {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let (width, height) = self.bounds();
-
-        for i in 0..height {
-            for j in 0..width {
-                // Write '#' for true and '.' for false
-                let c = if self.fields[i][j] { '#' } else { '.' };
-                write!(f, "{}", c)?;
-            }
-            // After each row except the last one, add a newline
-            if i < height - 1 {
-                write!(f, "\n")?;
-            }
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            cells: Grid::default(),
+            meta: Grid::default(),
+            revision: 0,
+            turn: 0,
+            width: BOARD_WIDTH,
+            height: BOARD_HEIGHT,
+            region_size: 0,
         }
-
-        Ok(())
     }
 }
 
-#[macro_export]
-macro_rules! shapes {
-    // Match one or more shape definitions, separated by semicolons
-    ($(($x:expr, $y:expr) $pattern:literal);+ $(;)?) => {
-        {
-            // Create a mutable vector to hold the generated shapes
-            let mut temp = Vec::new();
-
-            // Process each shape definition
-            $(
-                // Extend the temp vector with the generated shapes from the provided patterns
-                temp.extend(crate::board::Shape::from_pattern($x, $y, $pattern).equivalents());
-            )+
-
-            // Return the filled temp vector
-            temp
-        }
-    };
-}
-
-pub struct Grid<T, const W: usize, const H: usize>(pub [[T; W]; H]);
-
-pub const BOARD_WIDTH: usize = 20;
-pub const BOARD_HEIGHT: usize = 20;
-pub type Board = Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>;
-
+/// Rows from the bottom one up, `#` for filled cells and `.` for empty ones. The
+/// alternate form `{:#}` writes each cell's [`TileColor::to_char`] instead, which
+/// [`Board::from_text`] reads back.
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..BOARD_HEIGHT {
-            for j in 0..BOARD_WIDTH {
-                // Write '#' for true and '.' for false
-                write!(f, "{}", if self.0[i][j].is_some() { '#' } else { '.' })?;
+        for i in 0..self.height {
+            for j in 0..self.width {
+                let c = match self.cells.0[i][j] {
+                    Some(color) if f.alternate() => color.to_char(),
+                    Some(_) => '#',
+                    None => '.',
+                };
+                write!(f, "{}", c)?;
             }
             // After each row except the last one, add a newline
-            if i < BOARD_HEIGHT - 1 {
-                write!(f, "\n")?;
+            if i < self.height - 1 {
+                writeln!(f)?;
             }
         }
 
@@ -200,6 +235,50 @@ impl<T: Clone, const W: usize, const H: usize> Clone for Grid<T, W, H> {
     }
 }
 impl<T: Copy, const W: usize, const H: usize> Copy for Grid<T, W, H> {}
+impl<T: PartialEq, const W: usize, const H: usize> PartialEq for Grid<T, W, H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+/// A sequence of `H` rows of `W` cells each.
+impl<T: Serialize, const W: usize, const H: usize> Serialize for Grid<T, W, H> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(|row| row.as_slice()))
+    }
+}
+impl<'de, T, const W: usize, const H: usize> Deserialize<'de> for Grid<T, W, H>
+where
+    T: Deserialize<'de> + Default + Copy,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rows = Vec::<Vec<T>>::deserialize(deserializer)?;
+        if rows.len() != H || rows.iter().any(|row| row.len() != W) {
+            return Err(de::Error::custom(format!("expected {H} rows of {W} cells")));
+        }
+        let mut grid = Self::default();
+        for (row, cells) in grid.0.iter_mut().zip(rows) {
+            row.copy_from_slice(&cells);
+        }
+        Ok(grid)
+    }
+}
+
+/// A board's cells and metadata as of a revision, to compare boards against or send
+/// them elsewhere, see [`Board::diff`].
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct BoardSnapshot {
+    pub cells: Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>,
+    pub meta: Grid<CellMeta, BOARD_WIDTH, BOARD_HEIGHT>,
+    pub revision: u64,
+}
+
+/// A cell's content and metadata on the newer of two boards.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CellDelta {
+    pub cell: (usize, usize),
+    pub content: Option<TileColor>,
+    pub meta: CellMeta,
+}
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum SuperimpositionState {
@@ -208,20 +287,484 @@ pub enum SuperimpositionState {
     Blank,
 }
 
-#[derive(Clone, Copy)]
+/// Where a shape lands on the board. Only the cells it covers are kept, a shape has
+/// at most 64 of them.
+#[derive(Clone)]
 pub struct Superimposition {
-    pub fields: Grid<SuperimpositionState, BOARD_WIDTH, BOARD_HEIGHT>,
+    /// `(x, y, state)` of each board cell the shape covers, never
+    /// [`SuperimpositionState::Blank`]. Fields off the board are left out.
+    pub cells: SmallVec<[(u8, u8, SuperimpositionState); 64]>,
+    /// Board position of the shape's top-left field, may lie outside the board.
+    pub anchor: (isize, isize),
     pub success: bool,
 }
+impl Superimposition {
+    /// The covered cells as a board-sized grid, [`SuperimpositionState::Blank`] elsewhere.
+    pub fn to_grid(&self) -> Grid<SuperimpositionState, BOARD_WIDTH, BOARD_HEIGHT> {
+        let mut grid = Grid([[SuperimpositionState::Blank; BOARD_WIDTH]; BOARD_HEIGHT]);
+        for &(x, y, state) in self.cells.iter() {
+            grid.0[y as usize][x as usize] = state;
+        }
+        grid
+    }
+}
+
+/// One line per row, row 0 first like [`Board`]'s: `.` for blank fields, `F` for fields
+/// the shape fits into and `X` for fields it intersects, then the anchor and outcome.
+impl fmt::Display for Superimposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.to_grid().0.iter() {
+            for state in row {
+                let c = match state {
+                    SuperimpositionState::Blank => '.',
+                    SuperimpositionState::Fits => 'F',
+                    SuperimpositionState::Intersects => 'X',
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "anchor {:?} success {}", self.anchor, self.success)
+    }
+}
+impl fmt::Debug for Superimposition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Rows and columns that are completely filled.
+#[derive(Clone, Default)]
+pub struct ClearedLines {
+    pub rows: SmallVec<[usize; BOARD_HEIGHT]>,
+    pub columns: SmallVec<[usize; BOARD_WIDTH]>,
+    /// Bottom left cells of the square regions that are completely filled, see
+    /// [`Board::set_region_size`].
+    pub regions: SmallVec<[(usize, usize); 4]>,
+    /// Side of the regions.
+    pub region_size: usize,
+    /// Cracked obstacles removed by the clear.
+    pub destroyed_obstacles: usize,
+    /// Product of the bonus multipliers of the removed cells, 1 without any and at most
+    /// [`MAX_BONUS_MULTIPLIER`].
+    pub multiplier: u32,
+    /// Cells with a bonus multiplier removed by the clear.
+    pub bonus_cells: usize,
+    /// Puzzle target cells removed by the clear.
+    pub targets_cleared: usize,
+    /// Width and height of the board the lines run across.
+    pub size: (usize, usize),
+}
+impl ClearedLines {
+    /// Rows, columns and regions cleared, each counting as a line.
+    pub fn len(&self) -> usize {
+        self.rows.len() + self.columns.len() + self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every cell covered by the lines and regions, cells where they overlap only once.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (width, height) = self.size;
+        let row_cells = self
+            .rows
+            .iter()
+            .flat_map(move |&y| (0..width).map(move |x| (x, y)));
+        let column_cells = self.columns.iter().flat_map(move |&x| {
+            (0..height)
+                .filter(|y| !self.rows.contains(y))
+                .map(move |y| (x, y))
+        });
+        let side = self.region_size;
+        let region_cells = self
+            .regions
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, &(x0, y0))| {
+                (y0..y0 + side)
+                    .flat_map(move |y| (x0..x0 + side).map(move |x| (x, y)))
+                    .filter(move |&(x, y)| {
+                        // Left to the lines and the regions listed before
+                        let in_earlier = self.regions[..i].iter().any(|&(rx, ry)| {
+                            (rx..rx + side).contains(&x) && (ry..ry + side).contains(&y)
+                        });
+                        !self.rows.contains(&y) && !self.columns.contains(&x) && !in_earlier
+                    })
+            });
+        row_cells.chain(column_cells).chain(region_cells)
+    }
+}
+
+/// Whether boards can be `width` by `height` cells, see [`Board::resize`].
+pub fn size_supported(width: usize, height: usize) -> bool {
+    (MIN_BOARD_SIZE..=BOARD_WIDTH).contains(&width)
+        && (MIN_BOARD_SIZE..=BOARD_HEIGHT).contains(&height)
+}
+
+/// A supported board size written as `<width>x<height>`, like `20x20`.
+pub fn parse_size(text: &str) -> Option<(usize, usize)> {
+    let (width, height) = text.split_once('x')?;
+    let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+    size_supported(width, height).then_some((width, height))
+}
+
+/// Reads [`BOARD_HEIGHT`] rows of [`TileColor::to_char`] characters off `lines`, `.`
+/// for empty cells. `None` if a row is missing, of the wrong length or holds an unknown
+/// character.
+pub fn parse_cells<'a>(
+    mut lines: impl Iterator<Item = &'a str>,
+) -> Option<Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>> {
+    let mut cells = Grid::default();
+    for row in cells.0.iter_mut() {
+        let line = lines.next()?;
+        if line.chars().count() != BOARD_WIDTH {
+            return None;
+        }
+        for (cell, c) in row.iter_mut().zip(line.chars()) {
+            *cell = match c {
+                '.' => None,
+                c => Some(TileColor::from_char(c)?),
+            };
+        }
+    }
+    Some(cells)
+}
 
 impl Board {
-    pub fn superimpose(&self, shape: &Shape, translation: (f32, f32)) -> Superimposition {
+    /// Parses either form written by `Display`, the board taking the size of the rows.
+    /// The plain form's `#` reads as [`TileColor::Gray`], so only the alternate form
+    /// keeps the colors.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let rows: Vec<&str> = text
+            .lines()
+            .take_while(|line| !line.trim().is_empty())
+            .collect();
+        if text
+            .lines()
+            .skip(rows.len())
+            .any(|line| !line.trim().is_empty())
+        {
+            return None;
+        }
+        let width = rows.first()?.chars().count();
+        if !size_supported(width, rows.len()) {
+            return None;
+        }
+        let mut board = Self {
+            width,
+            height: rows.len(),
+            ..default()
+        };
+        for (row, line) in board.cells.0.iter_mut().zip(&rows) {
+            if line.chars().count() != width {
+                return None;
+            }
+            for (cell, c) in row.iter_mut().zip(line.chars()) {
+                *cell = match c {
+                    '.' => None,
+                    c => Some(TileColor::from_char(c)?),
+                };
+            }
+        }
+        Some(board)
+    }
+
+    /// An empty board of the given size, see [`Board::resize`].
+    pub fn with_size(width: usize, height: usize) -> Result<Self, UnsupportedSize> {
+        let mut board = Self::default();
+        board.resize(width, height)?;
+        Ok(board)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the cell is within the played width and height.
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// Every cell played on, rows then columns.
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width, self.height);
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    /// Empties the board and plays on its bottom left `width` by `height` cells from
+    /// now on. Fails without touching the board for sizes out of
+    /// [`MIN_BOARD_SIZE`]..=[`BOARD_WIDTH`] by [`BOARD_HEIGHT`].
+    pub fn resize(&mut self, width: usize, height: usize) -> Result<(), UnsupportedSize> {
+        if !size_supported(width, height) {
+            return Err(UnsupportedSize);
+        }
+        self.cells = Grid::default();
+        self.meta = Grid::default();
+        self.width = width;
+        self.height = height;
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Side of the square regions clearing once full, 0 for none.
+    pub fn region_size(&self) -> usize {
+        self.region_size
+    }
+
+    /// Has square regions of `size` cells a side clear once full, alongside rows and
+    /// columns, 0 for none. Regions are laid out from cell `(0, 0)`, those running past
+    /// the board's edge never clear.
+    pub fn set_region_size(&mut self, size: usize) {
+        self.region_size = size;
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Freezes the filled cells among the given ones for the next `placements` turns,
+    /// they neither clear nor can be placed on meanwhile.
+    pub fn freeze(&mut self, cells: impl IntoIterator<Item = (usize, usize)>, placements: u32) {
+        for (x, y) in cells {
+            if self.contains(x, y) && self.cells.0[y][x].is_some() {
+                self.meta.0[y][x].frozen_until = self.turn + placements;
+            }
+        }
+        self.revision += 1;
+    }
+
+    /// Sets the metadata of the given cells, keeping their content.
+    pub fn set_meta(&mut self, cells: impl IntoIterator<Item = (usize, usize)>, meta: CellMeta) {
+        for (x, y) in cells {
+            if self.contains(x, y) {
+                self.meta.0[y][x] = meta;
+            }
+        }
+        self.revision += 1;
+    }
+
+    /// Sets the given cells, e.g. for garbage or editing. Colors a cell can't hold empty
+    /// the cells instead, see [`TileColor::is_placeable`]. Cells beyond the board's size
+    /// are left out.
+    pub fn fill_cells(
+        &mut self,
+        cells: impl IntoIterator<Item = (usize, usize)>,
+        value: Option<TileColor>,
+    ) {
+        let value = value.filter(|color| color.is_placeable());
+        for (x, y) in cells {
+            if self.contains(x, y) {
+                self.cells.0[y][x] = value;
+            }
+        }
+        self.revision += 1;
+    }
+
+    /// Pushes every row up by `count` and fills the new bottom rows with gray garbage,
+    /// leaving the cell at `gap(row)` of each empty. The garbage is tagged in its
+    /// [`CellMeta`]. Fails without touching the board if a filled cell would be pushed
+    /// off the top.
+    pub fn insert_garbage_rows(
+        &mut self,
+        count: usize,
+        mut gap: impl FnMut(usize) -> usize,
+    ) -> Result<(), BoardFull> {
+        let (width, height) = (self.width, self.height);
+        let count = count.min(height);
+        let pushed_off = &self.cells.0[height - count..height];
+        if pushed_off.iter().flatten().any(Option::is_some) {
+            return Err(BoardFull);
+        }
+        self.cells.0[..height].rotate_right(count);
+        self.meta.0[..height].rotate_right(count);
+        for (y, row) in self.cells.0[..count].iter_mut().enumerate() {
+            let gap = gap(y) % width;
+            for (x, cell) in row[..width].iter_mut().enumerate() {
+                *cell = (x != gap).then_some(TileColor::Gray);
+            }
+        }
+        for (cells, meta) in self.cells.0[..count].iter().zip(&mut self.meta.0[..count]) {
+            for (cell, meta) in cells.iter().zip(meta.iter_mut()) {
+                *meta = CellMeta {
+                    garbage: cell.is_some(),
+                    ..default()
+                };
+            }
+        }
+        self.revision += 1;
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> BoardSnapshot {
+        BoardSnapshot {
+            cells: self.cells,
+            meta: self.meta,
+            revision: self.revision,
+        }
+    }
+
+    /// Every cell differing from the snapshot, in content or metadata, as it is on this
+    /// board. Applying them to a board matching the snapshot makes it match this one,
+    /// see [`Board::apply_deltas`].
+    pub fn diff(&self, since: &BoardSnapshot) -> Vec<CellDelta> {
+        let mut deltas = Vec::new();
+        for y in 0..BOARD_HEIGHT {
+            for x in 0..BOARD_WIDTH {
+                let (content, meta) = (self.cells.0[y][x], self.meta.0[y][x]);
+                if content != since.cells.0[y][x] || meta != since.meta.0[y][x] {
+                    deltas.push(CellDelta {
+                        cell: (x, y),
+                        content,
+                        meta,
+                    });
+                }
+            }
+        }
+        deltas
+    }
+
+    /// Sets the cells of the deltas to their content and metadata, see [`Board::diff`].
+    /// Deltas beyond the board's size are left out, as are those with a color no cell
+    /// can hold.
+    pub fn apply_deltas(&mut self, deltas: &[CellDelta]) {
+        for delta in deltas {
+            let (x, y) = delta.cell;
+            if !self.contains(x, y) || delta.content.is_some_and(|color| !color.is_placeable()) {
+                continue;
+            }
+            self.cells.0[y][x] = delta.content;
+            self.meta.0[y][x] = delta.meta;
+        }
+        if !deltas.is_empty() {
+            self.revision += 1;
+        }
+    }
+
+    /// Mirrors the board left to right, the metadata along with the cells.
+    pub fn mirror_horizontal(&mut self) {
+        let width = self.width;
+        for (cells, meta) in self.cells.0.iter_mut().zip(self.meta.0.iter_mut()) {
+            cells[..width].reverse();
+            meta[..width].reverse();
+        }
+        self.revision += 1;
+    }
+
+    /// Mirrors the board top to bottom, the metadata along with the cells.
+    pub fn mirror_vertical(&mut self) {
+        self.cells.0[..self.height].reverse();
+        self.meta.0[..self.height].reverse();
+        self.revision += 1;
+    }
+
+    /// Turns the board a quarter clockwise, the metadata along with the cells. Fails
+    /// without touching the board unless it's square.
+    pub fn rotate_90(&mut self) -> Result<(), NotSquare> {
+        if self.width != self.height {
+            return Err(NotSquare);
+        }
+        let (cells, meta) = (self.cells.0, self.meta.0);
+        for (x, y) in self.positions() {
+            // Rows count from the bottom, the left column ends up on top
+            let (from_x, from_y) = (self.width - 1 - y, x);
+            self.cells.0[y][x] = cells[from_y][from_x];
+            self.meta.0[y][x] = meta[from_y][from_x];
+        }
+        self.revision += 1;
+        Ok(())
+    }
+
+    /// Replaces every cell at once, e.g. when loading a save. Cells beyond the board's
+    /// size are left empty.
+    pub fn set_cells(&mut self, cells: Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>) {
+        self.cells = cells;
+        for (y, row) in self.cells.0.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if x >= self.width || y >= self.height {
+                    *cell = None;
+                }
+            }
+        }
+        self.meta = Grid::default();
+        self.sanitize();
+        self.revision += 1;
+    }
+
+    /// Empties the cells holding a color they can't, see [`TileColor::is_placeable`],
+    /// returning how many there were.
+    pub fn sanitize(&mut self) -> usize {
+        let mut emptied = 0;
+        for cell in self.cells.0.iter_mut().flatten() {
+            if cell.is_some_and(|color| !color.is_placeable()) {
+                *cell = None;
+                emptied += 1;
+            }
+        }
+        if emptied > 0 {
+            self.revision += 1;
+        }
+        emptied
+    }
+
+    /// Rows still holding garbage from [`Board::insert_garbage_rows`].
+    pub fn garbage_rows(&self) -> usize {
+        self.meta
+            .0
+            .iter()
+            .zip(&self.cells.0)
+            .filter(|(meta, cells)| {
+                meta.iter()
+                    .zip(cells.iter())
+                    .any(|(meta, cell)| meta.garbage && cell.is_some())
+            })
+            .count()
+    }
+
+    /// Cells that can't be placed on, frozen cells and those beyond the board's size
+    /// included.
+    pub fn bitboard(&self) -> Bitboard {
+        let mut rows = [0; BOARD_HEIGHT];
+        for (y, mask) in rows.iter_mut().enumerate() {
+            for x in 0..BOARD_WIDTH {
+                if self.is_blocked(x, y) {
+                    *mask |= 1 << x;
+                }
+            }
+        }
+        Bitboard { rows }
+    }
+
+    /// Filled cells within the board's size, unlike [`Board::bitboard`] leaving frozen
+    /// cells and those beyond the size out.
+    pub fn filled_bitboard(&self) -> Bitboard {
+        let mut rows = [0; BOARD_HEIGHT];
+        for (x, y) in self.positions() {
+            if self.cells.0[y][x].is_some() {
+                rows[y] |= 1 << x;
+            }
+        }
+        Bitboard { rows }
+    }
+
+    /// Anchor centering the shape on `translation`, the cursor position relative to
+    /// the board's extents.
+    pub fn anchor_at(&self, shape: &Shape, translation: (f32, f32)) -> (isize, isize) {
         let shape_bounds = shape.bounds();
         let shape_center = (shape_bounds.0 as f32 * 0.5, shape_bounds.1 as f32 * 0.5);
 
         let cursor_center = (
-            ((self.0[0].len()) as f32) * translation.0,
-            ((self.0.len()) as f32) * translation.1,
+            self.width as f32 * translation.0,
+            self.height as f32 * translation.1,
         );
 
         let shape_offset_to_board = (
@@ -229,38 +772,674 @@ impl Board {
             cursor_center.1 - shape_center.1,
         );
 
-        let mut superimposition = Grid::<SuperimpositionState, BOARD_HEIGHT, BOARD_WIDTH>(
-            [[SuperimpositionState::Blank; BOARD_WIDTH]; BOARD_HEIGHT],
-        );
+        (
+            shape_offset_to_board.0.round() as isize,
+            shape_offset_to_board.1.round() as isize,
+        )
+    }
+
+    pub fn superimpose_at(&self, shape: &Shape, anchor: (isize, isize)) -> Superimposition {
+        let mut cells = SmallVec::new();
         let mut success = true;
 
         for (y, row) in shape.fields.iter().enumerate() {
             for (x, &cell) in row.iter().enumerate() {
                 if cell {
-                    let board_x = (x as f32 + shape_offset_to_board.0).round() as isize;
-                    let board_y = (y as f32 + shape_offset_to_board.1).round() as isize;
+                    let board_x = x as isize + anchor.0;
+                    let board_y = y as isize + anchor.1;
 
                     if board_x < 0
-                        || board_x >= BOARD_WIDTH as isize
+                        || board_x >= self.width as isize
                         || board_y < 0
-                        || board_y >= BOARD_HEIGHT as isize
+                        || board_y >= self.height as isize
                     {
                         success = false;
-                    } else if let Some(_color) = self.0[board_y as usize][board_x as usize] {
-                        superimposition.0[board_y as usize][board_x as usize] =
-                            SuperimpositionState::Intersects;
+                    } else if self.is_blocked(board_x as usize, board_y as usize) {
+                        cells.push((
+                            board_x as u8,
+                            board_y as u8,
+                            SuperimpositionState::Intersects,
+                        ));
                         success = false;
                     } else {
-                        superimposition.0[board_y as usize][board_x as usize] =
-                            SuperimpositionState::Fits;
+                        cells.push((board_x as u8, board_y as u8, SuperimpositionState::Fits));
                     }
                 }
             }
         }
 
         Superimposition {
-            fields: superimposition,
+            cells,
+            anchor,
             success,
         }
     }
+
+    fn is_blocked(&self, x: usize, y: usize) -> bool {
+        !self.contains(x, y)
+            || self.cells.0[y][x].is_some()
+            || self.meta.0[y][x].is_frozen(self.turn)
+    }
+
+    /// First anchor, scanning rows then columns, at which the shape fits.
+    pub fn find_placement(&self, shape: &Shape) -> Option<(isize, isize)> {
+        let bitboard = self.bitboard();
+        let shape_rows = shape.row_masks();
+        anchors(shape)
+            .find(|&anchor| bitboard.fits_at(&shape_rows, anchor))
+            .map(|(x, y)| (x as isize, y as isize))
+    }
+
+    /// Places the shape with its top-left field at `anchor`, returning the filled cells,
+    /// or `None` without touching the board if the shape does not fit there.
+    pub fn try_place(
+        &mut self,
+        shape: &Shape,
+        anchor: (isize, isize),
+        color: TileColor,
+    ) -> Option<CellList> {
+        let superimposition = self.superimpose_at(shape, anchor);
+        if !superimposition.success || !color.is_placeable() {
+            return None;
+        }
+        let mut placed = CellList::new();
+        for &(x, y, _) in superimposition.cells.iter() {
+            let (x, y) = (x as usize, y as usize);
+            self.cells.0[y][x] = Some(color);
+            placed.push((x, y));
+        }
+        self.revision += 1;
+        self.turn += 1;
+        Some(placed)
+    }
+
+    pub fn full_lines(&self) -> ClearedLines {
+        let (width, height) = (self.width, self.height);
+        ClearedLines {
+            rows: (0..height)
+                .filter(|&y| self.cells.0[y][..width].iter().all(Option::is_some))
+                .collect(),
+            columns: (0..width)
+                .filter(|&x| self.cells.0[..height].iter().all(|row| row[x].is_some()))
+                .collect(),
+            regions: self.full_regions().collect(),
+            region_size: self.region_size,
+            destroyed_obstacles: 0,
+            multiplier: 1,
+            bonus_cells: 0,
+            targets_cleared: 0,
+            size: (width, height),
+        }
+    }
+
+    /// Bottom left cells of the regions that are completely filled.
+    fn full_regions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let side = self.region_size;
+        let (columns, rows) = match side {
+            0 => (0, 0),
+            side => (self.width / side, self.height / side),
+        };
+        (0..rows)
+            .flat_map(move |ry| (0..columns).map(move |rx| (rx * side, ry * side)))
+            .filter(move |&(x0, y0)| {
+                self.cells.0[y0..y0 + side]
+                    .iter()
+                    .all(|row| row[x0..x0 + side].iter().all(Option::is_some))
+            })
+    }
+
+    /// Empties every full row, column and region at once, so a cell shared by a full row
+    /// and column counts towards both. Obstacles count as filled but only crack on their
+    /// first clear, frozen cells stay in place and emptied cells lose their metadata.
+    pub fn clear_full_lines(&mut self) -> ClearedLines {
+        let mut lines = self.full_lines();
+        let (mut destroyed, mut multiplier, mut targets, mut bonus_cells) = (0, 1, 0, 0);
+        for (x, y) in lines.cells() {
+            let (cell, meta) = (&mut self.cells.0[y][x], &mut self.meta.0[y][x]);
+            if meta.is_frozen(self.turn) {
+                continue;
+            }
+            if *cell == Some(TileColor::Cracked) {
+                destroyed += 1;
+            }
+            *cell = cell.and_then(TileColor::after_clear);
+            if cell.is_none() {
+                multiplier = (multiplier * meta.bonus.max(1) as u32).min(MAX_BONUS_MULTIPLIER);
+                bonus_cells += (meta.bonus > 1) as usize;
+                targets += meta.target as usize;
+                *meta = CellMeta::default();
+            }
+        }
+        lines.destroyed_obstacles = destroyed;
+        lines.multiplier = multiplier;
+        lines.targets_cleared = targets;
+        lines.bonus_cells = bonus_cells;
+        if !lines.is_empty() {
+            self.revision += 1;
+        }
+        lines
+    }
+    /// Lets every cell fall towards row 0 until it rests on another cell, returning
+    /// whether anything moved.
+    pub fn apply_gravity(&mut self) -> bool {
+        let mut moved = false;
+        for x in 0..self.width {
+            let mut floor = 0;
+            for y in 0..self.height {
+                let Some(cell) = self.cells.0[y][x] else {
+                    continue;
+                };
+                if y != floor {
+                    self.cells.0[floor][x] = Some(cell);
+                    self.cells.0[y][x] = None;
+                    let meta = self.meta.0[floor][x];
+                    self.meta.0[floor][x] = self.meta.0[y][x];
+                    self.meta.0[y][x] = meta;
+                    moved = true;
+                }
+                floor += 1;
+            }
+        }
+        if moved {
+            self.revision += 1;
+        }
+        moved
+    }
+
+    /// One step of a gravity cascade: cells fall, then the lines this completed clear.
+    /// `None` once nothing clears anymore.
+    pub fn cascade_step(&mut self) -> Option<ClearedLines> {
+        self.apply_gravity();
+        let cleared = self.clear_full_lines();
+        (!cleared.is_empty()).then_some(cleared)
+    }
+
+    /// The board once the shape is placed and every clear resolved, cascading under
+    /// gravity. `None` if the shape does not fit or the cascade is still going after
+    /// `max_steps`.
+    pub fn predict(
+        &self,
+        shape: &Shape,
+        anchor: (isize, isize),
+        color: TileColor,
+        gravity: bool,
+        max_steps: usize,
+    ) -> Option<Board> {
+        let mut board = *self;
+        board.try_place(shape, anchor, color)?;
+        if board.clear_full_lines().is_empty() || !gravity {
+            return Some(board);
+        }
+        for _ in 0..max_steps {
+            if board.cascade_step().is_none() {
+                return Some(board);
+            }
+        }
+        None
+    }
+}
+
+/// Every anchor that keeps the shape within the board.
+pub fn anchors(shape: &Shape) -> impl Iterator<Item = (usize, usize)> {
+    let (width, height) = shape.bounds();
+    (0..=BOARD_HEIGHT.saturating_sub(height))
+        .flat_map(move |y| (0..=BOARD_WIDTH.saturating_sub(width)).map(move |x| (x, y)))
+}
+
+/// Occupancy of a board packed into one bitmask per row, for fast exhaustive scans.
+#[derive(Clone, Copy)]
+pub struct Bitboard {
+    pub rows: [u32; BOARD_HEIGHT],
+}
+impl Bitboard {
+    const FULL_ROW: u32 = (1 << BOARD_WIDTH) - 1;
+
+    /// Whether a shape, given as [`Shape::row_masks`], fits with its top-left field at
+    /// `anchor`. The anchor must keep the shape within the board, see [`anchors`].
+    pub fn fits_at(&self, shape_rows: &[u32; 8], anchor: (usize, usize)) -> bool {
+        shape_rows
+            .iter()
+            .zip(&self.rows[anchor.1..])
+            .all(|(shape_row, board_row)| shape_row << anchor.0 & board_row == 0)
+    }
+
+    /// Empty cells sharing an edge with a blocked one.
+    pub fn neighbors(&self) -> Bitboard {
+        let mut rows = [0; BOARD_HEIGHT];
+        for (y, mask) in rows.iter_mut().enumerate() {
+            let row = self.rows[y];
+            let above = self.rows.get(y + 1).copied().unwrap_or(0);
+            let below = y.checked_sub(1).map_or(0, |y| self.rows[y]);
+            *mask = (row << 1 | row >> 1 | above | below) & !row & Self::FULL_ROW;
+        }
+        Bitboard { rows }
+    }
+
+    /// Whether the shape at `anchor` covers any of the set cells, the anchor keeping
+    /// the shape within the board as for [`Bitboard::fits_at`].
+    pub fn overlaps(&self, shape_rows: &[u32; 8], anchor: (usize, usize)) -> bool {
+        shape_rows
+            .iter()
+            .zip(&self.rows[anchor.1..])
+            .any(|(shape_row, board_row)| shape_row << anchor.0 & board_row != 0)
+    }
+
+    /// Whether placing the shape at `anchor` would complete at least one row or column
+    /// that isn't complete yet. Cells beyond a board's size are blocked, so its rows and
+    /// columns past the size always count as complete.
+    pub fn completes_line(&self, shape_rows: &[u32; 8], anchor: (usize, usize)) -> bool {
+        let mut placed = self.rows;
+        for (board_row, shape_row) in placed[anchor.1..].iter_mut().zip(shape_rows) {
+            *board_row |= shape_row << anchor.0;
+        }
+        let full_columns = |rows: &[u32]| rows.iter().fold(Self::FULL_ROW, |acc, row| acc & row);
+        placed
+            .iter()
+            .zip(&self.rows)
+            .any(|(&after, &before)| after == Self::FULL_ROW && before != Self::FULL_ROW)
+            || full_columns(&placed) & !full_columns(&self.rows) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn every_tile_color_reads_back_from_its_char() {
+        let custom = (0..MAX_CUSTOM_COLORS).map(|index| TileColor::Custom(CustomColor(index)));
+        let builtin = [
+            TileColor::Gray,
+            TileColor::Red,
+            TileColor::Green,
+            TileColor::Blue,
+            TileColor::Transparent,
+            TileColor::Stone,
+            TileColor::Cracked,
+        ];
+        for color in builtin.into_iter().chain(custom) {
+            assert!(TileColor::from_char(color.to_char()) == Some(color));
+        }
+    }
+
+    #[test]
+    fn custom_colors_stop_at_the_last_letter() {
+        assert!(CustomColor::new(MAX_CUSTOM_COLORS - 1).is_some());
+        assert!(CustomColor::new(MAX_CUSTOM_COLORS).is_none());
+        assert!(CustomColor::new(u8::MAX).is_none());
+    }
+
+    /// A board without any symmetry, with metadata to follow its cells.
+    fn lopsided() -> Board {
+        let mut board = Board::default();
+        board.cells.0[0][..3].fill(Some(TileColor::Red));
+        board.cells.0[1][0] = Some(TileColor::Blue);
+        board.cells.0[5][7] = Some(TileColor::Stone);
+        board.meta.0[0][2].bonus = 3;
+        board.meta.0[5][7].frozen_until = 4;
+        board
+    }
+
+    fn same_cells(a: &Board, b: &Board) -> bool {
+        a.cells == b.cells && a.meta == b.meta
+    }
+
+    #[test]
+    fn mirroring_twice_restores_the_board() {
+        let original = lopsided();
+        let mut board = original;
+        board.mirror_horizontal();
+        assert!(!same_cells(&board, &original));
+        board.mirror_horizontal();
+        assert!(same_cells(&board, &original), "{board:#}");
+        board.mirror_vertical();
+        assert!(!same_cells(&board, &original));
+        board.mirror_vertical();
+        assert!(same_cells(&board, &original), "{board:#}");
+    }
+
+    #[test]
+    fn four_quarter_turns_restore_the_board() {
+        let original = lopsided();
+        let mut board = original;
+        for _ in 0..3 {
+            board.rotate_90().unwrap();
+            assert!(!same_cells(&board, &original));
+        }
+        board.rotate_90().unwrap();
+        assert!(same_cells(&board, &original), "{board:#}");
+    }
+
+    #[test]
+    fn a_quarter_turn_moves_cells_and_metadata_clockwise() {
+        let mut board = lopsided();
+        board.rotate_90().unwrap();
+        // The bottom row, left to right, becomes the left column, bottom to top
+        let top = BOARD_HEIGHT - 1;
+        assert_eq!(board.cells.0[top][0], Some(TileColor::Red));
+        assert_eq!(board.cells.0[top - 2][0], Some(TileColor::Red));
+        assert_eq!(board.meta.0[top - 2][0].bonus, 3);
+        assert_eq!(board.cells.0[top][1], Some(TileColor::Blue));
+        assert_eq!(board.cells.0[top - 7][5], Some(TileColor::Stone));
+        assert_eq!(board.meta.0[top - 7][5].frozen_until, 4);
+    }
+
+    #[test]
+    fn two_quarter_turns_are_both_mirrors() {
+        let mut turned = lopsided();
+        turned.rotate_90().unwrap();
+        turned.rotate_90().unwrap();
+        let mut mirrored = lopsided();
+        mirrored.mirror_horizontal();
+        mirrored.mirror_vertical();
+        assert!(same_cells(&turned, &mirrored), "{turned:#}");
+    }
+
+    /// A board with row 0 full, cleared by the next [`Board::clear_full_lines`].
+    fn full_bottom_row() -> Board {
+        let mut board = Board::default();
+        board.cells.0[0].fill(Some(TileColor::Green));
+        board
+    }
+
+    #[test]
+    fn bonus_cells_multiply_the_clear_passing_through_them() {
+        let mut board = full_bottom_row();
+        board.meta.0[0][3].bonus = 2;
+        board.meta.0[0][9].bonus = 3;
+        // Outside the cleared row
+        board.meta.0[1][9].bonus = 3;
+
+        let lines = board.clear_full_lines();
+
+        assert_eq!((lines.multiplier, lines.bonus_cells), (6, 2));
+        assert_eq!(board.meta.0[0][3], CellMeta::default());
+        assert_eq!(board.meta.0[1][9].bonus, 3);
+    }
+
+    #[test]
+    fn bonus_multiplier_is_capped() {
+        let mut board = full_bottom_row();
+        for x in 0..5 {
+            board.meta.0[0][x].bonus = 3;
+        }
+
+        let lines = board.clear_full_lines();
+
+        assert_eq!(
+            (lines.multiplier, lines.bonus_cells),
+            (MAX_BONUS_MULTIPLIER, 5)
+        );
+    }
+
+    #[test]
+    fn clears_without_bonus_cells_multiply_by_one() {
+        let mut board = full_bottom_row();
+        let lines = board.clear_full_lines();
+        assert_eq!((lines.multiplier, lines.bonus_cells), (1, 0));
+    }
+
+    #[test]
+    fn only_filled_cells_freeze() {
+        let mut board = full_bottom_row();
+        board.freeze([(2, 0), (2, 1)], 3);
+        assert_eq!(board.meta.0[0][2].frozen_for(board.turn()), 3);
+        assert!(!board.meta.0[1][2].is_frozen(board.turn()));
+    }
+
+    #[test]
+    fn frozen_cells_survive_clears_until_they_thaw() {
+        let dot = Shape::from_pattern(1, 1, "#");
+        let mut board = full_bottom_row();
+        board.freeze([(5, 0)], 2);
+
+        board.clear_full_lines();
+        assert_eq!(board.cells.0[0][5], Some(TileColor::Green));
+        assert_eq!(board.cells.0[0].iter().flatten().count(), 1);
+        // Nor can it be placed on
+        assert!(board.try_place(&dot, (5, 0), TileColor::Red).is_none());
+
+        // Two placements later it thaws, and the next clear takes it
+        board.try_place(&dot, (0, 5), TileColor::Red).unwrap();
+        assert!(board.meta.0[0][5].is_frozen(board.turn()));
+        board.try_place(&dot, (1, 5), TileColor::Red).unwrap();
+        assert!(!board.meta.0[0][5].is_frozen(board.turn()));
+        for x in (0..BOARD_WIDTH).filter(|&x| x != 5) {
+            board.cells.0[0][x] = Some(TileColor::Blue);
+        }
+        board.clear_full_lines();
+        assert!(board.cells.0[0].iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn superimpositions_print_fitting_and_intersecting_fields() {
+        let mut board = Board::default();
+        board.cells.0[1][1] = Some(TileColor::Stone);
+        let square = Shape::from_pattern(2, 2, "####");
+        assert_eq!(format!("{square:?}"), "##\n##");
+        let superimposition = board.superimpose_at(&square, (0, 0));
+        assert!(!superimposition.success, "{superimposition}");
+
+        let text = superimposition.to_string();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), BOARD_HEIGHT + 1);
+        assert_eq!(lines[0], format!("FF{}", ".".repeat(BOARD_WIDTH - 2)));
+        assert_eq!(lines[1], format!("FX{}", ".".repeat(BOARD_WIDTH - 2)));
+        assert!(lines[2..BOARD_HEIGHT]
+            .iter()
+            .all(|line| !line.contains(['F', 'X'])));
+        assert_eq!(lines[BOARD_HEIGHT], "anchor (0, 0) success false");
+        assert_eq!(format!("{superimposition:?}"), text);
+    }
+
+    #[test]
+    fn fields_off_the_board_are_left_out_of_the_print() {
+        let square = Shape::from_pattern(2, 2, "####");
+        let superimposition = Board::default().superimpose_at(&square, (-1, 0));
+        let text = superimposition.to_string();
+        assert_eq!(text.matches('F').count(), 2, "{superimposition}");
+        assert!(text.ends_with("anchor (-1, 0) success false"));
+    }
+
+    /// A board with every cell empty or of a random placeable color.
+    fn random_board(rng: &mut StdRng) -> Board {
+        let colors = [
+            TileColor::Gray,
+            TileColor::Red,
+            TileColor::Green,
+            TileColor::Blue,
+            TileColor::Stone,
+            TileColor::Cracked,
+            TileColor::Custom(CustomColor(0)),
+            TileColor::Custom(CustomColor(MAX_CUSTOM_COLORS - 1)),
+        ];
+        let mut board = Board::default();
+        for cell in board.cells.0.iter_mut().flatten() {
+            if rng.gen_bool(0.6) {
+                *cell = Some(colors[rng.gen_range(0..colors.len())]);
+            }
+        }
+        board
+    }
+
+    #[test]
+    fn colored_boards_read_back_from_their_text() {
+        let mut rng = StdRng::seed_from_u64(159);
+        for _ in 0..100 {
+            let board = random_board(&mut rng);
+            let parsed = Board::from_text(&format!("{board:#}")).unwrap();
+            assert!(parsed.cells == board.cells, "{board:#}");
+        }
+    }
+
+    #[test]
+    fn plain_boards_read_back_in_gray() {
+        let board = random_board(&mut StdRng::seed_from_u64(159));
+        let plain = board.to_string();
+        assert!(plain.chars().all(|c| matches!(c, '#' | '.' | '\n')));
+        let parsed = Board::from_text(&plain).unwrap();
+        for (row, parsed_row) in board.cells.0.iter().zip(parsed.cells.0.iter()) {
+            for (cell, parsed_cell) in row.iter().zip(parsed_row) {
+                assert_eq!(*parsed_cell, cell.map(|_| TileColor::Gray), "{parsed:#}");
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_text_is_no_board() {
+        let board = format!("{:#}", lopsided());
+        assert!(Board::from_text(&board[1..]).is_none());
+        assert!(Board::from_text(&board.replacen('R', "?", 1)).is_none());
+        assert!(Board::from_text(&format!("{board}\n....")).is_none());
+        assert!(Board::from_text(&format!("{board}\n\n")).is_some());
+    }
+
+    #[test]
+    fn transparent_is_never_written_to_a_cell() {
+        let mut board = Board::default();
+        board.fill_cells([(2, 2), (3, 2)], Some(TileColor::Transparent));
+        assert!(board.cells.0[2].iter().all(Option::is_none), "{board:#}");
+
+        let dot = Shape::from_pattern(1, 1, "#");
+        assert!(board
+            .try_place(&dot, (2, 2), TileColor::Transparent)
+            .is_none());
+        assert!(board.try_place(&dot, (2, 2), TileColor::Gray).is_some());
+
+        let mut cells = Grid::default();
+        cells.0[4][4] = Some(TileColor::Transparent);
+        cells.0[4][5] = Some(TileColor::Blue);
+        board.set_cells(cells);
+        assert_eq!(board.cells.0[4][4], None);
+        assert_eq!(board.cells.0[4][5], Some(TileColor::Blue));
+    }
+
+    #[test]
+    fn sanitized_cells_no_longer_block_shapes() {
+        let mut board = Board::default();
+        board.cells.0[0][0] = Some(TileColor::Transparent);
+        board.cells.0[0][1] = Some(TileColor::Red);
+        let dot = Shape::from_pattern(1, 1, "#");
+        assert!(!board.superimpose_at(&dot, (0, 0)).success);
+
+        let revision = board.revision();
+        assert_eq!(board.sanitize(), 1);
+        assert!(board.revision() > revision);
+        let superimposition = board.superimpose_at(&dot, (0, 0));
+        assert!(superimposition.success, "{superimposition}");
+        assert_eq!(board.cells.0[0][1], Some(TileColor::Red));
+        assert_eq!(board.sanitize(), 0);
+    }
+
+    /// Changes the board in one of the ways the game does, picked at random.
+    fn mutate(board: &mut Board, rng: &mut StdRng) {
+        let cell = (
+            rng.gen_range(0..BOARD_WIDTH),
+            rng.gen_range(0..BOARD_HEIGHT),
+        );
+        match rng.gen_range(0..8) {
+            0 => {
+                let square = Shape::from_pattern(2, 2, "####");
+                let anchor = (cell.0 as isize, cell.1 as isize);
+                board.try_place(&square, anchor, TileColor::Green);
+            }
+            1 => board.fill_cells([cell], Some(TileColor::Red)),
+            2 => board.fill_cells([cell], None),
+            3 => board.set_meta(
+                [cell],
+                CellMeta {
+                    bonus: rng.gen_range(1..4),
+                    target: rng.gen(),
+                    ..default()
+                },
+            ),
+            4 => board.freeze([cell], rng.gen_range(1..5)),
+            5 => {
+                board.clear_full_lines();
+            }
+            6 => board.mirror_horizontal(),
+            _ => {
+                let _ = board.insert_garbage_rows(1, |_| cell.0);
+            }
+        }
+    }
+
+    #[test]
+    fn applying_a_diff_reproduces_the_board() {
+        let mut rng = StdRng::seed_from_u64(189);
+        for _ in 0..50 {
+            let mut board = random_board(&mut rng);
+            let snapshot = board.snapshot();
+            for _ in 0..rng.gen_range(0..20) {
+                mutate(&mut board, &mut rng);
+            }
+            let deltas = board.diff(&snapshot);
+
+            // Exact, every delta a cell that changed
+            for delta in deltas.iter() {
+                let (x, y) = delta.cell;
+                let changed = snapshot.cells.0[y][x] != board.cells.0[y][x]
+                    || snapshot.meta.0[y][x] != board.meta.0[y][x];
+                assert!(changed, "{delta:?}");
+            }
+            let mut replica = Board::default();
+            replica.set_cells(snapshot.cells);
+            replica.meta = snapshot.meta;
+            replica.apply_deltas(&deltas);
+            assert!(same_cells(&replica, &board), "{board:#}\n\n{replica:#}");
+            assert!(board.diff(&replica.snapshot()).is_empty());
+        }
+    }
+
+    #[test]
+    fn snapshots_and_deltas_read_back_from_ron() {
+        let mut rng = StdRng::seed_from_u64(189);
+        let mut board = random_board(&mut rng);
+        let snapshot = board.snapshot();
+        let text = ron::to_string(&snapshot).unwrap();
+        assert!(ron::from_str::<BoardSnapshot>(&text).unwrap() == snapshot);
+        for _ in 0..30 {
+            mutate(&mut board, &mut rng);
+        }
+        let deltas = board.diff(&snapshot);
+        assert!(!deltas.is_empty());
+        let text = ron::to_string(&deltas).unwrap();
+        assert_eq!(ron::from_str::<Vec<CellDelta>>(&text).unwrap(), deltas);
+
+        let delta = "(cell: (0, 0), content: Some(Custom(26)), meta: (bonus: 0, \
+                     frozen_until: 0, target: false, garbage: false))";
+        assert!(ron::from_str::<CellDelta>(delta).is_err());
+        let short_grid = "[[None, Some(Red)]]";
+        assert!(ron::from_str::<Grid<Option<TileColor>, 2, 2>>(short_grid).is_err());
+    }
+
+    #[test]
+    fn full_regions_clear_with_the_lines_crossing_them() {
+        let mut board = Board::with_size(9, 9).unwrap();
+        board.set_region_size(3);
+        for y in 3..6 {
+            board.cells.0[y][3..6].fill(Some(TileColor::Red));
+        }
+        // A full row through the region, its cells cleared once
+        board.cells.0[4][..9].fill(Some(TileColor::Blue));
+
+        let lines = board.clear_full_lines();
+
+        assert_eq!(lines.regions.as_slice(), [(3, 3)]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines.cells().count(), 9 + 6);
+        assert!(board.cells.0.iter().flatten().all(Option::is_none));
+    }
+
+    #[test]
+    fn regions_past_the_edge_never_clear() {
+        let mut board = Board::with_size(10, 10).unwrap();
+        board.set_region_size(3);
+        for y in 0..3 {
+            board.cells.0[y][9] = Some(TileColor::Red);
+        }
+        assert!(board.full_lines().is_empty());
+    }
 }
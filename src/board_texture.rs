@@ -0,0 +1,126 @@
+//! [`BoardRenderer::Texture`]: a board drawn as one sprite showing an image, with a
+//! square of texels per cell that repaints write into instead of touching sprites.
+//!
+//! Cells keep a texel of gap on their right and top edge, and badges are a corner
+//! square of texels, so the board reads like the sprite one at its usual scale. The
+//! image always holds the largest board, smaller ones show its bottom left corner.
+
+use bevy::{
+    ecs::system::EntityCommands,
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+use crate::{
+    board::{Board as BoardGrid, TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    components::board_extents,
+    render_order, Board, TILE_SIZE,
+};
+
+/// Texels per cell edge.
+const CELL_TEXELS: usize = 8;
+/// Edge of a badge, in texels.
+const BADGE_TEXELS: usize = 3;
+
+/// Image a board is painted into.
+pub struct BoardTexture {
+    pub image: Handle<Image>,
+    /// Sprite showing the image, a child of the board.
+    pub sprite: Entity,
+}
+
+/// Spawns a playable board drawn by [`BoardRenderer::Texture`], scaled by [`TILE_SIZE`]
+/// and centered on the origin like [`crate::spawn_board`]'s. It has no tile or badge
+/// entities.
+pub fn spawn_textured_board<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    images: &mut Assets<Image>,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: (BOARD_WIDTH * CELL_TEXELS) as u32,
+            height: (BOARD_HEIGHT * CELL_TEXELS) as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::nearest();
+    for y in 0..BOARD_HEIGHT {
+        for x in 0..BOARD_WIDTH {
+            paint_cell(
+                &mut image,
+                (x, y),
+                TileColor::Gray.into(),
+                TileColor::Transparent.into(),
+            );
+        }
+    }
+    let image = images.add(image);
+    let mut board_entity = commands.spawn(SpatialBundle {
+        transform: Transform::from_scale(Vec3::splat(TILE_SIZE)),
+        ..default()
+    });
+    let mut sprite = Entity::PLACEHOLDER;
+    board_entity.with_children(|commands| {
+        sprite = commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(BOARD_WIDTH as f32, BOARD_HEIGHT as f32)),
+                    ..default()
+                },
+                texture: image.clone(),
+                transform: Transform::from_xyz(0., 0., render_order::in_board(render_order::BOARD)),
+                ..default()
+            })
+            .id();
+    });
+    let board = Board {
+        grid: BoardGrid::default(),
+        entities: [[Entity::PLACEHOLDER; BOARD_WIDTH]; BOARD_HEIGHT],
+        badges: [[Entity::PLACEHOLDER; BOARD_WIDTH]; BOARD_HEIGHT],
+        extents: board_extents((BOARD_WIDTH, BOARD_HEIGHT)),
+        texture: Some(BoardTexture { image, sprite }),
+    };
+    board_entity.insert(board);
+    board_entity
+}
+
+/// Has the sprite showing a board's image show the cells of a `width` by `height` board
+/// only, centered on the board's origin.
+pub(crate) fn fit_sprite(sprite: &mut Sprite, (width, height): (usize, usize)) {
+    sprite.custom_size = Some(Vec2::new(width as f32, height as f32));
+    sprite.rect = Some(Rect::new(
+        0.,
+        ((BOARD_HEIGHT - height) * CELL_TEXELS) as f32,
+        (width * CELL_TEXELS) as f32,
+        (BOARD_HEIGHT * CELL_TEXELS) as f32,
+    ));
+}
+
+/// Paints the texels of a cell, with its badge in the top right corner unless that is
+/// transparent.
+pub fn paint_cell(image: &mut Image, (x, y): (usize, usize), color: Color, badge: Color) {
+    let width = BOARD_WIDTH * CELL_TEXELS;
+    let color = color.as_rgba_u8();
+    let badge = (badge != TileColor::Transparent.into()).then(|| badge.as_rgba_u8());
+    for ty in 0..CELL_TEXELS {
+        for tx in 0..CELL_TEXELS {
+            // Texel rows run top to bottom, board rows bottom to top
+            let texel_y = (BOARD_HEIGHT - 1 - y) * CELL_TEXELS + ty;
+            let texel = (texel_y * width + x * CELL_TEXELS + tx) * 4;
+            let gap = tx == CELL_TEXELS - 1 || ty == 0;
+            let in_badge = tx >= CELL_TEXELS - 1 - BADGE_TEXELS && (1..=BADGE_TEXELS).contains(&ty);
+            let rgba = match badge {
+                _ if gap => [0; 4],
+                Some(badge) if in_badge => badge,
+                _ => color,
+            };
+            image.data[texel..texel + 4].copy_from_slice(&rgba);
+        }
+    }
+}
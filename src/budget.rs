@@ -0,0 +1,75 @@
+//! Cooperative frame budgets for scans too expensive to always finish in one frame.
+//! A scan checks its [`FrameBudget`] between steps, keeps its progress when it runs
+//! out and resumes on the next frame, ending up with the same result either way.
+
+use bevy::{prelude::*, utils::Instant};
+use std::{cell::Cell, time::Duration};
+
+/// Microseconds per frame each budgeted scan may take.
+#[derive(Resource)]
+pub struct ScanBudget(pub u64);
+impl Default for ScanBudget {
+    fn default() -> Self {
+        Self(1_000)
+    }
+}
+
+pub struct FrameBudget {
+    start: Instant,
+    /// `None` for no limit.
+    limit: Option<Duration>,
+    /// Whether [`Self::check`] was called yet.
+    checked: Cell<bool>,
+}
+impl FrameBudget {
+    /// A budget of `micros` starting now.
+    pub fn new(micros: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            limit: Some(Duration::from_micros(micros)),
+            checked: Cell::new(false),
+        }
+    }
+
+    /// A budget that never runs out, for finishing a scan in one go.
+    pub fn unlimited() -> Self {
+        Self {
+            start: Instant::now(),
+            limit: None,
+            checked: Cell::new(false),
+        }
+    }
+
+    /// Whether there is time left for another step. The first check always passes, for
+    /// scans whose setup takes up the budget to still make progress.
+    pub fn check(&self) -> bool {
+        !self.checked.replace(true) || self.limit.is_none_or(|limit| self.start.elapsed() < limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_unlimited_budget_never_runs_out() {
+        let budget = FrameBudget::unlimited();
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(budget.check());
+    }
+
+    #[test]
+    fn a_budget_allows_a_step_however_small() {
+        let budget = FrameBudget::new(0);
+        assert!(budget.check());
+        assert!(!budget.check());
+    }
+
+    #[test]
+    fn a_budget_runs_out_after_its_time() {
+        let budget = FrameBudget::new(1_000);
+        assert!(budget.check());
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(!budget.check());
+    }
+}
@@ -0,0 +1,135 @@
+//! Keeps clicks not aimed at the board from placing the selected shape.
+//!
+//! Clicks on the HUD are captured by it, see [`PointerCapture`]. After the click that
+//! focused the window, or the one closing a menu or screen over the board, clicks don't
+//! place for `click_grace` seconds: the preview still shows, and a hint asks to click
+//! again.
+
+use bevy::{prelude::*, ui::UiSystem, window::WindowFocused};
+
+use crate::{settings::Settings, strings::Strings, tween::Tween, BlocksSystems};
+
+/// Seconds the click again hint takes to fade out.
+const HINT_SECONDS: f32 = 1.5;
+
+#[derive(Resource, Default)]
+pub struct ClickGuard {
+    /// Seconds left of the grace period.
+    remaining: f32,
+    /// Whether a click was kept from the board since the hint last showed.
+    swallowed: bool,
+}
+impl ClickGuard {
+    /// Whether clicks are kept from the board.
+    pub fn active(&self) -> bool {
+        self.remaining > 0.
+    }
+
+    /// Records a click kept from the board, for the hint.
+    pub fn swallow(&mut self) {
+        self.swallowed = true;
+    }
+}
+
+/// Whether the cursor is over the HUD this frame, so a click is the HUD's and not the
+/// board's. Set in `PreUpdate` for bevy_ui nodes with an [`Interaction`]. Widgets drawn
+/// in the world hit-test the cursor themselves and call [`PointerCapture::capture`] in
+/// `Update` before [`BlocksSystems::Preview`].
+#[derive(Resource, Default)]
+pub struct PointerCapture(bool);
+impl PointerCapture {
+    pub fn capture(&mut self) {
+        self.0 = true;
+    }
+
+    pub fn captured(&self) -> bool {
+        self.0
+    }
+}
+
+/// A menu or screen over the board, despawned once closed. Its closing starts the
+/// grace period.
+#[derive(Component)]
+pub struct ModalUi;
+
+#[derive(Component)]
+struct ClickHint;
+
+pub struct ClickGuardPlugin;
+impl Plugin for ClickGuardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClickGuard>()
+            .init_resource::<PointerCapture>()
+            .add_systems(PreUpdate, capture_pointer.after(UiSystem::Focus))
+            .add_systems(
+                Update,
+                (
+                    arm_guard.in_set(BlocksSystems::Input),
+                    show_click_hint.in_set(BlocksSystems::Presentation),
+                ),
+            );
+    }
+}
+
+/// Captures the pointer while it's over a node with an [`Interaction`], like a button,
+/// the history panel, the mini-map or a toast.
+fn capture_pointer(mut capture: ResMut<PointerCapture>, q_interactions: Query<&Interaction>) {
+    capture.0 = q_interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+}
+
+/// Counts the grace period down and restarts it when the window regained focus or a
+/// modal UI closed.
+fn arm_guard(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut guard: ResMut<ClickGuard>,
+    mut focused: EventReader<WindowFocused>,
+    mut closed: RemovedComponents<ModalUi>,
+) {
+    guard.remaining = (guard.remaining - time.delta_seconds()).max(0.);
+    let focus_regained = focused.read().any(|event| event.focused);
+    let modal_closed = closed.read().count() > 0;
+    if focus_regained || modal_closed {
+        guard.remaining = settings.click_grace;
+    }
+}
+
+fn show_click_hint(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    mut guard: ResMut<ClickGuard>,
+    q_hints: Query<Entity, With<ClickHint>>,
+) {
+    if !guard.swallowed {
+        return;
+    }
+    guard.swallowed = false;
+    for entity in q_hints.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                strings.get("hud.click_again"),
+                TextStyle {
+                    font_size: 16.,
+                    color: Color::rgba(1., 1., 1., 0.7),
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(32.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            ..default()
+        },
+        Tween::new(HINT_SECONDS)
+            .with_alpha(0.7, 0.)
+            .despawn_on_finish(),
+        ClickHint,
+    ));
+}
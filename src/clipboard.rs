@@ -0,0 +1,28 @@
+//! The system clipboard, for handing text like crash reports and replay codes between
+//! the game and other programs. Native only, on the web copying and pasting fail.
+
+/// Puts the text on the clipboard.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn copy(text: &str) -> Result<(), String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn copy(_text: &str) -> Result<(), String> {
+    Err("no clipboard on the web".into())
+}
+
+/// The text on the clipboard.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn paste() -> Result<String, String> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn paste() -> Result<String, String> {
+    Err("no clipboard on the web".into())
+}
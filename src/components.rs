@@ -0,0 +1,259 @@
+//! Entities of the game's boards: the [`Board`] component tying a board's cells to its
+//! tile sprites, the markers telling boards and shapes apart, and spawning boards.
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+
+use crate::{
+    board::{Board as BoardGrid, TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    board_texture, render_order, tile_fade,
+    tile_style::Tile,
+};
+
+#[derive(Component)]
+pub struct Board {
+    pub(crate) grid: BoardGrid,
+    /// Tile sprites of the cells within the grid's size, placeholders beyond it.
+    pub(crate) entities: [[Entity; BOARD_WIDTH]; BOARD_HEIGHT],
+    /// Marks drawn over bonus, target and frozen cells, children of the tiles.
+    pub(crate) badges: [[Entity; BOARD_WIDTH]; BOARD_HEIGHT],
+    pub(crate) extents: Rect,
+    /// Image the cells are painted into on boards drawn by [`BoardRenderer::Texture`],
+    /// whose tile and badge entities are placeholders.
+    pub(crate) texture: Option<board_texture::BoardTexture>,
+}
+impl Board {
+    /// Cells and metadata of the board, changed only by the game's own systems.
+    pub fn grid(&self) -> &BoardGrid {
+        &self.grid
+    }
+
+    /// A world position in the board's cell units, from the corner of cell `(0, 0)`.
+    /// Not clamped to the board.
+    pub(crate) fn world_to_local(&self, transform: &GlobalTransform, world: Vec2) -> Vec2 {
+        let local = transform
+            .affine()
+            .inverse()
+            .transform_point3(world.extend(0.));
+        local.xy() - self.extents.min
+    }
+
+    /// Cell under a world position, `None` outside the board.
+    pub fn world_to_cell(
+        &self,
+        transform: &GlobalTransform,
+        world: Vec2,
+    ) -> Option<(usize, usize)> {
+        let local = self.world_to_local(transform, world).floor();
+        let (x, y) = (local.x as isize, local.y as isize);
+        (x >= 0 && y >= 0 && self.grid.contains(x as usize, y as usize))
+            .then_some((x as usize, y as usize))
+    }
+
+    /// World position of the center of a cell.
+    pub fn cell_to_world(&self, transform: &GlobalTransform, cell: (usize, usize)) -> Vec2 {
+        transform.transform_point(self.tile_translation(cell)).xy()
+    }
+
+    /// Local translation of the tile of a cell, the board being centered on its origin.
+    pub(crate) fn tile_translation(&self, (x, y): (usize, usize)) -> Vec3 {
+        tile_translation(x, y, (self.grid.width(), self.grid.height()))
+    }
+
+    /// Entities drawing the board's cells: its tiles and badges, or the sprite showing
+    /// its texture.
+    pub(crate) fn drawing_entities(&self) -> Vec<Entity> {
+        match &self.texture {
+            Some(texture) => vec![texture.sprite],
+            None => self
+                .entities
+                .iter()
+                .chain(self.badges.iter())
+                .flatten()
+                .copied()
+                .filter(|&entity| entity != Entity::PLACEHOLDER)
+                .collect(),
+        }
+    }
+}
+
+/// How the main board draws its cells, chosen with [`config::BlocksPluginBuilder::renderer`]
+/// or by inserting it before adding the [`BlocksPlugin`]. The overlay board always uses
+/// sprites.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoardRenderer {
+    /// A sprite per cell and one per badge.
+    #[default]
+    Sprites,
+    /// One sprite showing an image the cells are painted into, for when a sprite per
+    /// cell is too many entities. Hover and peel highlights aren't drawn on it.
+    Texture,
+}
+
+/// World size of a cell, the scale boards and shapes are spawned with.
+pub const TILE_SIZE: f32 = 30.;
+
+/// Local translation of the tile at `(x, y)` within a board of the given width and
+/// height.
+pub(crate) fn tile_translation(x: usize, y: usize, (width, height): (usize, usize)) -> Vec3 {
+    Vec3::new(
+        (x as f32 - (width as f32) * 0.5) + 0.5,
+        (y as f32 - (height as f32) * 0.5) + 0.5,
+        render_order::in_board(render_order::BOARD),
+    )
+}
+
+/// Local rectangle a board of the given width and height covers, centered on its origin.
+pub(crate) fn board_extents((width, height): (usize, usize)) -> Rect {
+    Rect {
+        min: Vec2::new(-0.5 * width as f32, -0.5 * height as f32),
+        max: Vec2::new(0.5 * width as f32, 0.5 * height as f32),
+    }
+}
+
+/// Spawns the tile of a cell and its badge as children of the board, gray for playable
+/// boards and transparent for overlays. Returns the tile and the badge.
+pub(crate) fn spawn_tile(
+    commands: &mut ChildBuilder,
+    (x, y): (usize, usize),
+    size: (usize, usize),
+    is_main_board: bool,
+) -> (Entity, Entity) {
+    let color = if is_main_board {
+        TileColor::Gray.into()
+    } else {
+        TileColor::Transparent.into()
+    };
+    let mut cmds = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::ONE),
+                ..default()
+            },
+            transform: Transform::from_translation(tile_translation(x, y, size)),
+            ..default()
+        },
+        Tile,
+    ));
+    // Overlay tiles are painted at once by the preview
+    if is_main_board {
+        cmds.insert(tile_fade::TargetTileColor(color));
+    }
+    let mut badge = Entity::PLACEHOLDER;
+    cmds.with_children(|commands| {
+        badge = commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: TileColor::Transparent.into(),
+                    custom_size: Some(Vec2::new(0.3, 0.3)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    0.25,
+                    0.25,
+                    render_order::in_board(render_order::BADGES),
+                ),
+                ..default()
+            })
+            .id();
+    });
+    (cmds.id(), badge)
+}
+
+/// Spawns a board with one tile sprite per cell, gray for playable boards and
+/// transparent for overlays. Playable boards are scaled by [`TILE_SIZE`] and centered
+/// on the origin.
+pub fn spawn_board<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    is_main_board: bool,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut board_entity = commands.spawn(SpatialBundle {
+        transform: if is_main_board {
+            Transform::from_scale(Vec3::splat(TILE_SIZE))
+        } else {
+            Transform::default()
+        },
+        ..default()
+    });
+    let grid = BoardGrid::default();
+    let size = (grid.width(), grid.height());
+    let mut entities = [[Entity::PLACEHOLDER; BOARD_WIDTH]; BOARD_HEIGHT];
+    let mut badges = entities;
+    board_entity.with_children(|commands| {
+        for (x, y) in grid.positions() {
+            (entities[y][x], badges[y][x]) = spawn_tile(commands, (x, y), size, is_main_board);
+        }
+    });
+    board_entity.insert(Board {
+        grid,
+        entities,
+        badges,
+        extents: board_extents(size),
+        texture: None,
+    });
+    board_entity
+}
+
+/// The board the player places shapes on.
+#[derive(Component)]
+pub struct MainBoard;
+
+/// Board drawn over the main board showing the placement preview.
+#[derive(Component)]
+pub struct OverlayBoard;
+
+/// The shape following the cursor, placed on click.
+#[derive(Component)]
+pub struct SelectedShape;
+
+/// The camera showing the game, whose cursor position is the one the game reads.
+#[derive(Component)]
+pub struct MainCamera;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board as spawned, without its tiles.
+    fn board() -> Board {
+        Board {
+            grid: BoardGrid::default(),
+            entities: [[Entity::PLACEHOLDER; BOARD_WIDTH]; BOARD_HEIGHT],
+            badges: [[Entity::PLACEHOLDER; BOARD_WIDTH]; BOARD_HEIGHT],
+            extents: board_extents((BOARD_WIDTH, BOARD_HEIGHT)),
+            texture: None,
+        }
+    }
+
+    #[test]
+    fn every_cell_center_maps_back_to_its_cell() {
+        let board = board();
+        for scale in [1., TILE_SIZE, 12.5] {
+            for translation in [
+                Vec3::ZERO,
+                Vec3::new(-310., 45.5, 2.),
+                Vec3::new(1e4, -7., 0.),
+            ] {
+                let transform = GlobalTransform::from(
+                    Transform::from_translation(translation).with_scale(Vec3::splat(scale)),
+                );
+                for y in 0..BOARD_HEIGHT {
+                    for x in 0..BOARD_WIDTH {
+                        let world = board.cell_to_world(&transform, (x, y));
+                        assert_eq!(board.world_to_cell(&transform, world), Some((x, y)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn positions_off_the_board_have_no_cell() {
+        let board = board();
+        let transform = GlobalTransform::from(Transform::from_scale(Vec3::splat(TILE_SIZE)));
+        let corner = board.cell_to_world(&transform, (0, 0));
+        let beyond = board.cell_to_world(&transform, (BOARD_WIDTH - 1, BOARD_HEIGHT - 1));
+        assert_eq!(board.world_to_cell(&transform, corner - TILE_SIZE), None);
+        assert_eq!(board.world_to_cell(&transform, beyond + TILE_SIZE), None);
+    }
+}
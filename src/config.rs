@@ -0,0 +1,267 @@
+//! [`BlocksPluginBuilder`]: configuring the [`BlocksPlugin`], checked when it's built so
+//! a misconfigured game fails before the app runs.
+
+use std::{fmt, path::PathBuf};
+
+use crate::{
+    board::{size_supported, BOARD_HEIGHT, BOARD_WIDTH, MIN_BOARD_SIZE},
+    rules::{RuleOverrides, RuleSet},
+    shape::{NamedShape, ShapeRegistry},
+    BlocksPlugin, BoardRenderer, TILE_SIZE,
+};
+
+/// Why a [`BlocksPluginBuilder`] can't build its plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// Boards are from `MIN_BOARD_SIZE` up to `BOARD_WIDTH` by `BOARD_HEIGHT` cells.
+    BoardSize { width: usize, height: usize },
+    /// Tile sizes have to be positive and finite.
+    TileSize(f32),
+    /// The tile size is applied by zooming the camera the plugin spawns, without it the
+    /// app's own camera decides.
+    TileSizeWithoutCamera,
+    /// The puzzle directory doesn't exist.
+    MissingPuzzleDir(PathBuf),
+    /// No shape of the pool is placeable, each being empty or larger than the board.
+    NoPlaceableShapes { width: usize, height: usize },
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BoardSize { width, height } => write!(
+                f,
+                "boards of {width}x{height} cells aren't supported, only \
+                 {MIN_BOARD_SIZE}x{MIN_BOARD_SIZE} up to {BOARD_WIDTH}x{BOARD_HEIGHT}"
+            ),
+            Self::TileSize(size) => write!(f, "tile size {size} isn't positive"),
+            Self::TileSizeWithoutCamera => {
+                write!(f, "a tile size needs the plugin to spawn the camera")
+            }
+            Self::MissingPuzzleDir(dir) => {
+                write!(f, "puzzle directory {} doesn't exist", dir.display())
+            }
+            Self::NoPlaceableShapes { width, height } => {
+                write!(f, "no shape of the pool fits on a {width}x{height} board")
+            }
+        }
+    }
+}
+impl std::error::Error for ConfigError {}
+
+/// Options of a [`BlocksPlugin`], from [`BlocksPlugin::builder`]. Options left alone
+/// keep the plugin's defaults, those of [`BlocksPlugin::default`].
+#[derive(Clone)]
+pub struct BlocksPluginBuilder {
+    plugin: BlocksPlugin,
+    board_size: (usize, usize),
+}
+impl BlocksPluginBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            plugin: BlocksPlugin::default(),
+            board_size: (BOARD_WIDTH, BOARD_HEIGHT),
+        }
+    }
+
+    /// Cells across and up the board the game starts with, `BOARD_WIDTH` by
+    /// `BOARD_HEIGHT` by default and at most. Changed later with a
+    /// [`crate::ResizeBoardEvent`].
+    pub fn board_size(mut self, width: usize, height: usize) -> Self {
+        self.board_size = (width, height);
+        self
+    }
+
+    /// Pixels a cell takes on screen, [`TILE_SIZE`] by default.
+    pub fn tile_size(mut self, size: f32) -> Self {
+        self.plugin.tile_size = size;
+        self
+    }
+
+    /// Whether the plugin spawns the [`crate::MainCamera`], on by default. Without it the
+    /// app has to spawn one for the cursor to reach the board.
+    pub fn spawn_camera(mut self, spawn: bool) -> Self {
+        self.plugin.spawn_camera = spawn;
+        self
+    }
+
+    /// Rules played with until a mode is picked in the menu.
+    pub fn rules(mut self, rules: RuleSet) -> Self {
+        self.plugin.rules = Some(rules);
+        self
+    }
+
+    /// Rules added to every mode's, like those of `--gravity` and `--hard`, none by
+    /// default.
+    pub fn rule_overrides(mut self, overrides: RuleOverrides) -> Self {
+        self.plugin.rule_overrides = Some(overrides);
+        self
+    }
+
+    /// How the main board is drawn.
+    pub fn renderer(mut self, renderer: BoardRenderer) -> Self {
+        self.plugin.renderer = Some(renderer);
+        self
+    }
+
+    /// Directory the puzzle select screen lists, `puzzles` by default, where it may be
+    /// missing.
+    pub fn puzzle_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.plugin.puzzle_dir = Some(dir.into());
+        self
+    }
+
+    /// Shapes drawn in place of the default pool. Those that can't be placed are left
+    /// out, see [`ShapeRegistry::swap_pool`].
+    pub fn shapes<S: Into<NamedShape>>(mut self, shapes: impl IntoIterator<Item = S>) -> Self {
+        self.plugin.shapes = Some(shapes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn build(self) -> Result<BlocksPlugin, ConfigError> {
+        let (width, height) = self.board_size;
+        if !size_supported(width, height) {
+            return Err(ConfigError::BoardSize { width, height });
+        }
+        let mut plugin = self.plugin;
+        plugin.board_size =
+            Some((width, height)).filter(|&size| size != (BOARD_WIDTH, BOARD_HEIGHT));
+        if !plugin.tile_size.is_finite() || plugin.tile_size <= 0. {
+            return Err(ConfigError::TileSize(plugin.tile_size));
+        }
+        if plugin.tile_size != TILE_SIZE && !plugin.spawn_camera {
+            return Err(ConfigError::TileSizeWithoutCamera);
+        }
+        if let Some(dir) = plugin.puzzle_dir.as_ref().filter(|dir| !dir.is_dir()) {
+            return Err(ConfigError::MissingPuzzleDir(dir.clone()));
+        }
+        if let Some(shapes) = &plugin.shapes {
+            if ShapeRegistry::try_for_board(shapes.iter().cloned(), (width, height)).is_none() {
+                return Err(ConfigError::NoPlaceableShapes { width, height });
+            }
+        }
+        Ok(plugin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shapes;
+
+    #[test]
+    fn the_default_builder_builds_the_default_plugin() {
+        let built = BlocksPlugin::builder().build().unwrap();
+        let default = BlocksPlugin::default();
+        assert_eq!(built.tile_size, default.tile_size);
+        assert_eq!(built.spawn_camera, default.spawn_camera);
+        assert!(built.rules.is_none() && built.renderer.is_none());
+        assert!(built.puzzle_dir.is_none() && built.shapes.is_none());
+        assert!(built.board_size.is_none());
+    }
+
+    #[test]
+    fn every_option_reaches_the_plugin() {
+        let dir = std::env::temp_dir();
+        let plugin = BlocksPlugin::builder()
+            .board_size(12, 9)
+            .tile_size(30.)
+            .rules(RuleSet::adjacency())
+            .renderer(BoardRenderer::Texture)
+            .puzzle_dir(&dir)
+            .shapes(shapes![(2, 1) "##" as "Bar"])
+            .build()
+            .unwrap();
+        assert_eq!(plugin.board_size, Some((12, 9)));
+        assert_eq!(plugin.tile_size, 30.);
+        assert!(plugin.spawn_camera);
+        assert_eq!(plugin.rules, Some(RuleSet::adjacency()));
+        assert_eq!(plugin.renderer, Some(BoardRenderer::Texture));
+        assert_eq!(plugin.puzzle_dir, Some(dir));
+        assert_eq!(plugin.shapes.map(|shapes| shapes.len()), Some(1));
+    }
+
+    #[test]
+    fn tile_sizes_need_the_camera_and_to_be_positive() {
+        for spawn_camera in [true, false] {
+            for size in [TILE_SIZE, 12., 0., -4., f32::NAN, f32::INFINITY] {
+                let built = BlocksPlugin::builder()
+                    .tile_size(size)
+                    .spawn_camera(spawn_camera)
+                    .build();
+                match built {
+                    Ok(plugin) => {
+                        assert!(size.is_finite() && size > 0., "{size}");
+                        assert!(size == TILE_SIZE || spawn_camera, "{size}");
+                        assert_eq!(plugin.spawn_camera, spawn_camera);
+                    }
+                    Err(ConfigError::TileSize(_)) => {
+                        assert!(!size.is_finite() || size <= 0., "{size}");
+                    }
+                    Err(error) => {
+                        assert_eq!(error, ConfigError::TileSizeWithoutCamera, "{size}");
+                        assert!(size > 0. && size != TILE_SIZE && !spawn_camera);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn board_sizes_out_of_range_are_refused() {
+        for (width, height) in [
+            (BOARD_WIDTH + 1, BOARD_HEIGHT),
+            (BOARD_WIDTH, MIN_BOARD_SIZE - 1),
+            (0, BOARD_HEIGHT),
+        ] {
+            let error = BlocksPlugin::builder()
+                .board_size(width, height)
+                .build()
+                .err();
+            assert_eq!(error, Some(ConfigError::BoardSize { width, height }));
+        }
+    }
+
+    #[test]
+    fn a_missing_puzzle_dir_is_refused() {
+        let dir = std::env::temp_dir().join("blocks-no-such-puzzle-dir");
+        let error = BlocksPlugin::builder().puzzle_dir(&dir).build().err();
+        assert_eq!(error, Some(ConfigError::MissingPuzzleDir(dir)));
+    }
+
+    #[test]
+    fn a_pool_without_placeable_shapes_is_refused() {
+        let error = BlocksPlugin::builder()
+            .shapes(shapes![(2, 1) ".."])
+            .build()
+            .err();
+        assert_eq!(
+            error,
+            Some(ConfigError::NoPlaceableShapes {
+                width: BOARD_WIDTH,
+                height: BOARD_HEIGHT
+            })
+        );
+    }
+
+    #[test]
+    fn the_pool_is_checked_against_the_board_size() {
+        let error = BlocksPlugin::builder()
+            .board_size(5, 5)
+            .shapes(shapes![(6, 1) "######"])
+            .build()
+            .err();
+        assert_eq!(
+            error,
+            Some(ConfigError::NoPlaceableShapes {
+                width: 5,
+                height: 5
+            })
+        );
+        // A shape exactly as wide as the board fits
+        let plugin = BlocksPlugin::builder()
+            .board_size(5, 5)
+            .shapes(shapes![(5, 1) "#####"])
+            .build();
+        assert!(plugin.is_ok());
+    }
+}
@@ -0,0 +1,434 @@
+//! Developer console toggled with the backtick key. Enter runs the typed command, Tab
+//! completes command names and `help` lists them. While it's open the game doesn't
+//! see the keyboard.
+//!
+//! Commands are handlers in the [`ConsoleCommands`] registry, other modules add theirs
+//! with [`ConsoleAppExt::add_console_command`].
+
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::{ecs::system::SystemState, input::InputSystem, prelude::*};
+
+use crate::{
+    board::{parse_size, TileColor, BOARD_HEIGHT, BOARD_WIDTH, MIN_BOARD_SIZE},
+    events::BoardCommand,
+    menu::GameState,
+    palette::ColorPalette,
+    resize,
+    scoring::Score,
+    shape::{ShapeInstance, ShapeRegistry},
+    systems::replace_selected_shape,
+    systems::GameRng,
+    Board, CellList, MainBoard, OverlayBoard, ResizeBoardEvent, SelectedShape,
+};
+
+/// Output lines kept in the console.
+const LOG_LEN: usize = 12;
+
+/// Runs a command with its arguments, returning what to print.
+type Handler = Box<dyn Fn(&[&str], &mut World) -> Result<String, String> + Send + Sync>;
+
+struct ConsoleCommand {
+    /// Arguments the command takes, shown by `help`.
+    usage: &'static str,
+    handler: Handler,
+}
+
+/// Commands of the console by name.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(BTreeMap<&'static str, ConsoleCommand>);
+impl ConsoleCommands {
+    pub fn register(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        handler: impl Fn(&[&str], &mut World) -> Result<String, String> + Send + Sync + 'static,
+    ) {
+        self.0.insert(
+            name,
+            ConsoleCommand {
+                usage,
+                handler: Box::new(handler),
+            },
+        );
+    }
+
+    fn help(&self) -> String {
+        let mut help = String::from("help");
+        for (name, command) in &self.0 {
+            help += &format!("\n{name} {}", command.usage);
+        }
+        help
+    }
+
+    /// Completes `input` to the longest prefix shared by the command names it starts.
+    fn complete(&self, input: &str) -> Option<String> {
+        let mut matches = self
+            .0
+            .keys()
+            .copied()
+            .chain(["help"])
+            .filter(|name| name.starts_with(input));
+        let first = matches.next()?;
+        let mut prefix = first.to_owned();
+        let mut unique = true;
+        for name in matches {
+            unique = false;
+            let shared = prefix
+                .chars()
+                .zip(name.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            prefix.truncate(shared);
+        }
+        if unique {
+            prefix.push(' ');
+        }
+        Some(prefix)
+    }
+}
+
+pub trait ConsoleAppExt {
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        handler: impl Fn(&[&str], &mut World) -> Result<String, String> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+impl ConsoleAppExt for App {
+    fn add_console_command(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        handler: impl Fn(&[&str], &mut World) -> Result<String, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ConsoleCommands::default)
+            .register(name, usage, handler);
+        self
+    }
+}
+
+#[derive(Resource, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    log: VecDeque<String>,
+    /// Lines entered since the commands last ran.
+    submitted: Vec<String>,
+}
+impl Console {
+    fn print(&mut self, text: &str) {
+        self.log.extend(text.lines().map(str::to_owned));
+        let excess = self.log.len().saturating_sub(LOG_LEN);
+        self.log.drain(..excess);
+    }
+}
+
+#[derive(Component)]
+struct ConsoleText;
+
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .init_resource::<ConsoleCommands>()
+            .add_console_command("fill", "<x0> <y0> <x1> <y1> <color>", fill)
+            .add_console_command("clear", "", clear)
+            .add_console_command("spawn", "<shape>", spawn)
+            .add_console_command("seed", "<seed>", seed)
+            .add_console_command("score", "<points>", score)
+            .add_console_command(
+                "state",
+                "<menu|playing|sandbox|puzzles|settings|gameover>",
+                state,
+            )
+            .add_console_command("board", "<width>x<height>", board)
+            .add_systems(Startup, spawn_console)
+            .add_systems(
+                PreUpdate,
+                type_in_console
+                    .after(InputSystem)
+                    .before(crate::keyboard::move_keyboard_cursor),
+            )
+            .add_systems(
+                Update,
+                (
+                    run_console_commands,
+                    show_console.run_if(resource_changed::<Console>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn spawn_console(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.),
+                left: Val::Px(0.),
+                width: Val::Percent(100.),
+                padding: UiRect::all(Val::Px(6.)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.8).into(),
+            z_index: ZIndex::Global(20),
+            ..default()
+        },
+        ConsoleText,
+    ));
+}
+
+/// Edits the input line from the typed characters and keeps the keys from the game
+/// while the console is open.
+fn type_in_console(
+    mut console: ResMut<Console>,
+    commands: Res<ConsoleCommands>,
+    mut input_keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+) {
+    let typed: Vec<char> = characters.read().map(|event| event.char).collect();
+    if input_keys.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+        input_keys.reset_all();
+        return;
+    }
+    if !console.open {
+        return;
+    }
+    if input_keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if input_keys.just_pressed(KeyCode::Tab) {
+        if let Some(completed) = commands.complete(&console.input) {
+            console.input = completed;
+        }
+    }
+    if input_keys.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        if !line.trim().is_empty() {
+            console.submitted.push(line);
+        }
+    }
+    for c in typed {
+        if !c.is_control() && c != '`' {
+            console.input.push(c);
+        }
+    }
+    input_keys.reset_all();
+}
+
+fn run_console_commands(world: &mut World) {
+    if world.resource::<Console>().submitted.is_empty() {
+        return;
+    }
+    let submitted = std::mem::take(&mut world.resource_mut::<Console>().submitted);
+    world.resource_scope(|world, commands: Mut<ConsoleCommands>| {
+        for line in submitted {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let output = match words[0] {
+                "help" => Ok(commands.help()),
+                name => match commands.0.get(name) {
+                    Some(command) => (command.handler)(&words[1..], world)
+                        .map_err(|err| format!("{err}, usage: {name} {}", command.usage)),
+                    None => Err(format!("unknown command {name}, try help")),
+                },
+            };
+            let mut console = world.resource_mut::<Console>();
+            console.print(&format!("> {line}"));
+            match output {
+                Ok(text) => console.print(&text),
+                Err(err) => console.print(&format!("error: {err}")),
+            }
+        }
+    });
+}
+
+fn show_console(
+    console: Res<Console>,
+    mut q_text: Query<(&mut Text, &mut Style), With<ConsoleText>>,
+) {
+    let Ok((mut text, mut style)) = q_text.get_single_mut() else {
+        return;
+    };
+    style.display = if console.open {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    let mut lines: Vec<&str> = console.log.iter().map(String::as_str).collect();
+    let prompt = format!("> {}_", console.input);
+    lines.push(&prompt);
+    text.sections[0].value = lines.join("\n");
+}
+
+fn parse<T: std::str::FromStr>(word: Option<&&str>) -> Result<T, String> {
+    let word = word.ok_or("missing argument")?;
+    word.parse().map_err(|_| format!("invalid argument {word}"))
+}
+
+fn main_board(world: &mut World) -> Result<Entity, String> {
+    world
+        .query_filtered::<Entity, (With<MainBoard>, Without<OverlayBoard>)>()
+        .get_single(world)
+        .map_err(|_| "no main board".into())
+}
+
+/// Width and height of the main board.
+fn main_board_size(world: &World, board: Entity) -> (usize, usize) {
+    let grid = world.get::<Board>(board).map(|board| board.grid);
+    grid.map_or((BOARD_WIDTH, BOARD_HEIGHT), |grid| {
+        (grid.width(), grid.height())
+    })
+}
+
+fn parse_color(word: Option<&&str>) -> Result<TileColor, String> {
+    let word = *word.ok_or("missing color")?;
+    let color = match word {
+        "gray" => Some(TileColor::Gray),
+        "red" => Some(TileColor::Red),
+        "green" => Some(TileColor::Green),
+        "blue" => Some(TileColor::Blue),
+        "stone" => Some(TileColor::Stone),
+        "cracked" => Some(TileColor::Cracked),
+        // Or the letter of the text formats
+        _ => {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .filter(|_| chars.next().is_none())
+                .and_then(TileColor::from_char)
+        }
+    };
+    color.ok_or_else(|| format!("unknown color {word}"))
+}
+
+fn fill(args: &[&str], world: &mut World) -> Result<String, String> {
+    let x0: usize = parse(args.first())?;
+    let y0: usize = parse(args.get(1))?;
+    let x1: usize = parse(args.get(2))?;
+    let y1: usize = parse(args.get(3))?;
+    let color = parse_color(args.get(4))?;
+    let board = main_board(world)?;
+    let (width, height) = main_board_size(world, board);
+    let cells: CellList = (y0.min(y1)..=y0.max(y1).min(height - 1))
+        .flat_map(|y| (x0.min(x1)..=x0.max(x1).min(width - 1)).map(move |x| (x, y)))
+        .collect();
+    let filled = cells.len();
+    world.send_event(BoardCommand::Fill {
+        board,
+        cells,
+        color: Some(color),
+    });
+    Ok(format!("filled {filled} cells"))
+}
+
+fn clear(_: &[&str], world: &mut World) -> Result<String, String> {
+    let board = main_board(world)?;
+    let (width, height) = main_board_size(world, board);
+    world.send_event(BoardCommand::Fill {
+        board,
+        cells: (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect(),
+        color: None,
+    });
+    Ok("cleared the board".into())
+}
+
+fn spawn(args: &[&str], world: &mut World) -> Result<String, String> {
+    let name = *args.first().ok_or("missing shape name")?;
+    let mut state: SystemState<(
+        Commands,
+        Res<ShapeRegistry>,
+        Res<ColorPalette>,
+        Query<(Entity, &mut ShapeInstance, &Children, &Transform), With<SelectedShape>>,
+        Query<&mut Sprite>,
+    )> = SystemState::new(world);
+    let (mut commands, registry, palette, mut q_selected_shape, mut q_sprites) =
+        state.get_mut(world);
+    let id = registry
+        .pool()
+        .map(|(id, _)| id)
+        .find(|&id| registry.name(id).eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("no shape named {name}"))?;
+    let (entity, instance, children, &transform) = q_selected_shape
+        .get_single_mut()
+        .map_err(|_| "no selected shape")?;
+    let color = instance.color;
+    replace_selected_shape(
+        &mut commands,
+        (&registry, &palette),
+        (entity, instance, children),
+        &mut q_sprites,
+        ShapeInstance {
+            id,
+            rotation: 0,
+            color,
+        },
+        transform,
+    );
+    state.apply(world);
+    Ok(format!("selected {name}"))
+}
+
+fn seed(args: &[&str], world: &mut World) -> Result<String, String> {
+    let seed = parse(args.first())?;
+    world.insert_resource(GameRng::new(seed));
+    Ok(format!("seeded with {seed}"))
+}
+
+fn score(args: &[&str], world: &mut World) -> Result<String, String> {
+    let points = parse(args.first())?;
+    let board = main_board(world)?;
+    let mut board = world.entity_mut(board);
+    let mut score = board
+        .get_mut::<Score>()
+        .ok_or("the main board keeps no score")?;
+    score.0 = points;
+    Ok(format!("score set to {points}"))
+}
+
+fn state(args: &[&str], world: &mut World) -> Result<String, String> {
+    let next = match *args.first().ok_or("missing state")? {
+        "menu" => GameState::Menu,
+        "playing" => GameState::Playing,
+        "sandbox" => GameState::Sandbox,
+        "puzzles" => GameState::PuzzleSelect,
+        "settings" => GameState::Settings,
+        "paused" => GameState::Paused,
+        "gameover" => GameState::GameOver,
+        state => return Err(format!("unknown state {state}")),
+    };
+    world.resource_mut::<NextState<GameState>>().set(next);
+    Ok(format!("switching to {next:?}"))
+}
+
+fn board(args: &[&str], world: &mut World) -> Result<String, String> {
+    let size = *args.first().ok_or("missing size")?;
+    let (width, height) = parse_size(size).ok_or_else(|| {
+        format!(
+            "invalid size {size}, boards are from {MIN_BOARD_SIZE}x{MIN_BOARD_SIZE} \
+             to {BOARD_WIDTH}x{BOARD_HEIGHT}"
+        )
+    })?;
+    world.send_event(ResizeBoardEvent { width, height });
+    if resize::resizes_at_once(*world.resource::<State<GameState>>().get()) {
+        Ok(format!("board resized to {width}x{height}"))
+    } else {
+        Ok(format!(
+            "the board turns {width}x{height} with the next game"
+        ))
+    }
+}
@@ -0,0 +1,255 @@
+//! Rules vetoing placements on top of the board's geometry, registered by plugins
+//! such as the tutorial.
+
+use std::{borrow::Cow, sync::Mutex};
+
+use bevy::{math::URect, prelude::*};
+
+use crate::{
+    board::{anchors, Bitboard, Board as BoardGrid},
+    budget::FrameBudget,
+    shape::{Shape, ShapeInstance, ShapeRegistry},
+    strings::Strings,
+    toast::spawn_toast,
+    BlocksSystems, Board, GameOverEvent, MainBoard, OverlayBoard, PlacementEvent, SelectedShape,
+};
+
+pub enum ConstraintResult {
+    Allowed,
+    /// Blocked, with the reason shown to the player. Borrowed for fixed reasons, as the
+    /// preview checks every frame.
+    Vetoed(Cow<'static, str>),
+}
+
+type Rule = dyn Fn(&BoardGrid, &Shape, (isize, isize)) -> ConstraintResult + Send + Sync;
+
+pub struct PlacementConstraint {
+    /// Identifies the constraint to remove it again.
+    pub name: &'static str,
+    /// Region highlighted on the overlay, for constraints limiting placements to it.
+    pub region: Option<URect>,
+    rule: Box<Rule>,
+}
+impl PlacementConstraint {
+    pub fn new(
+        name: &'static str,
+        rule: impl Fn(&BoardGrid, &Shape, (isize, isize)) -> ConstraintResult + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name,
+            region: None,
+            rule: Box::new(rule),
+        }
+    }
+
+    /// Only allows placements within `region`, `max` excluded.
+    pub fn inside_rect(name: &'static str, region: URect) -> Self {
+        let mut constraint = Self::new(name, move |board, shape, anchor| {
+            let superimposition = board.superimpose_at(shape, anchor);
+            let inside = superimposition.cells.iter().all(|&(x, y, _)| {
+                (region.min.x..region.max.x).contains(&(x as u32))
+                    && (region.min.y..region.max.y).contains(&(y as u32))
+            });
+            if inside {
+                ConstraintResult::Allowed
+            } else {
+                ConstraintResult::Vetoed("Place it on the highlighted area".into())
+            }
+        });
+        constraint.region = Some(region);
+        constraint
+    }
+
+    /// Only allows placements sharing an edge with a filled cell, unless the board
+    /// is empty. The cells next to filled ones are worked out once per board revision.
+    pub fn touches_filled(name: &'static str) -> Self {
+        let cache: Mutex<Option<(u64, bool, Bitboard)>> = Mutex::new(None);
+        Self::new(name, move |board, shape, anchor| {
+            let mut cache = cache.lock().unwrap();
+            if !cache.is_some_and(|(revision, ..)| revision == board.revision()) {
+                let filled = board.filled_bitboard();
+                let empty = filled.rows.iter().all(|&row| row == 0);
+                *cache = Some((board.revision(), empty, filled.neighbors()));
+            }
+            let (_, empty, neighbors) = cache.unwrap();
+            let touches = usize::try_from(anchor.0)
+                .ok()
+                .zip(usize::try_from(anchor.1).ok())
+                .is_some_and(|anchor| neighbors.overlaps(&shape.row_masks(), anchor));
+            if empty || touches {
+                ConstraintResult::Allowed
+            } else {
+                ConstraintResult::Vetoed("Must touch a placed block".into())
+            }
+        })
+    }
+}
+
+/// Constraints every placement on the main board has to satisfy, checked once the
+/// shape fits geometrically.
+#[derive(Resource, Default)]
+pub struct PlacementConstraints(Vec<PlacementConstraint>);
+impl PlacementConstraints {
+    pub fn add(&mut self, constraint: PlacementConstraint) {
+        self.0.push(constraint);
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.retain(|constraint| constraint.name != name);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The first veto, if any constraint objects to the placement.
+    pub fn check(
+        &self,
+        board: &BoardGrid,
+        shape: &Shape,
+        anchor: (isize, isize),
+    ) -> Option<Cow<'static, str>> {
+        self.0
+            .iter()
+            .find_map(|constraint| match (constraint.rule)(board, shape, anchor) {
+                ConstraintResult::Allowed => None,
+                ConstraintResult::Vetoed(message) => Some(message),
+            })
+    }
+
+    /// First anchor, scanning rows then columns, at which the shape fits and every
+    /// constraint allows it.
+    pub fn find_placement(&self, board: &BoardGrid, shape: &Shape) -> Option<(isize, isize)> {
+        self.resume_find_placement(board, shape, &mut 0, &FrameBudget::unlimited())
+            .flatten()
+    }
+
+    /// [`Self::find_placement`] spread over several calls, `scanned` counting the anchors
+    /// checked so far. `Some` with the result once the scan is done, `None` if the
+    /// budget ran out first.
+    pub fn resume_find_placement(
+        &self,
+        board: &BoardGrid,
+        shape: &Shape,
+        scanned: &mut usize,
+        budget: &FrameBudget,
+    ) -> Option<Option<(isize, isize)>> {
+        let bitboard = board.bitboard();
+        let shape_rows = shape.row_masks();
+        for (x, y) in anchors(shape).skip(*scanned) {
+            if !budget.check() {
+                return None;
+            }
+            *scanned += 1;
+            let anchor = (x as isize, y as isize);
+            if bitboard.fits_at(&shape_rows, (x, y)) && self.check(board, shape, anchor).is_none() {
+                return Some(Some(anchor));
+            }
+        }
+        Some(None)
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = URect> + '_ {
+        self.0.iter().filter_map(|constraint| constraint.region)
+    }
+}
+
+pub struct ConstraintsPlugin;
+impl Plugin for ConstraintsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlacementConstraints>()
+            .add_systems(Update, announce_no_moves.after(BlocksSystems::Resolution));
+    }
+}
+
+/// Tells the player once the selected shape can't go anywhere the constraints allow,
+/// which ends the game.
+fn announce_no_moves(
+    mut commands: Commands,
+    registry: Res<ShapeRegistry>,
+    strings: Res<Strings>,
+    constraints: Res<PlacementConstraints>,
+    mut placements: EventReader<PlacementEvent>,
+    mut game_over: EventWriter<GameOverEvent>,
+    q_board: Query<(&Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+) {
+    let Ok((board, entity)) = q_board.get_single() else {
+        return;
+    };
+    if !placements.read().any(|event| event.board == entity) {
+        return;
+    }
+    let Ok(shape) = q_selected_shape.get_single() else {
+        return;
+    };
+    if constraints
+        .find_placement(&board.grid, shape.resolve(&registry))
+        .is_none()
+    {
+        spawn_toast(&mut commands, strings.get("toast.no_moves"));
+        game_over.send(GameOverEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH};
+
+    #[test]
+    fn a_scan_spread_over_frames_finds_the_same_placement() {
+        let mut board = BoardGrid::default();
+        for y in 0..BOARD_HEIGHT {
+            for x in 0..BOARD_WIDTH {
+                if (x * 7 + y * 3) % 5 == 0 {
+                    board.cells.0[y][x] = Some(TileColor::Stone);
+                }
+            }
+        }
+        let mut constraints = PlacementConstraints::default();
+        constraints.add(PlacementConstraint::inside_rect(
+            "corner",
+            URect::new(12, 12, 20, 20),
+        ));
+        let shape = Shape::from_pattern(2, 2, "####");
+
+        let (mut scanned, mut frames) = (0, 0);
+        let found = loop {
+            frames += 1;
+            assert!(frames < 100_000, "the scan made no progress");
+            let budget = FrameBudget::new(10);
+            if let Some(found) =
+                constraints.resume_find_placement(&board, &shape, &mut scanned, &budget)
+            {
+                break found;
+            }
+        };
+
+        let expected = constraints.find_placement(&board, &shape);
+        assert!(expected.is_some_and(|(x, y)| x >= 12 && y >= 12));
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn placements_must_touch_a_filled_cell_on_a_small_board() {
+        let mut board = BoardGrid::with_size(9, 9).unwrap();
+        let mut constraints = PlacementConstraints::default();
+        constraints.add(PlacementConstraint::touches_filled("adjacency"));
+        let shape = Shape::from_pattern(2, 1, "##");
+
+        // The first placement goes anywhere, along the board's edges too
+        for anchor in [(0, 0), (3, 4), (7, 8)] {
+            assert!(constraints.check(&board, &shape, anchor).is_none());
+        }
+
+        board.fill_cells([(4, 4)], Some(TileColor::Stone));
+        // Cells beyond the board's size don't count as filled
+        assert!(constraints.check(&board, &shape, (7, 0)).is_some());
+        assert!(constraints.check(&board, &shape, (0, 8)).is_some());
+        assert!(constraints.check(&board, &shape, (1, 4)).is_some());
+        assert!(constraints.check(&board, &shape, (2, 4)).is_none());
+        assert!(constraints.check(&board, &shape, (4, 5)).is_none());
+        assert_eq!(constraints.find_placement(&board, &shape), Some((3, 3)));
+    }
+}
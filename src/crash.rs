@@ -0,0 +1,223 @@
+//! Crash reports: a panic hook writing the state of the game to `crash_<time>.txt`,
+//! offered for a bug report on the next launch, F7 copying it to the clipboard.
+//!
+//! The hook can't reach into the world while unwinding, so a system copies what it
+//! writes into a [`CrashSnapshot`] after every placement.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    clipboard,
+    persistence::DataDir,
+    replay::ReplayLog,
+    shape::{ShapeInstance, ShapeRegistry},
+    systems::GameRng,
+    toast::spawn_toast,
+    BlocksSystems, Board, MainBoard, OverlayBoard, PlacementEvent, SelectedShape,
+};
+
+const CRASH_PREFIX: &str = "crash_";
+/// Prefix a crash report is renamed to once it was printed, so it isn't offered again.
+const REPORTED_PREFIX: &str = "reported_crash_";
+/// Latest placements written to the report.
+const REPORTED_ACTIONS: usize = 10;
+
+/// What the panic hook writes, as of the last placement.
+#[derive(Default)]
+struct CrashSnapshot {
+    board: String,
+    selected: String,
+    seed: u64,
+    actions: Vec<String>,
+}
+impl CrashSnapshot {
+    fn report(&self, panic: &str) -> String {
+        format!(
+            "{panic}\n\nseed {}\n\nboard\n{}\n\nselected shape\n{}\n\nlast placements\n{}\n",
+            self.seed,
+            self.board,
+            self.selected,
+            self.actions.join("\n")
+        )
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+struct SharedCrashSnapshot(Arc<Mutex<CrashSnapshot>>);
+
+/// Report left behind by a crashed session.
+#[derive(Resource)]
+struct PendingCrashReport(std::path::PathBuf);
+
+pub struct CrashPlugin;
+impl Plugin for CrashPlugin {
+    fn build(&self, app: &mut App) {
+        let snapshot = SharedCrashSnapshot::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        install_panic_hook(
+            snapshot.clone(),
+            app.world
+                .get_resource::<DataDir>()
+                .cloned()
+                .unwrap_or_default(),
+        );
+        app.insert_resource(snapshot)
+            .add_systems(Startup, find_crash_report)
+            .add_systems(
+                Update,
+                (
+                    refresh_crash_snapshot.after(BlocksSystems::Resolution),
+                    copy_crash_report.run_if(resource_exists::<PendingCrashReport>()),
+                ),
+            );
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn install_panic_hook(snapshot: SharedCrashSnapshot, dir: DataDir) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // Panicking hooks are caught and disabled, the game goes on
+        if crate::hooks::running_hook() {
+            previous(info);
+            return;
+        }
+        // A panic while the snapshot was being refreshed leaves it poisoned, its
+        // contents are still the best there is
+        let report = match snapshot.0.try_lock() {
+            Ok(snapshot) => Some(snapshot.report(&info.to_string())),
+            Err(std::sync::TryLockError::Poisoned(poisoned)) => {
+                Some(poisoned.into_inner().report(&info.to_string()))
+            }
+            Err(std::sync::TryLockError::WouldBlock) => None,
+        };
+        if let Some(report) = report {
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs());
+            let path = dir.path(format!("{CRASH_PREFIX}{time}.txt"));
+            match std::fs::write(&path, report) {
+                Ok(()) => eprintln!("game state written to {}", path.display()),
+                Err(err) => eprintln!("couldn't write {}: {err}", path.display()),
+            }
+        }
+        previous(info);
+    }));
+}
+
+fn describe(registry: &ShapeRegistry, shape: &ShapeInstance) -> String {
+    format!(
+        "{} rotation {} color {}",
+        registry.name(shape.id),
+        shape.rotation,
+        shape.color.to_char()
+    )
+}
+
+fn refresh_crash_snapshot(
+    registry: Res<ShapeRegistry>,
+    rng: Res<GameRng>,
+    log: Res<ReplayLog>,
+    snapshot: Res<SharedCrashSnapshot>,
+    mut placements: EventReader<PlacementEvent>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+) {
+    if placements.read().count() == 0 {
+        return;
+    }
+    let (Ok(board), Ok(selected)) = (q_board.get_single(), q_selected_shape.get_single()) else {
+        return;
+    };
+    let Ok(mut snapshot) = snapshot.0.lock() else {
+        return;
+    };
+    snapshot.board = format!(
+        "{:#}\n{}",
+        board.grid,
+        ron::to_string(&board.grid.snapshot()).unwrap_or_default()
+    );
+    snapshot.selected = format!(
+        "{}\n{}",
+        describe(&registry, selected),
+        selected.resolve(&registry)
+    );
+    snapshot.seed = rng.seed;
+    let skipped = log.0.len().saturating_sub(REPORTED_ACTIONS);
+    snapshot.actions = log.0[skipped..]
+        .iter()
+        .map(|action| {
+            format!(
+                "{} at {:?}",
+                describe(&registry, &action.shape),
+                action.anchor
+            )
+        })
+        .collect();
+}
+
+/// Offers the report of a crashed session, if there is one.
+fn find_crash_report(mut commands: Commands, dir: Res<DataDir>) {
+    let Ok(entries) = std::fs::read_dir(dir.path(".")) else {
+        return;
+    };
+    let Some(path) = entries.flatten().map(|entry| entry.path()).find(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(CRASH_PREFIX) && name.ends_with(".txt"))
+    }) else {
+        return;
+    };
+    spawn_toast(
+        &mut commands,
+        format!(
+            "The last session crashed, F7 copies {} for a bug report",
+            path.display()
+        ),
+    );
+    commands.insert_resource(PendingCrashReport(path));
+}
+
+/// Copies the pending report to the clipboard, or prints it to the log without one, and
+/// marks it as reported.
+fn copy_crash_report(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    report: Res<PendingCrashReport>,
+) {
+    if !input_keys.just_pressed(KeyCode::F7) {
+        return;
+    }
+    commands.remove_resource::<PendingCrashReport>();
+    let path = &report.0;
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!("couldn't read {}: {err}", path.display());
+            return;
+        }
+    };
+    let message = match clipboard::copy(&contents) {
+        Ok(()) => "Crash report copied to the clipboard",
+        Err(err) => {
+            warn!("couldn't copy the crash report: {err}");
+            info!("crash report {}:\n{contents}", path.display());
+            "Crash report printed to the log"
+        }
+    };
+    let reported = path.with_file_name(
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.replacen(CRASH_PREFIX, REPORTED_PREFIX, 1))
+            .unwrap_or_default(),
+    );
+    if let Err(err) = std::fs::rename(path, &reported) {
+        warn!("couldn't rename {}: {err}", path.display());
+    }
+    spawn_toast(&mut commands, message);
+}
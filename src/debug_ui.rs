@@ -0,0 +1,136 @@
+use bevy::{
+    diagnostic::{
+        Diagnostic, DiagnosticId, Diagnostics, DiagnosticsStore, EntityCountDiagnosticsPlugin,
+        FrameTimeDiagnosticsPlugin, RegisterDiagnostic,
+    },
+    prelude::*,
+};
+
+use crate::{
+    menu::GameState,
+    shape::{ShapeInstance, ShapeRegistry},
+    systems::Preview,
+    systems::ShapeDrought,
+    ui::SpriteMutations,
+    Board, MainBoard, OverlayBoard, SelectedShape,
+};
+
+pub const SPRITE_MUTATIONS: DiagnosticId =
+    DiagnosticId::from_u128(0x6a1f_3c2e_9b4d_4e1a_8f0c_5d7b_2a91_e364);
+
+/// Corner overlay with FPS, entity count, sprite mutations, the game state and shape
+/// droughts, toggled with F1. F4 logs the placement preview's state.
+pub struct DebugUiPlugin;
+impl Plugin for DebugUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin))
+            .register_diagnostic(Diagnostic::new(SPRITE_MUTATIONS, "sprite_mutations", 20))
+            .add_systems(Startup, spawn_overlay)
+            .add_systems(PostUpdate, measure_sprite_mutations)
+            .add_systems(Update, (toggle_overlay, update_overlay, dump_preview));
+    }
+}
+
+#[derive(Component)]
+struct DebugOverlay;
+
+fn spawn_overlay(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                right: Val::Px(8.),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        DebugOverlay,
+    ));
+}
+
+fn measure_sprite_mutations(mut diagnostics: Diagnostics, sprite_mutations: Res<SpriteMutations>) {
+    diagnostics.add_measurement(SPRITE_MUTATIONS, || sprite_mutations.0 as f64);
+}
+
+fn toggle_overlay(
+    input_keys: Res<Input<KeyCode>>,
+    mut q_overlay: Query<&mut Visibility, With<DebugOverlay>>,
+) {
+    if input_keys.just_pressed(KeyCode::F1) {
+        for mut visibility in q_overlay.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+fn update_overlay(
+    diagnostics: Res<DiagnosticsStore>,
+    drought: Res<ShapeDrought>,
+    state: Res<State<GameState>>,
+    mut q_overlay: Query<(&mut Text, &Visibility), With<DebugOverlay>>,
+) {
+    let Ok((mut text, visibility)) = q_overlay.get_single_mut() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    let value = |id| {
+        diagnostics
+            .get(id)
+            .and_then(Diagnostic::smoothed)
+            .unwrap_or_default()
+    };
+    let drought: Vec<String> = drought.0.iter().map(u32::to_string).collect();
+    text.sections[0].value = format!(
+        "FPS: {:.0}\nEntities: {:.0}\nSprite mutations: {:.0}\nState: {:?}\nDrought: {}",
+        value(FrameTimeDiagnosticsPlugin::FPS),
+        value(EntityCountDiagnosticsPlugin::ENTITY_COUNT),
+        value(SPRITE_MUTATIONS),
+        state.get(),
+        drought.join(" "),
+    );
+}
+
+/// Logs the main board, the selected shape, and where and how it superimposes onto the
+/// board, in one block.
+fn dump_preview(
+    input_keys: Res<Input<KeyCode>>,
+    registry: Res<ShapeRegistry>,
+    preview: Res<Preview>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+) {
+    if !input_keys.just_pressed(KeyCode::F4) {
+        return;
+    }
+    let (Ok(board), Ok(selected_shape)) = (q_board.get_single(), q_selected_shape.get_single())
+    else {
+        return;
+    };
+    let superimposition = preview
+        .superimposition
+        .as_ref()
+        .map_or("none, the cursor is off the board".into(), |s| {
+            s.to_string()
+        });
+    info!(
+        "board:\n{}\nshape {} rotation {}:\n{}\nsuperimposition:\n{superimposition}",
+        board.grid,
+        registry.name(selected_shape.id),
+        selected_shape.rotation,
+        selected_shape.resolve(&registry),
+    );
+}
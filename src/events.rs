@@ -0,0 +1,88 @@
+//! Events and commands about boards, sent by the game's systems and by embedders.
+
+use bevy::prelude::*;
+
+pub use crate::board::CellList;
+use crate::{
+    board::{CellMeta, ClearedLines, TileColor},
+    shape::{ShapeInstance, ShapePool},
+};
+
+/// Sent whenever cells of a board change, listing the touched cells.
+#[derive(Event)]
+pub struct BoardChangedEvent {
+    pub board: Entity,
+    pub revision: u64,
+    pub cells: CellList,
+}
+
+/// Sent when placing a shape completed rows or columns, after they were emptied.
+#[derive(Event)]
+pub struct LinesClearedEvent {
+    pub board: Entity,
+    pub lines: ClearedLines,
+}
+
+/// Sent when the game ended, by sudden death or with no moves left.
+#[derive(Event)]
+pub struct GameOverEvent;
+
+/// Resizes the main board to `width` by `height` cells, from [`MIN_BOARD_SIZE`] up to
+/// [`BOARD_WIDTH`] by [`BOARD_HEIGHT`]. In the menu, the settings and the sandbox it
+/// applies at once and empties the board. Elsewhere, during a game or while it's paused
+/// or over, the new size waits for the next game to start.
+///
+/// [`MIN_BOARD_SIZE`]: crate::board::MIN_BOARD_SIZE
+/// [`BOARD_WIDTH`]: crate::board::BOARD_WIDTH
+/// [`BOARD_HEIGHT`]: crate::board::BOARD_HEIGHT
+#[derive(Event, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ResizeBoardEvent {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Replaces the shapes drawn from now on, e.g. after reloading a shape file, without
+/// restarting the game. Pieces already handed out keep their shapes.
+#[derive(Event)]
+pub struct ShapePoolChanged(pub ShapePool);
+
+/// Changes to a board requested by game rules, applied along with placements.
+#[derive(Event)]
+pub(crate) enum BoardCommand {
+    /// Sets the cells to the color, or empties them with `None`.
+    Fill {
+        board: Entity,
+        cells: CellList,
+        color: Option<TileColor>,
+    },
+    /// Sets the metadata of the cells, keeping their content.
+    SetMeta {
+        board: Entity,
+        cells: CellList,
+        meta: CellMeta,
+    },
+    /// Freezes the filled cells among these for the next placements.
+    Freeze {
+        board: Entity,
+        cells: CellList,
+        placements: u32,
+    },
+    /// Pushes the board up by one garbage row per gap, see
+    /// [`BoardGrid::insert_garbage_rows`].
+    GarbageRows { board: Entity, gaps: Vec<usize> },
+    /// Mirrors the whole board left to right.
+    MirrorHorizontal { board: Entity },
+    /// Mirrors the whole board top to bottom.
+    MirrorVertical { board: Entity },
+    /// Turns the whole board a quarter clockwise, only on square boards.
+    Rotate90 { board: Entity },
+}
+
+/// Sent when the player places a shape on a board.
+#[derive(Event)]
+pub struct PlacementEvent {
+    pub board: Entity,
+    pub shape: ShapeInstance,
+    pub anchor: (isize, isize),
+    pub cells: CellList,
+}
@@ -0,0 +1,236 @@
+//! Exporting the main board as an SVG image for sharing, with F11. The image is drawn
+//! from the board's cells rather than read back from the GPU, so it also works headless.
+
+use std::fmt::Write;
+
+use bevy::prelude::*;
+
+use crate::{
+    board::{Board as BoardGrid, TileColor},
+    menu::daily_seed,
+    palette::ColorPalette,
+    persistence::PersistRequest,
+    scoring::Score,
+    toast::spawn_toast,
+    Board, MainBoard, OverlayBoard,
+};
+
+pub const EXPORT_PATH: &str = "board.svg";
+/// Height of the footer below the board, in pixels.
+const FOOTER_HEIGHT: u32 = 28;
+
+pub struct ImageOptions {
+    /// Width and height of a cell, in pixels.
+    pub cell_size: u32,
+    pub score: u64,
+    /// Days since the Unix epoch, see [`daily_seed`].
+    pub day: u64,
+}
+
+pub struct ExportPlugin;
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, export_board_image);
+    }
+}
+
+/// `#rrggbb` of a color, dropping its alpha.
+fn hex(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_u8();
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// `yyyy-mm-dd` of a day since the Unix epoch, in the proleptic Gregorian calendar.
+fn format_day(day: u64) -> String {
+    // Shifted to start on 0000-03-01, so leap days end the year
+    let days = day + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+/// Draws the board as a standalone SVG: empty cells in gray, filled ones in their
+/// palette color, gridlines between them and the score and date in a footer.
+pub fn render_board_image(
+    board: &BoardGrid,
+    palette: &ColorPalette,
+    options: &ImageOptions,
+) -> String {
+    let cell = options.cell_size;
+    let (columns, rows) = (board.width() as u32, board.height() as u32);
+    let (width, height) = (columns * cell, rows * cell);
+    let mut svg = String::new();
+    // Writing to a `String` can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{}">"#,
+        height + FOOTER_HEIGHT
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{width}" height="{}" fill="{}"/>"#,
+        height + FOOTER_HEIGHT,
+        hex(Color::BLACK)
+    );
+    for (x, y) in board.positions() {
+        // Row 0 is the bottom one, SVG coordinates grow downwards
+        let top = (rows - 1 - y as u32) * cell;
+        let tile = board.cells.0[y][x];
+        let color = tile.map_or(TileColor::Gray.into(), |tile| palette.color(tile));
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{top}" width="{cell}" height="{cell}" fill="{}"/>"#,
+            x as u32 * cell,
+            hex(color)
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"<g stroke="{}" stroke-width="1">"#,
+        hex(Color::BLACK)
+    );
+    for x in 0..=columns {
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{0}" y1="0" x2="{0}" y2="{height}"/>"#,
+            x * cell
+        );
+    }
+    for y in 0..=rows {
+        let _ = writeln!(
+            svg,
+            r#"<line x1="0" y1="{0}" x2="{width}" y2="{0}"/>"#,
+            y * cell
+        );
+    }
+    let _ = writeln!(svg, "</g>");
+    let _ = writeln!(
+        svg,
+        r#"<text x="8" y="{}" font-family="sans-serif" font-size="16" fill="{}">Score {}  {}</text>"#,
+        height + FOOTER_HEIGHT - 8,
+        hex(Color::WHITE),
+        options.score,
+        format_day(options.day)
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn export_board_image(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    palette: Res<ColorPalette>,
+    mut persist: EventWriter<PersistRequest>,
+    q_board: Query<(&Board, &Score), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    if !input_keys.just_pressed(KeyCode::F11) {
+        return;
+    }
+    let Ok((board, score)) = q_board.get_single() else {
+        return;
+    };
+    let options = ImageOptions {
+        cell_size: 24,
+        score: score.0,
+        day: daily_seed(),
+    };
+    persist.send(PersistRequest {
+        path: EXPORT_PATH.into(),
+        contents: render_board_image(&board.grid, &palette, &options),
+    });
+    spawn_toast(&mut commands, format!("Exported to {EXPORT_PATH}"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BOARD_HEIGHT, BOARD_WIDTH};
+
+    fn options() -> ImageOptions {
+        ImageOptions {
+            cell_size: 10,
+            score: 1520,
+            day: 19_000,
+        }
+    }
+
+    #[test]
+    fn days_format_as_dates() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(11_016), "2000-02-29");
+        assert_eq!(format_day(19_000), "2022-01-08");
+    }
+
+    #[test]
+    fn every_cell_is_drawn_in_its_color() {
+        let palette = ColorPalette::default();
+        let mut board = BoardGrid::default();
+        board.cells.0[0][0] = Some(TileColor::Red);
+        let svg = render_board_image(&board, &palette, &options());
+
+        let cells = svg
+            .lines()
+            .filter(|line| line.starts_with("<rect x="))
+            .collect::<Vec<_>>();
+        assert_eq!(cells.len(), BOARD_WIDTH * BOARD_HEIGHT);
+        // Row 0 is drawn at the bottom of the image
+        let bottom_left = format!(
+            r#"<rect x="0" y="{}" width="10" height="10" fill="{}"/>"#,
+            (BOARD_HEIGHT - 1) * 10,
+            hex(palette.color(TileColor::Red))
+        );
+        assert!(cells.contains(&bottom_left.as_str()));
+        let gray = hex(TileColor::Gray.into());
+        let empty = cells.iter().filter(|cell| cell.contains(&gray)).count();
+        assert_eq!(empty, BOARD_WIDTH * BOARD_HEIGHT - 1);
+    }
+
+    #[test]
+    fn smaller_boards_draw_only_their_cells() {
+        let mut board = BoardGrid::with_size(6, 4).unwrap();
+        board.cells.0[0][0] = Some(TileColor::Red);
+        let svg = render_board_image(&board, &ColorPalette::default(), &options());
+
+        assert!(svg.starts_with(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="60" height="{}">"#,
+            40 + FOOTER_HEIGHT
+        )));
+        let cells = svg.lines().filter(|line| line.starts_with("<rect x="));
+        assert_eq!(cells.count(), 6 * 4);
+        assert!(svg.contains(r#"<rect x="0" y="30" width="10" height="10""#));
+    }
+
+    #[test]
+    fn a_fixture_board_renders_as_the_golden_file() {
+        let mut board = BoardGrid::default();
+        board.cells.0[0][0] = Some(TileColor::Red);
+        board.cells.0[0][1] = Some(TileColor::Red);
+        board.cells.0[1][0] = Some(TileColor::Blue);
+        board.cells.0[5][7] = Some(TileColor::Stone);
+        let svg = render_board_image(&board, &ColorPalette::default(), &options());
+        assert_eq!(svg, include_str!("../tests/fixtures/board.svg"));
+    }
+
+    #[test]
+    fn the_footer_shows_score_and_date() {
+        let svg = render_board_image(&BoardGrid::default(), &ColorPalette::default(), &options());
+        let height = BOARD_HEIGHT as u32 * 10 + FOOTER_HEIGHT;
+        assert!(svg.starts_with(&format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{height}">"#,
+            BOARD_WIDTH * 10
+        )));
+        assert!(svg.contains(">Score 1520  2022-01-08</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
@@ -0,0 +1,276 @@
+//! The end of a game: once it's over, or a race ended, the game stops under a screen
+//! showing its score, lines, level and play time, the place it took on the leaderboard
+//! and the [`LifetimeStats`]. Races show their results instead of the title, see
+//! [`sprint::results_text`]. Enter, a click or Ctrl+R plays again.
+
+use bevy::prelude::*;
+
+use crate::{
+    click_guard::ModalUi,
+    leaderboard::{LatestPlace, Leaderboard},
+    menu::{GameMode, GameState},
+    play_time::PlayTime,
+    replay::CopyReplayEvent,
+    rules::RuleSet,
+    scoring::{Level, Score},
+    sprint::{self, SprintEndedEvent, SprintProgress},
+    stats::{self, LifetimeStats},
+    strings::{t, Strings},
+    GameOverEvent, MainBoard,
+};
+
+const IDLE_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.5);
+const DETAIL_TEXT: Color = Color::rgb(0.7, 0.7, 0.7);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum GameOverButton {
+    PlayAgain,
+    CopyReplay,
+    Menu,
+}
+impl GameOverButton {
+    const ALL: [GameOverButton; 3] = [
+        GameOverButton::PlayAgain,
+        GameOverButton::CopyReplay,
+        GameOverButton::Menu,
+    ];
+
+    /// Key of the label in [`Strings`].
+    fn label(self) -> &'static str {
+        match self {
+            GameOverButton::PlayAgain => "game_over.play_again",
+            GameOverButton::CopyReplay => "game_over.copy_replay",
+            GameOverButton::Menu => "game_over.menu",
+        }
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct GameOverRoot;
+
+/// Index into [`GameOverButton::ALL`] of the entry Enter activates.
+#[derive(Resource, Default)]
+struct GameOverSelection(usize);
+
+pub struct GameOverPlugin;
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameOverSelection>()
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                spawn_game_over_screen.after(stats::count_game),
+            )
+            .add_systems(OnExit(GameState::GameOver), despawn_game_over_screen)
+            .add_systems(
+                Update,
+                (
+                    end_game.run_if(in_state(GameState::Playing)),
+                    (navigate_game_over, highlight_buttons)
+                        .chain()
+                        .run_if(in_state(GameState::GameOver)),
+                ),
+            );
+    }
+}
+
+/// Stops the game once it's over or the race ended.
+fn end_game(
+    mut game_over: EventReader<GameOverEvent>,
+    mut sprint_ended: EventReader<SprintEndedEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let ended = game_over.read().count() > 0;
+    if sprint_ended.read().count() > 0 || ended {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    (mode, rules): (Res<GameMode>, Res<RuleSet>),
+    strings: Res<Strings>,
+    (play_time, stats): (Res<PlayTime>, Res<LifetimeStats>),
+    (leaderboard, latest, progress): (Res<Leaderboard>, Res<LatestPlace>, Res<SprintProgress>),
+    mut selection: ResMut<GameOverSelection>,
+    q_board: Query<(&Score, &Level), With<MainBoard>>,
+) {
+    selection.0 = 0;
+    let (title, race) =
+        match sprint::results_text((*mode, &rules), &strings, &leaderboard, &progress) {
+            Some((title, detail)) => (title, Some(detail)),
+            None => (strings.get("game_over.title").to_owned(), None),
+        };
+    let (score, lines, level) = q_board.get_single().map_or((0, 0, 1), |(score, level)| {
+        (score.0, level.lines, level.level)
+    });
+    let mut details = vec![
+        t!(
+            strings,
+            "game_over.stats",
+            strings.number(score),
+            lines,
+            level
+        ),
+        t!(strings, "game_over.time", play_time.format()),
+    ];
+    details.extend(race);
+    details.extend(latest.0.map(|place| match place {
+        1 => strings.get("game_over.best").to_owned(),
+        place => t!(strings, "game_over.place", place),
+    }));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            GameOverRoot,
+            ModalUi,
+        ))
+        .with_children(|commands| {
+            commands.spawn(
+                TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font_size: 48.,
+                        color: Color::GOLD,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(8.)),
+                    ..default()
+                }),
+            );
+            for detail in details {
+                commands.spawn(TextBundle::from_section(
+                    detail,
+                    TextStyle {
+                        font_size: 22.,
+                        ..default()
+                    },
+                ));
+            }
+            commands.spawn(
+                TextBundle::from_section(
+                    t!(
+                        strings,
+                        "game_over.lifetime",
+                        stats.games,
+                        stats.format_play_time(),
+                        strings.number(stats.best_score)
+                    ),
+                    TextStyle {
+                        font_size: 16.,
+                        color: DETAIL_TEXT,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::vertical(Val::Px(8.)),
+                    ..default()
+                }),
+            );
+            for button in GameOverButton::ALL {
+                commands
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(200.),
+                                padding: UiRect::all(Val::Px(8.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: IDLE_BUTTON.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            strings.get(button.label()),
+                            TextStyle {
+                                font_size: 24.,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn despawn_game_over_screen(mut commands: Commands, q_root: Query<Entity, With<GameOverRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Up and Down move the selection, Enter or a click activates an entry, Ctrl+R plays
+/// again as it restarts a game.
+fn navigate_game_over(
+    input_keys: Res<Input<KeyCode>>,
+    mut selection: ResMut<GameOverSelection>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut copies: EventWriter<CopyReplayEvent>,
+    q_buttons: Query<(&Interaction, &GameOverButton), Changed<Interaction>>,
+) {
+    let count = GameOverButton::ALL.len();
+    if input_keys.just_pressed(KeyCode::Down) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+    if input_keys.just_pressed(KeyCode::Up) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    let mut activated = None;
+    for (interaction, button) in q_buttons.iter() {
+        let index = GameOverButton::ALL
+            .iter()
+            .position(|b| b == button)
+            .unwrap();
+        match interaction {
+            Interaction::Hovered => selection.0 = index,
+            Interaction::Pressed => activated = Some(*button),
+            Interaction::None => {}
+        }
+    }
+    if input_keys.just_pressed(KeyCode::Return) {
+        activated = Some(GameOverButton::ALL[selection.0]);
+    }
+    let ctrl = input_keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl && input_keys.just_pressed(KeyCode::R) {
+        activated = Some(GameOverButton::PlayAgain);
+    }
+    match activated {
+        // Entering the game again starts a new one
+        Some(GameOverButton::PlayAgain) => next_state.set(GameState::Playing),
+        Some(GameOverButton::CopyReplay) => copies.send(CopyReplayEvent),
+        Some(GameOverButton::Menu) => next_state.set(GameState::Menu),
+        None => {}
+    }
+}
+
+fn highlight_buttons(
+    selection: Res<GameOverSelection>,
+    mut q_buttons: Query<(&GameOverButton, &mut BackgroundColor)>,
+) {
+    for (button, mut background) in q_buttons.iter_mut() {
+        let selected = GameOverButton::ALL[selection.0] == *button;
+        *background = if selected {
+            SELECTED_BUTTON
+        } else {
+            IDLE_BUTTON
+        }
+        .into();
+    }
+}
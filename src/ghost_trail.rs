@@ -0,0 +1,102 @@
+//! A fading ghost of the preview where it was last frame, when the cursor moved fast
+//! enough for the preview to jump more than a cell, so the eye can follow the jump.
+//! Ghosts fade out over [`GHOST_SECONDS`] on their own highlight layer and are gone
+//! as soon as a shape is placed or the cursor leaves the board. The `ghost_trail`
+//! setting turns them off.
+
+use bevy::prelude::*;
+
+use crate::{
+    highlight::{composite_highlights, HighlightLayer, Highlights},
+    hover::CursorBoardPos,
+    settings::Settings,
+    systems::Preview,
+    tween::Tween,
+    BlocksSystems, CellList, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+/// Seconds a ghost takes to fade out.
+const GHOST_SECONDS: f32 = 0.12;
+const GHOST_COLOR: Color = Color::rgba(1., 1., 1., 0.3);
+
+struct Ghost {
+    cells: CellList,
+    fade: Tween,
+}
+
+#[derive(Default)]
+struct GhostTrail {
+    /// Anchor and cells of the preview last frame.
+    last: Option<((isize, isize), CellList)>,
+    ghosts: Vec<Ghost>,
+}
+
+pub struct GhostTrailPlugin;
+impl Plugin for GhostTrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            show_ghost_trail
+                .in_set(BlocksSystems::Presentation)
+                .before(composite_highlights),
+        );
+    }
+}
+
+fn show_ghost_trail(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    cursor: Res<CursorBoardPos>,
+    preview: Res<Preview>,
+    mut placements: EventReader<PlacementEvent>,
+    mut highlights: ResMut<Highlights>,
+    q_main_board: Query<(), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut trail: Local<GhostTrail>,
+) {
+    highlights.clear(HighlightLayer::Ghost);
+    let placed = placements.read().count() > 0;
+    let on_board = cursor
+        .point
+        .is_some_and(|point| q_main_board.contains(point.board));
+    let current = preview
+        .superimposition
+        .as_ref()
+        .filter(|_| settings.ghost_trail && on_board && !placed);
+    let Some(superimposition) = current else {
+        *trail = GhostTrail::default();
+        return;
+    };
+
+    let anchor = superimposition.anchor;
+    let cells: CellList = superimposition
+        .cells
+        .iter()
+        .map(|&(x, y, _)| (x as usize, y as usize))
+        .collect();
+    if let Some((last_anchor, last_cells)) = trail.last.replace((anchor, cells)) {
+        let jump = (anchor.0 - last_anchor.0)
+            .abs()
+            .max((anchor.1 - last_anchor.1).abs());
+        if jump > 1 {
+            trail.ghosts.push(Ghost {
+                cells: last_cells,
+                fade: Tween::new(GHOST_SECONDS).with_alpha(GHOST_COLOR.a(), 0.),
+            });
+        }
+    }
+
+    trail.ghosts.retain_mut(|ghost| {
+        ghost.fade.elapsed += time.delta_seconds();
+        !ghost.fade.finished()
+    });
+    // The older ghosts first, for the newer ones to cover them
+    for ghost in trail.ghosts.iter() {
+        let Some((from, to)) = ghost.fade.alpha else {
+            continue;
+        };
+        let color = GHOST_COLOR.with_a(from + (to - from) * ghost.fade.progress());
+        for &cell in ghost.cells.iter() {
+            highlights.set(HighlightLayer::Ghost, cell, color);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Input injection for driving the game without a real mouse, and a headless app to
+//! drive it in tests.
+
+use bevy::{
+    ecs::system::CommandQueue,
+    input::{
+        gamepad::{GamepadButtonChangedEvent, GamepadEvent},
+        keyboard::KeyboardInput,
+        ButtonState,
+    },
+    prelude::*,
+    time::TimeUpdateStrategy,
+    window::{ExitCondition, WindowPlugin},
+};
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use crate::{
+    board::{CellMeta, TileColor},
+    click_guard::ClickGuard,
+    events::BoardCommand,
+    intro::BoardReady,
+    menu::{GameMode, GameState},
+    palette::ColorPalette,
+    persistence::DataDir,
+    replay::{self, Playback, ReplayLog, WatchReplayEvent},
+    rules::RuleSet,
+    settings::Settings,
+    shape::{ShapeInstance, ShapeRegistry},
+    systems::{build_shape, GameRng, Tray},
+    ui, BlocksPlugin, Board, CellList, MainBoard, OverlayBoard, SelectedShape, TILE_SIZE,
+};
+
+/// Settings the headless app starts with: no tutorial forcing its shapes, no intro
+/// holding input back and no fades keeping tiles apart from their cells' colors.
+const HEADLESS_SETTINGS: &str =
+    "tutorial_completed = true\nintro_animation = false\ntile_fade = false\n";
+/// Frames [`start_game`] waits for the board to take placements.
+const MAX_START_FRAMES: usize = 60;
+/// Frames [`watch_replay`] waits for the replay to be over.
+const MAX_REPLAY_FRAMES: usize = 3600;
+/// Time every frame of the headless app takes, whatever the wall clock says.
+pub const FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+/// When present, replaces the window cursor. Works without a window or camera.
+#[derive(Resource)]
+pub enum SimulatedCursor {
+    World(Vec2),
+    /// Center of a cell of the main board.
+    Cell((usize, usize)),
+}
+
+/// Treated like a left click on the current cursor position.
+#[derive(Event)]
+pub struct SimulatedClick;
+
+/// Cursor given with `--cursor <x>,<y>` in world space or `--cursor-cell <x>,<y>`.
+pub fn cursor_from_args() -> Option<SimulatedCursor> {
+    let args: Vec<String> = std::env::args().collect();
+    let value = |flag: &str| {
+        let position = args.iter().position(|arg| arg == flag)?;
+        let (x, y) = args.get(position + 1)?.split_once(',')?;
+        Some((x.trim().to_owned(), y.trim().to_owned()))
+    };
+    if let Some((x, y)) = value("--cursor-cell") {
+        return Some(SimulatedCursor::Cell((x.parse().ok()?, y.parse().ok()?)));
+    }
+    let (x, y) = value("--cursor")?;
+    Some(SimulatedCursor::World(Vec2::new(
+        x.parse().ok()?,
+        y.parse().ok()?,
+    )))
+}
+
+/// Every this many frames, despawns the selected shape or strips its tiles in turn, to
+/// check play recovers. Given with `--chaos <frames>`.
+#[derive(Resource)]
+pub struct Chaos {
+    every: u32,
+    frames: u32,
+    strikes: u32,
+}
+
+impl Chaos {
+    /// Striking every `frames` frames, at least one.
+    pub fn every(frames: u32) -> Self {
+        Self {
+            every: frames.max(1),
+            frames: 0,
+            strikes: 0,
+        }
+    }
+
+    /// Times it despawned or stripped the selected shape so far.
+    pub fn strikes(&self) -> u32 {
+        self.strikes
+    }
+}
+
+pub fn chaos_from_args() -> Option<Chaos> {
+    let args: Vec<String> = std::env::args().collect();
+    let position = args.iter().position(|arg| arg == "--chaos")?;
+    let every = args
+        .get(position + 1)?
+        .parse()
+        .ok()
+        .filter(|&every| every > 0)?;
+    Some(Chaos::every(every))
+}
+
+pub fn unleash_chaos(
+    mut commands: Commands,
+    mut chaos: ResMut<Chaos>,
+    q_selected_shape: Query<(Entity, &Children), With<SelectedShape>>,
+) {
+    chaos.frames += 1;
+    if !chaos.frames.is_multiple_of(chaos.every) {
+        return;
+    }
+    let Ok((entity, children)) = q_selected_shape.get_single() else {
+        return;
+    };
+    chaos.strikes += 1;
+    if chaos.strikes.is_multiple_of(2) {
+        info!("chaos: stripping the selected shape's tiles");
+        // Detached first, clearing children touches each of them
+        commands.entity(entity).clear_children();
+        for &child in children.iter() {
+            commands.entity(child).despawn();
+        }
+    } else {
+        info!("chaos: despawning the selected shape");
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// A fresh directory for the files of one headless app, holding only its settings. Apps
+/// of the same test process never share one.
+fn headless_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "blocks-harness-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("creating the harness directory");
+    std::fs::write(dir.join("settings.txt"), HEADLESS_SETTINGS)
+        .expect("writing the harness settings");
+    dir
+}
+
+/// The game on bevy's `MinimalPlugins`, without a window, renderer or audio output. Its
+/// frames take [`FRAME_TIME`] each, and its files are kept in a [`DataDir`] of its own,
+/// see [`headless_dir`].
+pub fn headless_app() -> App {
+    let mut app = App::new();
+    app.insert_resource(DataDir(headless_dir()));
+    app.add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        // Hidden boards don't take the cursor
+        bevy::render::view::VisibilityPlugin,
+        bevy::input::InputPlugin,
+        AssetPlugin::default(),
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        },
+    ))
+    // Assets the game loads or creates, whose plugins need a renderer or audio device
+    .init_asset::<bevy::audio::AudioSource>()
+    .init_asset::<Image>()
+    .init_asset::<Font>()
+    .init_asset::<TextureAtlas>()
+    .init_asset::<ColorMaterial>()
+    .init_asset::<Mesh>()
+    .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME_TIME))
+    .add_plugins(BlocksPlugin::default())
+    // Until the settings file is read in
+    .insert_resource(Settings {
+        tutorial_completed: true,
+        intro_animation: false,
+        tile_fade: false,
+        ..default()
+    });
+    app
+}
+
+/// Starts a game and runs frames until the main board takes placements, its intro done
+/// and the grace period of the closed menu over.
+pub fn start_game(app: &mut App) {
+    app.update();
+    app.world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    for _ in 0..MAX_START_FRAMES {
+        app.update();
+        let ready = app
+            .world
+            .query_filtered::<(), (With<MainBoard>, With<BoardReady>)>()
+            .iter(&app.world)
+            .next()
+            .is_some();
+        if ready && !app.world.resource::<ClickGuard>().active() {
+            return;
+        }
+    }
+    panic!("the board wasn't ready after {MAX_START_FRAMES} frames");
+}
+
+/// Moves the simulated cursor over a cell of the main board and clicks it, running the
+/// frame that reads the cursor and the one that places.
+pub fn click_cell(app: &mut App, cell: (usize, usize)) {
+    app.insert_resource(SimulatedCursor::Cell(cell));
+    app.update();
+    app.world.send_event(SimulatedClick);
+    app.update();
+}
+
+/// Presses and releases a key, running the frame that sees it pressed.
+pub fn press_key(app: &mut App, key_code: KeyCode) {
+    press_keys(app, &[key_code]);
+}
+
+/// Presses keys together, like Ctrl+R, and releases them the frame after.
+pub fn press_keys(app: &mut App, key_codes: &[KeyCode]) {
+    for state in [ButtonState::Pressed, ButtonState::Released] {
+        for &key_code in key_codes {
+            app.world.send_event(KeyboardInput {
+                scan_code: 0,
+                key_code: Some(key_code),
+                state,
+                window: Entity::PLACEHOLDER,
+            });
+        }
+        app.update();
+    }
+}
+
+/// Presses and releases a button of the first gamepad, running the frame that sees it
+/// pressed.
+pub fn press_button(app: &mut App, button: GamepadButtonType) {
+    for value in [1., 0.] {
+        app.world
+            .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                Gamepad::new(0),
+                button,
+                value,
+            )));
+        app.update();
+    }
+}
+
+/// Entity of the main board.
+pub fn main_board(app: &mut App) -> Entity {
+    app.world
+        .query_filtered::<Entity, (With<MainBoard>, Without<OverlayBoard>)>()
+        .single(&app.world)
+}
+
+/// The selected shape as drawn now.
+pub fn selected_shape(app: &mut App) -> ShapeInstance {
+    *app.world
+        .query_filtered::<&ShapeInstance, With<SelectedShape>>()
+        .single(&app.world)
+}
+
+/// Cells of the main board whose tile sprite isn't painted in the color of the cell.
+pub fn unpainted_tiles(app: &mut App) -> Vec<(usize, usize)> {
+    let board = main_board(app);
+    let board = app.world.get::<Board>(board).unwrap();
+    let (zone_map, palette) = (app.world.resource(), app.world.resource());
+    board
+        .grid
+        .positions()
+        .filter(|&(x, y)| {
+            let sprite = app.world.get::<Sprite>(board.entities[y][x]).unwrap();
+            sprite.color != ui::tile_color(board, zone_map, palette, x, y)
+        })
+        .collect()
+}
+
+/// The shapes coming up after the selected one, the next first.
+pub fn tray(app: &mut App) -> Vec<ShapeInstance> {
+    let board = main_board(app);
+    app.world
+        .get::<Tray>(board)
+        .unwrap()
+        .iter()
+        .copied()
+        .collect()
+}
+
+/// The registered shape of the given name, unrotated, in the color.
+pub fn named_shape(app: &App, name: &str, color: TileColor) -> ShapeInstance {
+    let registry = app.world.resource::<ShapeRegistry>();
+    let id = registry
+        .iter()
+        .map(|(id, _)| id)
+        .find(|&id| registry.name(id) == name)
+        .unwrap_or_else(|| panic!("no shape named {name}"));
+    ShapeInstance {
+        id,
+        rotation: 0,
+        color,
+    }
+}
+
+/// Replaces the selected shape, as if it had been drawn.
+pub fn select_shape(app: &mut App, instance: ShapeInstance) {
+    let selected = app
+        .world
+        .query_filtered::<Entity, With<SelectedShape>>()
+        .single(&app.world);
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    commands.entity(selected).despawn_recursive();
+    build_shape(
+        &mut commands,
+        app.world.resource::<ShapeRegistry>(),
+        app.world.resource::<ColorPalette>(),
+        &instance,
+    )
+    .insert((SelectedShape, Transform::from_scale(Vec3::splat(TILE_SIZE))));
+    queue.apply(&mut app.world);
+}
+
+/// Sets the metadata of cells of the main board, like bonus multipliers.
+pub fn set_meta(app: &mut App, cells: impl IntoIterator<Item = (usize, usize)>, meta: CellMeta) {
+    let board = main_board(app);
+    app.world.send_event(BoardCommand::SetMeta {
+        board,
+        cells: cells.into_iter().collect::<CellList>(),
+        meta,
+    });
+    app.update();
+}
+
+/// Fills cells of the main board, or empties them with `None`, as game rules do.
+pub fn fill(
+    app: &mut App,
+    cells: impl IntoIterator<Item = (usize, usize)>,
+    color: Option<TileColor>,
+) {
+    let board = main_board(app);
+    app.world.send_event(BoardCommand::Fill {
+        board,
+        cells: cells.into_iter().collect::<CellList>(),
+        color,
+    });
+    app.update();
+}
+
+/// Code of the replay of the game in progress, as copied from the game over screen.
+pub fn replay_code(app: &mut App) -> String {
+    let board = main_board(app);
+    replay::record(
+        app.world.resource::<ReplayLog>(),
+        app.world.resource::<GameRng>(),
+        (
+            *app.world.resource::<GameMode>(),
+            app.world.resource::<RuleSet>(),
+        ),
+        app.world.get::<Board>(board).unwrap(),
+    )
+    .encode()
+}
+
+/// Watches the replay of the code, as from the menu, and runs frames until its last
+/// placement was resolved.
+pub fn watch_replay(app: &mut App, code: &str) {
+    app.world.send_event(WatchReplayEvent(code.to_owned()));
+    for _ in 0..MAX_REPLAY_FRAMES {
+        app.update();
+        if app
+            .world
+            .get_resource::<Playback>()
+            .is_some_and(Playback::finished)
+        {
+            app.update();
+            return;
+        }
+    }
+    panic!("the replay wasn't over after {MAX_REPLAY_FRAMES} frames");
+}
@@ -0,0 +1,97 @@
+//! Holding Alt shows where the selected shape can go: every anchor at which it fits
+//! and the placement constraints allow it is tinted on the overlay, brighter where the
+//! placement would clear lines. The scan is spread over frames within the
+//! [`ScanBudget`] and kept until the board, the shape or the constraints change.
+
+use bevy::prelude::*;
+
+use crate::{
+    board::anchors,
+    budget::{FrameBudget, ScanBudget},
+    constraints::PlacementConstraints,
+    highlight::{composite_highlights, HighlightLayer, Highlights},
+    menu::GameState,
+    shape::{ShapeInstance, ShapeRegistry},
+    BlocksSystems, Board, MainBoard, OverlayBoard, SelectedShape,
+};
+
+const PLACEABLE_COLOR: Color = Color::rgba(0.3, 0.6, 1., 0.2);
+const CLEARING_COLOR: Color = Color::rgba(0.6, 0.85, 1., 0.55);
+
+/// Anchors found placeable for a board revision and shape.
+#[derive(Default)]
+struct HeatmapScan {
+    key: Option<(u64, ShapeInstance)>,
+    /// Anchors checked so far, in scan order.
+    scanned: usize,
+    /// Placeable anchors and whether placing there clears lines.
+    found: Vec<((usize, usize), bool)>,
+}
+
+pub struct HeatmapPlugin;
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            show_heatmap
+                .in_set(BlocksSystems::Presentation)
+                .before(composite_highlights)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn show_heatmap(
+    input_keys: Res<Input<KeyCode>>,
+    registry: Res<ShapeRegistry>,
+    constraints: Res<PlacementConstraints>,
+    scan_budget: Res<ScanBudget>,
+    mut highlights: ResMut<Highlights>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+    mut scan: Local<HeatmapScan>,
+) {
+    highlights.clear(HighlightLayer::Heatmap);
+    if !input_keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
+    let (Ok(board), Ok(selected_shape)) = (q_board.get_single(), q_selected_shape.get_single())
+    else {
+        return;
+    };
+    let board = &board.grid;
+    let key = (board.revision(), *selected_shape);
+    if scan.key != Some(key) || constraints.is_changed() {
+        *scan = HeatmapScan {
+            key: Some(key),
+            ..default()
+        };
+    }
+
+    let shape = selected_shape.resolve(&registry);
+    let shape_rows = shape.row_masks();
+    let bitboard = board.bitboard();
+    let budget = FrameBudget::new(scan_budget.0);
+    for (x, y) in anchors(shape).skip(scan.scanned) {
+        if !budget.check() {
+            break;
+        }
+        scan.scanned += 1;
+        let anchor = (x as isize, y as isize);
+        if bitboard.fits_at(&shape_rows, (x, y))
+            && constraints.check(board, shape, anchor).is_none()
+        {
+            let clears = bitboard.completes_line(&shape_rows, (x, y));
+            scan.found.push(((x, y), clears));
+        }
+    }
+
+    for &(cell, clears) in scan.found.iter() {
+        let color = if clears {
+            CLEARING_COLOR
+        } else {
+            PLACEABLE_COLOR
+        };
+        highlights.set(HighlightLayer::Heatmap, cell, color);
+    }
+}
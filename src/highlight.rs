@@ -0,0 +1,185 @@
+//! Highlights on the overlay board, kept in [`HighlightLayer`]s so features drawing
+//! there don't paint over each other. Systems put their cells into [`Highlights`] and
+//! [`composite_highlights`] paints the overlay from them once a frame, each cell in the
+//! color of the highest layer holding it.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{board::TileColor, ui::SpriteMutations, Board, OverlayBoard};
+
+/// Layers of the overlay, from the bottom up.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum HighlightLayer {
+    /// Where the placement constraints allow placing.
+    Regions,
+    /// Every anchor the selected shape can be placed at, while Alt is held.
+    Heatmap,
+    /// The idle hint's placement.
+    Hint,
+    /// Placements staged in the planning mode.
+    Plan,
+    /// Where the preview was before jumping with a fast cursor, fading out.
+    Ghost,
+    /// Where the selected shape would land, and the cascade it would cause.
+    Preview,
+    /// Candidate placements of the autoplaying AI in its step mode.
+    Autoplay,
+    /// The placement shown while reviewing a finished game.
+    Review,
+    Tutorial,
+    Danger,
+}
+const LAYERS: usize = HighlightLayer::Danger as usize + 1;
+
+/// Colored cells of every [`HighlightLayer`].
+#[derive(Resource, Default)]
+pub struct Highlights {
+    layers: [HashMap<(usize, usize), Color>; LAYERS],
+    /// Cells painted by the last composite.
+    shown: Vec<(usize, usize)>,
+}
+impl Highlights {
+    /// Colors a cell in the layer, replacing its color there.
+    pub fn set(&mut self, layer: HighlightLayer, cell: (usize, usize), color: Color) {
+        self.layers[layer as usize].insert(cell, color);
+    }
+
+    /// Removes every cell of the layer.
+    pub fn clear(&mut self, layer: HighlightLayer) {
+        self.layers[layer as usize].clear();
+    }
+
+    /// Removes every cell of every layer, e.g. for a feature taking over the overlay.
+    pub fn clear_all(&mut self) {
+        for layer in self.layers.iter_mut() {
+            layer.clear();
+        }
+    }
+
+    /// Color of the cell in the highest layer holding it.
+    pub fn color_at(&self, cell: (usize, usize)) -> Option<Color> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.get(&cell).copied())
+    }
+}
+
+/// Paints the cells of the layers onto the overlay and clears those that dropped out
+/// of all of them.
+pub fn composite_highlights(
+    mut highlights: ResMut<Highlights>,
+    q_overlay_board: Query<&Board, With<OverlayBoard>>,
+    mut q_sprites: Query<&mut Sprite>,
+    mut sprite_mutations: ResMut<SpriteMutations>,
+) {
+    let Ok(overlay) = q_overlay_board.get_single() else {
+        return;
+    };
+    let mut cells: Vec<_> = highlights
+        .layers
+        .iter()
+        .flat_map(|layer| layer.keys().copied())
+        .collect();
+    cells.sort_unstable();
+    cells.dedup();
+    let dropped = highlights
+        .shown
+        .iter()
+        .filter(|cell| cells.binary_search(cell).is_err())
+        .map(|&cell| (cell, TileColor::Transparent.into()));
+    let painted = cells
+        .iter()
+        .filter_map(|&cell| Some((cell, highlights.color_at(cell)?)));
+    for ((x, y), color) in dropped.chain(painted) {
+        if let Ok(mut sprite) = q_sprites.get_mut(overlay.entities[y][x]) {
+            if sprite.color != color {
+                sprite.color = color;
+                sprite_mutations.0 += 1;
+            }
+        }
+    }
+    highlights.shown = cells;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::board::{Board as BoardGrid, BOARD_HEIGHT, BOARD_WIDTH};
+
+    #[test]
+    fn higher_layers_win_and_clearing_reveals_lower_ones() {
+        let mut highlights = Highlights::default();
+        highlights.set(HighlightLayer::Preview, (1, 1), Color::RED);
+        highlights.set(HighlightLayer::Tutorial, (1, 1), Color::GREEN);
+        highlights.set(HighlightLayer::Hint, (2, 1), Color::BLUE);
+        assert_eq!(highlights.color_at((1, 1)), Some(Color::GREEN));
+
+        highlights.clear(HighlightLayer::Tutorial);
+        assert_eq!(highlights.color_at((1, 1)), Some(Color::RED));
+        assert_eq!(highlights.color_at((2, 1)), Some(Color::BLUE));
+        highlights.clear_all();
+        assert_eq!(highlights.color_at((1, 1)), None);
+    }
+
+    /// A world with an overlay board of plain sprites.
+    fn overlay_world() -> (World, Entity) {
+        let mut world = World::new();
+        world.init_resource::<Highlights>();
+        world.init_resource::<SpriteMutations>();
+        let mut entities = [[Entity::PLACEHOLDER; BOARD_WIDTH]; BOARD_HEIGHT];
+        for entity in entities.iter_mut().flatten() {
+            *entity = world.spawn(Sprite::default()).id();
+        }
+        let board = world
+            .spawn((
+                Board {
+                    grid: BoardGrid::default(),
+                    entities,
+                    badges: entities,
+                    extents: Rect::default(),
+                    texture: None,
+                },
+                OverlayBoard,
+            ))
+            .id();
+        (world, board)
+    }
+
+    fn sprite_color(world: &World, board: Entity, (x, y): (usize, usize)) -> Color {
+        let tile = world.get::<Board>(board).unwrap().entities[y][x];
+        world.get::<Sprite>(tile).unwrap().color
+    }
+
+    #[test]
+    fn compositing_paints_the_top_layer_and_clears_dropped_cells() {
+        let (mut world, board) = overlay_world();
+        let mut highlights = world.resource_mut::<Highlights>();
+        highlights.set(HighlightLayer::Preview, (3, 4), Color::RED);
+        highlights.set(HighlightLayer::Tutorial, (3, 4), Color::GREEN);
+        highlights.set(HighlightLayer::Preview, (5, 4), Color::RED);
+        world.run_system_once(composite_highlights);
+        assert_eq!(sprite_color(&world, board, (3, 4)), Color::GREEN);
+        assert_eq!(sprite_color(&world, board, (5, 4)), Color::RED);
+        assert_eq!(world.resource::<SpriteMutations>().0, 2);
+
+        world
+            .resource_mut::<Highlights>()
+            .clear(HighlightLayer::Preview);
+        world.run_system_once(composite_highlights);
+        assert_eq!(sprite_color(&world, board, (3, 4)), Color::GREEN);
+        assert_eq!(
+            sprite_color(&world, board, (5, 4)),
+            TileColor::Transparent.into()
+        );
+
+        // Cells no layer holds are left alone
+        world.resource_mut::<SpriteMutations>().0 = 0;
+        world.run_system_once(composite_highlights);
+        assert_eq!(world.resource::<SpriteMutations>().0, 0);
+    }
+}
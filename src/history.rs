@@ -0,0 +1,366 @@
+//! Panel listing the latest placements on the main board: a tiny drawing of the shape,
+//! where it went, the points it earned and the lines it cleared. Tab shows or hides
+//! it, the mouse wheel over it scrolls through the entries.
+//!
+//! Clicking an entry while watching a replay or reviewing a finished game shows the
+//! board as it was after that move, see [`Review`].
+
+use std::collections::VecDeque;
+
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    ui::RelativeCursorPosition,
+};
+
+use crate::{
+    highlight::Highlights,
+    palette::ColorPalette,
+    replay::Playback,
+    restart::RestartEvent,
+    review::Review,
+    scoring::Score,
+    shape::{ShapeInstance, ShapeRegistry},
+    CellList, LinesClearedEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+/// Placements kept in the history, the oldest dropping off.
+const HISTORY_LEN: usize = 20;
+/// Pixels per cell of the shape drawings.
+const CELL_PIXELS: f32 = 4.;
+/// Pixels scrolled per line of the mouse wheel.
+const SCROLL_LINE_PIXELS: f32 = 20.;
+const PANEL_WIDTH: f32 = 180.;
+
+pub(crate) struct Move {
+    /// Placements since the restart, this one included.
+    pub number: usize,
+    pub shape: ShapeInstance,
+    /// Cells the shape filled.
+    pub cells: CellList,
+    pub points: u64,
+    /// Lines the placement cleared.
+    pub lines: usize,
+}
+impl Move {
+    /// Lowest, leftmost cell of the placement.
+    fn cell(&self) -> (usize, usize) {
+        self.cells
+            .iter()
+            .copied()
+            .min_by_key(|&(x, y)| (y, x))
+            .unwrap_or_default()
+    }
+}
+
+/// Latest placements on the main board, the newest first.
+#[derive(Resource, Default)]
+pub(crate) struct MoveHistory(VecDeque<Move>);
+impl MoveHistory {
+    pub fn latest(&self) -> Option<&Move> {
+        self.0.front()
+    }
+
+    /// Forgets the latest placement, which was taken back.
+    pub fn take_back(&mut self) -> Option<Move> {
+        self.0.pop_front()
+    }
+}
+
+#[derive(Component)]
+struct HistoryPanel;
+
+/// Entries of the panel, moved up by [`HistoryList::scroll`] pixels.
+#[derive(Component, Default)]
+struct HistoryList {
+    scroll: f32,
+}
+
+/// An entry of the panel, with the [`Move::number`] of its placement.
+#[derive(Component)]
+struct HistoryEntry(usize);
+
+pub struct HistoryPlugin;
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MoveHistory>()
+            .add_systems(Startup, spawn_history_panel)
+            .add_systems(Update, (toggle_history_panel, scroll_history, jump_to_move))
+            // Placements are only scored by the end of the frame
+            .add_systems(
+                PostUpdate,
+                (
+                    record_moves,
+                    list_moves.run_if(resource_changed::<MoveHistory>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+pub(crate) fn record_moves(
+    mut history: ResMut<MoveHistory>,
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    mut restarts: EventReader<RestartEvent>,
+    mut scored: Local<u64>,
+    q_main_board: Query<&Score, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok(score) = q_main_board.get_single() else {
+        return;
+    };
+    if restarts.read().count() > 0 {
+        history.0.clear();
+        *scored = score.0;
+    }
+    let lines: usize = lines_cleared
+        .read()
+        .filter(|event| q_main_board.contains(event.board))
+        .map(|event| event.lines.len())
+        .sum();
+    // A frame holds at most one placement, which earned everything scored since
+    let points = score.0.saturating_sub(*scored);
+    *scored = score.0;
+    for event in placements.read() {
+        if !q_main_board.contains(event.board) {
+            continue;
+        }
+        if event.cells.is_empty() {
+            continue;
+        }
+        let number = history.0.front().map_or(1, |latest| latest.number + 1);
+        history.0.push_front(Move {
+            number,
+            shape: event.shape,
+            cells: event.cells.clone(),
+            points,
+            lines,
+        });
+        history.0.truncate(HISTORY_LEN);
+    }
+}
+
+fn spawn_history_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(120.),
+                    right: Val::Px(8.),
+                    width: Val::Px(PANEL_WIDTH),
+                    height: Val::Percent(50.),
+                    padding: UiRect::all(Val::Px(4.)),
+                    overflow: Overflow::clip_y(),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+            RelativeCursorPosition::default(),
+            Interaction::default(),
+            HistoryPanel,
+        ))
+        .with_children(|commands| {
+            commands.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(6.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                HistoryList::default(),
+            ));
+        });
+}
+
+fn toggle_history_panel(
+    input_keys: Res<Input<KeyCode>>,
+    mut q_panel: Query<&mut Style, With<HistoryPanel>>,
+) {
+    if !input_keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    if let Ok(mut style) = q_panel.get_single_mut() {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+}
+
+fn scroll_history(
+    mut wheel: EventReader<MouseWheel>,
+    q_panel: Query<(&RelativeCursorPosition, &Node), With<HistoryPanel>>,
+    mut q_list: Query<(&mut HistoryList, &mut Style, &Node)>,
+) {
+    let delta: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE_PIXELS,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum();
+    let (Ok((cursor, panel)), Ok((mut list, mut style, list_node))) =
+        (q_panel.get_single(), q_list.get_single_mut())
+    else {
+        return;
+    };
+    if delta == 0. || !cursor.mouse_over() {
+        return;
+    }
+    let max_scroll = (list_node.size().y - panel.size().y).max(0.);
+    list.scroll = (list.scroll - delta).clamp(0., max_scroll);
+    style.top = Val::Px(-list.scroll);
+}
+
+/// Reviews the board after the clicked move, while watching a replay or reviewing.
+/// Playing on from an earlier board would undo moves, so the game itself is left alone.
+fn jump_to_move(
+    mut commands: Commands,
+    playback: Option<Res<Playback>>,
+    review: Option<Res<Review>>,
+    mut highlights: ResMut<Highlights>,
+    q_entries: Query<(&Interaction, &HistoryEntry), Changed<Interaction>>,
+) {
+    let Some((_, entry)) = q_entries
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+    else {
+        return;
+    };
+    if playback.is_none() && review.is_none() {
+        return;
+    }
+    if review.is_none() {
+        // The review takes over the overlay
+        highlights.clear_all();
+    }
+    commands.insert_resource(Review::after(entry.0));
+}
+
+/// Draws the shape out of UI nodes, a cell per filled field.
+fn shape_drawing(
+    commands: &mut ChildBuilder,
+    registry: &ShapeRegistry,
+    palette: &ColorPalette,
+    instance: &ShapeInstance,
+) {
+    let shape = instance.resolve(registry);
+    let (width, height) = shape.bounds();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(8. * CELL_PIXELS),
+                // Row 0 is at the bottom, like on the board
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|commands| {
+            for y in 0..height {
+                commands
+                    .spawn(NodeBundle::default())
+                    .with_children(|commands| {
+                        for x in 0..width {
+                            let color = if shape.fields[y][x] {
+                                palette.color(instance.color)
+                            } else {
+                                Color::NONE
+                            };
+                            commands.spawn(NodeBundle {
+                                style: Style {
+                                    width: Val::Px(CELL_PIXELS),
+                                    height: Val::Px(CELL_PIXELS),
+                                    ..default()
+                                },
+                                background_color: color.into(),
+                                ..default()
+                            });
+                        }
+                    });
+            }
+        });
+}
+
+/// Rebuilds the entries of the panel from the history.
+fn list_moves(
+    mut commands: Commands,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    history: Res<MoveHistory>,
+    mut q_list: Query<(Entity, &mut HistoryList, &mut Style)>,
+) {
+    let Ok((list_entity, mut list, mut style)) = q_list.get_single_mut() else {
+        return;
+    };
+    list.scroll = 0.;
+    style.top = Val::Px(0.);
+    let mut list = commands.entity(list_entity);
+    list.despawn_descendants();
+    list.with_children(|commands| {
+        for entry in &history.0 {
+            commands
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            column_gap: Val::Px(6.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Interaction::default(),
+                    HistoryEntry(entry.number),
+                ))
+                .with_children(|commands| {
+                    shape_drawing(commands, &registry, &palette, &entry.shape);
+                    let (x, y) = entry.cell();
+                    let mut label = format!("{}, {}  +{}", x + 1, y + 1, entry.points);
+                    if entry.lines > 0 {
+                        label += &format!("  {} cleared", entry.lines);
+                    }
+                    commands.spawn(TextBundle::from_section(
+                        label,
+                        TextStyle {
+                            font_size: 14.,
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    fn world_with_click(entry: usize) -> World {
+        let mut world = World::new();
+        world.init_resource::<Highlights>();
+        world.spawn((Interaction::Pressed, HistoryEntry(entry)));
+        world
+    }
+
+    #[test]
+    fn clicking_a_move_while_reviewing_shows_the_board_after_it() {
+        let mut world = world_with_click(3);
+        world.insert_resource(Review::after(9));
+        world.run_system_once(jump_to_move);
+        assert_eq!(world.get_resource::<Review>(), Some(&Review::after(3)));
+    }
+
+    #[test]
+    fn clicking_a_move_while_playing_leaves_the_game_alone() {
+        let mut world = world_with_click(3);
+        world.run_system_once(jump_to_move);
+        assert!(world.get_resource::<Review>().is_none());
+    }
+}
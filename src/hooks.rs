@@ -0,0 +1,279 @@
+//! Scripting hooks: Rust closures registered in [`BlocksHooks`] before the
+//! [`crate::BlocksPlugin`] is added, called on placements, clears and the end of the
+//! game on the main board. Hooks act through a [`BoardCommands`] handle, which queues
+//! board changes, toasts and score adjustments rather than reaching into the world.
+//!
+//! Hooks run at the end of the frame, after the game handled the event itself, in the
+//! order they were registered. A hook that panics is disabled for the rest of the
+//! session, with a toast, and whatever it queued is dropped.
+
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    panic::{self, AssertUnwindSafe},
+};
+
+use bevy::prelude::*;
+use smallvec::smallvec;
+
+use crate::{
+    board::{Board as BoardGrid, CellMeta, ClearedLines, TileColor},
+    events::BoardCommand,
+    scoring::Score,
+    shape::ShapeInstance,
+    strings::Strings,
+    toast::spawn_toast,
+    Board, CellList, GameOverEvent, LinesClearedEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+type Hook<T> = Box<dyn Fn(&T, &mut BoardCommands) + Send + Sync>;
+
+/// A placement on the main board, as hooks see it.
+pub struct PlacementResult {
+    pub shape: ShapeInstance,
+    pub anchor: (isize, isize),
+    pub cells: CellList,
+}
+
+/// The end of a game, as hooks see it.
+pub struct GameOverResult {
+    pub score: u64,
+}
+
+/// Hooks to call, each list in registration order. Insert it before adding the
+/// plugin, which otherwise starts without any.
+#[derive(Resource, Default)]
+pub struct BlocksHooks {
+    pub on_place: Vec<Hook<PlacementResult>>,
+    pub on_clear: Vec<Hook<ClearedLines>>,
+    pub on_game_over: Vec<Hook<GameOverResult>>,
+    /// Hooks that panicked, by list and index.
+    failed: HashSet<(&'static str, usize)>,
+}
+impl BlocksHooks {
+    pub fn on_place(
+        mut self,
+        hook: impl Fn(&PlacementResult, &mut BoardCommands) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_place.push(Box::new(hook));
+        self
+    }
+
+    pub fn on_clear(
+        mut self,
+        hook: impl Fn(&ClearedLines, &mut BoardCommands) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_clear.push(Box::new(hook));
+        self
+    }
+
+    pub fn on_game_over(
+        mut self,
+        hook: impl Fn(&GameOverResult, &mut BoardCommands) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_game_over.push(Box::new(hook));
+        self
+    }
+}
+
+/// What a hook may do to the game, applied once it returned.
+pub struct BoardCommands<'a> {
+    grid: &'a BoardGrid,
+    board: Entity,
+    commands: Vec<BoardCommand>,
+    toasts: Vec<String>,
+    points: i64,
+}
+impl<'a> BoardCommands<'a> {
+    fn new(grid: &'a BoardGrid, board: Entity) -> Self {
+        Self {
+            grid,
+            board,
+            commands: Vec::new(),
+            toasts: Vec::new(),
+            points: 0,
+        }
+    }
+
+    /// The main board as of the event, before anything queued here.
+    pub fn grid(&self) -> &BoardGrid {
+        self.grid
+    }
+
+    /// Sets the cells to the color, or empties them with `None`.
+    pub fn fill(
+        &mut self,
+        cells: impl IntoIterator<Item = (usize, usize)>,
+        color: Option<TileColor>,
+    ) {
+        self.commands.push(BoardCommand::Fill {
+            board: self.board,
+            cells: cells.into_iter().collect(),
+            color,
+        });
+    }
+
+    /// Makes the cell multiply the points of the clear removing it.
+    pub fn set_bonus(&mut self, cell: (usize, usize), multiplier: u8) {
+        self.commands.push(BoardCommand::SetMeta {
+            board: self.board,
+            cells: smallvec![cell],
+            meta: CellMeta {
+                bonus: multiplier,
+                ..default()
+            },
+        });
+    }
+
+    /// Pushes the board up by a garbage row per gap.
+    pub fn garbage_rows(&mut self, gaps: Vec<usize>) {
+        self.commands.push(BoardCommand::GarbageRows {
+            board: self.board,
+            gaps,
+        });
+    }
+
+    /// Mirrors the board left to right.
+    pub fn mirror_horizontal(&mut self) {
+        self.commands
+            .push(BoardCommand::MirrorHorizontal { board: self.board });
+    }
+
+    /// Mirrors the board top to bottom.
+    pub fn mirror_vertical(&mut self) {
+        self.commands
+            .push(BoardCommand::MirrorVertical { board: self.board });
+    }
+
+    /// Turns the board a quarter clockwise.
+    pub fn rotate_90(&mut self) {
+        self.commands
+            .push(BoardCommand::Rotate90 { board: self.board });
+    }
+
+    pub fn toast(&mut self, message: impl Into<String>) {
+        self.toasts.push(message.into());
+    }
+
+    /// Adds to the score, or takes from it down to 0 with negative points.
+    pub fn add_score(&mut self, points: i64) {
+        self.points += points;
+    }
+}
+
+thread_local! {
+    static RUNNING_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether a hook is running on this thread, so a panic is its to be caught and no
+/// crash of the game.
+pub fn running_hook() -> bool {
+    RUNNING_HOOK.with(Cell::get)
+}
+
+pub struct HooksPlugin;
+impl Plugin for HooksPlugin {
+    fn build(&self, app: &mut App) {
+        // Keeps the hooks the app registered
+        app.init_resource::<BlocksHooks>()
+            .add_systems(PostUpdate, run_hooks);
+    }
+}
+
+/// Calls the list's hooks that haven't failed yet, disabling those that panic.
+/// Returns whether any did.
+fn call_hooks<T>(
+    hooks: &[Hook<T>],
+    list: &'static str,
+    failed: &mut HashSet<(&'static str, usize)>,
+    event: &T,
+    commands: &mut BoardCommands,
+) -> bool {
+    let mut panicked = false;
+    for (index, hook) in hooks.iter().enumerate() {
+        if failed.contains(&(list, index)) {
+            continue;
+        }
+        let mut queued = BoardCommands::new(commands.grid, commands.board);
+        RUNNING_HOOK.with(|running| running.set(true));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| hook(event, &mut queued)));
+        RUNNING_HOOK.with(|running| running.set(false));
+        match result {
+            Ok(()) => {
+                commands.commands.append(&mut queued.commands);
+                commands.toasts.append(&mut queued.toasts);
+                commands.points += queued.points;
+            }
+            Err(_) => {
+                error!("{list} hook {index} panicked and was disabled");
+                failed.insert((list, index));
+                panicked = true;
+            }
+        }
+    }
+    panicked
+}
+
+fn run_hooks(
+    mut commands: Commands,
+    mut hooks: ResMut<BlocksHooks>,
+    strings: Res<Strings>,
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    mut game_over: EventReader<GameOverEvent>,
+    mut board_commands: EventWriter<BoardCommand>,
+    mut q_board: Query<(&Board, &mut Score, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok((board, mut score, entity)) = q_board.get_single_mut() else {
+        return;
+    };
+    let hooks = &mut *hooks;
+    let mut queued = BoardCommands::new(&board.grid, entity);
+    let mut panicked = false;
+    for event in placements.read().filter(|event| event.board == entity) {
+        let result = PlacementResult {
+            shape: event.shape,
+            anchor: event.anchor,
+            cells: event.cells.clone(),
+        };
+        panicked |= call_hooks(
+            &hooks.on_place,
+            "on_place",
+            &mut hooks.failed,
+            &result,
+            &mut queued,
+        );
+    }
+    for event in lines_cleared.read().filter(|event| event.board == entity) {
+        panicked |= call_hooks(
+            &hooks.on_clear,
+            "on_clear",
+            &mut hooks.failed,
+            &event.lines,
+            &mut queued,
+        );
+    }
+    if game_over.read().count() > 0 {
+        let result = GameOverResult { score: score.0 };
+        panicked |= call_hooks(
+            &hooks.on_game_over,
+            "on_game_over",
+            &mut hooks.failed,
+            &result,
+            &mut queued,
+        );
+    }
+
+    if panicked {
+        spawn_toast(&mut commands, strings.get("toast.hook_failed"));
+    }
+    for command in queued.commands {
+        board_commands.send(command);
+    }
+    for toast in queued.toasts {
+        spawn_toast(&mut commands, toast);
+    }
+    if queued.points != 0 {
+        score.0 = score.0.saturating_add_signed(queued.points);
+    }
+}
@@ -0,0 +1,209 @@
+//! Tracking the cursor across the boards, and a subtle highlight of the board cell under
+//! it, so it is clear which cell the game thinks is pointed at even without a shape
+//! preview.
+
+use bevy::{input::InputSystem, prelude::*, window::PrimaryWindow};
+
+#[cfg(feature = "test-harness")]
+use crate::harness::SimulatedCursor;
+use crate::{
+    keyboard::{ActiveInputKind, GamepadCursor, KeyboardCursor},
+    palette::ColorPalette,
+    systems::Preview,
+    tile_fade::{paint_now, TargetTileColor},
+    ui::tile_color,
+    ui::SpriteMutations,
+    zones::ZoneMap,
+    BlocksSystems, Board, MainBoard, MainCamera, OverlayBoard,
+};
+
+/// How far the hovered tile is brightened towards white.
+const HOVER_BRIGHTEN: f32 = 0.2;
+
+/// Point of a visible board under the cursor.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BoardPoint {
+    pub board: Entity,
+    pub cell: (usize, usize),
+    pub world: Vec2,
+    /// Position within the cell, from `(0, 0)` at its lower left corner to `(1, 1)`.
+    pub subcell: Vec2,
+}
+
+/// Where the cursor points, written once per frame before `Update`. The test harness's
+/// simulated cursor wins over the keyboard's target cell while the keyboard has control,
+/// which wins over the gamepad's virtual cursor while the gamepad has control, which
+/// wins over the first touch, which wins over the mouse.
+#[derive(Resource, Default, PartialEq)]
+pub struct CursorBoardPos {
+    /// Position in the window in logical pixels, `None` for the simulated cursor.
+    pub viewport: Option<Vec2>,
+    /// World position of the cursor, also away from the boards.
+    pub world: Option<Vec2>,
+    pub point: Option<BoardPoint>,
+}
+impl CursorBoardPos {
+    /// Board and cell under the cursor.
+    pub fn cell(&self) -> Option<(Entity, (usize, usize))> {
+        self.point.map(|point| (point.board, point.cell))
+    }
+}
+
+pub struct HoverPlugin;
+impl Plugin for HoverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CursorBoardPos>()
+            .add_systems(PreUpdate, track_cursor.after(InputSystem))
+            .add_systems(
+                Update,
+                highlight_hovered_cell
+                    .in_set(BlocksSystems::Presentation)
+                    .run_if(crate::review::not_reviewing)
+                    .after(crate::ui::update_board),
+            );
+    }
+}
+
+pub fn track_cursor(
+    mut cursor: ResMut<CursorBoardPos>,
+    touches: Res<Touches>,
+    (input_kind, keyboard_cursor, gamepad_cursor): (
+        Res<ActiveInputKind>,
+        Res<KeyboardCursor>,
+        Res<GamepadCursor>,
+    ),
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    q_boards: Query<
+        (&Board, &GlobalTransform, &InheritedVisibility, Entity),
+        Without<OverlayBoard>,
+    >,
+    #[cfg(feature = "test-harness")] simulated_cursor: Option<Res<SimulatedCursor>>,
+    q_main_board: Query<
+        (&Board, &GlobalTransform, Entity),
+        (With<MainBoard>, Without<OverlayBoard>),
+    >,
+) {
+    let viewport = touches
+        .iter()
+        .next()
+        .map(|touch| touch.position())
+        .or_else(|| {
+            q_windows
+                .get_single()
+                .ok()
+                .and_then(Window::cursor_position)
+        });
+    let camera = q_camera.get_single().ok();
+    let world = viewport
+        .zip(camera)
+        .and_then(|(position, (camera, camera_transform))| {
+            camera.viewport_to_world(camera_transform, position)
+        })
+        .map(|ray| ray.origin.truncate());
+    // The virtual cursor is shown where it is, e.g. for tooltips
+    let gamepad_world = gamepad_cursor
+        .0
+        .filter(|_| *input_kind == ActiveInputKind::Gamepad);
+    let viewport = gamepad_world
+        .zip(camera)
+        .and_then(|(world, (camera, camera_transform))| {
+            camera.world_to_viewport(camera_transform, world.extend(0.))
+        })
+        .or(viewport);
+    let world = gamepad_world.or(world);
+    let keyboard_world = q_main_board
+        .get_single()
+        .ok()
+        .filter(|_| *input_kind == ActiveInputKind::Keyboard)
+        .and_then(|(board, transform, entity)| {
+            let &cell = keyboard_cursor.cells.get(&entity)?;
+            Some(board.cell_to_world(transform, cell))
+        });
+    let world = keyboard_world.or(world);
+    #[cfg(feature = "test-harness")]
+    let world = simulated_cursor
+        .and_then(|simulated| match *simulated {
+            SimulatedCursor::World(world) => Some(world),
+            SimulatedCursor::Cell(cell) => q_main_board
+                .get_single()
+                .ok()
+                .map(|(board, transform, _)| board.cell_to_world(transform, cell)),
+        })
+        .or(world);
+    let point = world.and_then(|world| {
+        q_boards
+            .iter()
+            .filter(|(_, _, visibility, _)| visibility.get())
+            .find_map(|(board, transform, _, entity)| {
+                let cell = board.world_to_cell(transform, world)?;
+                Some(BoardPoint {
+                    board: entity,
+                    cell,
+                    world,
+                    subcell: board.world_to_local(transform, world).fract(),
+                })
+            })
+    });
+    let next = CursorBoardPos {
+        viewport,
+        world,
+        point,
+    };
+    // Only a moving cursor counts as a change
+    if *cursor != next {
+        *cursor = next;
+    }
+}
+
+/// Moves the color towards white by `amount`, keeping its alpha.
+pub fn brighten(color: Color, amount: f32) -> Color {
+    Color::rgba(
+        color.r() + (1. - color.r()) * amount,
+        color.g() + (1. - color.g()) * amount,
+        color.b() + (1. - color.b()) * amount,
+        color.a(),
+    )
+}
+
+/// Brightens the hovered tile and restores the one hovered before. Colors are derived
+/// from the board each time, so repaints and zone colors are never overwritten with a
+/// stale copy.
+pub fn highlight_hovered_cell(
+    cursor: Res<CursorBoardPos>,
+    preview: Res<Preview>,
+    zone_map: Res<ZoneMap>,
+    palette: Res<ColorPalette>,
+    mut highlighted: Local<Option<(Entity, (usize, usize))>>,
+    q_boards: Query<&Board, Without<OverlayBoard>>,
+    mut q_tiles: Query<(&mut Sprite, Option<&mut TargetTileColor>)>,
+    mut sprite_mutations: ResMut<SpriteMutations>,
+) {
+    // The shape preview already shows where the cursor is
+    let target = cursor.cell().filter(|_| preview.superimposition.is_none());
+    let mut paint = |(board_entity, (x, y)): (Entity, (usize, usize)), lit: bool| {
+        let Ok(board) = q_boards.get(board_entity) else {
+            return;
+        };
+        let Ok((mut sprite, target)) = q_tiles.get_mut(board.entities[y][x]) else {
+            return;
+        };
+        let base = tile_color(board, &zone_map, &palette, x, y);
+        let color = if lit {
+            brighten(base, HOVER_BRIGHTEN)
+        } else {
+            base
+        };
+        if sprite.color != color {
+            paint_now(&mut sprite, target, color);
+            sprite_mutations.0 += 1;
+        }
+    };
+    if let Some(previous) = highlighted.filter(|&previous| Some(previous) != target) {
+        paint(previous, false);
+    }
+    if let Some(target) = target {
+        paint(target, true);
+    }
+    *highlighted = target;
+}
@@ -0,0 +1,41 @@
+//! Input the game reacts to outside of placing shapes: idle tracking and the assist
+//! overlay toggle.
+
+use bevy::prelude::*;
+
+use crate::ui::AssistOverlay;
+
+pub(crate) fn track_idle(
+    time: Res<Time>,
+    mut idle: ResMut<IdleTimer>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    input_mb: Res<Input<MouseButton>>,
+    input_keys: Res<Input<KeyCode>>,
+) {
+    let any_input = cursor_moved.read().count() > 0
+        || input_mb.get_just_pressed().len() > 0
+        || input_keys.get_just_pressed().len() > 0;
+    if any_input {
+        idle.0 = 0.;
+    } else {
+        idle.0 += time.delta_seconds();
+    }
+}
+
+pub(crate) fn toggle_assist_overlay(
+    input_keys: Res<Input<KeyCode>>,
+    mut q_overlay: Query<&mut Visibility, With<AssistOverlay>>,
+) {
+    if input_keys.just_pressed(KeyCode::F3) {
+        for mut visibility in q_overlay.iter_mut() {
+            *visibility = match *visibility {
+                Visibility::Hidden => Visibility::Inherited,
+                _ => Visibility::Hidden,
+            };
+        }
+    }
+}
+
+/// Seconds since the player last moved the cursor, clicked or pressed a key.
+#[derive(Resource, Default)]
+pub struct IdleTimer(pub f32);
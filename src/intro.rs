@@ -0,0 +1,121 @@
+//! Intro of the main board: its tiles grow in a diagonal wave when it spawns and on
+//! every restart, and the selected shape only responds once the wave is over.
+
+use bevy::prelude::*;
+
+use crate::{
+    restart::RestartEvent, settings::Settings, tween::Tween, BlocksSystems, Board, MainBoard,
+    OverlayBoard,
+};
+
+/// Seconds from the first tile starting to grow until the last one is full size.
+const INTRO_DURATION: f32 = 0.4;
+/// Seconds each tile takes to grow.
+const TILE_DURATION: f32 = 0.15;
+
+/// Marks a board whose intro finished, or that never had one.
+#[derive(Component)]
+pub struct BoardReady;
+
+/// Running intro of a board, replaced by [`BoardReady`] when it runs out.
+#[derive(Component)]
+struct IntroWave(Timer);
+
+pub struct IntroPlugin;
+impl Plugin for IntroPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (intro_new_boards, replay_intro, finish_intro)
+                .chain()
+                .before(BlocksSystems::Preview),
+        );
+    }
+}
+
+/// Run condition holding back input on the selected shape while the main board's
+/// intro plays.
+pub fn board_ready(
+    q_board: Query<(), (With<MainBoard>, With<BoardReady>, Without<OverlayBoard>)>,
+) -> bool {
+    !q_board.is_empty()
+}
+
+/// Shrinks the board's tiles to nothing and has them grow back from the bottom left
+/// corner to the top right one.
+fn start_wave(
+    commands: &mut Commands,
+    board_entity: Entity,
+    board: &Board,
+    q_tiles: &mut Query<&mut Transform>,
+) {
+    let last_diagonal = (board.grid.width() + board.grid.height() - 2) as f32;
+    for (y, row) in board.entities.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            let Ok(mut transform) = q_tiles.get_mut(tile) else {
+                continue;
+            };
+            // Held at zero until its delay ran out, also on the first frame
+            transform.scale = Vec3::ZERO;
+            let delay = (x + y) as f32 / last_diagonal * (INTRO_DURATION - TILE_DURATION);
+            commands.entity(tile).insert(
+                Tween::new(TILE_DURATION)
+                    .with_delay(delay)
+                    .with_scale(Vec3::ZERO, Vec3::ONE),
+            );
+        }
+    }
+    commands
+        .entity(board_entity)
+        .remove::<BoardReady>()
+        .insert(IntroWave(Timer::from_seconds(
+            INTRO_DURATION,
+            TimerMode::Once,
+        )));
+}
+
+/// Plays the intro on a new main board, other boards are ready right away.
+fn intro_new_boards(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    q_boards: Query<(Entity, &Board, Has<MainBoard>, Has<OverlayBoard>), Added<Board>>,
+    mut q_tiles: Query<&mut Transform>,
+) {
+    for (entity, board, is_main, is_overlay) in q_boards.iter() {
+        if settings.intro_animation && is_main && !is_overlay {
+            start_wave(&mut commands, entity, board, &mut q_tiles);
+        } else {
+            commands.entity(entity).insert(BoardReady);
+        }
+    }
+}
+
+fn replay_intro(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut restarts: EventReader<RestartEvent>,
+    q_board: Query<(Entity, &Board), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_tiles: Query<&mut Transform>,
+) {
+    if restarts.read().count() == 0 || !settings.intro_animation {
+        return;
+    }
+    for (entity, board) in q_board.iter() {
+        start_wave(&mut commands, entity, board, &mut q_tiles);
+    }
+}
+
+fn finish_intro(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_waves: Query<(Entity, &mut IntroWave)>,
+) {
+    for (entity, mut wave) in q_waves.iter_mut() {
+        if wave.0.tick(time.delta()).finished() {
+            commands
+                .entity(entity)
+                .remove::<IntroWave>()
+                .insert(BoardReady);
+        }
+    }
+}
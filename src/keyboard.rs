@@ -0,0 +1,289 @@
+//! Mouse-free play: the arrow keys or a gamepad's D-pad move a target cell over the
+//! main board, which the preview follows as if the mouse were over it. A gamepad's left
+//! stick moves a virtual cursor over the board instead, freely like the mouse. Space or
+//! the gamepad's south button places, its east button rotates.
+//!
+//! Moving the mouse hands control back to it and hides the target until an arrow is
+//! pressed or the stick pushed again.
+
+use bevy::{input::InputSystem, prelude::*, utils::HashMap};
+
+use crate::{
+    hover::CursorBoardPos, menu::GameState, render_order, replay::Playback, BlocksSystems, Board,
+    MainBoard, OverlayBoard, TILE_SIZE,
+};
+
+/// Seconds an arrow key is held before the target starts repeating its step.
+const REPEAT_DELAY: f32 = 0.25;
+/// Seconds between two repeated steps.
+const REPEAT_INTERVAL: f32 = 0.08;
+/// Length of a bracket's arms and their thickness, in cells.
+const BRACKET_LENGTH: f32 = 0.3;
+const BRACKET_THICKNESS: f32 = 0.08;
+const BRACKET_COLOR: Color = Color::rgb(1., 0.85, 0.3);
+/// Cells per second the virtual cursor moves at with the stick pushed all the way.
+const STICK_SPEED: f32 = 12.;
+/// Stick deflection below which the stick counts as at rest.
+const STICK_DEAD_ZONE: f32 = 0.2;
+const ARROWS: [(KeyCode, GamepadButtonType, (isize, isize)); 4] = [
+    (KeyCode::Left, GamepadButtonType::DPadLeft, (-1, 0)),
+    (KeyCode::Right, GamepadButtonType::DPadRight, (1, 0)),
+    (KeyCode::Down, GamepadButtonType::DPadDown, (0, -1)),
+    (KeyCode::Up, GamepadButtonType::DPadUp, (0, 1)),
+];
+/// Gamepad button placing the selected shape in cursor mode, like Space.
+pub const PLACE_BUTTON: GamepadButtonType = GamepadButtonType::South;
+/// Gamepad button rotating the selected shape, like R.
+pub const ROTATE_BUTTON: GamepadButtonType = GamepadButtonType::East;
+
+/// Which device last steered the cursor.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ActiveInputKind {
+    #[default]
+    Mouse,
+    /// The arrow keys or a gamepad's D-pad.
+    Keyboard,
+    /// A gamepad's left stick, moving the [`GamepadCursor`].
+    Gamepad,
+}
+
+/// World position of the virtual cursor a gamepad's left stick moves, kept while another
+/// device has control.
+#[derive(Resource, Default)]
+pub struct GamepadCursor(pub Option<Vec2>);
+
+/// Target cell of the arrows on each board, kept while the mouse has control.
+#[derive(Resource, Default)]
+pub struct KeyboardCursor {
+    pub cells: HashMap<Entity, (usize, usize)>,
+    /// Index into [`ARROWS`] of the arrow held and the seconds until its next step.
+    held: Option<(usize, f32)>,
+}
+
+/// Whether a gamepad button of the type was pressed this frame, on any gamepad.
+pub fn gamepad_just_pressed(
+    input_gamepad: &Input<GamepadButton>,
+    button: GamepadButtonType,
+) -> bool {
+    input_gamepad
+        .get_just_pressed()
+        .any(|pressed| pressed.button_type == button)
+}
+
+/// Corner brackets framing the target cell.
+#[derive(Component)]
+struct CursorBrackets;
+
+pub struct KeyboardPlugin;
+impl Plugin for KeyboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveInputKind>()
+            .init_resource::<KeyboardCursor>()
+            .init_resource::<GamepadCursor>()
+            .add_systems(Startup, spawn_brackets)
+            .add_systems(
+                PreUpdate,
+                (move_keyboard_cursor, move_gamepad_cursor)
+                    .chain()
+                    .after(InputSystem)
+                    .before(crate::hover::track_cursor),
+            )
+            .add_systems(Update, show_brackets.in_set(BlocksSystems::Presentation));
+    }
+}
+
+/// Whether the arrow keys steer the cursor in the current state.
+fn steerable(state: &GameState) -> bool {
+    matches!(state, GameState::Playing | GameState::Sandbox)
+}
+
+pub fn move_keyboard_cursor(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    input_keys: Res<Input<KeyCode>>,
+    input_mb: Res<Input<MouseButton>>,
+    input_gamepad: Res<Input<GamepadButton>>,
+    cursor: Res<CursorBoardPos>,
+    mut input_kind: ResMut<ActiveInputKind>,
+    mut keyboard_cursor: ResMut<KeyboardCursor>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    playback: Option<Res<Playback>>,
+    q_board: Query<(Entity, &Board), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    if cursor_moved.read().count() > 0 || input_mb.get_just_pressed().next().is_some() {
+        *input_kind = ActiveInputKind::Mouse;
+    }
+    // Replays step with the arrow keys instead
+    if !steerable(state.get()) || playback.is_some() {
+        return;
+    }
+    let Ok((board, grid)) = q_board.get_single() else {
+        return;
+    };
+    let (width, height) = (grid.grid.width(), grid.grid.height());
+
+    let held = |(key, button, _): (KeyCode, GamepadButtonType, _)| {
+        input_keys.pressed(key)
+            || input_gamepad
+                .get_pressed()
+                .any(|pressed| pressed.button_type == button)
+    };
+    let mut step = None;
+    for (index, (key, button, direction)) in ARROWS.into_iter().enumerate() {
+        if input_keys.just_pressed(key) || gamepad_just_pressed(&input_gamepad, button) {
+            keyboard_cursor.held = Some((index, REPEAT_DELAY));
+            step = Some(direction);
+        }
+    }
+    if let Some((index, wait)) = keyboard_cursor.held {
+        if !held(ARROWS[index]) {
+            keyboard_cursor.held = None;
+        } else if step.is_none() {
+            let wait = wait - time.delta_seconds();
+            if wait <= 0. {
+                step = Some(ARROWS[index].2);
+            }
+            keyboard_cursor.held = Some((index, if wait <= 0. { REPEAT_INTERVAL } else { wait }));
+        }
+    }
+    let Some((dx, dy)) = step else {
+        return;
+    };
+
+    // Taking over from the mouse starts where it left off
+    let start = match *input_kind {
+        ActiveInputKind::Mouse | ActiveInputKind::Gamepad => cursor
+            .cell()
+            .filter(|&(entity, _)| entity == board)
+            .map(|(_, cell)| cell),
+        ActiveInputKind::Keyboard => None,
+    };
+    let (x, y) = start
+        .or_else(|| keyboard_cursor.cells.get(&board).copied())
+        .unwrap_or((width / 2, height / 2));
+    let cell = (
+        x.saturating_add_signed(dx).min(width - 1),
+        y.saturating_add_signed(dy).min(height - 1),
+    );
+    keyboard_cursor.cells.insert(board, cell);
+    *input_kind = ActiveInputKind::Keyboard;
+}
+
+/// Moves the virtual cursor with the left stick of any gamepad, within the main board.
+pub fn move_gamepad_cursor(
+    time: Res<Time>,
+    state: Res<State<GameState>>,
+    axes: Res<Axis<GamepadAxis>>,
+    cursor: Res<CursorBoardPos>,
+    mut input_kind: ResMut<ActiveInputKind>,
+    mut gamepad_cursor: ResMut<GamepadCursor>,
+    playback: Option<Res<Playback>>,
+    q_board: Query<(&Board, &GlobalTransform), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    if !steerable(state.get()) || playback.is_some() {
+        return;
+    }
+    let stick = axes.devices().fold(Vec2::ZERO, |stick, axis| {
+        let value = axes.get(*axis).unwrap_or_default();
+        match axis.axis_type {
+            GamepadAxisType::LeftStickX => stick + Vec2::X * value,
+            GamepadAxisType::LeftStickY => stick + Vec2::Y * value,
+            _ => stick,
+        }
+    });
+    if stick.length() < STICK_DEAD_ZONE {
+        return;
+    }
+    let Ok((board, transform)) = q_board.get_single() else {
+        return;
+    };
+    let (width, height) = (board.grid.width(), board.grid.height());
+    let corners =
+        [(0, 0), (width - 1, height - 1)].map(|cell| board.cell_to_world(transform, cell));
+    let (min, max) = (corners[0].min(corners[1]), corners[0].max(corners[1]));
+    // Taking over from another device starts where it left off
+    let start = match *input_kind {
+        ActiveInputKind::Gamepad => gamepad_cursor.0,
+        ActiveInputKind::Mouse | ActiveInputKind::Keyboard => cursor.world,
+    };
+    let start = start.unwrap_or(0.5 * (min + max));
+    let moved = start + stick.clamp_length_max(1.) * STICK_SPEED * TILE_SIZE * time.delta_seconds();
+    gamepad_cursor.0 = Some(moved.clamp(min, max));
+    *input_kind = ActiveInputKind::Gamepad;
+}
+
+fn spawn_brackets(mut commands: Commands) {
+    commands
+        .spawn((
+            SpatialBundle {
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            CursorBrackets,
+        ))
+        .with_children(|commands| {
+            let corner = 0.5 - BRACKET_LENGTH / 2.;
+            let edge = 0.5 - BRACKET_THICKNESS / 2.;
+            for (sx, sy) in [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)] {
+                // One horizontal and one vertical arm per corner
+                for (size, offset) in [
+                    (
+                        Vec2::new(BRACKET_LENGTH, BRACKET_THICKNESS),
+                        Vec2::new(corner, edge),
+                    ),
+                    (
+                        Vec2::new(BRACKET_THICKNESS, BRACKET_LENGTH),
+                        Vec2::new(edge, corner),
+                    ),
+                ] {
+                    commands.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: BRACKET_COLOR,
+                            custom_size: Some(size),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(
+                            (offset * Vec2::new(sx, sy)).extend(0.),
+                        ),
+                        ..default()
+                    });
+                }
+            }
+        });
+}
+
+/// Frames the target cell while the keyboard has control.
+fn show_brackets(
+    state: Res<State<GameState>>,
+    input_kind: Res<ActiveInputKind>,
+    keyboard_cursor: Res<KeyboardCursor>,
+    q_board: Query<(&Board, &GlobalTransform, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_brackets: Query<(&mut Transform, &mut Visibility), With<CursorBrackets>>,
+) {
+    let Ok((mut transform, mut visibility)) = q_brackets.get_single_mut() else {
+        return;
+    };
+    let target = q_board
+        .get_single()
+        .ok()
+        .and_then(|(board, board_transform, entity)| {
+            let &cell = keyboard_cursor.cells.get(&entity)?;
+            Some((board_transform, board.tile_translation(cell)))
+        });
+    let shown = *input_kind == ActiveInputKind::Keyboard && steerable(state.get());
+    let Some((board_transform, local)) = target.filter(|_| shown) else {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    let local = local
+        .truncate()
+        .extend(render_order::in_board(render_order::OVERLAY + 1.));
+    *transform = board_transform
+        .mul_transform(Transform::from_translation(local))
+        .compute_transform();
+    if *visibility != Visibility::Inherited {
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,280 @@
+//! Where the HUD goes around the board, for wide and for tall windows. The `layout`
+//! setting picks landscape, with the HUD above the board and the minimap in the lower
+//! right corner, or portrait, with the board at the top and the HUD in a band below.
+//!
+//! Both fill a [`LayoutConfig`], which is rebuilt when the setting changes, the window
+//! is resized or the main board changes size. Entities with a [`LayoutSlot`] take their position from it, and the
+//! [`MainCamera`] the plugin spawns is framed and zoomed out for the board and the
+//! shape slot beside it to fit, then moved by the [`CameraPan`].
+
+use std::fmt;
+
+use bevy::{
+    prelude::*,
+    transform::TransformSystem,
+    ui::UiSystem,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use crate::{
+    board::{BOARD_HEIGHT, BOARD_WIDTH},
+    settings::Settings,
+    ui::CameraZoom,
+    Board, MainBoard, MainCamera, OverlayBoard, TILE_SIZE,
+};
+
+/// Pixels kept free beside the board.
+const MARGIN: f32 = 16.;
+/// Pixels above and below the board in landscape, where the HUD and toasts go.
+const LANDSCAPE_BAND: f32 = 60.;
+/// Pixels below the board in portrait, where the HUD goes.
+const PORTRAIT_BAND: f32 = 160.;
+/// Cells of the square the shape slot takes up beside the board.
+pub const SHAPE_SLOT_CELLS: f32 = 5.;
+/// Cells between the board and the shape slot in landscape.
+const SHAPE_SLOT_GAP: f32 = 1.;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum LayoutMode {
+    #[default]
+    Landscape,
+    /// For tall windows, like those of vertical monitors.
+    Portrait,
+}
+impl LayoutMode {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "landscape" => Some(LayoutMode::Landscape),
+            "portrait" => Some(LayoutMode::Portrait),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for LayoutMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LayoutMode::Landscape => "landscape",
+            LayoutMode::Portrait => "portrait",
+        })
+    }
+}
+
+/// Offsets of an absolutely positioned UI node from the window's edges.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Anchor {
+    pub top: Val,
+    pub bottom: Val,
+    pub left: Val,
+    pub right: Val,
+}
+impl Anchor {
+    fn apply(&self, style: &mut Style) {
+        style.top = self.top;
+        style.bottom = self.bottom;
+        style.left = self.left;
+        style.right = self.right;
+    }
+}
+
+/// The piece of the HUD an entity is, placed by the [`LayoutConfig`].
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LayoutSlot {
+    Score,
+    PlayTime,
+    Minimap,
+    Toasts,
+}
+
+/// Positions of the HUD and the framing of the board for the current layout mode and
+/// window.
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct LayoutConfig {
+    pub mode: LayoutMode,
+    pub score: Anchor,
+    pub play_time: Anchor,
+    pub minimap: Anchor,
+    pub toasts: Anchor,
+    /// Center of the shape slot, in cells from the board's center.
+    pub shape_slot: Vec2,
+    /// Point of the world the [`MainCamera`] centers on.
+    pub camera_center: Vec2,
+    /// Projection scale of the [`MainCamera`], the configured zoom or less if the board
+    /// wouldn't fit otherwise.
+    pub camera_scale: f32,
+}
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self::new(LayoutMode::Landscape, (BOARD_WIDTH, BOARD_HEIGHT), None, 1.)
+    }
+}
+impl LayoutConfig {
+    /// The layout of the mode around a board of the given cells, in a window of the
+    /// given size in pixels. Without a window, the camera keeps the zoom.
+    pub fn new(
+        mode: LayoutMode,
+        (width, height): (usize, usize),
+        window: Option<Vec2>,
+        zoom: f32,
+    ) -> Self {
+        let board = Vec2::new(width as f32, height as f32);
+        // The slot is left of the board in landscape, framed as if on both sides for the
+        // board to stay centered, and in the band below the board in portrait
+        let framed = match mode {
+            LayoutMode::Landscape => board + Vec2::X * 2. * (SHAPE_SLOT_CELLS + SHAPE_SLOT_GAP),
+            LayoutMode::Portrait => board,
+        } * TILE_SIZE;
+        // Pixels the board may take up
+        let available = |window: Vec2| match mode {
+            LayoutMode::Landscape => window - Vec2::new(2. * MARGIN, 2. * LANDSCAPE_BAND),
+            LayoutMode::Portrait => window - Vec2::new(2. * MARGIN, MARGIN + PORTRAIT_BAND),
+        };
+        let camera_scale = window.map_or(zoom, |window| {
+            let needed = framed / available(window).max(Vec2::ONE);
+            zoom.max(needed.max_element())
+        });
+        match mode {
+            LayoutMode::Landscape => Self {
+                mode,
+                score: Anchor {
+                    top: Val::Px(8.),
+                    left: Val::Percent(40.),
+                    ..default()
+                },
+                play_time: Anchor {
+                    top: Val::Px(32.),
+                    left: Val::Percent(40.),
+                    ..default()
+                },
+                minimap: Anchor {
+                    right: Val::Px(12.),
+                    bottom: Val::Px(12.),
+                    ..default()
+                },
+                toasts: Anchor {
+                    top: Val::Px(80.),
+                    left: Val::Percent(40.),
+                    ..default()
+                },
+                // In the upper left corner beside the board
+                shape_slot: Vec2::new(
+                    -0.5 * (board.x + SHAPE_SLOT_CELLS) - SHAPE_SLOT_GAP,
+                    0.5 * (board.y - SHAPE_SLOT_CELLS),
+                ),
+                camera_center: Vec2::ZERO,
+                camera_scale,
+            },
+            LayoutMode::Portrait => Self {
+                mode,
+                score: Anchor {
+                    bottom: Val::Px(128.),
+                    left: Val::Px(12.),
+                    ..default()
+                },
+                play_time: Anchor {
+                    bottom: Val::Px(104.),
+                    left: Val::Px(12.),
+                    ..default()
+                },
+                minimap: Anchor {
+                    right: Val::Px(12.),
+                    bottom: Val::Px(12.),
+                    ..default()
+                },
+                toasts: Anchor {
+                    bottom: Val::Px(72.),
+                    left: Val::Px(12.),
+                    ..default()
+                },
+                // Centered in the band, between the score and the minimap
+                shape_slot: Vec2::new(0., -0.5 * (board.y + SHAPE_SLOT_CELLS)),
+                // The board's center sits halfway between the top margin and the band,
+                // above the window's center
+                camera_center: Vec2::new(0., -0.5 * (PORTRAIT_BAND - MARGIN) * camera_scale),
+                camera_scale,
+            },
+        }
+    }
+
+    pub fn anchor(&self, slot: LayoutSlot) -> Anchor {
+        match slot {
+            LayoutSlot::Score => self.score,
+            LayoutSlot::PlayTime => self.play_time,
+            LayoutSlot::Minimap => self.minimap,
+            LayoutSlot::Toasts => self.toasts,
+        }
+    }
+}
+
+/// World units the [`MainCamera`] is moved by from the layout's framing, set by
+/// clicking the minimap.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub struct CameraPan(pub Vec2);
+
+pub struct LayoutPlugin;
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LayoutConfig>()
+            .init_resource::<CameraPan>()
+            .add_systems(Update, update_layout)
+            .add_systems(
+                PostUpdate,
+                apply_layout
+                    .before(UiSystem::Layout)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Rebuilds the layout when the setting changed, the window was resized or the main
+/// board changed size.
+fn update_layout(
+    settings: Res<Settings>,
+    zoom: Option<Res<CameraZoom>>,
+    mut resized: EventReader<WindowResized>,
+    mut board_size: Local<Option<(usize, usize)>>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    mut layout: ResMut<LayoutConfig>,
+) {
+    let resized = resized.read().count() > 0;
+    let size = q_board
+        .get_single()
+        .map_or((BOARD_WIDTH, BOARD_HEIGHT), |board| {
+            (board.grid.width(), board.grid.height())
+        });
+    if !(resized || settings.is_changed() || *board_size != Some(size)) {
+        return;
+    }
+    *board_size = Some(size);
+    let window = q_windows
+        .get_single()
+        .ok()
+        .map(|window| Vec2::new(window.width(), window.height()));
+    let zoom = zoom.map_or(1., |zoom| zoom.0);
+    layout.set_if_neq(LayoutConfig::new(settings.layout, size, window, zoom));
+}
+
+/// Moves the slots to their anchors, new ones right away and all of them when the layout
+/// changed, and frames the camera the plugin spawned.
+fn apply_layout(
+    layout: Res<LayoutConfig>,
+    pan: Res<CameraPan>,
+    zoom: Option<Res<CameraZoom>>,
+    mut q_slots: Query<(Ref<LayoutSlot>, &mut Style)>,
+    mut q_camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    for (slot, mut style) in q_slots.iter_mut() {
+        if layout.is_changed() || slot.is_added() {
+            layout.anchor(*slot).apply(&mut style);
+        }
+    }
+    // A camera of the embedding game is its own to frame
+    if !(layout.is_changed() || pan.is_changed()) || zoom.is_none() {
+        return;
+    }
+    for (mut transform, mut projection) in q_camera.iter_mut() {
+        transform.translation.x = layout.camera_center.x + pan.0.x;
+        transform.translation.y = layout.camera_center.y + pan.0.y;
+        projection.scale = layout.camera_scale;
+    }
+}
@@ -0,0 +1,206 @@
+//! Best results of every mode that keeps them, in `leaderboard.txt`. Most modes rank
+//! by score, highest first, and record it when the game ends. Races rank by time,
+//! lowest first, and record their own finishes, see [`crate::sprint`]. Every entry
+//! keeps the play time of its game too.
+
+use std::{collections::BTreeMap, fmt};
+
+use bevy::prelude::*;
+
+use crate::{
+    assist::Assist,
+    menu::GameMode,
+    persistence::{PersistAppExt, PersistRequest, Persisted},
+    play_time::PlayTime,
+    replay::Playback,
+    scoring::Score,
+    GameOverEvent, MainBoard,
+};
+
+/// Results kept per table.
+const TABLE_SIZE: usize = 10;
+
+/// Which end of a table is best.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ranking {
+    HighestFirst,
+    LowestFirst,
+}
+
+/// Name of the mode's table in the file and how it ranks, for modes keeping one.
+fn table(mode: GameMode) -> Option<(&'static str, Ranking)> {
+    match mode {
+        GameMode::Endless => Some(("endless", Ranking::HighestFirst)),
+        GameMode::Daily => Some(("daily", Ranking::HighestFirst)),
+        GameMode::Cascade => Some(("cascade", Ranking::HighestFirst)),
+        GameMode::Adjacency => Some(("adjacency", Ranking::HighestFirst)),
+        GameMode::Blockudoku => Some(("blockudoku", Ranking::HighestFirst)),
+        GameMode::Custom => Some(("custom", Ranking::HighestFirst)),
+        // Milliseconds to reach the goal
+        GameMode::Sprint => Some(("sprint", Ranking::LowestFirst)),
+        GameMode::Dig => Some(("dig", Ranking::LowestFirst)),
+        // Puzzles keep their own records
+        GameMode::Timed | GameMode::Puzzle | GameMode::Versus => None,
+    }
+}
+
+/// A result in a table.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Entry {
+    pub value: u64,
+    /// Seconds the game was played, see [`PlayTime`].
+    pub play_time: f32,
+}
+
+/// Results by table name, each sorted best first.
+#[derive(Resource, Default)]
+pub struct Leaderboard(BTreeMap<String, Vec<Entry>>);
+impl Leaderboard {
+    /// Enters a result in the mode's table, returning its place counting from 1 if it
+    /// made the table. Ties rank below the results already there.
+    pub fn record(&mut self, mode: GameMode, entry: Entry) -> Option<usize> {
+        let (name, ranking) = table(mode)?;
+        let entries = self.0.entry(name.to_owned()).or_default();
+        let place = entries
+            .iter()
+            .position(|other| match ranking {
+                Ranking::HighestFirst => entry.value > other.value,
+                Ranking::LowestFirst => entry.value < other.value,
+            })
+            .unwrap_or(entries.len());
+        if place >= TABLE_SIZE {
+            return None;
+        }
+        entries.insert(place, entry);
+        entries.truncate(TABLE_SIZE);
+        Some(place + 1)
+    }
+
+    /// Best result of the mode, if it has any.
+    pub fn best(&self, mode: GameMode) -> Option<u64> {
+        let (name, _) = table(mode)?;
+        Some(self.0.get(name)?.first()?.value)
+    }
+}
+impl Persisted for Leaderboard {
+    const PATH: &'static str = "leaderboard.txt";
+    const VERSION: u32 = 2;
+
+    /// Reads `<table> <result> <play time>` lines, best first within each table.
+    fn parse(text: &str) -> Option<Self> {
+        let mut tables: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            let (name, value) = (words.next()?, words.next()?.parse().ok()?);
+            // Results of the first version have no play time
+            let play_time = words.next().map_or(Some(0.), |word| word.parse().ok())?;
+            tables
+                .entry(name.to_owned())
+                .or_default()
+                .push(Entry { value, play_time });
+        }
+        Some(Self(tables))
+    }
+
+    fn migrate(version: u32, text: &str) -> Option<Self> {
+        (version <= 1).then(|| Self::parse(text)).flatten()
+    }
+}
+impl fmt::Display for Leaderboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, entries) in &self.0 {
+            for entry in entries {
+                writeln!(f, "{name} {} {}", entry.value, entry.play_time)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Place the last game's result took in its table, `None` if it made none.
+#[derive(Resource, Default)]
+pub struct LatestPlace(pub Option<usize>);
+
+/// Records a finished game's result in its mode's table, along with its play time.
+pub fn record_result(
+    (leaderboard, latest): (&mut Leaderboard, &mut LatestPlace),
+    persist: &mut EventWriter<PersistRequest>,
+    mode: GameMode,
+    entry: Entry,
+) -> Option<usize> {
+    latest.0 = leaderboard.record(mode, entry);
+    let place = latest.0?;
+    persist.send(leaderboard.persist_request());
+    Some(place)
+}
+
+pub struct LeaderboardPlugin;
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.load_persisted::<Leaderboard>()
+            .init_resource::<LatestPlace>()
+            // A watched replay is not a result of the player's
+            .add_systems(
+                Update,
+                record_score.run_if(not(resource_exists::<Playback>())),
+            );
+    }
+}
+
+/// Enters the score of a game that ended in the tables ranking by score.
+fn record_score(
+    (mode, assist, play_time): (Res<GameMode>, Res<Assist>, Res<PlayTime>),
+    (mut leaderboard, mut latest): (ResMut<Leaderboard>, ResMut<LatestPlace>),
+    mut game_over: EventReader<GameOverEvent>,
+    mut persist: EventWriter<PersistRequest>,
+    q_main_board: Query<&Score, With<MainBoard>>,
+) {
+    if game_over.read().count() == 0 {
+        return;
+    }
+    latest.0 = None;
+    // Games the assist eased or toughened don't compare with the others
+    if assist.used {
+        return;
+    }
+    let Ok(score) = q_main_board.get_single() else {
+        return;
+    };
+    if matches!(table(*mode), Some((_, Ranking::HighestFirst))) {
+        let entry = Entry {
+            value: score.0,
+            play_time: play_time.0,
+        };
+        record_result((&mut leaderboard, &mut latest), &mut persist, *mode, entry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_version_results_read_without_a_play_time() {
+        let leaderboard = Leaderboard::migrate(1, "endless 120\nendless 80\n").unwrap();
+        assert_eq!(leaderboard.best(GameMode::Endless), Some(120));
+        assert_eq!(
+            leaderboard.0["endless"][1],
+            Entry {
+                value: 80,
+                play_time: 0.
+            }
+        );
+    }
+
+    #[test]
+    fn results_read_back_with_their_play_time() {
+        let mut leaderboard = Leaderboard::default();
+        let entry = Entry {
+            value: 42_000,
+            play_time: 42.5,
+        };
+        assert_eq!(leaderboard.record(GameMode::Sprint, entry), Some(1));
+        let read = Leaderboard::parse(&leaderboard.to_string()).unwrap();
+        assert_eq!(read.0["sprint"], vec![entry]);
+    }
+}
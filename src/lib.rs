@@ -0,0 +1,118 @@
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub mod ai;
+mod announce;
+mod assist;
+mod attract;
+mod audio;
+mod autoplay;
+pub mod board;
+mod board_texture;
+pub mod budget;
+mod click_guard;
+mod clipboard;
+mod components;
+mod config;
+#[cfg(feature = "debug-ui")]
+mod console;
+mod constraints;
+mod crash;
+#[cfg(feature = "debug-ui")]
+mod debug_ui;
+mod events;
+mod export;
+mod game_over;
+mod ghost_trail;
+#[cfg(feature = "test-harness")]
+pub mod harness;
+mod heatmap;
+mod highlight;
+mod history;
+mod hooks;
+mod hover;
+pub mod input;
+mod intro;
+mod keyboard;
+mod layout;
+mod leaderboard;
+mod menu;
+mod minimap;
+#[cfg(feature = "net")]
+pub mod net;
+mod opening;
+mod overtime;
+mod palette;
+mod pause;
+mod peel;
+pub mod persistence;
+mod planning;
+mod play_time;
+pub mod plugin;
+mod puzzle;
+mod puzzle_select;
+mod render_order;
+mod replay;
+mod resize;
+mod restart;
+mod review;
+mod rotation;
+mod rules;
+mod rumble;
+mod sandbox;
+mod save;
+mod save_slots;
+mod schedule;
+mod score_popups;
+mod scoring;
+mod settings;
+mod settings_menu;
+pub mod shape;
+mod shape_outline;
+mod shape_slot;
+mod snapshot;
+mod sprint;
+mod stats;
+mod strings;
+pub mod systems;
+mod tile_fade;
+mod tile_style;
+mod toast;
+mod tooltip;
+mod tutorial;
+mod tween;
+pub mod ui;
+#[cfg(all(feature = "net", target_arch = "wasm32"))]
+mod websocket;
+mod zones;
+
+pub use components::{
+    spawn_board, Board, BoardRenderer, MainBoard, MainCamera, OverlayBoard, SelectedShape,
+    TILE_SIZE,
+};
+pub use events::{
+    BoardChangedEvent, CellList, GameOverEvent, LinesClearedEvent, PlacementEvent,
+    ResizeBoardEvent, ShapePoolChanged,
+};
+pub use persistence::DataDir;
+pub use plugin::{BlocksPlugin, BlocksSystems};
+pub use shape::{default_shapes, ShapePool};
+
+/// What embedding the game usually needs, `use blocks::prelude::*`.
+pub mod prelude {
+    pub use crate::{
+        board::{BoardSnapshot, CellDelta, TileColor},
+        board_texture::spawn_textured_board,
+        click_guard::PointerCapture,
+        config::{BlocksPluginBuilder, ConfigError},
+        default_shapes,
+        highlight::{HighlightLayer, Highlights},
+        hooks::{BlocksHooks, BoardCommands, GameOverResult, PlacementResult},
+        menu::{GameMode, GameState},
+        opening::StartPreset,
+        rules::{RuleOverrides, RuleSet},
+        scoring::{Combo, Level, Score, ScoreBreakdownEvent},
+        shape::{NamedShape, Shape, ShapeId, ShapeInstance, ShapePool, ShapeRegistry},
+        shapes, spawn_board, BlocksPlugin, BlocksSystems, Board, BoardChangedEvent, BoardRenderer,
+        CellList, DataDir, GameOverEvent, LinesClearedEvent, MainBoard, MainCamera, OverlayBoard,
+        PlacementEvent, ResizeBoardEvent, SelectedShape, ShapePoolChanged, TILE_SIZE,
+    };
+}
@@ -0,0 +1,638 @@
+//! Main menu, shown at launch over a demo game the greedy AI plays on its own board.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{app::AppExit, prelude::*};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ai,
+    board::{Grid, BOARD_HEIGHT, BOARD_WIDTH},
+    budget::{FrameBudget, ScanBudget},
+    click_guard::ModalUi,
+    opening::StartPreset,
+    persistence::{PersistRequest, Persisted},
+    replay::{self, PasteBox},
+    restart::RestartEvent,
+    rules::{CustomRules, RuleOverrides, RuleSet},
+    scoring::{Combo, Level, Score},
+    settings::Settings,
+    shape::ShapeInstance,
+    shape::ShapeRegistry,
+    spawn_board,
+    strings::{t, Strings},
+    BlocksSystems, Board, BoardChangedEvent, MainBoard, PlacementEvent, ResizeBoardEvent,
+    SelectedShape,
+};
+
+/// Seconds between two placements of the demo game.
+const DEMO_INTERVAL: f32 = 0.8;
+const IDLE_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.5);
+const DISABLED_TEXT: Color = Color::rgb(0.4, 0.4, 0.4);
+
+#[derive(States, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameState {
+    #[default]
+    Menu,
+    Playing,
+    /// Free board editing, see [`crate::sandbox`].
+    Sandbox,
+    /// Picking a puzzle, see [`crate::puzzle_select`].
+    PuzzleSelect,
+    /// Changing the settings, see [`crate::settings_menu`].
+    Settings,
+    /// The game stopped halfway, see [`crate::pause`].
+    Paused,
+    /// The game ended and its results are shown, see [`crate::game_over`].
+    GameOver,
+}
+
+/// State the game left for the current one.
+#[derive(Resource, Default)]
+pub struct PreviousState(pub GameState);
+
+/// Run condition telling entering [`GameState::Playing`] to start a game apart from
+/// resuming the paused one.
+pub fn not_resuming(previous: Res<PreviousState>) -> bool {
+    previous.0 != GameState::Paused
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum GameMode {
+    #[default]
+    Endless,
+    Timed,
+    Puzzle,
+    /// Endless with a seed shared by everyone playing the same day.
+    Daily,
+    Versus,
+    /// Endless with gravity.
+    Cascade,
+    /// Every shape after the first has to touch one placed before.
+    Adjacency,
+    /// A 9x9 board whose 3x3 squares clear like lines.
+    Blockudoku,
+    /// Rules read from `rules.ron`.
+    Custom,
+    /// Clearing a number of lines against the clock.
+    Sprint,
+    /// Clearing the garbage rows the board starts with against the clock.
+    Dig,
+}
+
+impl GameMode {
+    /// Name of the mode in files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::Timed => "timed",
+            GameMode::Puzzle => "puzzle",
+            GameMode::Daily => "daily",
+            GameMode::Versus => "versus",
+            GameMode::Cascade => "cascade",
+            GameMode::Adjacency => "adjacency",
+            GameMode::Blockudoku => "blockudoku",
+            GameMode::Custom => "custom",
+            GameMode::Sprint => "sprint",
+            GameMode::Dig => "dig",
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "endless" => Some(GameMode::Endless),
+            "timed" => Some(GameMode::Timed),
+            "puzzle" => Some(GameMode::Puzzle),
+            "daily" => Some(GameMode::Daily),
+            "versus" => Some(GameMode::Versus),
+            "cascade" => Some(GameMode::Cascade),
+            "adjacency" => Some(GameMode::Adjacency),
+            "blockudoku" => Some(GameMode::Blockudoku),
+            "custom" => Some(GameMode::Custom),
+            "sprint" => Some(GameMode::Sprint),
+            "dig" => Some(GameMode::Dig),
+            _ => None,
+        }
+    }
+
+    /// Key of the mode's name in [`Strings`].
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "menu.endless",
+            GameMode::Timed => "menu.timed",
+            GameMode::Puzzle => "menu.puzzle",
+            GameMode::Daily => "menu.daily",
+            GameMode::Versus => "menu.versus",
+            GameMode::Cascade => "menu.cascade",
+            GameMode::Adjacency => "menu.adjacency",
+            GameMode::Blockudoku => "menu.blockudoku",
+            GameMode::Custom => "menu.custom",
+            GameMode::Sprint => "menu.sprint",
+            GameMode::Dig => "menu.dig",
+        }
+    }
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum MenuButton {
+    Mode(GameMode),
+    /// Switches the board games start from, see [`StartChoice`].
+    Start,
+    /// Cycles the size of the main board through [`BOARD_SIZES`].
+    BoardSize,
+    Sandbox,
+    /// Opens the box taking a replay code to watch.
+    WatchReplay,
+    Settings,
+    Quit,
+}
+impl MenuButton {
+    const ALL: [MenuButton; 17] = [
+        MenuButton::Mode(GameMode::Endless),
+        MenuButton::Mode(GameMode::Timed),
+        MenuButton::Mode(GameMode::Sprint),
+        MenuButton::Mode(GameMode::Dig),
+        MenuButton::Mode(GameMode::Puzzle),
+        MenuButton::Mode(GameMode::Daily),
+        MenuButton::Mode(GameMode::Versus),
+        MenuButton::Mode(GameMode::Cascade),
+        MenuButton::Mode(GameMode::Adjacency),
+        MenuButton::Mode(GameMode::Blockudoku),
+        MenuButton::Mode(GameMode::Custom),
+        MenuButton::Start,
+        MenuButton::BoardSize,
+        MenuButton::Sandbox,
+        MenuButton::WatchReplay,
+        MenuButton::Settings,
+        MenuButton::Quit,
+    ];
+
+    /// Key of the label in [`Strings`].
+    fn label(&self, start: StartPreset) -> &'static str {
+        match self {
+            MenuButton::Mode(mode) => mode.label(),
+            MenuButton::Start => match start {
+                StartPreset::Empty => "menu.start_empty",
+                StartPreset::Scattered => "menu.start_scattered",
+            },
+            MenuButton::BoardSize => "menu.board_size",
+            MenuButton::Sandbox => "menu.sandbox",
+            MenuButton::WatchReplay => "menu.watch_replay",
+            MenuButton::Settings => "menu.settings",
+            MenuButton::Quit => "menu.quit",
+        }
+    }
+
+    /// Whether the entry leads anywhere yet, the others are greyed out.
+    fn enabled(&self) -> bool {
+        matches!(
+            self,
+            MenuButton::Mode(
+                GameMode::Endless
+                    | GameMode::Puzzle
+                    | GameMode::Daily
+                    | GameMode::Cascade
+                    | GameMode::Adjacency
+                    | GameMode::Blockudoku
+                    | GameMode::Custom
+                    | GameMode::Sprint
+                    | GameMode::Dig
+            ) | MenuButton::Start
+                | MenuButton::BoardSize
+                | MenuButton::Sandbox
+                | MenuButton::WatchReplay
+                | MenuButton::Settings
+                | MenuButton::Quit
+        )
+    }
+}
+
+#[derive(Component)]
+pub struct MenuRoot;
+
+/// Text of the menu showing the [`Strings`] entry of the key.
+#[derive(Component)]
+struct MenuLabel(&'static str);
+
+/// Label of [`MenuButton::Start`], changing with the choice.
+#[derive(Component)]
+struct StartLabel;
+
+/// Label of [`MenuButton::BoardSize`], showing the size.
+#[derive(Component)]
+struct BoardSizeLabel;
+
+/// Sizes [`MenuButton::BoardSize`] cycles through, the full board first.
+const BOARD_SIZES: [(usize, usize); 3] = [(BOARD_WIDTH, BOARD_HEIGHT), (15, 15), (10, 10)];
+
+/// Text of a menu label, the board size one being formatted with the size.
+fn label_text(strings: &Strings, label: &'static str, (width, height): (usize, usize)) -> String {
+    match label {
+        "menu.board_size" => t!(strings, label, width, height),
+        _ => strings.get(label).to_owned(),
+    }
+}
+
+/// Board the games picked in the menu start from, except custom games taking theirs
+/// from `rules.ron` and digs starting from their garbage.
+#[derive(Resource, Default)]
+struct StartChoice(StartPreset);
+
+/// Board the demo game is played on, only visible in the menu. It keeps a [`Score`] and
+/// [`Level`] of its own.
+#[derive(Component)]
+pub struct DemoBoard;
+
+/// Index into [`MenuButton::ALL`] of the entry Enter activates.
+#[derive(Resource, Default)]
+struct MenuSelection(usize);
+
+/// The demo game has its own randomness so it never disturbs the game's.
+#[derive(Resource)]
+pub struct Demo {
+    /// How many times its normal pace the demo plays at.
+    pub speed: f32,
+    timer: Timer,
+    rng: StdRng,
+    /// Shape drawn for the next placement while the AI searches a spot for it.
+    pending: Option<(ShapeInstance, ai::PlacementSearch)>,
+}
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<GameState>()
+            .init_resource::<PreviousState>()
+            .init_resource::<GameMode>()
+            .init_resource::<MenuSelection>()
+            .init_resource::<StartChoice>()
+            .insert_resource(Demo {
+                speed: 1.,
+                timer: Timer::from_seconds(DEMO_INTERVAL, TimerMode::Repeating),
+                rng: StdRng::from_entropy(),
+                pending: None,
+            })
+            .add_systems(Startup, spawn_demo_board)
+            .add_systems(OnEnter(GameState::Menu), (spawn_menu, show_boards))
+            .add_systems(OnExit(GameState::Menu), despawn_menu)
+            .add_systems(
+                StateTransition,
+                remember_previous_state.before(apply_state_transition::<GameState>),
+            )
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (show_boards, start_game.run_if(not_resuming)),
+            )
+            .add_systems(OnEnter(GameState::Sandbox), show_boards)
+            .add_systems(OnEnter(GameState::PuzzleSelect), show_boards)
+            .add_systems(OnEnter(GameState::Settings), show_boards)
+            .add_systems(
+                Update,
+                (
+                    (
+                        navigate_menu.run_if(replay::paste_box_closed),
+                        highlight_buttons.after(navigate_menu),
+                        relabel_menu.run_if(
+                            resource_changed::<Strings>()
+                                .or_else(resource_changed::<StartChoice>())
+                                .or_else(resource_changed::<Settings>()),
+                        ),
+                    )
+                        .run_if(in_state(GameState::Menu)),
+                    // Keeps playing behind the puzzle list and the settings
+                    play_demo.before(BlocksSystems::Resolution).run_if(
+                        in_state(GameState::Menu)
+                            .or_else(in_state(GameState::PuzzleSelect))
+                            .or_else(in_state(GameState::Settings)),
+                    ),
+                ),
+            );
+    }
+}
+
+fn spawn_demo_board(mut commands: Commands) {
+    spawn_board(&mut commands, true).insert((
+        DemoBoard,
+        Score::default(),
+        Level::default(),
+        Combo::default(),
+    ));
+}
+
+fn spawn_menu(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    start: Res<StartChoice>,
+    settings: Res<Settings>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            MenuRoot,
+            ModalUi,
+        ))
+        .with_children(|commands| {
+            commands.spawn((
+                TextBundle::from_section(
+                    strings.get("menu.title"),
+                    TextStyle {
+                        font_size: 64.,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(24.)),
+                    ..default()
+                }),
+                MenuLabel("menu.title"),
+            ));
+            for button in MenuButton::ALL {
+                commands
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(200.),
+                                padding: UiRect::all(Val::Px(8.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: IDLE_BUTTON.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|commands| {
+                        let label = button.label(start.0);
+                        let mut text = commands.spawn((
+                            TextBundle::from_section(
+                                label_text(&strings, label, settings.board_size),
+                                TextStyle {
+                                    font_size: 24.,
+                                    color: if button.enabled() {
+                                        Color::WHITE
+                                    } else {
+                                        DISABLED_TEXT
+                                    },
+                                    ..default()
+                                },
+                            ),
+                            MenuLabel(label),
+                        ));
+                        if button == MenuButton::Start {
+                            text.insert(StartLabel);
+                        }
+                        if button == MenuButton::BoardSize {
+                            text.insert(BoardSizeLabel);
+                        }
+                    });
+            }
+        });
+}
+
+fn relabel_menu(
+    strings: Res<Strings>,
+    start: Res<StartChoice>,
+    settings: Res<Settings>,
+    mut q_labels: Query<(&mut MenuLabel, &mut Text, Has<StartLabel>)>,
+) {
+    for (mut label, mut text, start_label) in q_labels.iter_mut() {
+        if start_label {
+            label.0 = MenuButton::Start.label(start.0);
+        }
+        text.sections[0].value = label_text(&strings, label.0, settings.board_size);
+    }
+}
+
+fn despawn_menu(mut commands: Commands, q_menu: Query<Entity, With<MenuRoot>>) {
+    for entity in q_menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Shows the demo board in the menus and the game's boards and shape otherwise.
+fn show_boards(
+    state: Res<State<GameState>>,
+    mut q_demo: Query<&mut Visibility, With<DemoBoard>>,
+    mut q_game: Query<
+        &mut Visibility,
+        (
+            Or<(With<MainBoard>, With<SelectedShape>)>,
+            Without<DemoBoard>,
+        ),
+    >,
+) {
+    let in_menu = matches!(
+        state.get(),
+        GameState::Menu | GameState::PuzzleSelect | GameState::Settings
+    );
+    let visible = |shown: bool| {
+        if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    };
+    for mut visibility in q_demo.iter_mut() {
+        *visibility = visible(in_menu);
+    }
+    for mut visibility in q_game.iter_mut() {
+        *visibility = visible(!in_menu);
+    }
+}
+
+/// Up and Down move the selection, Enter or a click activates an entry.
+fn navigate_menu(
+    input_keys: Res<Input<KeyCode>>,
+    mut selection: ResMut<MenuSelection>,
+    mut mode: ResMut<GameMode>,
+    mut rules: ResMut<RuleSet>,
+    (custom_rules, overrides, mut start): (
+        Res<CustomRules>,
+        Res<RuleOverrides>,
+        ResMut<StartChoice>,
+    ),
+    (mut settings, mut paste_box): (ResMut<Settings>, ResMut<PasteBox>),
+    mut next_state: ResMut<NextState<GameState>>,
+    (mut persist, mut resizes): (EventWriter<PersistRequest>, EventWriter<ResizeBoardEvent>),
+    mut exit: EventWriter<AppExit>,
+    q_buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+) {
+    let count = MenuButton::ALL.len();
+    if input_keys.just_pressed(KeyCode::Down) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+    if input_keys.just_pressed(KeyCode::Up) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    let mut activated = None;
+    for (interaction, button) in q_buttons.iter() {
+        let index = MenuButton::ALL.iter().position(|b| b == button).unwrap();
+        match interaction {
+            Interaction::Hovered => selection.0 = index,
+            Interaction::Pressed => activated = Some(*button),
+            Interaction::None => {}
+        }
+    }
+    if input_keys.just_pressed(KeyCode::Return) {
+        activated = Some(MenuButton::ALL[selection.0]);
+    }
+
+    match activated.filter(MenuButton::enabled) {
+        // Picks a puzzle first, which sets up the rules
+        Some(MenuButton::Mode(GameMode::Puzzle)) => next_state.set(GameState::PuzzleSelect),
+        Some(MenuButton::Mode(selected)) => {
+            *mode = selected;
+            *rules = RuleSet::for_mode(selected, &custom_rules, *overrides);
+            // The tutorial runs the first endless game, laid out on the full board
+            if selected == GameMode::Endless && !settings.tutorial_completed {
+                rules.board_size = Some((BOARD_WIDTH, BOARD_HEIGHT));
+            }
+            if !matches!(selected, GameMode::Custom | GameMode::Dig) {
+                rules.start_preset = start.0;
+            }
+            next_state.set(GameState::Playing);
+        }
+        Some(MenuButton::Start) => {
+            start.0 = match start.0 {
+                StartPreset::Empty => StartPreset::Scattered,
+                StartPreset::Scattered => StartPreset::Empty,
+            };
+        }
+        Some(MenuButton::BoardSize) => {
+            let next = BOARD_SIZES
+                .iter()
+                .position(|&size| size == settings.board_size)
+                .map_or(0, |index| (index + 1) % BOARD_SIZES.len());
+            let (width, height) = BOARD_SIZES[next];
+            settings.board_size = (width, height);
+            persist.send(settings.persist_request());
+            resizes.send(ResizeBoardEvent { width, height });
+        }
+        Some(MenuButton::Sandbox) => next_state.set(GameState::Sandbox),
+        Some(MenuButton::WatchReplay) => paste_box.open = true,
+        Some(MenuButton::Settings) => next_state.set(GameState::Settings),
+        Some(MenuButton::Quit) => exit.send(AppExit),
+        None => {}
+    }
+}
+
+/// Days since the Unix epoch, the same for everyone on a given (UTC) day.
+pub fn daily_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs() / 86_400)
+}
+
+fn highlight_buttons(
+    selection: Res<MenuSelection>,
+    mut q_buttons: Query<(&MenuButton, &mut BackgroundColor)>,
+) {
+    for (button, mut background) in q_buttons.iter_mut() {
+        let selected = MenuButton::ALL[selection.0] == *button;
+        *background = if selected {
+            SELECTED_BUTTON
+        } else {
+            IDLE_BUTTON
+        }
+        .into();
+    }
+}
+
+/// Keeps the state being left, for telling a resumed game from a new one.
+fn remember_previous_state(
+    state: Res<State<GameState>>,
+    next_state: Res<NextState<GameState>>,
+    mut previous: ResMut<PreviousState>,
+) {
+    if next_state.0.is_some_and(|next| next != *state.get()) {
+        previous.0 = *state.get();
+    }
+}
+
+/// Every game starts from scratch, as if restarted.
+fn start_game(mut restarts: EventWriter<RestartEvent>) {
+    restarts.send(RestartEvent);
+}
+
+/// Places a random shape where the greedy AI likes it best, starting over once
+/// nothing fits. Clears and repaints go through the regular board systems. The search
+/// is spread over frames within the [`ScanBudget`].
+fn play_demo(
+    time: Res<Time>,
+    registry: Res<ShapeRegistry>,
+    scan_budget: Res<ScanBudget>,
+    mut demo: ResMut<Demo>,
+    mut q_board: Query<(&mut Board, &mut Score, &mut Level, &mut Combo, Entity), With<DemoBoard>>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut placements: EventWriter<PlacementEvent>,
+) {
+    let speed = demo.speed;
+    let due = demo.timer.tick(time.delta().mul_f32(speed)).just_finished();
+    let Ok((mut board, mut score, mut level, mut combo, entity)) = q_board.get_single_mut() else {
+        return;
+    };
+    let demo = &mut *demo;
+    if demo.pending.is_none() {
+        if !due {
+            return;
+        }
+        let (id, rotation) = registry.random_weighted(&mut demo.rng, &registry.default_weights());
+        let shape = ShapeInstance {
+            id,
+            rotation,
+            color: demo.rng.gen(),
+        };
+        demo.pending = Some((shape, ai::PlacementSearch::default()));
+    }
+    let Some((shape, search)) = &mut demo.pending else {
+        return;
+    };
+    let shape = *shape;
+    let budget = FrameBudget::new(scan_budget.0);
+    let Some(best) = search.resume(&board.grid, shape.resolve(&registry), &budget) else {
+        return;
+    };
+    demo.pending = None;
+    let Some((anchor, _)) = best else {
+        board.grid.set_cells(Grid::default());
+        *score = Score::default();
+        *level = Level::default();
+        *combo = Combo::default();
+        board_changed.send(BoardChangedEvent {
+            board: entity,
+            revision: board.grid.revision(),
+            cells: board.grid.positions().collect(),
+        });
+        return;
+    };
+    let cells = board
+        .grid
+        .try_place(shape.resolve(&registry), anchor, shape.color)
+        .unwrap_or_default();
+    board_changed.send(BoardChangedEvent {
+        board: entity,
+        revision: board.grid.revision(),
+        cells: cells.clone(),
+    });
+    placements.send(PlacementEvent {
+        board: entity,
+        shape,
+        anchor,
+        cells,
+    });
+}
@@ -0,0 +1,223 @@
+//! Mini-map of the main board in the bottom right corner, one texture pixel per cell.
+//! The corner comes from the [`crate::layout::LayoutConfig`].
+//!
+//! Clicking a cell of the mini-map pans the camera onto it, a right click pans back.
+//! Hovering it tells which cell is under the cursor.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    ui::RelativeCursorPosition,
+};
+
+use crate::{
+    board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    layout::{CameraPan, LayoutSlot},
+    palette::ColorPalette,
+    strings::{t, Strings},
+    tooltip::Tooltip,
+    BlocksSystems, Board, MainBoard, OverlayBoard,
+};
+
+/// Screen pixels per board cell.
+const MINIMAP_SCALE: f32 = 4.;
+const EMPTY_PIXEL: [u8; 4] = [20, 20, 20, 255];
+
+/// UI node showing the mini-map.
+#[derive(Component)]
+struct MinimapNode;
+
+/// Texture the mini-map shows, and the board revision it was last written for.
+#[derive(Resource)]
+struct Minimap {
+    image: Handle<Image>,
+    revision: Option<u64>,
+}
+
+pub struct MinimapPlugin;
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_minimap).add_systems(
+            Update,
+            (
+                pan_to_clicked_cell.in_set(BlocksSystems::Input),
+                (update_minimap, describe_hovered_cell).in_set(BlocksSystems::Presentation),
+            ),
+        );
+    }
+}
+
+fn spawn_minimap(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: BOARD_WIDTH as u32,
+            height: BOARD_HEIGHT as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &EMPTY_PIXEL,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    // Keep cells crisp instead of blurring them together
+    image.sampler = ImageSampler::nearest();
+    let image = images.add(image);
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(12.),
+                bottom: Val::Px(12.),
+                width: Val::Px(BOARD_WIDTH as f32 * MINIMAP_SCALE),
+                height: Val::Px(BOARD_HEIGHT as f32 * MINIMAP_SCALE),
+                ..default()
+            },
+            image: UiImage::new(image.clone()),
+            ..default()
+        },
+        LayoutSlot::Minimap,
+        // Captures the pointer, see `crate::click_guard`
+        Interaction::default(),
+        RelativeCursorPosition::default(),
+        Tooltip(String::new()),
+        MinimapNode,
+    ));
+    commands.insert_resource(Minimap {
+        image,
+        revision: None,
+    });
+}
+
+/// Cell of the full-size board under a point of the mini-map, given from its top left
+/// corner as a fraction of its size.
+fn minimap_cell(normalized: Vec2) -> Option<(usize, usize)> {
+    if !(0. ..1.).contains(&normalized.x) || !(0. ..1.).contains(&normalized.y) {
+        return None;
+    }
+    let x = (normalized.x * BOARD_WIDTH as f32) as usize;
+    // Rows go up the board, down the mini-map
+    let y = BOARD_HEIGHT - 1 - (normalized.y * BOARD_HEIGHT as f32) as usize;
+    Some((x, y))
+}
+
+/// Names the cell under the cursor in the mini-map's tooltip.
+fn describe_hovered_cell(
+    strings: Res<Strings>,
+    mut q_minimap: Query<(&RelativeCursorPosition, &mut Tooltip), With<MinimapNode>>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    mut described: Local<Option<Option<(usize, usize)>>>,
+) {
+    let (Ok((cursor, mut tooltip)), Ok(board)) = (q_minimap.get_single_mut(), q_board.get_single())
+    else {
+        return;
+    };
+    let cell = cursor
+        .normalized
+        .and_then(minimap_cell)
+        .filter(|&(x, y)| board.grid.contains(x, y));
+    // Only the texts of another cell or language are worth allocating
+    if *described == Some(cell) && !strings.is_changed() {
+        return;
+    }
+    *described = Some(cell);
+    let text = match cell {
+        Some((x, y)) => t!(strings, "minimap.tooltip", x + 1, y + 1),
+        None => strings.get("minimap.outside").to_owned(),
+    };
+    if tooltip.0 != text {
+        tooltip.0 = text;
+    }
+}
+
+/// A left click on the mini-map centers the camera on the cell under it, a right click
+/// centers it on the board again.
+fn pan_to_clicked_cell(
+    input_mb: Res<Input<MouseButton>>,
+    mut pan: ResMut<CameraPan>,
+    q_minimap: Query<&RelativeCursorPosition, With<MinimapNode>>,
+    q_board: Query<(&Board, &GlobalTransform), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok(cursor) = q_minimap.get_single() else {
+        return;
+    };
+    if !cursor.mouse_over() {
+        return;
+    }
+    if input_mb.just_pressed(MouseButton::Right) {
+        pan.set_if_neq(CameraPan::default());
+        return;
+    }
+    if !input_mb.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (Some((x, y)), Ok((board, transform))) = (
+        cursor.normalized.and_then(minimap_cell),
+        q_board.get_single(),
+    ) else {
+        return;
+    };
+    // Cells beyond a smaller board are left out of the mini-map
+    if !board.grid.contains(x, y) {
+        return;
+    }
+    let center = transform.translation().truncate();
+    pan.set_if_neq(CameraPan(board.cell_to_world(transform, (x, y)) - center));
+}
+
+/// Rewrites the texture from the main board whenever its revision changed.
+fn update_minimap(
+    mut minimap: ResMut<Minimap>,
+    mut images: ResMut<Assets<Image>>,
+    palette: Res<ColorPalette>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+    let revision = board.grid.revision();
+    if minimap.revision == Some(revision) {
+        return;
+    }
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+    minimap.revision = Some(revision);
+    // Texture rows go top to bottom, board rows bottom to top
+    for ((y, row), pixels) in board
+        .grid
+        .cells
+        .0
+        .iter()
+        .enumerate()
+        .rev()
+        .zip(image.data.chunks_exact_mut(BOARD_WIDTH * 4))
+    {
+        for ((x, cell), pixel) in row.iter().enumerate().zip(pixels.chunks_exact_mut(4)) {
+            let rgba = match cell {
+                // Cells beyond a smaller board are left out
+                _ if !board.grid.contains(x, y) => [0; 4],
+                Some(TileColor::Transparent) | None => EMPTY_PIXEL,
+                Some(color) => palette.color(*color).as_rgba_u8(),
+            };
+            pixel.copy_from_slice(&rgba);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_minimap_turns_the_board_upside_down() {
+        assert_eq!(minimap_cell(Vec2::new(0., 0.)), Some((0, BOARD_HEIGHT - 1)));
+        assert_eq!(
+            minimap_cell(Vec2::new(0.999, 0.999)),
+            Some((BOARD_WIDTH - 1, 0))
+        );
+        assert_eq!(minimap_cell(Vec2::new(1., 0.5)), None);
+        assert_eq!(minimap_cell(Vec2::new(0.5, -0.1)), None);
+    }
+}
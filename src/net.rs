@@ -0,0 +1,512 @@
+//! Versus play over TCP: every placement is sent to the peer, which replays it on a
+//! read-only mirror of our board through the same `try_place` path. The cells that
+//! changed since are sent after it as [`CellDelta`]s, a `cell` line of RON each, keeping
+//! the mirror exact through whatever else changes the board. Clearing several lines at
+//! once sends the peer a `garbage` line, pushing garbage rows up its board.
+//!
+//! Start one game with `--host <addr>` and the other with `--connect <addr>`, or open
+//! the web build with `?connect=<url>` of a WebSocket bridged to the host. Messages go
+//! through a [`Transport`]: TCP natively, a WebSocket on wasm32 and an in-memory
+//! [`memory_pair`] for running both sides in one process. Losing the peer leaves a
+//! local game.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    board::{BoardSnapshot, CellDelta, TileColor},
+    events::BoardCommand,
+    menu::{GameMode, GameState},
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    spawn_board,
+    strings::Strings,
+    toast::spawn_toast,
+    BlocksSystems, Board, BoardChangedEvent, LinesClearedEvent, MainBoard, OverlayBoard,
+    PlacementEvent, TILE_SIZE,
+};
+
+/// A placement as it goes over the wire, one per line.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PlacementAction {
+    pub seq: u64,
+    pub shape: ShapeInstance,
+    pub anchor: (isize, isize),
+}
+impl PlacementAction {
+    fn encode(&self) -> String {
+        format!(
+            "place {} {} {} {} {} {}\n",
+            self.seq,
+            self.shape.id.0,
+            self.shape.rotation,
+            self.shape.color.to_char(),
+            self.anchor.0,
+            self.anchor.1
+        )
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let mut parts = line.strip_prefix("place ")?.split(' ');
+        let mut next = || parts.next();
+        Some(Self {
+            seq: next()?.parse().ok()?,
+            shape: ShapeInstance {
+                id: ShapeId(next()?.parse().ok()?),
+                rotation: next()?.parse().ok()?,
+                color: TileColor::from_char(next()?.chars().next()?)?,
+            },
+            anchor: (next()?.parse().ok()?, next()?.parse().ok()?),
+        })
+    }
+}
+
+/// Garbage rows sent to the opponent for a clear, one line with the gap of each row.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GarbageAction {
+    pub gaps: Vec<usize>,
+}
+impl GarbageAction {
+    /// Rows sent for clearing `lines` at once, one fewer than the lines.
+    pub fn rows_for(lines: usize) -> usize {
+        lines.saturating_sub(1)
+    }
+
+    fn encode(&self) -> String {
+        let gaps: Vec<String> = self.gaps.iter().map(usize::to_string).collect();
+        format!("garbage {}\n", gaps.join(" "))
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let gaps = line.strip_prefix("garbage ")?.split(' ');
+        Some(Self {
+            gaps: gaps.map(|gap| gap.parse().ok()).collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// A byte stream to the peer that never blocks: reads and writes do what they can
+/// right away, failing with `WouldBlock` when they can't do anything.
+pub trait Transport: Read + Write + Send + Sync + 'static {}
+impl<T: Read + Write + Send + Sync + 'static> Transport for T {}
+
+/// Bytes on their way from one end of a [`memory_pair`] to the other.
+type Pipe = Arc<Mutex<VecDeque<u8>>>;
+
+/// One end of an in-memory stream, see [`memory_pair`]. Once the other end is dropped,
+/// reads see the end of the stream and writes fail.
+pub struct MemoryTransport {
+    incoming: Pipe,
+    outgoing: Pipe,
+    capacity: usize,
+}
+impl Read for MemoryTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut incoming = self.incoming.lock().unwrap();
+        if incoming.is_empty() {
+            return match Arc::strong_count(&self.incoming) {
+                1 => Ok(0),
+                _ => Err(io::ErrorKind::WouldBlock.into()),
+            };
+        }
+        let read = buf.len().min(incoming.len());
+        for (byte, received) in buf.iter_mut().zip(incoming.drain(..read)) {
+            *byte = received;
+        }
+        Ok(read)
+    }
+}
+impl Write for MemoryTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if Arc::strong_count(&self.outgoing) == 1 {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        let mut outgoing = self.outgoing.lock().unwrap();
+        let written = buf.len().min(self.capacity - outgoing.len());
+        if written == 0 && !buf.is_empty() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        outgoing.extend(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Both ends of an in-memory stream holding up to `capacity` bytes in each direction,
+/// like a socket whose buffers fill up.
+pub fn memory_pair(capacity: usize) -> (MemoryTransport, MemoryTransport) {
+    let (there, back) = (Pipe::default(), Pipe::default());
+    (
+        MemoryTransport {
+            incoming: back.clone(),
+            outgoing: there.clone(),
+            capacity,
+        },
+        MemoryTransport {
+            incoming: there,
+            outgoing: back,
+            capacity,
+        },
+    )
+}
+
+/// Read-only copy of the opponent's board.
+#[derive(Component)]
+pub struct MirrorBoard;
+
+#[derive(Resource)]
+struct Listener(TcpListener);
+
+/// The link to the opponent. Inserting one before the app starts plays versus over it.
+#[derive(Resource)]
+pub struct Connection {
+    transport: Box<dyn Transport>,
+    received: Vec<u8>,
+    /// Bytes the transport didn't take yet, sent first thing next frame.
+    outgoing: Vec<u8>,
+    sent_seq: u64,
+    received_seq: u64,
+    /// Our board as the peer knows it.
+    synced: BoardSnapshot,
+    /// Set once reading or writing failed, the connection is dropped at the end of the
+    /// frame.
+    lost: bool,
+}
+impl Connection {
+    pub fn new(transport: impl Transport) -> Self {
+        Self {
+            transport: Box::new(transport),
+            received: Vec::new(),
+            outgoing: Vec::new(),
+            sent_seq: 0,
+            received_seq: 0,
+            synced: BoardSnapshot::default(),
+            lost: false,
+        }
+    }
+
+    fn tcp(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream))
+    }
+
+    fn lose(&mut self, err: impl std::fmt::Display) {
+        if !self.lost {
+            warn!("opponent disconnected, continuing locally: {err}");
+        }
+        self.lost = true;
+    }
+}
+
+pub struct NetPlugin;
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        match start_versus(app) {
+            Some(Ok(())) => {
+                app.insert_resource(GameMode::Versus)
+                    .insert_resource(NextState(Some(GameState::Playing)));
+            }
+            Some(Err(err)) => error!("versus play unavailable, playing locally: {err}"),
+            None => {}
+        }
+
+        let versus = resource_exists::<Listener>().or_else(resource_exists::<Connection>());
+        let connected = resource_exists::<Connection>();
+        app.add_systems(Startup, spawn_mirror_board.run_if(versus))
+            .add_systems(
+                Update,
+                (
+                    accept_connection
+                        .run_if(resource_exists::<Listener>())
+                        .before(BlocksSystems::Preview),
+                    receive_actions
+                        .run_if(connected.clone())
+                        .in_set(BlocksSystems::Placement),
+                    send_placements
+                        .run_if(connected.clone())
+                        .after(BlocksSystems::Placement),
+                    (
+                        send_garbage.run_if(|mode: Res<GameMode>| *mode == GameMode::Versus),
+                        send_board_deltas,
+                        flush_outgoing,
+                        drop_lost_connection,
+                    )
+                        .chain()
+                        .run_if(connected)
+                        .after(send_placements)
+                        .after(BlocksSystems::Resolution),
+                ),
+            );
+    }
+}
+
+/// Hosts or connects as asked by `--host <addr>` or `--connect <addr>`, `None` when
+/// neither was given.
+#[cfg(not(target_arch = "wasm32"))]
+fn start_versus(app: &mut App) -> Option<io::Result<()>> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        return Some(match (arg.as_str(), args.next()) {
+            ("--host", Some(addr)) => TcpListener::bind(&addr).and_then(|listener| {
+                listener.set_nonblocking(true)?;
+                info!("waiting for an opponent on {addr}");
+                app.insert_resource(Listener(listener));
+                Ok(())
+            }),
+            ("--connect", Some(addr)) => {
+                TcpStream::connect(&addr)
+                    .and_then(Connection::tcp)
+                    .map(|connection| {
+                        app.insert_resource(connection);
+                    })
+            }
+            _ => continue,
+        });
+    }
+    None
+}
+
+/// Connects to the WebSocket of the page's `?connect=<url>`, browsers can't host.
+#[cfg(target_arch = "wasm32")]
+fn start_versus(app: &mut App) -> Option<io::Result<()>> {
+    let url = crate::websocket::url_from_page()?;
+    Some(
+        crate::websocket::WebSocketTransport::connect(&url).map(|transport| {
+            app.insert_resource(Connection::new(transport));
+        }),
+    )
+}
+
+fn spawn_mirror_board(mut commands: Commands) {
+    spawn_board(&mut commands, true)
+        .insert(MirrorBoard)
+        .insert(Transform {
+            translation: Vec3::new(TILE_SIZE * 16., 0., 0.),
+            scale: Vec3::splat(TILE_SIZE * 0.4),
+            ..default()
+        });
+}
+
+fn accept_connection(mut commands: Commands, listener: Res<Listener>) {
+    match listener.0.accept() {
+        Ok((stream, addr)) => match Connection::tcp(stream) {
+            Ok(connection) => {
+                info!("opponent connected from {addr}");
+                commands.insert_resource(connection);
+                commands.remove_resource::<Listener>();
+            }
+            Err(err) => warn!("dropping connection from {addr}: {err}"),
+        },
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+        Err(err) => warn!("failed to accept an opponent: {err}"),
+    }
+}
+
+fn send_placements(
+    mut connection: ResMut<Connection>,
+    mut placements: EventReader<PlacementEvent>,
+    q_main_board: Query<(), With<MainBoard>>,
+) {
+    for event in placements.read() {
+        if !q_main_board.contains(event.board) {
+            continue;
+        }
+        connection.sent_seq += 1;
+        let action = PlacementAction {
+            seq: connection.sent_seq,
+            shape: event.shape,
+            anchor: event.anchor,
+        };
+        connection
+            .outgoing
+            .extend_from_slice(action.encode().as_bytes());
+    }
+}
+
+/// Queues the garbage rows our clears send the opponent.
+fn send_garbage(
+    mut connection: ResMut<Connection>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    q_main_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    for event in lines_cleared.read() {
+        let Ok(board) = q_main_board.get(event.board) else {
+            continue;
+        };
+        let rows = GarbageAction::rows_for(event.lines.len());
+        if rows == 0 {
+            continue;
+        }
+        let mut rng = rand::thread_rng();
+        let garbage = GarbageAction {
+            gaps: (0..rows)
+                .map(|_| rng.gen_range(0..board.grid.width()))
+                .collect(),
+        };
+        connection
+            .outgoing
+            .extend_from_slice(garbage.encode().as_bytes());
+    }
+}
+
+/// Queues the cells of our board that changed since they were last sent, one `cell`
+/// line each.
+fn send_board_deltas(
+    mut connection: ResMut<Connection>,
+    q_main_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok(board) = q_main_board.get_single() else {
+        return;
+    };
+    let deltas = board.grid.diff(&connection.synced);
+    if deltas.is_empty() {
+        return;
+    }
+    connection.synced = board.grid.snapshot();
+    for delta in deltas {
+        let Ok(delta) = ron::to_string(&delta) else {
+            continue;
+        };
+        connection
+            .outgoing
+            .extend_from_slice(format!("cell {delta}\n").as_bytes());
+    }
+}
+
+/// Hands the transport as much of the queued bytes as it takes, the rest waits for
+/// the next frame. Lines are never cut short on the wire, only delayed.
+fn flush_outgoing(mut connection: ResMut<Connection>) {
+    let connection = &mut *connection;
+    while !connection.outgoing.is_empty() && !connection.lost {
+        match connection.transport.write(&connection.outgoing) {
+            Ok(0) => connection.lose("connection closed"),
+            Ok(written) => {
+                connection.outgoing.drain(..written);
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => connection.lose(err),
+        }
+    }
+}
+
+/// Falls back to a local game once the opponent is gone, telling the player.
+fn drop_lost_connection(
+    mut commands: Commands,
+    connection: Res<Connection>,
+    strings: Res<Strings>,
+    q_mirror: Query<Entity, With<MirrorBoard>>,
+) {
+    if !connection.lost {
+        return;
+    }
+    commands.remove_resource::<Connection>();
+    for entity in q_mirror.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_toast(&mut commands, strings.get("toast.opponent_disconnected"));
+}
+
+/// Replays the opponent's placements on the mirror board, then corrects its cells. The
+/// garbage rows the opponent sent go up our board.
+fn receive_actions(
+    mut connection: ResMut<Connection>,
+    registry: Res<ShapeRegistry>,
+    mut q_mirror: Query<(&mut Board, Entity), (With<MirrorBoard>, Without<OverlayBoard>)>,
+    q_main_board: Query<Entity, (With<MainBoard>, Without<OverlayBoard>)>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut placements: EventWriter<PlacementEvent>,
+    mut board_commands: EventWriter<BoardCommand>,
+) {
+    let connection = &mut *connection;
+    let mut buffer = [0; 1024];
+    while !connection.lost {
+        match connection.transport.read(&mut buffer) {
+            Ok(0) => connection.lose("connection closed"),
+            Ok(read) => connection.received.extend_from_slice(&buffer[..read]),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => connection.lose(err),
+        }
+    }
+
+    let Ok((mut mirror, mirror_entity)) = q_mirror.get_single_mut() else {
+        return;
+    };
+    let mut deltas = Vec::new();
+    while let Some(end) = connection.received.iter().position(|&b| b == b'\n') {
+        let line: Vec<u8> = connection.received.drain(..=end).collect();
+        let line = std::str::from_utf8(&line).map(str::trim_end);
+        if let Some(delta) = line
+            .ok()
+            .and_then(|line| ron::from_str::<CellDelta>(line.strip_prefix("cell ")?).ok())
+        {
+            deltas.push(delta);
+            continue;
+        }
+        if let Some(garbage) = line.ok().and_then(GarbageAction::decode) {
+            if let Ok(board) = q_main_board.get_single() {
+                board_commands.send(BoardCommand::GarbageRows {
+                    board,
+                    gaps: garbage.gaps,
+                });
+            }
+            continue;
+        }
+        let Some(action) = line
+            .ok()
+            .and_then(PlacementAction::decode)
+            .filter(|action| registry.contains(action.shape.id))
+        else {
+            warn!("ignoring malformed message from opponent");
+            continue;
+        };
+        if action.seq <= connection.received_seq {
+            continue;
+        }
+        connection.received_seq = action.seq;
+
+        let Some(cells) = mirror.grid.try_place(
+            action.shape.resolve(&registry),
+            action.anchor,
+            action.shape.color,
+        ) else {
+            warn!(
+                "opponent placement {} does not fit the mirror board",
+                action.seq
+            );
+            continue;
+        };
+        board_changed.send(BoardChangedEvent {
+            board: mirror_entity,
+            revision: mirror.grid.revision(),
+            cells: cells.clone(),
+        });
+        placements.send(PlacementEvent {
+            board: mirror_entity,
+            shape: action.shape,
+            anchor: action.anchor,
+            cells,
+        });
+    }
+
+    if deltas.is_empty() {
+        return;
+    }
+    mirror.grid.apply_deltas(&deltas);
+    board_changed.send(BoardChangedEvent {
+        board: mirror_entity,
+        revision: mirror.grid.revision(),
+        cells: deltas.iter().map(|delta| delta.cell).collect(),
+    });
+}
@@ -0,0 +1,244 @@
+//! Boards a game can start from other than the empty one, picked with the
+//! [`crate::rules::RuleSet`]'s start preset.
+//!
+//! The scattered opening fills a share of the cells with small clumps of random colors.
+//! Openings are drawn from the game's seed until one leaves no line complete and room
+//! for at least [`MIN_PLACEABLE_SHAPES`] of the pool's shapes, settling for the empty
+//! board after [`MAX_ATTEMPTS`].
+
+use std::{fmt, ops::RangeInclusive};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{anchors, Board as BoardGrid, Grid, TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    palette::ColorPalette,
+    shape::ShapeRegistry,
+};
+
+/// Shapes of the pool that have to fit somewhere on an opening, unrotated.
+pub const MIN_PLACEABLE_SHAPES: usize = 3;
+/// Openings drawn before settling for the empty board.
+const MAX_ATTEMPTS: usize = 50;
+/// Cells of a clump, fewer where it runs into filled cells or the board's edge.
+const CLUMP_CELLS: RangeInclusive<usize> = 1..=4;
+
+type Cells = Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum StartPreset {
+    #[default]
+    Empty,
+    /// Clumps of random colors filling the start density of the board.
+    Scattered,
+}
+impl StartPreset {
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "empty" => Some(StartPreset::Empty),
+            "scattered" => Some(StartPreset::Scattered),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for StartPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StartPreset::Empty => "empty",
+            StartPreset::Scattered => "scattered",
+        })
+    }
+}
+
+/// Cells of the preset's opening on a `width` by `height` board, filling about
+/// `density` of it. The same rng state gives the same opening.
+pub fn generate<R: Rng + ?Sized>(
+    preset: StartPreset,
+    density: f32,
+    size: (usize, usize),
+    rng: &mut R,
+    (registry, palette): (&ShapeRegistry, &ColorPalette),
+) -> Cells {
+    match preset {
+        StartPreset::Empty => Grid::default(),
+        StartPreset::Scattered => (0..MAX_ATTEMPTS)
+            .map(|_| scatter(rng, density, size, palette))
+            .find(|cells| playable(cells, size, registry))
+            .unwrap_or_default(),
+    }
+}
+
+fn scatter<R: Rng + ?Sized>(
+    rng: &mut R,
+    density: f32,
+    (width, height): (usize, usize),
+    palette: &ColorPalette,
+) -> Cells {
+    let mut cells = Cells::default();
+    let target = (density.clamp(0., 1.) * (width * height) as f32).round() as usize;
+    let mut filled = 0;
+    while filled < target {
+        let color = palette.random(rng);
+        let (mut x, mut y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+        for _ in 0..rng.gen_range(CLUMP_CELLS).min(target - filled) {
+            if cells.0[y][x].is_none() {
+                cells.0[y][x] = Some(color);
+                filled += 1;
+            }
+            // Walks on to a neighbor, staying on the board
+            match rng.gen_range(0..4) {
+                0 => x = x.saturating_sub(1),
+                1 => x = (x + 1).min(width - 1),
+                2 => y = y.saturating_sub(1),
+                _ => y = (y + 1).min(height - 1),
+            }
+        }
+    }
+    cells
+}
+
+/// Whether no line of the opening on a `width` by `height` board is complete and at
+/// least [`MIN_PLACEABLE_SHAPES`] of the pool's shapes, or all of a smaller pool, fit
+/// somewhere unrotated.
+pub fn playable(cells: &Cells, (width, height): (usize, usize), registry: &ShapeRegistry) -> bool {
+    let Ok(mut board) = BoardGrid::with_size(width, height) else {
+        return false;
+    };
+    board.set_cells(*cells);
+    if !board.full_lines().is_empty() {
+        return false;
+    }
+    let bitboard = board.bitboard();
+    let needed = MIN_PLACEABLE_SHAPES.min(registry.pool().count());
+    registry
+        .pool()
+        .filter(|(_, rotations)| {
+            let shape = &rotations[0];
+            let shape_rows = shape.row_masks();
+            anchors(shape).any(|anchor| bitboard.fits_at(&shape_rows, anchor))
+        })
+        .take(needed)
+        .count()
+        == needed
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::shape::default_shapes;
+
+    const FULL: (usize, usize) = (BOARD_WIDTH, BOARD_HEIGHT);
+
+    fn filled(cells: &Cells) -> usize {
+        cells.0.iter().flatten().flatten().count()
+    }
+
+    #[test]
+    fn scattered_openings_are_playable_over_many_seeds() {
+        let (registry, palette) = (
+            ShapeRegistry::new(default_shapes()),
+            ColorPalette::default(),
+        );
+        let target = (0.15 * (BOARD_WIDTH * BOARD_HEIGHT) as f32).round() as usize;
+        let mut scattered = 0;
+        for seed in 0..300 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let cells = generate(
+                StartPreset::Scattered,
+                0.15,
+                FULL,
+                &mut rng,
+                (&registry, &palette),
+            );
+            assert!(playable(&cells, FULL, &registry), "seed {seed}");
+            let count = filled(&cells);
+            assert!(count == target || count == 0, "seed {seed}: {count} cells");
+            scattered += usize::from(count > 0);
+        }
+        assert!(scattered > 250);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_opening() {
+        let (registry, palette) = (
+            ShapeRegistry::new(default_shapes()),
+            ColorPalette::default(),
+        );
+        let [a, b] = [0; 2].map(|_| {
+            let mut rng = StdRng::seed_from_u64(192);
+            generate(
+                StartPreset::Scattered,
+                0.2,
+                FULL,
+                &mut rng,
+                (&registry, &palette),
+            )
+        });
+        assert!(a == b);
+        let mut rng = StdRng::seed_from_u64(192);
+        let empty = generate(
+            StartPreset::Empty,
+            0.2,
+            FULL,
+            &mut rng,
+            (&registry, &palette),
+        );
+        assert_eq!(filled(&empty), 0);
+    }
+
+    #[test]
+    fn openings_with_a_full_line_or_no_room_are_unplayable() {
+        let registry = ShapeRegistry::new(default_shapes());
+        let mut cells = Cells::default();
+        cells.0[7] = [Some(TileColor::Red); BOARD_WIDTH];
+        assert!(!playable(&cells, FULL, &registry));
+
+        // Every other cell filled, only single cells fit
+        let mut checkered = Cells::default();
+        for (y, row) in checkered.0.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if (x + y) % 2 == 0 {
+                    *cell = Some(TileColor::Blue);
+                }
+            }
+        }
+        assert!(!playable(&checkered, FULL, &registry));
+        let dots = ShapeRegistry::new(crate::shapes![(1, 1) "#"]);
+        assert!(playable(&checkered, FULL, &dots));
+    }
+
+    #[test]
+    fn openings_of_smaller_boards_stay_within_them() {
+        let (registry, palette) = (
+            ShapeRegistry::new(default_shapes()),
+            ColorPalette::default(),
+        );
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let cells = generate(
+                StartPreset::Scattered,
+                0.15,
+                (10, 8),
+                &mut rng,
+                (&registry, &palette),
+            );
+            let outside = (0..BOARD_HEIGHT)
+                .flat_map(|y| (0..BOARD_WIDTH).map(move |x| (x, y)))
+                .filter(|&(x, y)| (x >= 10 || y >= 8) && cells.0[y][x].is_some());
+            assert_eq!(outside.count(), 0, "seed {seed}");
+            let count = filled(&cells);
+            assert!(count == 12 || count == 0, "seed {seed}: {count} cells");
+        }
+    }
+
+    #[test]
+    fn presets_read_back_from_their_names() {
+        for preset in [StartPreset::Empty, StartPreset::Scattered] {
+            assert_eq!(StartPreset::parse(&preset.to_string()), Some(preset));
+        }
+        assert_eq!(StartPreset::parse("full"), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! Sudden death: once the main board fills past the rule set's threshold a countdown
+//! starts, and the game ends when it runs out unless clears bring the fill back down.
+
+use bevy::prelude::*;
+
+use crate::{
+    menu::GameState,
+    restart::RestartEvent,
+    rules::RuleSet,
+    strings::{t, Strings},
+    BlocksSystems, Board, BoardChangedEvent, GameOverEvent, MainBoard, OverlayBoard,
+};
+
+/// Width of the pulsing screen border during overtime, in pixels.
+const BORDER_WIDTH: f32 = 6.;
+/// Pulses of the border per second.
+const PULSE_RATE: f32 = 1.5;
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub enum SuddenDeath {
+    #[default]
+    Calm,
+    /// Seconds left to clear below the exit threshold.
+    Overtime(f32),
+    /// The countdown ran out, no more placements until a restart.
+    Ended,
+}
+impl SuddenDeath {
+    /// Moves between calm and overtime for a new fill ratio of the board.
+    fn update(self, fill: f32, rules: &RuleSet) -> Self {
+        match self {
+            SuddenDeath::Calm if fill > rules.overtime_enter => {
+                SuddenDeath::Overtime(rules.overtime_seconds)
+            }
+            SuddenDeath::Overtime(_) if fill < rules.overtime_exit => SuddenDeath::Calm,
+            state => state,
+        }
+    }
+}
+
+/// Run condition keeping placements going until sudden death ended the game.
+pub fn game_running(sudden_death: Res<SuddenDeath>) -> bool {
+    *sudden_death != SuddenDeath::Ended
+}
+
+#[derive(Component)]
+struct OvertimeBorder;
+
+#[derive(Component)]
+struct OvertimeText;
+
+pub struct OvertimePlugin;
+impl Plugin for OvertimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SuddenDeath>()
+            .add_systems(Startup, spawn_overtime_hud)
+            .add_systems(
+                Update,
+                (
+                    reset_sudden_death,
+                    // The sandbox has no game to end
+                    track_fill
+                        .after(BlocksSystems::Resolution)
+                        .run_if(in_state(GameState::Playing)),
+                    tick_overtime.run_if(in_state(GameState::Playing)),
+                    update_overtime_hud,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn reset_sudden_death(
+    mut restarts: EventReader<RestartEvent>,
+    mut sudden_death: ResMut<SuddenDeath>,
+) {
+    if restarts.read().count() > 0 {
+        *sudden_death = SuddenDeath::Calm;
+    }
+}
+
+fn track_fill(
+    rules: Res<RuleSet>,
+    mut sudden_death: ResMut<SuddenDeath>,
+    mut board_changed: EventReader<BoardChangedEvent>,
+    q_board: Query<(&Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok((board, entity)) = q_board.get_single() else {
+        return;
+    };
+    if !board_changed.read().any(|event| event.board == entity) {
+        return;
+    }
+    let filled = board.grid.cells.0.iter().flatten().flatten().count();
+    let fill = filled as f32 / (board.grid.width() * board.grid.height()) as f32;
+    let next = sudden_death.update(fill, &rules);
+    if next != *sudden_death {
+        *sudden_death = next;
+    }
+}
+
+fn tick_overtime(
+    time: Res<Time>,
+    mut sudden_death: ResMut<SuddenDeath>,
+    mut game_over: EventWriter<GameOverEvent>,
+) {
+    if let SuddenDeath::Overtime(remaining) = *sudden_death {
+        let remaining = remaining - time.delta_seconds();
+        *sudden_death = if remaining > 0. {
+            SuddenDeath::Overtime(remaining)
+        } else {
+            game_over.send(GameOverEvent);
+            SuddenDeath::Ended
+        };
+    }
+}
+
+fn spawn_overtime_hud(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                border: UiRect::all(Val::Px(BORDER_WIDTH)),
+                ..default()
+            },
+            border_color: Color::NONE.into(),
+            ..default()
+        },
+        OvertimeBorder,
+    ));
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 24.,
+                    color: Color::RED,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(56.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            ..default()
+        },
+        OvertimeText,
+    ));
+}
+
+/// Pulses the screen border and shows the countdown while in overtime.
+fn update_overtime_hud(
+    time: Res<Time>,
+    sudden_death: Res<SuddenDeath>,
+    strings: Res<Strings>,
+    mut shown: Local<Option<SuddenDeath>>,
+    mut q_border: Query<&mut BorderColor, With<OvertimeBorder>>,
+    mut q_text: Query<&mut Text, With<OvertimeText>>,
+) {
+    let alpha = match *sudden_death {
+        SuddenDeath::Calm => 0.,
+        SuddenDeath::Overtime(_) => {
+            let pulse =
+                0.5 + 0.5 * (time.elapsed_seconds() * std::f32::consts::TAU * PULSE_RATE).cos();
+            0.3 + 0.7 * pulse
+        }
+        SuddenDeath::Ended => 1.,
+    };
+    if let Ok(mut border) = q_border.get_single_mut() {
+        border.0 = Color::RED.with_a(alpha);
+    }
+    // The countdown text is only rebuilt when the shown second changes
+    let key = match *sudden_death {
+        SuddenDeath::Overtime(remaining) => SuddenDeath::Overtime(remaining.ceil()),
+        state => state,
+    };
+    if *shown == Some(key) && !strings.is_changed() {
+        return;
+    }
+    let Ok(mut text) = q_text.get_single_mut() else {
+        return;
+    };
+    *shown = Some(key);
+    text.sections[0].value = match key {
+        SuddenDeath::Calm => String::new(),
+        SuddenDeath::Overtime(remaining) => t!(strings, "overtime.countdown", remaining),
+        SuddenDeath::Ended => strings.get("overtime.over").to_owned(),
+    };
+}
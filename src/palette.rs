@@ -0,0 +1,194 @@
+//! Colors added on top of the built-in [`TileColor`]s, e.g. by puzzles or mods.
+//!
+//! Entries are read from `palette.txt`, holding `name = #rrggbb` lines. The n-th entry
+//! is [`TileColor::Custom`]`(n)` and is written as the n-th lowercase letter in the text
+//! formats, so saves and replays stay readable without the palette. The
+//! `preview_lighten` and `preview_alpha` keys set the [`OverlayStyle`] instead, the
+//! `outline` key the color of the selected shape's outline.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    board::{CustomColor, TileColor, MAX_CUSTOM_COLORS},
+    persistence::Persisted,
+};
+
+/// Filled gray cells, such as garbage, lighter than the gray of empty tiles so they
+/// don't pass for empty.
+const FILLED_GRAY: Color = Color::rgb(0.55, 0.55, 0.55);
+/// How far each channel of a custom color must be from the gray of empty tiles.
+const MIN_DISTANCE_FROM_EMPTY: f32 = 0.1;
+/// Least opacity of a custom color, anything fainter shows the empty tile below.
+const MIN_CUSTOM_ALPHA: f32 = 0.5;
+/// Built-in colors shapes are randomly drawn in.
+const SHAPE_COLORS: [TileColor; 3] = [TileColor::Red, TileColor::Green, TileColor::Blue];
+
+pub struct PaletteEntry {
+    pub name: String,
+    pub color: Color,
+}
+
+/// How the placement preview sets the cells a shape would fill apart from placed tiles.
+pub struct OverlayStyle {
+    /// How far the shape's color is moved towards white, washing it out at the same
+    /// time, so it never matches a placed tile of that color.
+    pub lighten: f32,
+    pub alpha: f32,
+}
+impl Default for OverlayStyle {
+    fn default() -> Self {
+        Self {
+            lighten: 0.6,
+            alpha: 0.8,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ColorPalette {
+    custom: Vec<PaletteEntry>,
+    pub overlay: OverlayStyle,
+    /// Color of the outline around the selected shape, see [`crate::shape_outline`].
+    pub outline: Color,
+}
+impl ColorPalette {
+    /// Adds a color, `None` once every custom color is taken or for a color that would
+    /// pass for an empty tile.
+    pub fn add(&mut self, name: impl Into<String>, color: Color) -> Option<TileColor> {
+        if looks_empty(color) {
+            return None;
+        }
+        let custom = CustomColor::new(u8::try_from(self.custom.len()).ok()?)?;
+        self.custom.push(PaletteEntry {
+            name: name.into(),
+            color,
+        });
+        Some(TileColor::Custom(custom))
+    }
+
+    /// Display color of a filled cell, custom colors missing from the palette are
+    /// fuchsia.
+    pub fn color(&self, tile: TileColor) -> Color {
+        match tile {
+            TileColor::Gray => FILLED_GRAY,
+            TileColor::Custom(custom) => self
+                .custom
+                .get(custom.index() as usize)
+                .map_or(Color::FUCHSIA, |entry| entry.color),
+            tile => tile.into(),
+        }
+    }
+
+    /// Color of a preview cell the shape fits into, see [`OverlayStyle`].
+    pub fn preview_color(&self, tile: TileColor) -> Color {
+        let color = self.color(tile);
+        let lighten = |channel: f32| channel + (1. - channel) * self.overlay.lighten;
+        Color::rgba(
+            lighten(color.r()),
+            lighten(color.g()),
+            lighten(color.b()),
+            self.overlay.alpha,
+        )
+    }
+
+    pub fn name(&self, tile: TileColor) -> &str {
+        match tile {
+            TileColor::Gray => "Gray",
+            TileColor::Red => "Red",
+            TileColor::Green => "Green",
+            TileColor::Blue => "Blue",
+            TileColor::Transparent => "Empty",
+            TileColor::Stone => "Stone",
+            TileColor::Cracked => "Cracked",
+            TileColor::Custom(custom) => self
+                .custom
+                .get(custom.index() as usize)
+                .map_or("Unknown", |entry| entry.name.as_str()),
+        }
+    }
+
+    pub fn custom_colors(&self) -> impl Iterator<Item = TileColor> {
+        (0..self.custom.len() as u8)
+            .filter_map(CustomColor::new)
+            .map(TileColor::Custom)
+    }
+
+    /// Colors a new shape can come in, the built-in ones followed by the custom ones.
+    pub fn shape_colors(&self) -> impl Iterator<Item = TileColor> {
+        SHAPE_COLORS.into_iter().chain(self.custom_colors())
+    }
+
+    /// Draws a shape color uniformly. Without custom colors this draws the same as
+    /// sampling [`TileColor`] directly, so seeds keep their games.
+    pub fn random<R: Rng + ?Sized>(&self, rng: &mut R) -> TileColor {
+        // Same integer type as the `Standard` distribution, which draws differently
+        let index = rng.gen_range(0..(SHAPE_COLORS.len() + self.custom.len()) as i32);
+        self.shape_colors().nth(index as usize).unwrap()
+    }
+}
+impl Persisted for ColorPalette {
+    const PATH: &'static str = "palette.txt";
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut palette = Self::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line.split_once('=')?;
+            match name.trim() {
+                "preview_lighten" => {
+                    palette.overlay.lighten = value.trim().parse().ok()?;
+                    continue;
+                }
+                "preview_alpha" => {
+                    palette.overlay.alpha = value.trim().parse().ok()?;
+                    continue;
+                }
+                "outline" => {
+                    palette.outline = Color::hex(value.trim()).ok()?;
+                    continue;
+                }
+                _ => {}
+            }
+            let color = Color::hex(value.trim()).ok()?;
+            if looks_empty(color) {
+                warn!("{}: {} looks like an empty tile", Self::PATH, name.trim());
+                continue;
+            }
+            if palette.add(name.trim(), color).is_none() {
+                warn!("{}: more than {MAX_CUSTOM_COLORS} colors", Self::PATH);
+                break;
+            }
+        }
+        Some(palette)
+    }
+}
+
+/// Whether cells of the color could be mistaken for empty ones: gray like the empty
+/// tiles or too transparent to cover them. Such cells would block shapes while looking
+/// free.
+fn looks_empty(color: Color) -> bool {
+    let empty = Color::from(TileColor::Gray);
+    let close = |a: f32, b: f32| (a - b).abs() < MIN_DISTANCE_FROM_EMPTY;
+    color.a() < MIN_CUSTOM_ALPHA
+        || close(color.r(), empty.r()) && close(color.g(), empty.g()) && close(color.b(), empty.b())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_passing_for_empty_tiles_are_rejected() {
+        let mut palette = ColorPalette::default();
+        assert_eq!(palette.add("Ash", Color::from(TileColor::Gray)), None);
+        assert_eq!(palette.add("Ghost", Color::rgba(1., 0., 1., 0.1)), None);
+        assert!(palette.add("Orange", Color::rgb(1., 0.5, 0.)).is_some());
+
+        let parsed = ColorPalette::parse("ash = #4d4d4d\nteal = #008080\n").unwrap();
+        let names: Vec<_> = parsed
+            .custom_colors()
+            .map(|tile| parsed.name(tile))
+            .collect();
+        assert_eq!(names, ["teal"]);
+    }
+}
@@ -0,0 +1,202 @@
+//! Pausing a game: Escape or a gamepad's start button while playing stops it under a
+//! screen offering to resume, restart or leave for the menu. Escape resumes too.
+
+use bevy::prelude::*;
+
+use crate::{
+    click_guard::ModalUi, keyboard, menu::GameState, planning::PlannedPlacements,
+    restart::RestartEvent, review::not_reviewing, strings::Strings,
+};
+
+const IDLE_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.5);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum PauseButton {
+    Resume,
+    Restart,
+    Menu,
+}
+impl PauseButton {
+    const ALL: [PauseButton; 3] = [PauseButton::Resume, PauseButton::Restart, PauseButton::Menu];
+
+    /// Key of the label in [`Strings`].
+    fn label(self) -> &'static str {
+        match self {
+            PauseButton::Resume => "pause.resume",
+            PauseButton::Restart => "pause.restart",
+            PauseButton::Menu => "pause.menu",
+        }
+    }
+}
+
+#[derive(Component)]
+struct PauseRoot;
+
+/// Index into [`PauseButton::ALL`] of the entry Enter activates.
+#[derive(Resource, Default)]
+struct PauseSelection(usize);
+
+pub struct PausePlugin;
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseSelection>()
+            .add_systems(OnEnter(GameState::Paused), spawn_pause_screen)
+            .add_systems(OnExit(GameState::Paused), despawn_pause_screen)
+            .add_systems(
+                Update,
+                (
+                    pause
+                        .run_if(in_state(GameState::Playing))
+                        .run_if(not_reviewing),
+                    (navigate_pause, highlight_buttons)
+                        .chain()
+                        .run_if(in_state(GameState::Paused)),
+                ),
+            );
+    }
+}
+
+/// Whether the key or button that pauses and resumes was pressed.
+fn pause_pressed(input_keys: &Input<KeyCode>, input_gamepad: &Input<GamepadButton>) -> bool {
+    input_keys.just_pressed(KeyCode::Escape)
+        || keyboard::gamepad_just_pressed(input_gamepad, GamepadButtonType::Start)
+}
+
+fn pause(
+    input_keys: Res<Input<KeyCode>>,
+    input_gamepad: Res<Input<GamepadButton>>,
+    plan: Res<PlannedPlacements>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Escape discards a plan being staged instead
+    if pause_pressed(&input_keys, &input_gamepad) && !plan.staging() {
+        next_state.set(GameState::Paused);
+    }
+}
+
+fn spawn_pause_screen(mut commands: Commands, strings: Res<Strings>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            PauseRoot,
+            ModalUi,
+        ))
+        .with_children(|commands| {
+            commands.spawn(
+                TextBundle::from_section(
+                    strings.get("pause.title"),
+                    TextStyle {
+                        font_size: 48.,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+            for button in PauseButton::ALL {
+                commands
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(200.),
+                                padding: UiRect::all(Val::Px(8.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: IDLE_BUTTON.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|commands| {
+                        commands.spawn(TextBundle::from_section(
+                            strings.get(button.label()),
+                            TextStyle {
+                                font_size: 24.,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn despawn_pause_screen(mut commands: Commands, q_root: Query<Entity, With<PauseRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Up and Down move the selection, Enter or a click activates an entry, Escape resumes.
+fn navigate_pause(
+    (input_keys, input_gamepad): (Res<Input<KeyCode>>, Res<Input<GamepadButton>>),
+    mut selection: ResMut<PauseSelection>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut restarts: EventWriter<RestartEvent>,
+    q_buttons: Query<(&Interaction, &PauseButton), Changed<Interaction>>,
+) {
+    let count = PauseButton::ALL.len();
+    if input_keys.just_pressed(KeyCode::Down) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+    if input_keys.just_pressed(KeyCode::Up) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    let mut activated = None;
+    for (interaction, button) in q_buttons.iter() {
+        let index = PauseButton::ALL.iter().position(|b| b == button).unwrap();
+        match interaction {
+            Interaction::Hovered => selection.0 = index,
+            Interaction::Pressed => activated = Some(*button),
+            Interaction::None => {}
+        }
+    }
+    if input_keys.just_pressed(KeyCode::Return) {
+        activated = Some(PauseButton::ALL[selection.0]);
+    }
+    if pause_pressed(&input_keys, &input_gamepad) {
+        activated = Some(PauseButton::Resume);
+    }
+    match activated {
+        Some(PauseButton::Resume) => next_state.set(GameState::Playing),
+        Some(PauseButton::Restart) => {
+            restarts.send(RestartEvent);
+            next_state.set(GameState::Playing);
+        }
+        Some(PauseButton::Menu) => next_state.set(GameState::Menu),
+        None => return,
+    }
+    // Opens on Resume next time
+    selection.0 = 0;
+}
+
+fn highlight_buttons(
+    selection: Res<PauseSelection>,
+    mut q_buttons: Query<(&PauseButton, &mut BackgroundColor)>,
+) {
+    for (button, mut background) in q_buttons.iter_mut() {
+        let selected = PauseButton::ALL[selection.0] == *button;
+        *background = if selected {
+            SELECTED_BUTTON
+        } else {
+            IDLE_BUTTON
+        }
+        .into();
+    }
+}
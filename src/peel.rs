@@ -0,0 +1,237 @@
+//! Taking back the last placement: for a few seconds after placing a shape that cleared
+//! nothing, holding the right mouse button over it lifts it off the board again. The
+//! shape becomes the selected one, the shape drawn after it goes back to the tray, and
+//! the points it scored are taken back. The piece is the latest entry of the
+//! [`MoveHistory`], which knows its cells, points and clears.
+
+use crate::{
+    events::BoardCommand,
+    history::{record_moves, MoveHistory},
+    hover::{brighten, CursorBoardPos},
+    intro::board_ready,
+    menu::GameState,
+    overtime::game_running,
+    palette::ColorPalette,
+    replay::Playback,
+    scoring::Score,
+    shape::{ShapeInstance, ShapeRegistry},
+    systems::replace_selected_shape,
+    systems::Tray,
+    tile_fade::{paint_now, TargetTileColor},
+    ui::tile_color,
+    ui::SpriteMutations,
+    zones::ZoneMap,
+    BlocksSystems, Board, CellList, MainBoard, OverlayBoard, SelectedShape,
+};
+use bevy::prelude::*;
+
+/// Seconds the right mouse button has to be held over the piece to lift it.
+const HOLD_SECONDS: f32 = 0.3;
+/// Strongest brightening of a liftable piece's tiles, at the top of the pulse.
+const GLOW_BRIGHTEN: f32 = 0.3;
+/// Pulses of the glow per second.
+const GLOW_RATE: f32 = 2.;
+
+/// Seconds after a placement during which it can be lifted, 0 to turn lifting off.
+#[derive(Resource)]
+pub struct PeelWindow(pub f32);
+impl Default for PeelWindow {
+    fn default() -> Self {
+        Self(3.)
+    }
+}
+
+/// The last placement, while it can still be lifted.
+#[derive(Resource, Default)]
+pub struct Peelable(Option<PeelablePlacement>);
+impl Peelable {
+    /// Whether the cell is part of the liftable piece.
+    pub fn covers(&self, cursor: &CursorBoardPos) -> bool {
+        match (&self.0, cursor.cell()) {
+            (Some(placement), Some((board, cell))) => {
+                placement.board == board && placement.cells.contains(&cell)
+            }
+            _ => false,
+        }
+    }
+}
+
+pub struct PeelablePlacement {
+    board: Entity,
+    /// [`Move::number`](crate::history::Move::number) of the placement.
+    number: usize,
+    cells: CellList,
+    remaining: f32,
+    /// Seconds the right mouse button was held over the piece.
+    held: f32,
+}
+
+pub struct PeelPlugin;
+impl Plugin for PeelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PeelWindow>()
+            .init_resource::<Peelable>()
+            .add_systems(
+                Update,
+                (
+                    peel.before(BlocksSystems::Preview)
+                        .run_if(in_state(GameState::Playing).and_then(board_ready))
+                        .run_if(game_running)
+                        .run_if(not(resource_exists::<Playback>())),
+                    glow_peelable
+                        .run_if(crate::review::not_reviewing)
+                        .in_set(BlocksSystems::Presentation)
+                        .after(crate::hover::highlight_hovered_cell),
+                ),
+            )
+            .add_systems(PostUpdate, track_placements.after(record_moves));
+    }
+}
+
+/// Follows the latest placement on the main board, liftable if it cleared nothing, and
+/// counts down the window to lift it.
+fn track_placements(
+    time: Res<Time>,
+    window: Res<PeelWindow>,
+    history: Res<MoveHistory>,
+    mut peelable: ResMut<Peelable>,
+    q_main_board: Query<Entity, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let latest = history.latest();
+    let current = peelable.0.as_ref().map(|placement| placement.number);
+    if latest.map(|placement| placement.number) != current {
+        // Cleared lines can't be put back, and took the piece's cells with them
+        peelable.0 = latest
+            .filter(|placement| placement.lines == 0 && window.0 > 0.)
+            .zip(q_main_board.get_single().ok())
+            .map(|(placement, board)| PeelablePlacement {
+                board,
+                number: placement.number,
+                cells: placement.cells.clone(),
+                remaining: window.0,
+                held: 0.,
+            });
+        return;
+    }
+    if let Some(placement) = &mut peelable.0 {
+        placement.remaining -= time.delta_seconds();
+        if placement.remaining <= 0. {
+            peelable.0 = None;
+        }
+    }
+}
+
+/// Lifts the piece once the right mouse button was held over it long enough.
+fn peel(
+    mut commands: Commands,
+    time: Res<Time>,
+    input_mb: Res<Input<MouseButton>>,
+    cursor: Res<CursorBoardPos>,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    (mut peelable, mut history): (ResMut<Peelable>, ResMut<MoveHistory>),
+    mut board_commands: EventWriter<BoardCommand>,
+    mut q_board: Query<(&Board, &mut Score, &mut Tray), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_selected_shape: Query<
+        (Entity, &mut ShapeInstance, &Children, &Transform),
+        With<SelectedShape>,
+    >,
+    mut q_sprites: Query<&mut Sprite>,
+) {
+    let held_over = input_mb.pressed(MouseButton::Right) && peelable.covers(&cursor);
+    let Some(placement) = &mut peelable.0 else {
+        return;
+    };
+    placement.held = if held_over {
+        placement.held + time.delta_seconds()
+    } else {
+        0.
+    };
+    if placement.held < HOLD_SECONDS {
+        return;
+    }
+    let Some(peeled) = peelable.0.take() else {
+        return;
+    };
+    let Some(placement) = history
+        .latest()
+        .filter(|placement| placement.number == peeled.number)
+    else {
+        return;
+    };
+    // Whatever changed the piece since, e.g. a garbage drop, it is no longer the same
+    let Ok((board, mut score, mut tray)) = q_board.get_mut(peeled.board) else {
+        return;
+    };
+    let intact = placement
+        .cells
+        .iter()
+        .all(|&(x, y)| board.grid.cells.0[y][x] == Some(placement.shape.color));
+    let Ok((entity, instance, children, &transform)) = q_selected_shape.get_single_mut() else {
+        return;
+    };
+    if !intact {
+        return;
+    }
+    let Some(placement) = history.take_back() else {
+        return;
+    };
+
+    board_commands.send(BoardCommand::Fill {
+        board: peeled.board,
+        cells: placement.cells,
+        color: None,
+    });
+    score.0 = score.0.saturating_sub(placement.points);
+    tray.0.push_front(*instance);
+    replace_selected_shape(
+        &mut commands,
+        (&registry, &palette),
+        (entity, instance, children),
+        &mut q_sprites,
+        placement.shape,
+        transform,
+    );
+}
+
+/// Pulses the liftable piece's tiles, and restores them once it can't be lifted anymore.
+fn glow_peelable(
+    time: Res<Time>,
+    peelable: Res<Peelable>,
+    zone_map: Res<ZoneMap>,
+    palette: Res<ColorPalette>,
+    mut glowing: Local<Option<(Entity, CellList)>>,
+    q_boards: Query<&Board, Without<OverlayBoard>>,
+    mut q_tiles: Query<(&mut Sprite, Option<&mut TargetTileColor>)>,
+    mut sprite_mutations: ResMut<SpriteMutations>,
+) {
+    let target = peelable
+        .0
+        .as_ref()
+        .map(|placement| (placement.board, placement.cells.clone()));
+    let pulse = 0.5 + 0.5 * (time.elapsed_seconds() * std::f32::consts::TAU * GLOW_RATE).cos();
+    let mut paint = |board_entity: Entity, cells: &[(usize, usize)], amount: f32| {
+        let Ok(board) = q_boards.get(board_entity) else {
+            return;
+        };
+        for &(x, y) in cells {
+            let Ok((mut sprite, target)) = q_tiles.get_mut(board.entities[y][x]) else {
+                continue;
+            };
+            let color = brighten(tile_color(board, &zone_map, &palette, x, y), amount);
+            if sprite.color != color {
+                paint_now(&mut sprite, target, color);
+                sprite_mutations.0 += 1;
+            }
+        }
+    };
+    if let Some((board, cells)) = glowing
+        .take()
+        .filter(|previous| Some(previous) != target.as_ref())
+    {
+        paint(board, &cells, 0.);
+    }
+    if let Some((board, cells)) = &target {
+        paint(*board, cells, GLOW_BRIGHTEN * pulse);
+    }
+    *glowing = target;
+}
@@ -0,0 +1,375 @@
+//! Files kept between sessions, read and written off the main thread.
+//!
+//! Files the game writes start with a `version <n>` line, the version of their format
+//! when they were written. Loading tries the current version and falls back to the
+//! migrations of older ones, files from a newer version of the game are refused with a
+//! message rather than misread. Files without the line are version 0, written before
+//! versions were stamped or by hand.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, IoTaskPool, Task},
+};
+
+use crate::{
+    strings::{t, Strings},
+    toast::spawn_toast,
+};
+
+const VERSION_PREFIX: &str = "version ";
+
+/// Directory the game's files are read from and written to, which relative paths are
+/// resolved against. Empty for the working directory.
+#[derive(Resource, Clone, Default)]
+pub struct DataDir(pub PathBuf);
+impl DataDir {
+    /// `path` within the directory, unchanged if it's absolute.
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+/// Asks for `contents` to be written to `path` without blocking the frame.
+#[derive(Event)]
+pub struct PersistRequest {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Sent once the write of a [`PersistRequest`] finished.
+#[derive(Event)]
+pub struct PersistResult {
+    pub path: PathBuf,
+    pub result: io::Result<()>,
+}
+
+/// Why a versioned file couldn't be loaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadError {
+    Malformed,
+    /// Written by a newer version of the game, in the given format version.
+    Newer(u32),
+}
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed"),
+            Self::Newer(version) => write!(
+                f,
+                "created by a newer version of the game (format {version})"
+            ),
+        }
+    }
+}
+
+/// A file format whose older versions can still be read.
+pub trait Versioned: Sized {
+    /// Version of the format written now.
+    const VERSION: u32;
+
+    /// Parses a file of the current version, without its version line.
+    fn parse_current(text: &str) -> Option<Self>;
+
+    /// Reads a file of an older version, `None` if there's no migration from it.
+    fn migrate(version: u32, text: &str) -> Option<Self>;
+}
+
+/// `body` with the version line of a file of the given version.
+pub fn stamp(version: u32, body: impl fmt::Display) -> String {
+    format!("{VERSION_PREFIX}{version}\n{body}")
+}
+
+/// Path of one of the numbered slots of a file kept several times, like `save_1.txt`
+/// for the first slot of `save`. Slots count from 0, their files from 1.
+pub fn slot_path(stem: &str, slot: usize) -> String {
+    format!("{stem}_{}.txt", slot + 1)
+}
+
+/// Splits the version line off a file, version 0 if it has none.
+fn split_version(text: &str) -> Result<(u32, &str), LoadError> {
+    let Some(rest) = text.strip_prefix(VERSION_PREFIX) else {
+        return Ok((0, text));
+    };
+    let (version, body) = rest.split_once('\n').unwrap_or((rest, ""));
+    let version = version.trim().parse().map_err(|_| LoadError::Malformed)?;
+    Ok((version, body))
+}
+
+/// Reads a file of the given format, migrating it from an older version if need be.
+pub fn load_versioned<T: Versioned>(text: &str) -> Result<T, LoadError> {
+    let (version, body) = split_version(text)?;
+    let loaded = match version {
+        version if version > T::VERSION => return Err(LoadError::Newer(version)),
+        version if version == T::VERSION => T::parse_current(body),
+        version => T::migrate(version, body),
+    };
+    loaded.ok_or(LoadError::Malformed)
+}
+
+/// A resource read from a file at startup. Until the file finished loading, or if it
+/// is missing, malformed or from a newer version, the resource holds its default.
+pub trait Persisted: Resource + Default {
+    const PATH: &'static str;
+    /// Version of the format [`Persisted::persist_request`] writes.
+    const VERSION: u32 = 1;
+
+    /// Parses a file of the current version.
+    fn parse(text: &str) -> Option<Self>;
+
+    /// Reads a file of an older version. Version 0 files, from before versions were
+    /// stamped, read like the first stamped version unless a resource says otherwise.
+    fn migrate(version: u32, text: &str) -> Option<Self> {
+        (version == 0).then(|| Self::parse(text)).flatten()
+    }
+
+    /// Writes the resource to its file, stamped with its version.
+    fn persist_request(&self) -> PersistRequest
+    where
+        Self: fmt::Display,
+    {
+        PersistRequest {
+            path: Self::PATH.into(),
+            contents: stamp(Self::VERSION, self),
+        }
+    }
+}
+
+/// Every [`Persisted`] resource is [`Versioned`] through its own methods.
+struct PersistedFormat<R>(R);
+impl<R: Persisted> Versioned for PersistedFormat<R> {
+    const VERSION: u32 = R::VERSION;
+
+    fn parse_current(text: &str) -> Option<Self> {
+        R::parse(text).map(Self)
+    }
+
+    fn migrate(version: u32, text: &str) -> Option<Self> {
+        R::migrate(version, text).map(Self)
+    }
+}
+
+pub trait PersistAppExt {
+    /// Inserts the default resource and replaces it with the one stored on disk once
+    /// it finished loading.
+    fn load_persisted<R: Persisted>(&mut self) -> &mut Self;
+}
+impl PersistAppExt for App {
+    fn load_persisted<R: Persisted>(&mut self) -> &mut Self {
+        self.init_resource::<R>()
+            .add_systems(Startup, start_load::<R>)
+            .add_systems(Update, poll_load_tasks::<R>)
+    }
+}
+
+pub struct PersistencePlugin;
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PersistRequest>()
+            .add_event::<PersistResult>()
+            .init_resource::<DataDir>()
+            .init_resource::<PathWrites>()
+            .add_systems(
+                Update,
+                (
+                    start_writes,
+                    poll_write_tasks.after(start_writes),
+                    log_persist_results.after(poll_write_tasks),
+                ),
+            );
+    }
+}
+
+#[derive(Component)]
+struct WriteTask {
+    path: PathBuf,
+    task: Task<io::Result<()>>,
+}
+
+/// Paths being written, and the contents waiting for their write in flight to finish,
+/// the latest request per path. Writes to a path never overlap, so an older write can't
+/// land after a newer one.
+#[derive(Resource, Default)]
+struct PathWrites {
+    in_flight: HashSet<PathBuf>,
+    queued: HashMap<PathBuf, String>,
+}
+
+#[derive(Component)]
+struct LoadTask<R> {
+    task: Task<io::Result<String>>,
+    resource: PhantomData<fn() -> R>,
+}
+
+/// Writes `contents` next to `path` and renames it over `path`, so a crash or a full
+/// disk leaves either the old file or the new one, never half of one.
+fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)
+}
+
+fn spawn_write(commands: &mut Commands, dir: &DataDir, path: PathBuf, contents: String) {
+    let task_path = dir.path(&path);
+    let task = IoTaskPool::get().spawn(async move { write_atomically(&task_path, &contents) });
+    commands.spawn(WriteTask { path, task });
+}
+
+fn start_writes(
+    mut commands: Commands,
+    dir: Res<DataDir>,
+    mut writes: ResMut<PathWrites>,
+    mut requests: EventReader<PersistRequest>,
+) {
+    for request in requests.read() {
+        if writes.in_flight.insert(request.path.clone()) {
+            spawn_write(
+                &mut commands,
+                &dir,
+                request.path.clone(),
+                request.contents.clone(),
+            );
+        } else {
+            writes
+                .queued
+                .insert(request.path.clone(), request.contents.clone());
+        }
+    }
+}
+
+fn poll_write_tasks(
+    mut commands: Commands,
+    dir: Res<DataDir>,
+    mut writes: ResMut<PathWrites>,
+    mut q_tasks: Query<(Entity, &mut WriteTask)>,
+    mut results: EventWriter<PersistResult>,
+) {
+    for (entity, mut write) in q_tasks.iter_mut() {
+        if !write.task.is_finished() {
+            continue;
+        }
+        let result = block_on(&mut write.task);
+        let path = std::mem::take(&mut write.path);
+        commands.entity(entity).despawn();
+        match writes.queued.remove(&path) {
+            Some(contents) => spawn_write(&mut commands, &dir, path.clone(), contents),
+            None => {
+                writes.in_flight.remove(&path);
+            }
+        }
+        results.send(PersistResult { path, result });
+    }
+}
+
+fn log_persist_results(mut results: EventReader<PersistResult>) {
+    for PersistResult { path, result } in results.read() {
+        if let Err(err) = result {
+            error!("failed to write {}: {err}", path.display());
+        }
+    }
+}
+
+/// Reads every file of a directory with the extension off the main thread, as file stems
+/// and contents. A missing directory reads as empty.
+pub fn read_text_files(
+    dir: impl Into<PathBuf>,
+    extension: &'static str,
+) -> Task<io::Result<Vec<(String, String)>>> {
+    let dir = dir.into();
+    IoTaskPool::get().spawn(async move {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|found| found == extension) {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                files.push((stem.into_owned(), std::fs::read_to_string(&path)?));
+            }
+        }
+        Ok(files)
+    })
+}
+
+fn start_load<R: Persisted>(mut commands: Commands, dir: Res<DataDir>) {
+    let path = dir.path(R::PATH);
+    let task = IoTaskPool::get().spawn(async move { std::fs::read_to_string(path) });
+    commands.spawn(LoadTask::<R> {
+        task,
+        resource: PhantomData,
+    });
+}
+
+fn poll_load_tasks<R: Persisted>(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    mut q_tasks: Query<(Entity, &mut LoadTask<R>)>,
+) {
+    for (entity, mut load) in q_tasks.iter_mut() {
+        if !load.task.is_finished() {
+            continue;
+        }
+        match block_on(&mut load.task) {
+            Ok(text) => match load_versioned::<PersistedFormat<R>>(&text) {
+                Ok(PersistedFormat(resource)) => commands.insert_resource(resource),
+                Err(LoadError::Malformed) => warn!("ignoring malformed {}", R::PATH),
+                Err(err @ LoadError::Newer(_)) => {
+                    warn!("ignoring {}: {err}", R::PATH);
+                    spawn_toast(&mut commands, t!(strings, "toast.newer_file", R::PATH));
+                }
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => error!("failed to read {}: {err}", R::PATH),
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("blocks-persistence-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file_without_leftovers() {
+        let path = temp_path("atomic.txt");
+        std::fs::write(&path, "old").unwrap();
+        write_atomically(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(!path.with_extension("txt.tmp").exists());
+    }
+
+    #[test]
+    fn the_last_request_to_a_path_wins() {
+        let path = temp_path("serialized.txt");
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, PersistencePlugin));
+        for contents in ["first", "second", "third"] {
+            app.world.send_event(PersistRequest {
+                path: path.clone(),
+                contents: contents.into(),
+            });
+        }
+        app.update();
+        while !app.world.resource::<PathWrites>().in_flight.is_empty() {
+            app.update();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "third");
+    }
+}
@@ -0,0 +1,263 @@
+//! Planning several placements at once. Holding Ctrl, clicks stage the selected shape
+//! and the [`PLAN_PIECES`] - 1 shapes drawn after it where they should go, shown on the
+//! overlay, and releasing Ctrl places them in order. Escape discards the plan.
+//!
+//! The shapes coming next are those of the tray, and should the tray hold fewer, drawn
+//! from copies of the game's random state, so the plan is only known to fit as long as
+//! nothing else draws in between.
+//! Committing places a step per frame, letting the clears of one resolve before the
+//! next, and stops with a toast at the first step that no longer fits.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    assist::Assist,
+    click_guard::PointerCapture,
+    highlight::HighlightLayer,
+    highlight::Highlights,
+    keyboard,
+    palette::ColorPalette,
+    render_order,
+    restart::RestartEvent,
+    rules::RuleSet,
+    shape::{ShapeInstance, ShapeRegistry},
+    strings::{t, Strings},
+    systems::draw_shape,
+    systems::replace_selected_shape,
+    systems::GameRng,
+    systems::Preview,
+    systems::ShapeDrought,
+    systems::ShapeQueue,
+    systems::ShapeWeights,
+    systems::Tray,
+    toast::spawn_toast,
+    Board, CellList, MainBoard, OverlayBoard, SelectedShape, TILE_SIZE,
+};
+
+/// Shapes in a plan, the selected one included.
+pub const PLAN_PIECES: usize = 3;
+/// Opacity of the staged footprints, below that of the preview.
+const PLANNED_ALPHA: f32 = 0.35;
+
+/// A staged placement.
+#[derive(Clone)]
+pub struct PlannedStep {
+    pub board: Entity,
+    pub shape: ShapeInstance,
+    /// The selected shape the step expects when it's placed: the shape as drawn, or as
+    /// staged for the first step, which committing selects again.
+    pub drawn: ShapeInstance,
+    pub anchor: (isize, isize),
+    /// Cells it covers, as of the earlier steps having been placed.
+    cells: CellList,
+}
+
+/// The plan being staged or committed.
+#[derive(Resource, Default)]
+pub struct PlannedPlacements {
+    /// Shapes to stage, the selected one first, while Ctrl is held.
+    pieces: Vec<ShapeInstance>,
+    steps: VecDeque<PlannedStep>,
+    committing: bool,
+}
+impl PlannedPlacements {
+    /// Whether clicks stage placements instead of placing.
+    pub fn staging(&self) -> bool {
+        !self.pieces.is_empty() && !self.committing
+    }
+
+    /// The step to place this frame while committing.
+    pub fn next_commit(&mut self) -> Option<PlannedStep> {
+        if !self.committing {
+            return None;
+        }
+        let step = self.steps.pop_front();
+        if self.steps.is_empty() {
+            *self = Self::default();
+        }
+        step
+    }
+
+    /// Drops the rest of a plan that stopped fitting.
+    pub fn abort(&mut self, commands: &mut Commands, strings: &Strings) {
+        *self = Self::default();
+        spawn_toast(commands, t!(strings, "toast.plan_aborted"));
+    }
+}
+
+/// Starts, stages, commits or discards the plan. Runs after the preview, whose
+/// superimposition a click stages.
+pub fn stage_plan(
+    mut commands: Commands,
+    (input_mb, input_keys, pointer): (
+        Res<Input<MouseButton>>,
+        Res<Input<KeyCode>>,
+        Res<PointerCapture>,
+    ),
+    input_kind: Res<keyboard::ActiveInputKind>,
+    preview: Res<Preview>,
+    (registry, palette, strings): (Res<ShapeRegistry>, Res<ColorPalette>, Res<Strings>),
+    (rng, rules, assist): (Res<GameRng>, Res<RuleSet>, Res<Assist>),
+    (weights, drought): (Res<ShapeWeights>, Res<ShapeDrought>),
+    mut plan: ResMut<PlannedPlacements>,
+    mut restarts: EventReader<RestartEvent>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_queue: Query<(&ShapeQueue, &Tray), With<MainBoard>>,
+    mut q_selected_shape: Query<(Entity, &mut ShapeInstance, &Children), With<SelectedShape>>,
+    mut q_sprites: Query<&mut Sprite>,
+) {
+    // Ctrl+R holds Ctrl too, peeking at the shapes of the game being left
+    if restarts.read().count() > 0 {
+        *plan = PlannedPlacements::default();
+        return;
+    }
+    if plan.committing {
+        return;
+    }
+    let ctrl = [KeyCode::ControlLeft, KeyCode::ControlRight];
+    let Ok(selected) = q_selected_shape.get_single_mut() else {
+        return;
+    };
+    if !plan.staging() {
+        if let (true, Ok((queue, tray))) = (input_keys.any_just_pressed(ctrl), q_queue.get_single())
+        {
+            plan.pieces = peek_shapes(
+                (*selected.1, tray),
+                &rng,
+                (&weights, &drought, queue, (&rules, &assist)),
+                (&registry, &palette),
+            );
+        }
+        return;
+    }
+
+    let transform = Transform {
+        translation: preview.world_position.extend(render_order::SHAPE),
+        scale: Vec3::splat(TILE_SIZE),
+        ..default()
+    };
+    let discarded = input_keys.just_pressed(KeyCode::Escape);
+    if discarded || !input_keys.any_pressed(ctrl) {
+        // Back to the selected shape, as the first step staged it
+        let first = plan.steps.front().map_or(plan.pieces[0], |step| step.shape);
+        replace_selected_shape(
+            &mut commands,
+            (&registry, &palette),
+            selected,
+            &mut q_sprites,
+            first,
+            transform,
+        );
+        if discarded || plan.steps.is_empty() {
+            *plan = PlannedPlacements::default();
+        } else {
+            plan.committing = true;
+        }
+        return;
+    }
+
+    // Clicks on the HUD are the HUD's
+    let clicked = (input_mb.just_pressed(MouseButton::Left) && !pointer.captured())
+        || (input_keys.just_pressed(KeyCode::Space)
+            && *input_kind != keyboard::ActiveInputKind::Mouse);
+    let staged = plan.steps.len();
+    if !clicked || staged >= plan.pieces.len() {
+        return;
+    }
+    let (Some(board_entity), Some(superimposition)) = (preview.board, &preview.superimposition)
+    else {
+        return;
+    };
+    let Ok(board) = q_board.get(board_entity) else {
+        return;
+    };
+    if !superimposition.success {
+        if let Some(message) = &preview.vetoed {
+            spawn_toast(&mut commands, message.clone());
+        }
+        return;
+    }
+    // Play the plan so far on a copy of the board, clearing lines as placing would
+    let mut grid = board.grid;
+    for step in plan.steps.iter() {
+        grid.try_place(step.shape.resolve(&registry), step.anchor, step.shape.color);
+        grid.clear_full_lines();
+    }
+    let shape = *selected.1;
+    let Some(cells) = grid.try_place(
+        shape.resolve(&registry),
+        superimposition.anchor,
+        shape.color,
+    ) else {
+        spawn_toast(&mut commands, t!(strings, "toast.plan_overlaps"));
+        return;
+    };
+    let drawn = if staged == 0 {
+        shape
+    } else {
+        plan.pieces[staged]
+    };
+    plan.steps.push_back(PlannedStep {
+        board: board_entity,
+        shape,
+        drawn,
+        anchor: superimposition.anchor,
+        cells,
+    });
+    if let Some(&next) = plan.pieces.get(staged + 1) {
+        replace_selected_shape(
+            &mut commands,
+            (&registry, &palette),
+            selected,
+            &mut q_sprites,
+            next,
+            transform,
+        );
+    }
+}
+
+/// The selected shape and the ones placing would draw after it, out of the tray or
+/// drawn from copies of the game's random state.
+fn peek_shapes(
+    (selected, tray): (ShapeInstance, &Tray),
+    rng: &GameRng,
+    (weights, drought, queue, (rules, assist)): (
+        &ShapeWeights,
+        &ShapeDrought,
+        &ShapeQueue,
+        (&RuleSet, &Assist),
+    ),
+    (registry, palette): (&ShapeRegistry, &ColorPalette),
+) -> Vec<ShapeInstance> {
+    let (mut rng, mut drought, mut forced) = (rng.rng.clone(), drought.clone(), queue.0.clone());
+    let mut pieces = vec![selected];
+    pieces.extend(tray.0.iter().take(PLAN_PIECES - 1));
+    while pieces.len() < PLAN_PIECES {
+        pieces.push(draw_shape(
+            (&mut forced, &mut drought),
+            &mut rng,
+            (&weights.0, rules.drought_threshold, assist),
+            (registry, palette),
+        ));
+    }
+    pieces
+}
+
+/// Highlights the staged footprints, under the preview.
+pub fn show_plan(
+    plan: Res<PlannedPlacements>,
+    palette: Res<ColorPalette>,
+    mut highlights: ResMut<Highlights>,
+) {
+    highlights.clear(HighlightLayer::Plan);
+    for step in plan.steps.iter() {
+        let color = palette
+            .preview_color(step.shape.color)
+            .with_a(PLANNED_ALPHA);
+        for &cell in step.cells.iter() {
+            highlights.set(HighlightLayer::Plan, cell, color);
+        }
+    }
+}
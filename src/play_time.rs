@@ -0,0 +1,95 @@
+//! Time spent playing the current game, shown on the HUD as minutes and seconds.
+
+use bevy::prelude::*;
+
+use crate::{
+    intro::board_ready, layout::LayoutSlot, menu::GameState, overtime::game_running,
+    restart::RestartEvent, sprint::sprint_unfinished, BlocksSystems,
+};
+
+/// Seconds played in the current game. Only counts while the game takes input, not in
+/// the menu, the sandbox, the board's intro or after the game or sprint ended.
+#[derive(Resource, Default)]
+pub struct PlayTime(pub f32);
+impl PlayTime {
+    /// `mm:ss`, minutes going past 59 rather than adding hours.
+    pub fn format(&self) -> String {
+        let seconds = self.0 as u64;
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[derive(Component)]
+struct PlayTimeText;
+
+pub struct PlayTimePlugin;
+impl Plugin for PlayTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayTime>()
+            .add_systems(Startup, spawn_play_time_text)
+            .add_systems(
+                Update,
+                (
+                    reset_play_time.before(BlocksSystems::Preview),
+                    count_play_time
+                        .after(BlocksSystems::Resolution)
+                        .run_if(in_state(GameState::Playing).and_then(board_ready))
+                        .run_if(game_running.and_then(sprint_unfinished)),
+                    update_play_time_text.after(count_play_time),
+                ),
+            );
+    }
+}
+
+pub(crate) fn reset_play_time(
+    mut restarts: EventReader<RestartEvent>,
+    mut play_time: ResMut<PlayTime>,
+) {
+    if restarts.read().count() > 0 {
+        *play_time = PlayTime::default();
+    }
+}
+
+fn count_play_time(time: Res<Time>, mut play_time: ResMut<PlayTime>) {
+    play_time.0 += time.delta_seconds();
+}
+
+fn spawn_play_time_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 20.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(32.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            ..default()
+        },
+        PlayTimeText,
+        LayoutSlot::PlayTime,
+    ));
+}
+
+fn update_play_time_text(
+    play_time: Res<PlayTime>,
+    mut shown: Local<Option<u64>>,
+    mut q_text: Query<&mut Text, With<PlayTimeText>>,
+) {
+    // Only formatted when the shown second changes
+    let seconds = play_time.0 as u64;
+    if *shown == Some(seconds) {
+        return;
+    }
+    let Ok(mut text) = q_text.get_single_mut() else {
+        return;
+    };
+    *shown = Some(seconds);
+    text.sections[0].value = format!("Time {}", play_time.format());
+}
@@ -0,0 +1,359 @@
+//! [`BlocksPlugin`], wiring every part of the game into an app.
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+#[cfg(feature = "test-harness")]
+use crate::harness;
+#[cfg(feature = "net")]
+use crate::net;
+use crate::{
+    announce, assist, attract, audio, autoplay,
+    board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    board_texture, budget, click_guard,
+    components::{spawn_board, BoardRenderer, MainBoard, OverlayBoard, SelectedShape, TILE_SIZE},
+    config, constraints, crash,
+    events::{
+        BoardChangedEvent, BoardCommand, GameOverEvent, LinesClearedEvent, PlacementEvent,
+        ResizeBoardEvent, ShapePoolChanged,
+    },
+    export, game_over, ghost_trail, heatmap, highlight,
+    highlight::Highlights,
+    history, hooks, hover,
+    input::{toggle_assist_overlay, track_idle, IdleTimer},
+    intro, keyboard, layout, leaderboard, menu, minimap, overtime,
+    palette::ColorPalette,
+    pause, peel,
+    persistence::{PersistAppExt, PersistencePlugin},
+    planning, play_time, puzzle, puzzle_select, render_order, replay, resize, restart, review,
+    rotation, rules, rumble, sandbox, save, save_slots, schedule, score_popups, scoring,
+    settings::Settings,
+    settings_menu,
+    shape::{default_shapes, ShapeId, ShapeInstance, ShapePool, ShapeRegistry},
+    shape_outline, shape_slot, snapshot, sprint, stats, strings,
+    systems::{
+        apply_board_commands, build_shape, clear_lines, idle_hint, place, preview,
+        repaint_frozen_cells, replace_empty_shape, restore_selected_shape, shake, swap_shape_pool,
+        GameRng, Gravity, HardMode, Preview, ShapeDrought, ShapeQueue, ShapeWeights, Tray,
+    },
+    tile_fade, tile_style, toast, tooltip, tutorial, tween,
+    ui::{
+        reset_sprite_mutations, spawn_camera, spawn_hud, update_assist_overlay, update_board,
+        CameraZoom, SpriteMutations,
+    },
+    zones,
+};
+#[cfg(feature = "debug-ui")]
+use crate::{console, debug_ui};
+
+/// Spawns the main board with its overlay board and the first selected shape.
+pub(crate) fn startup(
+    mut commands: Commands,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    renderer: Res<BoardRenderer>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let main_board = match *renderer {
+        BoardRenderer::Sprites => spawn_board(&mut commands, true),
+        BoardRenderer::Texture => board_texture::spawn_textured_board(&mut commands, &mut images),
+    }
+    .insert((
+        MainBoard,
+        scoring::Score::default(),
+        scoring::Level::default(),
+        scoring::Combo::default(),
+        ShapeQueue::default(),
+        Tray::default(),
+    ))
+    .id();
+    let overlay_board = spawn_board(&mut commands, false)
+        .insert((
+            OverlayBoard,
+            Transform::from_xyz(0., 0., render_order::in_board(render_order::OVERLAY)),
+        ))
+        .id();
+    commands.add(AddChild {
+        parent: main_board,
+        child: overlay_board,
+    });
+
+    let default_shape = ShapeInstance {
+        id: ShapeId(0),
+        rotation: 0,
+        color: TileColor::Blue,
+    };
+    let mut selected = build_shape(&mut commands, &registry, &palette, &default_shape);
+    selected.insert(SelectedShape);
+}
+
+/// Stages of the game's frame in `Update`, run in this order. Systems of other plugins
+/// can be ordered against them, they're kept stable across versions:
+///
+/// - the cursor's position on the boards is resolved in `PreUpdate`, before any of them;
+/// - commands of [`BlocksSystems::Placement`] are applied before
+///   [`BlocksSystems::Resolution`] and those of [`BlocksSystems::Resolution`] before
+///   [`BlocksSystems::Presentation`], so e.g. the next selected shape exists by then.
+///
+/// [`BlocksSystems::Preview`] and [`BlocksSystems::Placement`] only run while playing.
+#[derive(SystemSet, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum BlocksSystems {
+    /// Input acting on the selected shape, like rotating it.
+    Input,
+    /// The placement preview under the cursor.
+    Preview,
+    /// Changes to the board made by placing shapes or loading saves. Systems after it
+    /// see the board with the frame's placement, before its lines cleared.
+    Placement,
+    /// Line clears resulting from placements.
+    Resolution,
+    /// Writing the board state into sprites and overlays.
+    Presentation,
+}
+
+/// The whole game, to be added next to bevy's `DefaultPlugins`. Its options are set
+/// through [`BlocksPlugin::builder`].
+///
+/// The tutorial hands out the first three default shapes, so a custom registry should
+/// start with them.
+#[derive(Clone)]
+pub struct BlocksPlugin {
+    pub(crate) tile_size: f32,
+    pub(crate) spawn_camera: bool,
+    pub(crate) rules: Option<rules::RuleSet>,
+    pub(crate) rule_overrides: Option<rules::RuleOverrides>,
+    pub(crate) renderer: Option<BoardRenderer>,
+    pub(crate) puzzle_dir: Option<PathBuf>,
+    /// Pool replacing [`default_shapes`], checked by the builder.
+    pub(crate) shapes: Option<ShapePool>,
+    /// Size of the main board other than the full one, checked by the builder.
+    pub(crate) board_size: Option<(usize, usize)>,
+}
+impl Default for BlocksPlugin {
+    fn default() -> Self {
+        Self {
+            tile_size: TILE_SIZE,
+            spawn_camera: true,
+            rules: None,
+            rule_overrides: None,
+            renderer: None,
+            puzzle_dir: None,
+            shapes: None,
+            board_size: None,
+        }
+    }
+}
+impl BlocksPlugin {
+    pub fn builder() -> config::BlocksPluginBuilder {
+        config::BlocksPluginBuilder::new()
+    }
+}
+impl Plugin for BlocksPlugin {
+    fn build(&self, app: &mut App) {
+        let registry = app
+            .world
+            .remove_resource::<ShapeRegistry>()
+            .unwrap_or_else(|| {
+                let shapes = self.shapes.clone().unwrap_or_else(default_shapes);
+                let board_size = self.board_size.unwrap_or((BOARD_WIDTH, BOARD_HEIGHT));
+                ShapeRegistry::try_for_board(shapes, board_size)
+                    .expect("a shape registry needs at least one placeable shape")
+            });
+        // Taken over by the plugins below, like resources inserted before this one
+        if let Some(rules) = &self.rules {
+            app.insert_resource(rules.clone());
+        }
+        if let Some(overrides) = self.rule_overrides {
+            app.insert_resource(overrides);
+        }
+        if let Some(renderer) = self.renderer {
+            app.insert_resource(renderer);
+        }
+        if let Some(size) = self.board_size {
+            app.insert_resource(resize::PendingResize(Some(size)));
+        }
+        if let Some(dir) = &self.puzzle_dir {
+            app.insert_resource(puzzle_select::PuzzleDir(dir.clone()));
+        }
+        if self.spawn_camera {
+            app.insert_resource(CameraZoom(TILE_SIZE / self.tile_size))
+                .add_systems(Startup, spawn_camera);
+        }
+        app.add_plugins(PersistencePlugin)
+            .add_systems(Startup, (startup, spawn_hud, save::spawn_resume_prompt))
+            .add_systems(First, reset_sprite_mutations)
+            .configure_sets(
+                Update,
+                (
+                    BlocksSystems::Input,
+                    BlocksSystems::Preview,
+                    BlocksSystems::Placement,
+                    BlocksSystems::Resolution,
+                    BlocksSystems::Presentation,
+                )
+                    .chain(),
+            )
+            .configure_sets(
+                Update,
+                (BlocksSystems::Preview, BlocksSystems::Placement)
+                    .run_if(in_state(menu::GameState::Playing).and_then(intro::board_ready))
+                    .run_if(review::not_reviewing),
+            )
+            .add_systems(
+                Update,
+                (
+                    track_idle.in_set(BlocksSystems::Input),
+                    // The boundaries promised by `BlocksSystems`, whichever systems they
+                    // end up between
+                    apply_deferred
+                        .after(BlocksSystems::Placement)
+                        .before(BlocksSystems::Resolution),
+                    apply_deferred
+                        .after(BlocksSystems::Resolution)
+                        .before(BlocksSystems::Presentation),
+                    (
+                        restore_selected_shape,
+                        replace_empty_shape,
+                        preview,
+                        planning::stage_plan,
+                        planning::show_plan,
+                    )
+                        .chain()
+                        .in_set(BlocksSystems::Preview),
+                    (
+                        place.run_if(
+                            overtime::game_running
+                                .and_then(puzzle::puzzle_unsolved)
+                                .and_then(sprint::sprint_unfinished),
+                        ),
+                        (save::resume_autosave, save::apply_save).chain(),
+                    )
+                        .in_set(BlocksSystems::Placement),
+                    // Also editing the sandbox, where no placements happen
+                    apply_board_commands
+                        .after(BlocksSystems::Preview)
+                        .before(BlocksSystems::Resolution),
+                    (clear_lines, repaint_frozen_cells.after(clear_lines))
+                        .in_set(BlocksSystems::Resolution),
+                    (
+                        (update_board, tile_fade::fade_tiles).chain(),
+                        idle_hint.run_if(in_state(menu::GameState::Playing)),
+                        update_assist_overlay,
+                        highlight::composite_highlights.after(idle_hint),
+                    )
+                        .in_set(BlocksSystems::Presentation),
+                    save::autosave.after(BlocksSystems::Resolution),
+                    swap_shape_pool.before(BlocksSystems::Placement),
+                    shake,
+                    tween::tween,
+                    toggle_assist_overlay,
+                    toast::fade_toasts,
+                    save::write_pending_saves,
+                    save::relabel_resume_prompt.run_if(resource_changed::<strings::Strings>()),
+                ),
+            )
+            .insert_resource(ShapeWeights(registry.default_weights()))
+            .insert_resource(ShapeDrought::new(registry.default_weights().len()))
+            .insert_resource(registry)
+            .load_persisted::<Settings>()
+            .init_resource::<BoardRenderer>()
+            .load_persisted::<ColorPalette>()
+            .init_resource::<IdleTimer>()
+            .init_resource::<budget::ScanBudget>()
+            .init_resource::<SpriteMutations>()
+            .init_resource::<Preview>()
+            .init_resource::<Highlights>()
+            .init_resource::<planning::PlannedPlacements>()
+            .insert_resource(GameRng::new(rand::random()))
+            .init_resource::<Gravity>()
+            .init_resource::<HardMode>()
+            .init_resource::<save::Autosave>()
+            .init_resource::<save::PendingSaves>()
+            .add_event::<BoardChangedEvent>()
+            .add_event::<PlacementEvent>()
+            .add_event::<LinesClearedEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<BoardCommand>()
+            .add_event::<ShapePoolChanged>()
+            .add_event::<ResizeBoardEvent>()
+            .add_event::<save::LoadSaveEvent>()
+            .add_plugins((
+                menu::MenuPlugin,
+                minimap::MinimapPlugin,
+                snapshot::SnapshotPlugin,
+                constraints::ConstraintsPlugin,
+                // Before the replay plugin, which replaces the rules with the replay's
+                rules::RulesPlugin,
+                replay::ReplayPlugin,
+                restart::RestartPlugin,
+                rotation::RotationPlugin,
+                overtime::OvertimePlugin,
+                tutorial::TutorialPlugin,
+                scoring::ScoringPlugin,
+                schedule::SchedulePlugin,
+                zones::ZonesPlugin,
+                sandbox::SandboxPlugin,
+            ))
+            .add_plugins((
+                hover::HoverPlugin,
+                tile_style::TileStylePlugin,
+                intro::IntroPlugin,
+                play_time::PlayTimePlugin,
+                peel::PeelPlugin,
+                export::ExportPlugin,
+                announce::AnnouncePlugin,
+                tooltip::TooltipPlugin,
+                history::HistoryPlugin,
+                crash::CrashPlugin,
+                keyboard::KeyboardPlugin,
+                puzzle::PuzzlePlugin,
+                puzzle_select::PuzzleSelectPlugin,
+                strings::StringsPlugin,
+                review::ReviewPlugin,
+            ))
+            .add_plugins((
+                score_popups::ScorePopupsPlugin,
+                leaderboard::LeaderboardPlugin,
+                sprint::SprintPlugin,
+                audio::SfxPlugin,
+                hooks::HooksPlugin,
+                autoplay::AutoplayPlugin,
+                heatmap::HeatmapPlugin,
+                rumble::RumblePlugin,
+                ghost_trail::GhostTrailPlugin,
+                shape_outline::ShapeOutlinePlugin,
+                attract::AttractPlugin,
+                save_slots::SaveSlotsPlugin,
+                layout::LayoutPlugin,
+                click_guard::ClickGuardPlugin,
+                assist::AssistPlugin,
+            ))
+            .add_plugins((
+                shape_slot::ShapeSlotPlugin,
+                resize::ResizePlugin,
+                settings_menu::SettingsMenuPlugin,
+                pause::PausePlugin,
+                game_over::GameOverPlugin,
+                stats::StatsPlugin,
+            ));
+        #[cfg(feature = "debug-ui")]
+        app.add_plugins((debug_ui::DebugUiPlugin, console::ConsolePlugin));
+        #[cfg(feature = "test-harness")]
+        {
+            app.add_event::<harness::SimulatedClick>();
+            if let Some(cursor) = harness::cursor_from_args() {
+                app.insert_resource(cursor);
+            }
+            if let Some(chaos) = harness::chaos_from_args() {
+                app.insert_resource(chaos);
+            }
+            app.add_systems(
+                Update,
+                harness::unleash_chaos
+                    .run_if(resource_exists::<harness::Chaos>())
+                    .after(BlocksSystems::Presentation),
+            );
+        }
+        #[cfg(feature = "net")]
+        app.add_plugins(net::NetPlugin);
+    }
+}
@@ -0,0 +1,448 @@
+//! Puzzle mode: a prepared board and a goal to reach in as few placements as possible.
+//!
+//! Puzzles are the `.txt` files of the `puzzles` directory, see [`crate::puzzle_select`],
+//! each reading like:
+//!
+//! ```text
+//! condition = targets 2,0 17,1
+//! par = 6
+//! board
+//! ```
+//!
+//! followed by the board's rows in the alternate `Board` format, the bottom one first.
+//! The condition is one of `clear_all`, `targets <x,y>...`, `score <points>`,
+//! `lines <count>` and `survive <placements>`.
+//!
+//! The best result of every solved puzzle is kept in `puzzle_progress.txt`, along with
+//! the replay of its solution.
+
+use std::{collections::BTreeMap, fmt};
+
+use bevy::prelude::*;
+use smallvec::smallvec;
+
+use crate::{
+    board::{parse_cells, CellMeta, Grid, TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    events::BoardCommand,
+    menu::{GameMode, GameState},
+    persistence::{PersistAppExt, PersistRequest, Persisted},
+    replay::{self, ReplayLog},
+    restart::RestartEvent,
+    rules::RuleSet,
+    scoring::Score,
+    strings::{t, Strings},
+    systems::GameRng,
+    BlocksSystems, Board, CellList, LinesClearedEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+/// What solves a puzzle.
+#[derive(Clone, PartialEq, Debug)]
+pub enum WinCondition {
+    /// Empty the whole board.
+    ClearAllCells,
+    /// Clear every target cell, which only counts once a line clear removed a filled
+    /// tile on it, not when it's merely covered.
+    ClearTargetCells(Vec<(usize, usize)>),
+    ReachScore(u64),
+    ClearNLines(usize),
+    SurviveNPlacements(usize),
+}
+impl WinCondition {
+    fn parse(text: &str) -> Option<Self> {
+        let mut words = text.split_whitespace();
+        let condition = match words.next()? {
+            "clear_all" => Self::ClearAllCells,
+            "targets" => Self::ClearTargetCells(
+                words
+                    .by_ref()
+                    .map(|cell| {
+                        let (x, y) = cell.split_once(',')?;
+                        let (x, y) = (x.parse().ok()?, y.parse().ok()?);
+                        (x < BOARD_WIDTH && y < BOARD_HEIGHT).then_some((x, y))
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+            "score" => Self::ReachScore(words.next()?.parse().ok()?),
+            "lines" => Self::ClearNLines(words.next()?.parse().ok()?),
+            "survive" => Self::SurviveNPlacements(words.next()?.parse().ok()?),
+            _ => return None,
+        };
+        words.next().is_none().then_some(condition)
+    }
+}
+
+/// The puzzle being played.
+#[derive(Resource, Clone)]
+pub struct Puzzle {
+    /// File stem of the puzzle, the key of its [`PuzzleRecords`] entry.
+    pub id: String,
+    pub condition: WinCondition,
+    /// Placements a perfect solution takes.
+    pub par: u32,
+    pub cells: Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>,
+}
+impl Puzzle {
+    /// Stars earned for solving the puzzle in `placements`: 3 within par, 2 within one
+    /// and a half times par, 1 otherwise.
+    pub fn stars(&self, placements: u32) -> u8 {
+        if placements <= self.par {
+            3
+        } else if placements * 2 <= self.par * 3 {
+            2
+        } else {
+            1
+        }
+    }
+}
+impl Default for Puzzle {
+    /// Three gray rows with a gap in the middle, and three targets in them.
+    fn default() -> Self {
+        let mut cells = Grid::default();
+        for row in &mut cells.0[..3] {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if !(9..11).contains(&x) {
+                    *cell = Some(TileColor::Gray);
+                }
+            }
+        }
+        Self {
+            id: "builtin".into(),
+            condition: WinCondition::ClearTargetCells(vec![(2, 0), (17, 1), (5, 2)]),
+            par: 6,
+            cells,
+        }
+    }
+}
+impl Puzzle {
+    /// Parses the format described in the module documentation, `None` if it is
+    /// malformed.
+    pub fn parse(id: &str, text: &str) -> Option<Self> {
+        let mut puzzle = Self {
+            id: id.into(),
+            cells: Grid::default(),
+            ..default()
+        };
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.trim() == "board" {
+                puzzle.cells = parse_cells(lines.by_ref())?;
+                continue;
+            }
+            let (key, value) = line.split_once('=')?;
+            match key.trim() {
+                "condition" => puzzle.condition = WinCondition::parse(value)?,
+                "par" => puzzle.par = value.trim().parse().ok()?,
+                key => warn!("unknown puzzle key {key}"),
+            }
+        }
+        Some(puzzle)
+    }
+}
+
+/// Best result of a solved puzzle.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PuzzleRecord {
+    pub stars: u8,
+    pub placements: u32,
+    /// Code of the solution's [`crate::replay::Replay`], missing from records kept before
+    /// solutions were.
+    pub replay: Option<String>,
+}
+
+/// Best results by puzzle id. Puzzles without an entry weren't solved yet.
+#[derive(Resource, Default)]
+pub struct PuzzleRecords(pub BTreeMap<String, PuzzleRecord>);
+impl PuzzleRecords {
+    /// Keeps the result if it beats the puzzle's record, returning whether it did.
+    fn record(&mut self, id: &str, result: PuzzleRecord) -> bool {
+        match self.0.get_mut(id) {
+            Some(best) if best.placements <= result.placements => false,
+            Some(best) => {
+                *best = result;
+                true
+            }
+            None => {
+                self.0.insert(id.to_owned(), result);
+                true
+            }
+        }
+    }
+}
+impl Persisted for PuzzleRecords {
+    const PATH: &'static str = "puzzle_progress.txt";
+
+    /// Reads `<id> <stars> <placements> [<replay code>]` lines.
+    fn parse(text: &str) -> Option<Self> {
+        let mut records = BTreeMap::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            let id = words.next()?.to_owned();
+            let stars = words.next()?.parse().ok()?;
+            let placements = words.next()?.parse().ok()?;
+            let replay = words.next().map(str::to_owned);
+            records.insert(
+                id,
+                PuzzleRecord {
+                    stars,
+                    placements,
+                    replay,
+                },
+            );
+        }
+        Some(Self(records))
+    }
+}
+impl fmt::Display for PuzzleRecords {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (id, record) in &self.0 {
+            write!(f, "{id} {} {}", record.stars, record.placements)?;
+            match &record.replay {
+                Some(code) => writeln!(f, " {code}")?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sent once the puzzle's condition was met.
+#[derive(Event)]
+pub struct PuzzleWonEvent {
+    pub placements: u32,
+    pub stars: u8,
+}
+
+/// Progress towards the puzzle's condition since the last restart.
+#[derive(Resource, Default)]
+pub struct PuzzleProgress {
+    placements: u32,
+    lines: usize,
+    solved: bool,
+}
+
+/// Run condition stopping placements once the puzzle is solved.
+pub fn puzzle_unsolved(progress: Res<PuzzleProgress>) -> bool {
+    !progress.solved
+}
+
+#[derive(Component)]
+struct VictoryScreen;
+
+pub struct PuzzlePlugin;
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Puzzle>()
+            .load_persisted::<PuzzleRecords>()
+            .init_resource::<PuzzleProgress>()
+            .add_event::<PuzzleWonEvent>()
+            .add_systems(
+                Update,
+                (
+                    set_up_puzzle.before(BlocksSystems::Preview),
+                    (check_win_condition, (show_victory_screen, save_record))
+                        .chain()
+                        .after(BlocksSystems::Resolution)
+                        .after(replay::record_placements)
+                        .run_if(in_state(GameState::Playing)),
+                )
+                    .run_if(|mode: Res<GameMode>| *mode == GameMode::Puzzle),
+            );
+    }
+}
+
+/// Lays out the puzzle's board and targets on every restart.
+fn set_up_puzzle(
+    mut commands: Commands,
+    puzzle: Res<Puzzle>,
+    mut progress: ResMut<PuzzleProgress>,
+    mut restarts: EventReader<RestartEvent>,
+    mut board_commands: EventWriter<BoardCommand>,
+    q_board: Query<Entity, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_victory: Query<Entity, With<VictoryScreen>>,
+) {
+    if restarts.read().count() == 0 {
+        return;
+    }
+    *progress = PuzzleProgress::default();
+    for entity in q_victory.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+    // One fill per color
+    let mut fills: Vec<(TileColor, CellList)> = Vec::new();
+    for y in 0..BOARD_HEIGHT {
+        for x in 0..BOARD_WIDTH {
+            let Some(color) = puzzle.cells.0[y][x] else {
+                continue;
+            };
+            match fills.iter_mut().find(|(c, _)| *c == color) {
+                Some((_, cells)) => cells.push((x, y)),
+                None => fills.push((color, smallvec![(x, y)])),
+            }
+        }
+    }
+    for (color, cells) in fills {
+        board_commands.send(BoardCommand::Fill {
+            board,
+            cells,
+            color: Some(color),
+        });
+    }
+    if let WinCondition::ClearTargetCells(targets) = &puzzle.condition {
+        board_commands.send(BoardCommand::SetMeta {
+            board,
+            cells: targets.iter().copied().collect(),
+            meta: CellMeta {
+                target: true,
+                ..default()
+            },
+        });
+    }
+}
+
+fn check_win_condition(
+    puzzle: Res<Puzzle>,
+    mut progress: ResMut<PuzzleProgress>,
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    mut won: EventWriter<PuzzleWonEvent>,
+    q_board: Query<(&Board, &Score, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok((board, score, entity)) = q_board.get_single() else {
+        return;
+    };
+    progress.placements += placements
+        .read()
+        .filter(|event| event.board == entity)
+        .count() as u32;
+    progress.lines += lines_cleared
+        .read()
+        .filter(|event| event.board == entity)
+        .map(|event| event.lines.len())
+        .sum::<usize>();
+    if progress.solved || progress.placements == 0 {
+        return;
+    }
+    let grid = &board.grid;
+    let solved = match &puzzle.condition {
+        WinCondition::ClearAllCells => grid.cells.0.iter().flatten().all(Option::is_none),
+        // Clears reset the metadata of the cells they empty
+        WinCondition::ClearTargetCells(_) => !grid.meta.0.iter().flatten().any(|meta| meta.target),
+        WinCondition::ReachScore(points) => score.0 >= *points,
+        WinCondition::ClearNLines(lines) => progress.lines >= *lines,
+        WinCondition::SurviveNPlacements(placements) => progress.placements as usize >= *placements,
+    };
+    if solved {
+        progress.solved = true;
+        won.send(PuzzleWonEvent {
+            placements: progress.placements,
+            stars: puzzle.stars(progress.placements),
+        });
+    }
+}
+
+fn show_victory_screen(
+    mut commands: Commands,
+    puzzle: Res<Puzzle>,
+    strings: Res<Strings>,
+    mut won: EventReader<PuzzleWonEvent>,
+) {
+    let Some(event) = won.read().last() else {
+        return;
+    };
+    let stars = "*".repeat(event.stars as usize) + &"-".repeat(3 - event.stars as usize);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                ..default()
+            },
+            VictoryScreen,
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                t!(strings, "puzzle.solved", stars),
+                TextStyle {
+                    font_size: 40.,
+                    color: Color::GOLD,
+                    ..default()
+                },
+            ));
+            commands.spawn(TextBundle::from_section(
+                t!(strings, "puzzle.result", event.placements, puzzle.par),
+                TextStyle {
+                    font_size: 20.,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Keeps the result of a solved puzzle and the replay of the solution if it is the best
+/// yet.
+fn save_record(
+    puzzle: Res<Puzzle>,
+    mut records: ResMut<PuzzleRecords>,
+    mut won: EventReader<PuzzleWonEvent>,
+    mut persist: EventWriter<PersistRequest>,
+    (log, rng, rules): (Res<ReplayLog>, Res<GameRng>, Res<RuleSet>),
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Some(event) = won.read().last() else {
+        return;
+    };
+    let result = PuzzleRecord {
+        stars: event.stars,
+        placements: event.placements,
+        replay: q_board
+            .get_single()
+            .ok()
+            .map(|board| replay::record(&log, &rng, (GameMode::Puzzle, &rules), board).encode()),
+    };
+    if records.record(&puzzle.id, result) {
+        persist.send(records.persist_request());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_read_back_with_their_replays() {
+        let text = "first 3 6 AQID\nsecond 1 14\n";
+        let records = PuzzleRecords::parse(text).unwrap();
+        assert_eq!(records.0["first"].replay.as_deref(), Some("AQID"));
+        assert_eq!(records.0["second"].replay, None);
+        assert_eq!(records.to_string(), text);
+    }
+
+    #[test]
+    fn only_better_solutions_replace_the_record() {
+        let mut records = PuzzleRecords::default();
+        let solution = |placements, replay: &str| PuzzleRecord {
+            stars: 3,
+            placements,
+            replay: Some(replay.into()),
+        };
+        assert!(records.record("a", solution(8, "slow")));
+        assert!(!records.record("a", solution(8, "same")));
+        assert!(records.record("a", solution(6, "fast")));
+        assert_eq!(records.0["a"].replay.as_deref(), Some("fast"));
+    }
+}
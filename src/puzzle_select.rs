@@ -0,0 +1,289 @@
+//! Puzzle select screen, listing the puzzles of the [`PuzzleDir`] with the stars
+//! earned on each. A puzzle unlocks once the one before it was solved, solved ones can
+//! have their best solution replayed. Escape goes back to the menu.
+
+use std::{io, path::PathBuf};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, Task},
+};
+
+use crate::{
+    click_guard::ModalUi,
+    menu::{GameMode, GameState},
+    persistence::{read_text_files, DataDir},
+    puzzle::{Puzzle, PuzzleRecords},
+    replay::WatchReplayEvent,
+    rules::{CustomRules, RuleOverrides, RuleSet},
+};
+
+const IDLE_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.5);
+const LOCKED_TEXT: Color = Color::rgb(0.4, 0.4, 0.4);
+
+/// Puzzles to choose from, in file name order. Holds the built-in puzzle alone until
+/// the directory was read, or if it has none.
+#[derive(Resource)]
+struct PuzzleLibrary(Vec<Puzzle>);
+impl Default for PuzzleLibrary {
+    fn default() -> Self {
+        Self(vec![Puzzle::default()])
+    }
+}
+
+/// Directory the puzzles are read from.
+#[derive(Resource)]
+pub struct PuzzleDir(pub PathBuf);
+impl Default for PuzzleDir {
+    fn default() -> Self {
+        Self("puzzles".into())
+    }
+}
+
+/// Pending read of the puzzle directory.
+#[derive(Resource)]
+struct PuzzleScan(Task<io::Result<Vec<(String, String)>>>);
+
+#[derive(Component)]
+struct PuzzleSelectRoot;
+
+/// What a button does with a puzzle, by index into the [`PuzzleLibrary`].
+#[derive(Component, Clone, Copy)]
+enum PuzzleButton {
+    Play(usize),
+    /// Watches the best solution of a solved puzzle.
+    ReplayBest(usize),
+}
+
+pub struct PuzzleSelectPlugin;
+impl Plugin for PuzzleSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PuzzleLibrary>()
+            .init_resource::<PuzzleDir>()
+            .add_systems(Startup, start_scan)
+            .add_systems(OnEnter(GameState::PuzzleSelect), spawn_puzzle_select)
+            .add_systems(OnExit(GameState::PuzzleSelect), despawn_puzzle_select)
+            .add_systems(
+                Update,
+                (
+                    poll_scan.run_if(resource_exists::<PuzzleScan>()),
+                    (
+                        // Records load in the background too, the list follows them
+                        (despawn_puzzle_select, spawn_puzzle_select).chain().run_if(
+                            resource_changed::<PuzzleLibrary>()
+                                .or_else(resource_changed::<PuzzleRecords>()),
+                        ),
+                        pick_puzzle,
+                    )
+                        .run_if(in_state(GameState::PuzzleSelect)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn start_scan(mut commands: Commands, data_dir: Res<DataDir>, dir: Res<PuzzleDir>) {
+    commands.insert_resource(PuzzleScan(read_text_files(data_dir.path(&dir.0), "txt")));
+}
+
+fn poll_scan(
+    mut commands: Commands,
+    dir: Res<PuzzleDir>,
+    mut scan: ResMut<PuzzleScan>,
+    mut library: ResMut<PuzzleLibrary>,
+) {
+    if !scan.0.is_finished() {
+        return;
+    }
+    commands.remove_resource::<PuzzleScan>();
+    let files = match block_on(&mut scan.0) {
+        Ok(files) => files,
+        Err(err) => {
+            error!("failed to read {}: {err}", dir.0.display());
+            return;
+        }
+    };
+    let mut puzzles: Vec<Puzzle> = files
+        .iter()
+        .filter_map(|(id, text)| {
+            let puzzle = Puzzle::parse(id, text);
+            if puzzle.is_none() {
+                warn!("ignoring malformed puzzle {id}");
+            }
+            puzzle
+        })
+        .collect();
+    if puzzles.is_empty() {
+        return;
+    }
+    puzzles.sort_by(|a, b| a.id.cmp(&b.id));
+    library.0 = puzzles;
+}
+
+/// Whether the puzzle at `index` can be played: the first always, the others once the
+/// one before was solved.
+fn unlocked(library: &PuzzleLibrary, records: &PuzzleRecords, index: usize) -> bool {
+    index == 0 || records.0.contains_key(&library.0[index - 1].id)
+}
+
+fn spawn_puzzle_select(
+    mut commands: Commands,
+    library: Res<PuzzleLibrary>,
+    records: Res<PuzzleRecords>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            PuzzleSelectRoot,
+            ModalUi,
+        ))
+        .with_children(|commands| {
+            commands.spawn(
+                TextBundle::from_section(
+                    "Puzzles",
+                    TextStyle {
+                        font_size: 48.,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+            for (index, puzzle) in library.0.iter().enumerate() {
+                let open = unlocked(&library, &records, index);
+                let label = match records.0.get(&puzzle.id) {
+                    Some(record) => format!(
+                        "{}  {}{}  best {}",
+                        puzzle.id,
+                        "*".repeat(record.stars as usize),
+                        "-".repeat(3 - record.stars as usize),
+                        record.placements
+                    ),
+                    None if open => format!("{}  ---", puzzle.id),
+                    None => format!("{}  locked", puzzle.id),
+                };
+                commands
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|commands| {
+                        spawn_button(commands, PuzzleButton::Play(index), label, open);
+                        let record = records.0.get(&puzzle.id);
+                        if record.is_some_and(|record| record.replay.is_some()) {
+                            let button = PuzzleButton::ReplayBest(index);
+                            spawn_button(commands, button, "Replay best".into(), true);
+                        }
+                    });
+            }
+            commands.spawn(TextBundle::from_section(
+                "Escape to go back",
+                TextStyle {
+                    font_size: 16.,
+                    color: LOCKED_TEXT,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_button(commands: &mut ChildBuilder, button: PuzzleButton, label: String, open: bool) {
+    let width = match button {
+        PuzzleButton::Play(_) => Val::Px(320.),
+        PuzzleButton::ReplayBest(_) => Val::Auto,
+    };
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width,
+                    padding: UiRect::all(Val::Px(8.)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: IDLE_BUTTON.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 24.,
+                    color: if open { Color::WHITE } else { LOCKED_TEXT },
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn despawn_puzzle_select(mut commands: Commands, q_root: Query<Entity, With<PuzzleSelectRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// A click on an unlocked puzzle starts it, one on a replay button watches the puzzle's
+/// best solution. Escape goes back to the menu.
+fn pick_puzzle(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    library: Res<PuzzleLibrary>,
+    records: Res<PuzzleRecords>,
+    (custom_rules, overrides): (Res<CustomRules>, Res<RuleOverrides>),
+    mut mode: ResMut<GameMode>,
+    mut rules: ResMut<RuleSet>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut watches: EventWriter<WatchReplayEvent>,
+    mut q_buttons: Query<(&Interaction, &PuzzleButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    if input_keys.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+        return;
+    }
+    for (interaction, &button, mut background) in q_buttons.iter_mut() {
+        let (PuzzleButton::Play(index) | PuzzleButton::ReplayBest(index)) = button;
+        let open = unlocked(&library, &records, index);
+        *background = match interaction {
+            Interaction::Hovered | Interaction::Pressed if open => HOVERED_BUTTON,
+            _ => IDLE_BUTTON,
+        }
+        .into();
+        if *interaction != Interaction::Pressed || !open {
+            continue;
+        }
+        let puzzle = &library.0[index];
+        commands.insert_resource(puzzle.clone());
+        *mode = GameMode::Puzzle;
+        *rules = RuleSet::for_mode(GameMode::Puzzle, &custom_rules, *overrides);
+        match button {
+            PuzzleButton::Play(_) => next_state.set(GameState::Playing),
+            PuzzleButton::ReplayBest(_) => {
+                let best = records.0.get(&puzzle.id).and_then(|r| r.replay.clone());
+                if let Some(code) = best {
+                    watches.send(WatchReplayEvent(code));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Named z bands of the game's sprites, from back to front. bevy_ui draws above all of
+//! them.
+//!
+//! Bands are world z values. Sprites within a board are children of an entity scaled by
+//! [`TILE_SIZE`], [`in_board`] gives their local z.
+
+use crate::TILE_SIZE;
+
+/// Tiles of every board.
+pub const BOARD: f32 = 0.;
+/// Marks drawn over the tiles of a board.
+pub const BADGES: f32 = 3.;
+/// The shape slot beside the main board and its badge.
+pub const SHAPE_SLOT: f32 = 5.;
+/// Preview tints of the overlay board.
+pub const OVERLAY: f32 = 30.;
+/// Short-lived tiles such as the clear wave.
+pub const PARTICLES: f32 = 60.;
+/// Text floating over the boards, like score popups.
+pub const POPUPS: f32 = 80.;
+/// The selected shape following the cursor.
+pub const SHAPE: f32 = 100.;
+/// The outline traced around the selected shape.
+pub const SHAPE_OUTLINE: f32 = 101.;
+
+/// Local z of a band for a sprite within a board, whose board sits in [`BOARD`].
+pub fn in_board(band: f32) -> f32 {
+    (band - BOARD) / TILE_SIZE
+}
@@ -0,0 +1,690 @@
+//! Recording games as shareable replay codes and watching them back.
+//!
+//! A replay code is the base64 of a small binary payload starting with a version byte.
+//! The mode and the whole [`RuleSet`] the game was played under travel in it as RON, so
+//! openings, bonus cells and multipliers replay as they were. Export the current game
+//! with F6 or copy it from the game over screen, watch one from the menu's paste box or
+//! with `--replay <code>`.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{
+    board::{size_supported, TileColor},
+    clipboard,
+    menu::{GameMode, GameState},
+    persistence::PersistRequest,
+    resize::PendingResize,
+    rules::RuleSet,
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    strings::{t, Strings},
+    systems::GameRng,
+    toast::spawn_toast,
+    BlocksSystems, Board, BoardChangedEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+pub const REPLAY_VERSION: u8 = 2;
+pub const REPLAY_PATH: &str = "replay.txt";
+/// Seconds between two replayed placements at normal speed.
+const PLAYBACK_INTERVAL: f32 = 0.6;
+/// Characters of the code shown in the paste box, the last ones typed or pasted.
+const SHOWN_CODE_CHARS: usize = 48;
+
+#[derive(Clone, Copy)]
+pub struct ReplayAction {
+    pub shape: ShapeInstance,
+    pub anchor: (isize, isize),
+}
+
+#[derive(Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    /// Mode the game was played in.
+    pub mode: GameMode,
+    /// Rules the game was played under.
+    pub rules: RuleSet,
+    pub width: u8,
+    pub height: u8,
+    pub actions: Vec<ReplayAction>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    Malformed,
+    /// Recorded by a newer version of the game using the given format version.
+    NewerVersion(u8),
+}
+
+impl Replay {
+    pub fn encode(&self) -> String {
+        let mut bytes = vec![REPLAY_VERSION];
+        bytes.extend(self.seed.to_le_bytes());
+        bytes.extend([self.width, self.height]);
+        let rules = ron::to_string(&(self.mode, &self.rules)).unwrap_or_default();
+        bytes.extend((rules.len() as u32).to_le_bytes());
+        bytes.extend(rules.bytes());
+        bytes.extend((self.actions.len() as u32).to_le_bytes());
+        for action in &self.actions {
+            bytes.extend(action.shape.id.0.to_le_bytes());
+            bytes.extend([action.shape.rotation, action.shape.color.to_char() as u8]);
+            bytes.extend((action.anchor.0 as i16).to_le_bytes());
+            bytes.extend((action.anchor.1 as i16).to_le_bytes());
+        }
+        STANDARD.encode(bytes)
+    }
+
+    pub fn decode(code: &str) -> Result<Self, ReplayError> {
+        let bytes = STANDARD
+            .decode(code.trim())
+            .map_err(|_| ReplayError::Malformed)?;
+        let mut reader = Reader(&bytes);
+        let version = match reader.array::<1>()? {
+            [version @ (1 | REPLAY_VERSION)] => version,
+            [version] if version > REPLAY_VERSION => {
+                return Err(ReplayError::NewerVersion(version))
+            }
+            _ => return Err(ReplayError::Malformed),
+        };
+        let seed = u64::from_le_bytes(reader.array()?);
+        let (mode, rules, width, height) = if version == 1 {
+            let [bits, width, height] = reader.array()?;
+            (GameMode::Endless, first_version_rules(bits), width, height)
+        } else {
+            let [width, height] = reader.array()?;
+            let len = u32::from_le_bytes(reader.array()?) as usize;
+            let text =
+                std::str::from_utf8(reader.take(len)?).map_err(|_| ReplayError::Malformed)?;
+            let (mode, rules) = ron::from_str(text).map_err(|_| ReplayError::Malformed)?;
+            (mode, rules, width, height)
+        };
+        let count = u32::from_le_bytes(reader.array()?);
+        let actions = (0..count)
+            .map(|_| {
+                let id = ShapeId(u16::from_le_bytes(reader.array()?));
+                let [rotation, color] = reader.array()?;
+                let x = i16::from_le_bytes(reader.array()?);
+                let y = i16::from_le_bytes(reader.array()?);
+                Ok(ReplayAction {
+                    shape: ShapeInstance {
+                        id,
+                        rotation,
+                        color: TileColor::from_char(color as char).ok_or(ReplayError::Malformed)?,
+                    },
+                    anchor: (x as isize, y as isize),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            seed,
+            mode,
+            rules,
+            width,
+            height,
+            actions,
+        })
+    }
+}
+
+/// Rules of a first version code, which kept only a byte of them: bit 0 is gravity,
+/// bit 1 hard mode, bit 2 adjacency and bits 4 to 7 the side of the clearing regions.
+/// The rest were those of [`RuleSet::classic`].
+fn first_version_rules(bits: u8) -> RuleSet {
+    RuleSet {
+        gravity: bits & 1 != 0,
+        hard: bits & 2 != 0,
+        adjacency: bits & 4 != 0,
+        region_size: (bits >> 4) as usize,
+        ..RuleSet::classic()
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        if self.0.len() < len {
+            return Err(ReplayError::Malformed);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+}
+
+/// Placements of the game in progress.
+#[derive(Resource, Default)]
+pub struct ReplayLog(pub Vec<ReplayAction>);
+
+/// The game in progress as a replay.
+pub(crate) fn record(
+    log: &ReplayLog,
+    rng: &GameRng,
+    (mode, rules): (GameMode, &RuleSet),
+    board: &Board,
+) -> Replay {
+    Replay {
+        seed: rng.seed,
+        mode,
+        rules: rules.clone(),
+        width: board.grid.width() as u8,
+        height: board.grid.height() as u8,
+        actions: log.0.clone(),
+    }
+}
+
+/// Watches the replay of the code, in place of the game or menu.
+#[derive(Event)]
+pub struct WatchReplayEvent(pub String);
+
+/// Copies the code of the game in progress to the clipboard.
+#[derive(Event)]
+pub struct CopyReplayEvent;
+
+/// Present while watching a replay, player placements are disabled meanwhile.
+#[derive(Resource)]
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+    timer: Timer,
+    paused: bool,
+    fast: bool,
+}
+impl Playback {
+    /// Whether every placement of the replay was made.
+    pub(crate) fn finished(&self) -> bool {
+        self.next == self.replay.actions.len()
+    }
+}
+
+#[derive(Component)]
+struct PlaybackText;
+
+/// The menu's box taking a replay code to watch, typed or pasted with Ctrl+V.
+#[derive(Resource, Default)]
+pub(crate) struct PasteBox {
+    pub(crate) open: bool,
+    code: String,
+}
+
+pub(crate) fn paste_box_closed(paste_box: Res<PasteBox>) -> bool {
+    !paste_box.open
+}
+
+#[derive(Component)]
+struct PasteBoxText;
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayLog>()
+            .init_resource::<PasteBox>()
+            .add_event::<WatchReplayEvent>()
+            .add_event::<CopyReplayEvent>()
+            .add_systems(Startup, spawn_paste_box)
+            .add_systems(
+                PreUpdate,
+                type_replay_code
+                    .after(InputSystem)
+                    .run_if(in_state(GameState::Menu)),
+            )
+            .add_systems(OnEnter(GameState::Menu), stop_playback)
+            .add_systems(OnExit(GameState::Menu), close_paste_box)
+            .add_systems(
+                Update,
+                (
+                    record_placements.after(BlocksSystems::Placement),
+                    (export_replay, copy_replay).after(record_placements),
+                    watch_replay,
+                    show_paste_box.run_if(
+                        resource_changed::<PasteBox>().or_else(resource_changed::<Strings>()),
+                    ),
+                    play_back
+                        .in_set(BlocksSystems::Placement)
+                        .run_if(crate::review::not_reviewing),
+                ),
+            );
+
+        let mut args = std::env::args().skip_while(|arg| arg != "--replay").skip(1);
+        if let Some(code) = args.next() {
+            app.world.send_event(WatchReplayEvent(code));
+        }
+    }
+}
+
+/// Starts the replay of the last code to watch, as a game of the mode and rules it was
+/// recorded with. A code that can't be watched is refused with a toast.
+fn watch_replay(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    mut watches: EventReader<WatchReplayEvent>,
+    mut mode: ResMut<GameMode>,
+    mut next_state: ResMut<NextState<GameState>>,
+    q_text: Query<(), With<PlaybackText>>,
+) {
+    let Some(WatchReplayEvent(code)) = watches.read().last() else {
+        return;
+    };
+    let replay = match Replay::decode(code) {
+        Ok(replay) => replay,
+        Err(ReplayError::NewerVersion(version)) => {
+            spawn_toast(&mut commands, t!(strings, "toast.replay_newer", version));
+            return;
+        }
+        Err(ReplayError::Malformed) => {
+            spawn_toast(&mut commands, strings.get("toast.replay_malformed"));
+            return;
+        }
+    };
+    let size = (replay.width as usize, replay.height as usize);
+    if !size_supported(size.0, size.1) {
+        spawn_toast(
+            &mut commands,
+            t!(strings, "toast.replay_board_size", size.0, size.1),
+        );
+        return;
+    }
+    // Applied along with the restart starting the replay
+    commands.insert_resource(PendingResize(Some(size)));
+    commands.insert_resource(GameRng::new(replay.seed));
+    commands.insert_resource(RuleSet {
+        board_size: Some(size),
+        ..replay.rules.clone()
+    });
+    // Neither the clock nor the opponent are part of the replay
+    *mode = match replay.mode {
+        GameMode::Timed | GameMode::Versus => GameMode::Endless,
+        mode => mode,
+    };
+    commands.insert_resource(Playback {
+        replay,
+        next: 0,
+        timer: Timer::from_seconds(PLAYBACK_INTERVAL, TimerMode::Repeating),
+        paused: false,
+        fast: false,
+    });
+    if q_text.is_empty() {
+        spawn_playback_text(&mut commands);
+    }
+    next_state.set(GameState::Playing);
+}
+
+/// Leaves the replay being watched, if any, for the next game to be played.
+fn stop_playback(mut commands: Commands, q_text: Query<Entity, With<PlaybackText>>) {
+    commands.remove_resource::<Playback>();
+    for entity in q_text.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub(crate) fn record_placements(
+    mut placements: EventReader<PlacementEvent>,
+    mut log: ResMut<ReplayLog>,
+    q_main_board: Query<(), With<MainBoard>>,
+) {
+    for event in placements.read() {
+        if q_main_board.contains(event.board) {
+            log.0.push(ReplayAction {
+                shape: event.shape,
+                anchor: event.anchor,
+            });
+        }
+    }
+}
+
+fn export_replay(
+    input_keys: Res<Input<KeyCode>>,
+    log: Res<ReplayLog>,
+    (rng, mode, rules): (Res<GameRng>, Res<GameMode>, Res<RuleSet>),
+    mut persist: EventWriter<PersistRequest>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    if !input_keys.just_pressed(KeyCode::F6) {
+        return;
+    }
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+    let code = record(&log, &rng, (*mode, &rules), board).encode();
+    info!("replay code: {code}");
+    persist.send(PersistRequest {
+        path: REPLAY_PATH.into(),
+        contents: code,
+    });
+}
+
+/// Copies the code of the game to the clipboard, or prints it to the log without one.
+fn copy_replay(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    mut copies: EventReader<CopyReplayEvent>,
+    log: Res<ReplayLog>,
+    (rng, mode, rules): (Res<GameRng>, Res<GameMode>, Res<RuleSet>),
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    if copies.read().count() == 0 {
+        return;
+    }
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+    let code = record(&log, &rng, (*mode, &rules), board).encode();
+    let message = match clipboard::copy(&code) {
+        Ok(()) => "toast.replay_copied",
+        Err(err) => {
+            warn!("couldn't copy the replay code: {err}");
+            info!("replay code: {code}");
+            "toast.replay_logged"
+        }
+    };
+    spawn_toast(&mut commands, strings.get(message));
+}
+
+fn spawn_paste_box(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_sections([
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font_size: 20.,
+                        ..default()
+                    },
+                ),
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font_size: 24.,
+                        color: Color::GOLD,
+                        ..default()
+                    },
+                ),
+            ]),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.),
+                left: Val::Percent(15.),
+                width: Val::Percent(70.),
+                padding: UiRect::all(Val::Px(12.)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.9).into(),
+            z_index: ZIndex::Global(20),
+            ..default()
+        },
+        PasteBoxText,
+    ));
+}
+
+/// Edits the code in the open paste box and keeps the keys from the menu. Enter watches
+/// the code, Esc closes the box.
+fn type_replay_code(
+    mut paste_box: ResMut<PasteBox>,
+    mut input_keys: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut watches: EventWriter<WatchReplayEvent>,
+) {
+    let typed: Vec<char> = characters.read().map(|event| event.char).collect();
+    if !paste_box.open {
+        return;
+    }
+    if input_keys.just_pressed(KeyCode::Escape) {
+        *paste_box = PasteBox::default();
+        input_keys.reset_all();
+        return;
+    }
+    if input_keys.just_pressed(KeyCode::Back) {
+        paste_box.code.pop();
+    }
+    let ctrl = input_keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl && input_keys.just_pressed(KeyCode::V) {
+        match clipboard::paste() {
+            Ok(text) => paste_box.code.push_str(text.trim()),
+            Err(err) => warn!("couldn't paste a replay code: {err}"),
+        }
+    } else {
+        // Codes are base64, without spaces
+        let typed = typed.into_iter().filter(|c| c.is_ascii_graphic());
+        paste_box.code.extend(typed);
+    }
+    if input_keys.just_pressed(KeyCode::Return) {
+        let code = std::mem::take(&mut paste_box.code);
+        paste_box.open = false;
+        watches.send(WatchReplayEvent(code));
+    }
+    input_keys.reset_all();
+}
+
+fn close_paste_box(mut paste_box: ResMut<PasteBox>) {
+    *paste_box = PasteBox::default();
+}
+
+fn show_paste_box(
+    paste_box: Res<PasteBox>,
+    strings: Res<Strings>,
+    mut q_text: Query<(&mut Text, &mut Style), With<PasteBoxText>>,
+) {
+    let Ok((mut text, mut style)) = q_text.get_single_mut() else {
+        return;
+    };
+    style.display = if paste_box.open {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    text.sections[0].value = format!("{}\n", strings.get("replay.paste_hint"));
+    let shown = paste_box.code.len().saturating_sub(SHOWN_CODE_CHARS);
+    let ellipsis = if shown > 0 { "…" } else { "" };
+    text.sections[1].value = format!("{ellipsis}{}_", &paste_box.code[shown..]);
+}
+
+fn spawn_playback_text(commands: &mut Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 20.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.),
+                right: Val::Px(8.),
+                ..default()
+            },
+            ..default()
+        },
+        PlaybackText,
+    ));
+}
+
+/// Places the replay's shapes one after another. Space pauses, Right steps while
+/// paused and F toggles double speed.
+fn play_back(
+    time: Res<Time>,
+    input_keys: Res<Input<KeyCode>>,
+    (registry, strings): (Res<ShapeRegistry>, Res<Strings>),
+    playback: Option<ResMut<Playback>>,
+    mut q_board: Query<(&mut Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_text: Query<&mut Text, With<PlaybackText>>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut placements: EventWriter<PlacementEvent>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    if input_keys.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if input_keys.just_pressed(KeyCode::F) {
+        playback.fast = !playback.fast;
+    }
+    let speed = if playback.fast { 2. } else { 1. };
+    playback.timer.tick(time.delta().mul_f32(speed));
+    let due = if playback.paused {
+        input_keys.just_pressed(KeyCode::Right)
+    } else {
+        playback.timer.just_finished()
+    };
+
+    if let Some(&action) = playback.replay.actions.get(playback.next).filter(|_| due) {
+        playback.next += 1;
+        let (mut board, board_entity) = q_board.single_mut();
+        let placed = registry
+            .contains(action.shape.id)
+            .then(|| {
+                board.grid.try_place(
+                    action.shape.resolve(&registry),
+                    action.anchor,
+                    action.shape.color,
+                )
+            })
+            .flatten();
+        match placed {
+            Some(cells) => {
+                board_changed.send(BoardChangedEvent {
+                    board: board_entity,
+                    revision: board.grid.revision(),
+                    cells: cells.clone(),
+                });
+                placements.send(PlacementEvent {
+                    board: board_entity,
+                    shape: action.shape,
+                    anchor: action.anchor,
+                    cells,
+                });
+            }
+            None => warn!("replay placement {} does not fit the board", playback.next),
+        }
+    }
+
+    if let Ok(mut text) = q_text.get_single_mut() {
+        let state = if playback.finished() {
+            "replay.finished"
+        } else if playback.paused {
+            "replay.paused"
+        } else if playback.fast {
+            "replay.fast"
+        } else {
+            "replay.playing"
+        };
+        text.sections[0].value = t!(
+            strings,
+            "replay.status",
+            playback.next,
+            playback.replay.actions.len(),
+            strings.get(state)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{opening::StartPreset, rules::CustomRules};
+
+    fn replay() -> Replay {
+        Replay {
+            seed: 0x0123_4567_89ab_cdef,
+            mode: GameMode::Dig,
+            rules: RuleSet {
+                gravity: true,
+                multipliers: false,
+                start_preset: StartPreset::Scattered,
+                start_density: 0.3,
+                board_size: Some((9, 9)),
+                ..RuleSet::for_mode(GameMode::Dig, &CustomRules::default(), default())
+            },
+            width: 9,
+            height: 9,
+            actions: vec![
+                ReplayAction {
+                    shape: ShapeInstance {
+                        id: ShapeId(3),
+                        rotation: 1,
+                        color: TileColor::Blue,
+                    },
+                    anchor: (4, 0),
+                },
+                ReplayAction {
+                    shape: ShapeInstance {
+                        id: ShapeId(300),
+                        rotation: 3,
+                        color: TileColor::Red,
+                    },
+                    anchor: (-1, 8),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn codes_decode_to_the_replay_they_encode() {
+        let replay = replay();
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(decoded.seed, replay.seed);
+        assert_eq!(
+            (decoded.mode, decoded.width, decoded.height),
+            (replay.mode, replay.width, replay.height)
+        );
+        assert_eq!(decoded.rules, replay.rules);
+        assert_eq!(decoded.actions.len(), replay.actions.len());
+        for (decoded, action) in decoded.actions.iter().zip(&replay.actions) {
+            assert!(decoded.shape == action.shape);
+            assert_eq!(decoded.anchor, action.anchor);
+        }
+    }
+
+    #[test]
+    fn codes_of_newer_versions_are_told_apart() {
+        let mut bytes = STANDARD.decode(replay().encode()).unwrap();
+        bytes[0] = REPLAY_VERSION + 1;
+        assert_eq!(
+            Replay::decode(&STANDARD.encode(&bytes)).err(),
+            Some(ReplayError::NewerVersion(REPLAY_VERSION + 1))
+        );
+        bytes[0] = 0;
+        assert_eq!(
+            Replay::decode(&STANDARD.encode(&bytes)).err(),
+            Some(ReplayError::Malformed)
+        );
+    }
+
+    #[test]
+    fn first_version_codes_keep_their_rule_bits() {
+        let mut bytes = vec![1];
+        bytes.extend(7u64.to_le_bytes());
+        bytes.extend([1 | 4 | 3 << 4, 9, 9]);
+        bytes.extend(0u32.to_le_bytes());
+        let decoded = Replay::decode(&STANDARD.encode(bytes)).unwrap();
+        assert_eq!((decoded.seed, decoded.mode), (7, GameMode::Endless));
+        assert_eq!(
+            decoded.rules,
+            RuleSet {
+                gravity: true,
+                adjacency: true,
+                region_size: 3,
+                ..RuleSet::classic()
+            }
+        );
+    }
+
+    #[test]
+    fn truncated_codes_are_malformed() {
+        let bytes = STANDARD.decode(replay().encode()).unwrap();
+        let truncated = STANDARD.encode(&bytes[..bytes.len() - 1]);
+        assert_eq!(
+            Replay::decode(&truncated).err(),
+            Some(ReplayError::Malformed)
+        );
+        assert_eq!(
+            Replay::decode("not base64!").err(),
+            Some(ReplayError::Malformed)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Resizing the main board at runtime with a [`ResizeBoardEvent`].
+//!
+//! Boards keep their entity through a resize: tiles of the cells a board gains are
+//! spawned, those of the cells it loses despawned and the others moved, for the board to
+//! stay centered on its origin. The overlay board follows the main board's size, and the
+//! layout refits the camera to the new size.
+
+use bevy::prelude::*;
+
+use crate::{
+    board::{size_supported, BOARD_HEIGHT, BOARD_WIDTH, MIN_BOARD_SIZE},
+    board_texture,
+    components::{board_extents, spawn_tile, tile_translation},
+    keyboard::KeyboardCursor,
+    menu::GameState,
+    restart::{self, RestartEvent},
+    settings::Settings,
+    shape::ShapeRegistry,
+    systems::{ShapeDrought, ShapeWeights},
+    Board, BoardChangedEvent, MainBoard, OverlayBoard, ResizeBoardEvent,
+};
+
+/// Size requested during a game, applied with the next restart.
+#[derive(Resource, Default)]
+pub(crate) struct PendingResize(pub(crate) Option<(usize, usize)>);
+
+pub struct ResizePlugin;
+impl Plugin for ResizePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingResize>()
+            .add_systems(Startup, resume_board_size)
+            .add_systems(Update, resize_boards.before(restart::restart));
+    }
+}
+
+/// Starts on the size last picked in the menu, unless the plugin or a replay asked for
+/// another one.
+fn resume_board_size(settings: Res<Settings>, mut pending: ResMut<PendingResize>) {
+    if pending.0.is_none() && settings.board_size != (BOARD_WIDTH, BOARD_HEIGHT) {
+        pending.0 = Some(settings.board_size);
+    }
+}
+
+/// Whether a resize requested in the state applies at once. Games keep their board until
+/// the next one starts, paused and finished ones too.
+pub(crate) fn resizes_at_once(state: GameState) -> bool {
+    matches!(
+        state,
+        GameState::Menu | GameState::Settings | GameState::Sandbox
+    )
+}
+
+/// Resizes the main and overlay boards to the last size requested, right away where
+/// [`resizes_at_once`] and along with the restart starting the next game otherwise. The
+/// shape pool is checked against the new size, a size no shape fits on is refused.
+pub(crate) fn resize_boards(
+    mut commands: Commands,
+    state: Res<State<GameState>>,
+    mut registry: ResMut<ShapeRegistry>,
+    (mut weights, mut drought): (ResMut<ShapeWeights>, ResMut<ShapeDrought>),
+    mut resizes: EventReader<ResizeBoardEvent>,
+    mut restarts: EventReader<RestartEvent>,
+    mut pending: ResMut<PendingResize>,
+    mut keyboard_cursor: ResMut<KeyboardCursor>,
+    mut q_board: Query<
+        (&mut Board, Entity, Has<OverlayBoard>),
+        Or<(With<MainBoard>, With<OverlayBoard>)>,
+    >,
+    mut q_tiles: Query<(&mut Transform, Option<&mut Sprite>), Without<Board>>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+) {
+    for &ResizeBoardEvent { width, height } in resizes.read() {
+        if size_supported(width, height) {
+            pending.0 = Some((width, height));
+        } else {
+            warn!(
+                "ignoring a resize to {width}x{height}, boards are from \
+                 {MIN_BOARD_SIZE}x{MIN_BOARD_SIZE} to {BOARD_WIDTH}x{BOARD_HEIGHT}"
+            );
+        }
+    }
+    let restarting = restarts.read().count() > 0;
+    if !resizes_at_once(*state.get()) && !restarting {
+        return;
+    }
+    let Some(size) = pending.0.take() else {
+        return;
+    };
+    if registry.board_size() != size {
+        if !registry.fit_board(size) {
+            warn!(
+                "ignoring a resize to {}x{}, no shape of the pool fits on it",
+                size.0, size.1
+            );
+            return;
+        }
+        weights.0 = registry.default_weights();
+        *drought = ShapeDrought::new(weights.0.len());
+    }
+    for (mut board, entity, is_overlay) in q_board.iter_mut() {
+        if (board.grid.width(), board.grid.height()) == size {
+            continue;
+        }
+        resize_board(
+            &mut commands,
+            &mut board,
+            entity,
+            !is_overlay,
+            size,
+            &mut q_tiles,
+        );
+        keyboard_cursor.cells.remove(&entity);
+        if !is_overlay {
+            board_changed.send(BoardChangedEvent {
+                board: entity,
+                revision: board.grid.revision(),
+                cells: board.grid.positions().collect(),
+            });
+        }
+    }
+}
+
+/// Empties the board, resizes its grid and brings its tiles or its texture's sprite in
+/// line with the new size.
+fn resize_board(
+    commands: &mut Commands,
+    board: &mut Board,
+    entity: Entity,
+    is_main_board: bool,
+    size: (usize, usize),
+    q_tiles: &mut Query<(&mut Transform, Option<&mut Sprite>), Without<Board>>,
+) {
+    if board.grid.resize(size.0, size.1).is_err() {
+        return;
+    }
+    board.extents = board_extents(size);
+    if let Some(texture) = &board.texture {
+        if let Ok((_, Some(mut sprite))) = q_tiles.get_mut(texture.sprite) {
+            board_texture::fit_sprite(&mut sprite, size);
+        }
+        return;
+    }
+    for y in 0..BOARD_HEIGHT {
+        for x in 0..BOARD_WIDTH {
+            let tile = board.entities[y][x];
+            match (board.grid.contains(x, y), tile != Entity::PLACEHOLDER) {
+                (true, true) => {
+                    if let Ok((mut transform, _)) = q_tiles.get_mut(tile) {
+                        transform.translation = tile_translation(x, y, size);
+                    }
+                }
+                (true, false) => {
+                    commands.entity(entity).with_children(|commands| {
+                        (board.entities[y][x], board.badges[y][x]) =
+                            spawn_tile(commands, (x, y), size, is_main_board);
+                    });
+                }
+                (false, true) => {
+                    commands.entity(tile).despawn_recursive();
+                    board.entities[y][x] = Entity::PLACEHOLDER;
+                    board.badges[y][x] = Entity::PLACEHOLDER;
+                }
+                (false, false) => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Starting a new game in place: Ctrl+R, or entering [`GameState::Playing`].
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    assist::Assist,
+    board::{Grid, TileColor},
+    events::BoardCommand,
+    input::IdleTimer,
+    menu::{daily_seed, GameMode, GameState},
+    opening,
+    palette::ColorPalette,
+    replay::{Playback, ReplayLog},
+    rules::RuleSet,
+    schedule::{TurnCounter, UpcomingEvents},
+    scoring::{pick_bonus_cells, send_bonus_cells, Combo, Level, Score},
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    systems::GameRng,
+    systems::ShapeDrought,
+    systems::ShapeQueue,
+    systems::ShapeWeights,
+    systems::{draw_shape, replace_selected_shape},
+    systems::{Tray, UPCOMING_SHAPES},
+    tutorial::Tutorial,
+    BlocksSystems, Board, BoardChangedEvent, CellList, MainBoard, OverlayBoard, SelectedShape,
+};
+
+/// Resets the game to its initial state, keeping the mode.
+#[derive(Event)]
+pub struct RestartEvent;
+
+pub struct RestartPlugin;
+impl Plugin for RestartPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RestartEvent>().add_systems(
+            Update,
+            (
+                request_restart.run_if(in_state(GameState::Playing)),
+                restart
+                    .after(request_restart)
+                    .before(BlocksSystems::Preview),
+            ),
+        );
+    }
+}
+
+fn request_restart(
+    input_keys: Res<Input<KeyCode>>,
+    playback: Option<Res<Playback>>,
+    tutorial: Option<Res<Tutorial>>,
+    mut restarts: EventWriter<RestartEvent>,
+) {
+    let ctrl = input_keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    // The tutorial's scripted board can't be restarted halfway
+    if ctrl && input_keys.just_pressed(KeyCode::R) && playback.is_none() && tutorial.is_none() {
+        restarts.send(RestartEvent);
+    }
+}
+
+/// Seed of a new game in the given mode. Daily games share the day's seed and replays
+/// keep theirs, other modes draw a new one.
+fn restart_seed(mode: GameMode, previous: u64, replaying: bool) -> u64 {
+    match mode {
+        _ if replaying => previous,
+        GameMode::Daily => daily_seed(),
+        GameMode::Puzzle => previous,
+        GameMode::Endless
+        | GameMode::Timed
+        | GameMode::Versus
+        | GameMode::Cascade
+        | GameMode::Adjacency
+        | GameMode::Blockudoku
+        | GameMode::Custom
+        | GameMode::Sprint
+        | GameMode::Dig => rand::random(),
+    }
+}
+
+/// Resets the board, score, level, combo, shape pool, tray, turns, scheduled events and
+/// replay log, and reseeds the game. Tiles are repainted rather than respawned. Digs get their
+/// garbage rows, other games the opening of their start preset.
+pub(crate) fn restart(
+    mut commands: Commands,
+    mut restarts: EventReader<RestartEvent>,
+    mode: Res<GameMode>,
+    (rules, assist): (Res<RuleSet>, Res<Assist>),
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    playback: Option<Res<Playback>>,
+    mut rng: ResMut<GameRng>,
+    (mut weights, mut drought): (ResMut<ShapeWeights>, ResMut<ShapeDrought>),
+    (mut turns, mut upcoming): (ResMut<TurnCounter>, ResMut<UpcomingEvents>),
+    mut log: ResMut<ReplayLog>,
+    mut idle: ResMut<IdleTimer>,
+    mut q_board: Query<
+        (
+            &mut Board,
+            &mut Score,
+            &mut Level,
+            &mut Combo,
+            &mut ShapeQueue,
+            &mut Tray,
+            Entity,
+        ),
+        (With<MainBoard>, Without<OverlayBoard>),
+    >,
+    (mut q_selected_shape, mut q_sprites): (
+        Query<(Entity, &mut ShapeInstance, &Children, &Transform), With<SelectedShape>>,
+        Query<&mut Sprite>,
+    ),
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut board_commands: EventWriter<BoardCommand>,
+) {
+    if restarts.read().count() == 0 {
+        return;
+    }
+    *rng = GameRng::new(restart_seed(*mode, rng.seed, playback.is_some()));
+    weights.0 = registry.default_weights();
+    *drought = ShapeDrought::new(weights.0.len());
+    *turns = TurnCounter::default();
+    // The schedule fills the queue up again, telegraphing from the first turn
+    upcoming.0.clear();
+    log.0.clear();
+    *idle = IdleTimer::default();
+
+    let mut first_shape = None;
+    for (mut board, mut score, mut level, mut combo, mut queue, mut tray, entity) in
+        q_board.iter_mut()
+    {
+        *score = Score::default();
+        *level = Level::default();
+        *combo = Combo::default();
+        board.grid.set_cells(Grid::default());
+        board_changed.send(BoardChangedEvent {
+            board: entity,
+            revision: board.grid.revision(),
+            cells: board.grid.positions().collect(),
+        });
+        // Digs start from garbage rows with a gap each, drawn from the new seed
+        let mut grid = board.grid;
+        if *mode == GameMode::Dig {
+            let gaps: Vec<usize> = (0..rules.dig_rows.min(grid.height() - 1))
+                .map(|_| rng.rng.gen_range(0..grid.width()))
+                .collect();
+            if grid
+                .insert_garbage_rows(gaps.len(), |row| gaps[row])
+                .is_ok()
+            {
+                board_commands.send(BoardCommand::GarbageRows {
+                    board: entity,
+                    gaps,
+                });
+            }
+        } else {
+            let opening = opening::generate(
+                rules.start_preset,
+                rules.start_density,
+                (grid.width(), grid.height()),
+                &mut rng.rng,
+                (&registry, &palette),
+            );
+            // A fill per color, through the board commands like any other change
+            for color in palette.shape_colors() {
+                let cells: CellList = grid
+                    .positions()
+                    .filter(|&(x, y)| opening.0[y][x] == Some(color))
+                    .collect();
+                if cells.is_empty() {
+                    continue;
+                }
+                grid.fill_cells(cells.iter().copied(), Some(color));
+                board_commands.send(BoardCommand::Fill {
+                    board: entity,
+                    cells,
+                    color: Some(color),
+                });
+            }
+        }
+        let bonus = pick_bonus_cells(&grid, &mut rng, rules.bonus_cells, &[]);
+        send_bonus_cells(&mut board_commands, entity, bonus);
+        // Forced shapes first, like every draw
+        first_shape = Some(draw_shape(
+            (&mut queue.0, &mut drought),
+            &mut rng.rng,
+            (&weights.0, rules.drought_threshold, &assist),
+            (&registry, &palette),
+        ));
+        tray.0.clear();
+        tray.top_up(
+            UPCOMING_SHAPES,
+            (&mut queue.0, &mut drought),
+            &mut rng.rng,
+            (&weights.0, rules.drought_threshold, &assist),
+            (&registry, &palette),
+        );
+    }
+
+    let first_shape = first_shape.unwrap_or(ShapeInstance {
+        id: ShapeId(0),
+        rotation: 0,
+        color: TileColor::Blue,
+    });
+    if let Ok((entity, instance, children, &transform)) = q_selected_shape.get_single_mut() {
+        replace_selected_shape(
+            &mut commands,
+            (&registry, &palette),
+            (entity, instance, children),
+            &mut q_sprites,
+            first_shape,
+            transform,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_and_puzzles_keep_their_seed() {
+        assert_eq!(restart_seed(GameMode::Endless, 127, true), 127);
+        assert_eq!(restart_seed(GameMode::Puzzle, 127, false), 127);
+    }
+
+    #[test]
+    fn daily_games_restart_on_the_day_seed() {
+        assert_eq!(restart_seed(GameMode::Daily, 127, false), daily_seed());
+    }
+
+    #[test]
+    fn other_modes_draw_a_new_seed() {
+        let seeds: Vec<u64> = (0..4)
+            .map(|_| restart_seed(GameMode::Sprint, 127, false))
+            .collect();
+        assert!(seeds.iter().any(|&seed| seed != seeds[0] && seed != 127));
+    }
+}
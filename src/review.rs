@@ -0,0 +1,293 @@
+//! Reviewing a finished game: once it's over a Review button steps through its
+//! placements with the left and right arrow keys, showing the board as it was before
+//! each one with the placed shape ghosted over it and the points it scored. Escape or
+//! the button again returns to the finished game.
+//!
+//! Reviewing only repaints the tiles, the board itself is left alone, and placing and
+//! replay playback are suspended by [`not_reviewing`]. Clicking a move in the history
+//! panel while watching a replay reviews the board after it too.
+
+use bevy::prelude::*;
+
+use crate::{
+    board::BoardSnapshot,
+    highlight::HighlightLayer,
+    highlight::Highlights,
+    palette::ColorPalette,
+    restart::RestartEvent,
+    scoring::Score,
+    shape::{ShapeInstance, ShapeRegistry},
+    tile_fade::{paint_now, TargetTileColor},
+    zones::ZoneMap,
+    Board, BoardChangedEvent, CellList, GameOverEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+const IDLE_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+
+/// A placement of the game, with the board as it was before it.
+struct Step {
+    before: BoardSnapshot,
+    shape: ShapeInstance,
+    cells: CellList,
+    points: u64,
+}
+
+/// Every placement on the main board since the last restart.
+#[derive(Resource, Default)]
+struct Timeline {
+    steps: Vec<Step>,
+    /// The main board as of the end of the last frame.
+    current: BoardSnapshot,
+    /// Score as of the end of the last frame.
+    scored: u64,
+}
+
+/// The step being shown, present while reviewing. One past the last placement shows
+/// the final board.
+#[derive(Resource, Debug, PartialEq)]
+pub struct Review(usize);
+impl Review {
+    /// Shows the board as it was after the first `moves` placements since the restart.
+    pub(crate) fn after(moves: usize) -> Self {
+        Self(moves)
+    }
+}
+
+/// Run condition suspending play while a finished game is reviewed.
+pub fn not_reviewing(review: Option<Res<Review>>) -> bool {
+    review.is_none()
+}
+
+#[derive(Component)]
+struct ReviewButton;
+
+#[derive(Component)]
+struct ReviewText;
+
+pub struct ReviewPlugin;
+impl Plugin for ReviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Timeline>()
+            .add_systems(Startup, spawn_review_ui)
+            .add_systems(
+                Update,
+                (
+                    offer_review,
+                    toggle_review,
+                    step_review.run_if(resource_exists::<Review>()),
+                )
+                    .chain(),
+            )
+            // Placements are only scored by the end of the frame
+            .add_systems(
+                PostUpdate,
+                (
+                    record_steps,
+                    show_step.run_if(resource_exists_and_changed::<Review>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn record_steps(
+    mut timeline: ResMut<Timeline>,
+    mut restarts: EventReader<RestartEvent>,
+    mut placements: EventReader<PlacementEvent>,
+    q_board: Query<(&Board, &Score, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let Ok((board, score, entity)) = q_board.get_single() else {
+        return;
+    };
+    if restarts.read().count() > 0 {
+        timeline.steps.clear();
+        timeline.scored = score.0;
+    }
+    // A frame holds at most one placement, which earned everything scored since
+    let points = score.0.saturating_sub(timeline.scored);
+    for event in placements.read().filter(|event| event.board == entity) {
+        let before = timeline.current;
+        timeline.steps.push(Step {
+            before,
+            shape: event.shape,
+            cells: event.cells.clone(),
+            points,
+        });
+    }
+    timeline.scored = score.0;
+    timeline.current = board.grid.snapshot();
+}
+
+fn spawn_review_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(40.),
+                    left: Val::Percent(45.),
+                    padding: UiRect::all(Val::Px(8.)),
+                    display: Display::None,
+                    ..default()
+                },
+                background_color: IDLE_BUTTON.into(),
+                ..default()
+            },
+            ReviewButton,
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                "Review",
+                TextStyle {
+                    font_size: 24.,
+                    ..default()
+                },
+            ));
+        });
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(90.),
+            left: Val::Percent(35.),
+            ..default()
+        }),
+        ReviewText,
+    ));
+}
+
+/// Shows the Review button once the game is over, until the next restart.
+fn offer_review(
+    mut commands: Commands,
+    mut game_over: EventReader<GameOverEvent>,
+    mut restarts: EventReader<RestartEvent>,
+    mut q_button: Query<&mut Style, With<ReviewButton>>,
+    mut q_text: Query<&mut Text, With<ReviewText>>,
+) {
+    let display = if restarts.read().count() > 0 {
+        commands.remove_resource::<Review>();
+        if let Ok(mut text) = q_text.get_single_mut() {
+            text.sections[0].value.clear();
+        }
+        Display::None
+    } else if game_over.read().count() > 0 {
+        Display::Flex
+    } else {
+        return;
+    };
+    if let Ok(mut style) = q_button.get_single_mut() {
+        style.display = display;
+    }
+}
+
+/// The Review button starts and ends reviewing, Escape ends it too.
+fn toggle_review(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    timeline: Res<Timeline>,
+    review: Option<Res<Review>>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut highlights: ResMut<Highlights>,
+    q_button: Query<&Interaction, (Changed<Interaction>, With<ReviewButton>)>,
+    q_board: Query<(&Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_text: Query<&mut Text, With<ReviewText>>,
+) {
+    let clicked = q_button
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed);
+    let escaped = review.is_some() && input_keys.just_pressed(KeyCode::Escape);
+    if !clicked && !escaped {
+        return;
+    }
+    if review.is_none() {
+        // The review takes over the overlay
+        highlights.clear_all();
+        commands.insert_resource(Review(timeline.steps.len()));
+        return;
+    }
+    commands.remove_resource::<Review>();
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value.clear();
+    }
+    // Back to the board as it is
+    if let Ok((board, entity)) = q_board.get_single() {
+        board_changed.send(BoardChangedEvent {
+            board: entity,
+            revision: board.grid.revision(),
+            cells: board.grid.positions().collect(),
+        });
+    }
+    highlights.clear(HighlightLayer::Review);
+}
+
+fn step_review(
+    input_keys: Res<Input<KeyCode>>,
+    timeline: Res<Timeline>,
+    mut review: ResMut<Review>,
+) {
+    if input_keys.just_pressed(KeyCode::Left) && review.0 > 0 {
+        review.0 -= 1;
+    }
+    if input_keys.just_pressed(KeyCode::Right) && review.0 < timeline.steps.len() {
+        review.0 += 1;
+    }
+}
+
+/// Paints the reviewed step onto the main board's tiles and highlights its shape.
+fn show_step(
+    review: Res<Review>,
+    timeline: Res<Timeline>,
+    (registry, palette, zone_map): (Res<ShapeRegistry>, Res<ColorPalette>, Res<ZoneMap>),
+    mut highlights: ResMut<Highlights>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_tiles: Query<(&mut Sprite, Option<&mut TargetTileColor>)>,
+    mut q_text: Query<&mut Text, With<ReviewText>>,
+) {
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+    let step = timeline.steps.get(review.0);
+    let grid = step.map_or(&timeline.current, |step| &step.before);
+    for (y, row) in board.entities.iter().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
+            let Ok((mut sprite, target)) = q_tiles.get_mut(tile) else {
+                continue;
+            };
+            let color = grid.cells.0[y][x].map_or_else(
+                || zone_map.empty_tile_color(x, y),
+                |color| palette.color(color),
+            );
+            paint_now(&mut sprite, target, color);
+        }
+    }
+    highlights.clear(HighlightLayer::Review);
+    if let Some(step) = step {
+        for &cell in step.cells.iter() {
+            highlights.set(
+                HighlightLayer::Review,
+                cell,
+                palette.preview_color(step.shape.color),
+            );
+        }
+    }
+    if let Ok(mut text) = q_text.get_single_mut() {
+        let total = timeline.steps.len();
+        text.sections[0].value = match step {
+            Some(step) => format!(
+                "Move {} of {total}: {} +{}   Left/Right to step, Escape to leave",
+                review.0 + 1,
+                registry.name(step.shape.id),
+                step.points
+            ),
+            None => {
+                format!("Final board after {total} moves   Left/Right to step, Escape to leave")
+            }
+        };
+    }
+}
@@ -0,0 +1,155 @@
+//! Rotating the selected shape with R, a right click or a gamepad's east button,
+//! kicking it back onto the board when the rotation would make it stick out. Kicks
+//! apply alike when the arrow keys or the D-pad steer the cursor.
+
+use bevy::prelude::*;
+
+use crate::{
+    board::Board as BoardGrid,
+    click_guard::PointerCapture,
+    hover::CursorBoardPos,
+    intro::board_ready,
+    keyboard,
+    menu::GameState,
+    palette::ColorPalette,
+    peel::Peelable,
+    settings::Settings,
+    shape::{Shape, ShapeInstance, ShapeRegistry},
+    systems::build_shape,
+    systems::Preview,
+    systems::Shake,
+    systems::SHAKE_DURATION,
+    BlocksSystems, Board, MainBoard, OverlayBoard, SelectedShape,
+};
+
+/// Shake amplitude of the board when a rotation finds no room.
+const BLOCKED_SHAKE_AMPLITUDE: f32 = 2.;
+
+/// Anchor offsets tried in order after a rotation, the first keeping the shape within
+/// the board wins.
+#[derive(Resource)]
+pub struct RotationKicks(pub Vec<(isize, isize)>);
+impl Default for RotationKicks {
+    fn default() -> Self {
+        Self(vec![
+            (0, 0),
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (2, 0),
+            (-2, 0),
+            (0, 2),
+            (0, -2),
+        ])
+    }
+}
+
+/// Offset added to the anchor under the cursor by the last rotation's kick, until the
+/// cursor moves onto another anchor.
+#[derive(Resource, Default)]
+pub struct Kick {
+    /// Anchor under the cursor the kick was found for.
+    pub base: (isize, isize),
+    pub offset: (isize, isize),
+}
+impl Kick {
+    /// The anchor to use for the one under the cursor.
+    pub fn apply(&self, anchor: (isize, isize)) -> (isize, isize) {
+        if anchor == self.base {
+            (anchor.0 + self.offset.0, anchor.1 + self.offset.1)
+        } else {
+            anchor
+        }
+    }
+}
+
+pub struct RotationPlugin;
+impl Plugin for RotationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RotationKicks>()
+            .init_resource::<Kick>()
+            .add_systems(
+                Update,
+                rotate
+                    .run_if(in_state(GameState::Playing).and_then(board_ready))
+                    .in_set(BlocksSystems::Input),
+            );
+    }
+}
+
+fn within_board(board: &BoardGrid, shape: &Shape, anchor: (isize, isize)) -> bool {
+    let (width, height) = shape.bounds();
+    anchor.0 >= 0
+        && anchor.1 >= 0
+        && anchor.0 as usize + width <= board.width()
+        && anchor.1 as usize + height <= board.height()
+}
+
+/// Rotates the selected shape to its next rotation. Away from the board it just turns,
+/// over it the rotation only happens if a kick keeps it within the board, the board
+/// shaking otherwise.
+fn rotate(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    input_mb: Res<Input<MouseButton>>,
+    input_gamepad: Res<Input<GamepadButton>>,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    settings: Res<Settings>,
+    kicks: Res<RotationKicks>,
+    preview: Res<Preview>,
+    (cursor, peelable, pointer): (Res<CursorBoardPos>, Res<Peelable>, Res<PointerCapture>),
+    mut kick: ResMut<Kick>,
+    q_board: Query<(&Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<(&ShapeInstance, Entity, &Transform), With<SelectedShape>>,
+) {
+    // Ctrl+R restarts instead
+    let ctrl = input_keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    // Holding the right mouse button over the last placement lifts it instead, and right
+    // clicks on the HUD are the HUD's
+    let right_click = input_mb.just_pressed(MouseButton::Right)
+        && !peelable.covers(&cursor)
+        && !pointer.captured();
+    let pressed = (input_keys.just_pressed(KeyCode::R) && !ctrl)
+        || right_click
+        || keyboard::gamepad_just_pressed(&input_gamepad, keyboard::ROTATE_BUTTON);
+    if !pressed {
+        return;
+    }
+    let Ok((selected_shape, entity, &transform)) = q_selected_shape.get_single() else {
+        return;
+    };
+    let rotated = ShapeInstance {
+        rotation: ((selected_shape.rotation as usize + 1)
+            % registry.rotation_count(selected_shape.id)) as u8,
+        ..*selected_shape
+    };
+
+    let over_board = q_board.get_single().ok().filter(|&(_, board_entity)| {
+        cursor
+            .cell()
+            .is_some_and(|(board, _)| board == board_entity)
+    });
+    if let (Some(translation), Some((board, board_entity))) = (preview.translation, over_board) {
+        let shape = rotated.resolve(&registry);
+        let base = board.grid.anchor_at(shape, translation);
+        let Some(offset) = kicks.0.iter().copied().find(|offset| {
+            within_board(&board.grid, shape, (base.0 + offset.0, base.1 + offset.1))
+        }) else {
+            if settings.screen_shake {
+                commands.entity(board_entity).insert(Shake {
+                    timer: Timer::from_seconds(SHAKE_DURATION, TimerMode::Once),
+                    amplitude: BLOCKED_SHAKE_AMPLITUDE,
+                    origin: None,
+                });
+            }
+            return;
+        };
+        *kick = Kick { base, offset };
+    }
+
+    commands.entity(entity).despawn_recursive();
+    build_shape(&mut commands, &registry, &palette, &rotated)
+        .insert(SelectedShape)
+        .insert(transform);
+}
@@ -0,0 +1,351 @@
+//! Rule bundles selected by the game mode, applied to the resources the game's systems
+//! read all at once.
+//!
+//! Custom rules are read from `rules.ron`, a struct with any of the fields of
+//! [`RuleSet`], e.g. `(gravity: true, board_size: Some((10, 10)))`.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::{size_supported, BOARD_HEIGHT, BOARD_WIDTH},
+    constraints::{PlacementConstraint, PlacementConstraints},
+    menu::GameMode,
+    opening::StartPreset,
+    persistence::{PersistAppExt, Persisted},
+    resize,
+    settings::Settings,
+    systems::Gravity,
+    systems::HardMode,
+    BlocksSystems, Board, MainBoard, OverlayBoard, ResizeBoardEvent,
+};
+
+/// Name of the adjacency rule's [`PlacementConstraint`].
+const ADJACENCY: &str = "adjacency";
+
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    /// Cells fall into the gaps left by clears, possibly cascading.
+    pub gravity: bool,
+    /// Scheduled garbage drops and cell freezes.
+    pub hard: bool,
+    /// Every shape after the first has to touch one placed before.
+    pub adjacency: bool,
+    /// Bonus cells dropped at the start and every few clears.
+    pub bonus_cells: usize,
+    /// Whether bonus cells multiply the points of the clear they are part of.
+    pub multipliers: bool,
+    /// Fill ratio of the board above which the overtime countdown starts.
+    pub overtime_enter: f32,
+    /// Fill ratio the board has to drop below to cancel the overtime.
+    pub overtime_exit: f32,
+    /// Seconds the overtime countdown lasts.
+    pub overtime_seconds: f32,
+    /// Shapes that can go by without a shape appearing before its chances start to
+    /// rise, 0 for no drought protection.
+    pub drought_threshold: u32,
+    /// Lines to clear in [`GameMode::Sprint`].
+    pub sprint_lines: usize,
+    /// Garbage rows the board starts with in [`GameMode::Dig`], to be dug out.
+    pub dig_rows: usize,
+    /// Board the game starts from, outside of digs.
+    pub start_preset: StartPreset,
+    /// Share of the cells a [`StartPreset::Scattered`] opening fills.
+    pub start_density: f32,
+    /// Size the main board is played at, `None` for the one picked in the menu.
+    pub board_size: Option<(usize, usize)>,
+    /// Side of the square regions clearing once full alongside rows and columns, 0 for
+    /// none.
+    pub region_size: usize,
+}
+impl RuleSet {
+    pub fn classic() -> Self {
+        Self {
+            gravity: false,
+            hard: false,
+            adjacency: false,
+            bonus_cells: 3,
+            multipliers: true,
+            overtime_enter: 0.85,
+            overtime_exit: 0.75,
+            overtime_seconds: 20.,
+            drought_threshold: 15,
+            sprint_lines: 20,
+            dig_rows: 8,
+            start_preset: StartPreset::Empty,
+            start_density: 0.15,
+            board_size: None,
+            region_size: 0,
+        }
+    }
+
+    /// A 9x9 board whose 3x3 squares clear once full, like rows and columns.
+    pub fn blockudoku() -> Self {
+        Self {
+            board_size: Some((9, 9)),
+            region_size: 3,
+            ..Self::classic()
+        }
+    }
+
+    pub fn gravity_cascade() -> Self {
+        Self {
+            gravity: true,
+            ..Self::classic()
+        }
+    }
+
+    pub fn adjacency() -> Self {
+        Self {
+            adjacency: true,
+            ..Self::classic()
+        }
+    }
+
+    /// Classic rules, to clear `lines` lines.
+    pub fn sprint(lines: usize) -> Self {
+        Self {
+            sprint_lines: lines,
+            ..Self::classic()
+        }
+    }
+
+    /// Classic rules, digging out `rows` garbage rows.
+    pub fn dig(rows: usize) -> Self {
+        Self {
+            dig_rows: rows,
+            ..Self::classic()
+        }
+    }
+
+    /// The preset of a mode, with the overrides added on top. Races take their goal from
+    /// the custom rules.
+    pub fn for_mode(mode: GameMode, custom: &CustomRules, overrides: RuleOverrides) -> Self {
+        let rules = match mode {
+            GameMode::Cascade => Self::gravity_cascade(),
+            GameMode::Adjacency => Self::adjacency(),
+            GameMode::Blockudoku => Self::blockudoku(),
+            // Puzzles are laid out on the full board
+            GameMode::Puzzle => Self {
+                board_size: Some((BOARD_WIDTH, BOARD_HEIGHT)),
+                ..Self::classic()
+            },
+            GameMode::Custom => custom.0.clone(),
+            GameMode::Sprint => Self::sprint(custom.0.sprint_lines),
+            GameMode::Dig => Self::dig(custom.0.dig_rows),
+            GameMode::Endless | GameMode::Timed | GameMode::Daily | GameMode::Versus => {
+                Self::classic()
+            }
+        };
+        Self {
+            gravity: rules.gravity || overrides.gravity,
+            hard: rules.hard || overrides.hard,
+            ..rules
+        }
+    }
+
+    /// Combinations that are allowed but likely not what was meant.
+    pub fn warnings(&self) -> Vec<&'static str> {
+        let mut warnings = Vec::new();
+        if self.bonus_cells > 0 && !self.multipliers {
+            warnings.push("bonus cells have no effect without multipliers");
+        }
+        if self.multipliers && self.bonus_cells == 0 {
+            warnings.push("multipliers have no effect without bonus cells");
+        }
+        if self.sprint_lines == 0 {
+            warnings.push("sprints end before the first placement without lines to clear");
+        }
+        if self.dig_rows == 0 {
+            warnings.push("digs end before the first placement without garbage rows");
+        }
+        if self.dig_rows >= BOARD_HEIGHT {
+            warnings.push("digs can't start with the board full of garbage");
+        }
+        if self.overtime_exit > self.overtime_enter {
+            warnings.push("overtime can't be canceled when its exit is above its threshold");
+        }
+        if self.start_preset == StartPreset::Scattered && self.start_density <= 0. {
+            warnings.push("scattered openings are empty without a start density");
+        }
+        if self.start_preset == StartPreset::Scattered && self.start_density > 0.5 {
+            warnings.push("scattered openings this dense mostly fall back to the empty board");
+        }
+        if let Some((width, height)) = self.board_size {
+            if !size_supported(width, height) {
+                warnings.push("the board size isn't supported, the board keeps its size");
+            }
+            if self.region_size > 0
+                && (width % self.region_size != 0 || height % self.region_size != 0)
+            {
+                warnings.push("regions not tiling the board leave its edge cells to lines");
+            }
+        }
+        if self.region_size == 1 {
+            warnings.push("regions of a single cell clear every placed cell");
+        }
+        warnings
+    }
+}
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+/// Rules every mode is played with on top of its own, from `--gravity` and `--hard` on
+/// the command line. Given to the plugin through [`BlocksPluginBuilder::rule_overrides`].
+///
+/// [`BlocksPluginBuilder::rule_overrides`]: crate::config::BlocksPluginBuilder::rule_overrides
+#[derive(Resource, Clone, Copy, Default, PartialEq, Debug)]
+pub struct RuleOverrides {
+    pub gravity: bool,
+    pub hard: bool,
+}
+impl RuleOverrides {
+    /// The overrides among the program's arguments, other arguments are left to others.
+    pub fn from_args<S: AsRef<str>>(args: impl IntoIterator<Item = S>) -> Self {
+        let mut overrides = Self::default();
+        for arg in args {
+            match arg.as_ref() {
+                "--gravity" => overrides.gravity = true,
+                "--hard" => overrides.hard = true,
+                _ => {}
+            }
+        }
+        overrides
+    }
+}
+
+/// Rules of [`GameMode::Custom`], starting from [`RuleSet::classic`].
+#[derive(Resource, Default)]
+pub struct CustomRules(pub RuleSet);
+impl Persisted for CustomRules {
+    const PATH: &'static str = "rules.ron";
+
+    /// Reads a RON struct of rules, fields that are missing keep their classic value. A
+    /// file with a field that doesn't parse or isn't a rule is refused, with where ron
+    /// stopped reading it.
+    fn parse(text: &str) -> Option<Self> {
+        let rules: RuleSet = match ron::from_str(text) {
+            Ok(rules) => rules,
+            Err(err) => {
+                warn!("ignoring malformed {}: {err}", Self::PATH);
+                return None;
+            }
+        };
+        for warning in rules.warnings() {
+            warn!("{}: {warning}", Self::PATH);
+        }
+        Some(Self(rules))
+    }
+}
+
+pub struct RulesPlugin;
+impl Plugin for RulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RuleOverrides>();
+        // Unless the app or the plugin's configuration brought its own
+        if !app.world.contains_resource::<RuleSet>() {
+            let overrides = *app.world.resource::<RuleOverrides>();
+            app.insert_resource(RuleSet::for_mode(
+                GameMode::default(),
+                &CustomRules::default(),
+                overrides,
+            ));
+        }
+        app.load_persisted::<CustomRules>().add_systems(
+            Update,
+            apply_rule_set
+                .run_if(resource_changed::<RuleSet>())
+                .before(BlocksSystems::Preview)
+                .before(resize::resize_boards),
+        );
+    }
+}
+
+/// Hands a new rule set to the resources systems read their rules from, and the main
+/// board its size and regions. Rules that stop forcing a size go back to the menu's.
+fn apply_rule_set(
+    rules: Res<RuleSet>,
+    settings: Res<Settings>,
+    mut forced_size: Local<bool>,
+    mut gravity: ResMut<Gravity>,
+    mut hard_mode: ResMut<HardMode>,
+    mut constraints: ResMut<PlacementConstraints>,
+    mut resizes: EventWriter<ResizeBoardEvent>,
+    mut q_main_board: Query<&mut Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    gravity.0 = rules.gravity;
+    hard_mode.0 = rules.hard;
+    constraints.remove(ADJACENCY);
+    if rules.adjacency {
+        constraints.add(PlacementConstraint::touches_filled(ADJACENCY));
+    }
+    for mut board in q_main_board.iter_mut() {
+        board.grid.set_region_size(rules.region_size);
+    }
+    let size = match rules.board_size {
+        Some(size) => Some(size),
+        None if *forced_size => Some(settings.board_size),
+        None => None,
+    };
+    *forced_size = rules.board_size.is_some();
+    if let Some((width, height)) = size {
+        resizes.send(ResizeBoardEvent { width, height });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_rules_keep_the_classic_value_of_missing_fields() {
+        let text = "RuleSet(
+            gravity: true,
+            // Comments are fine
+            start_preset: Scattered,
+            board_size: Some((10, 12)),
+        )";
+        let CustomRules(rules) = CustomRules::parse(text).unwrap();
+        assert_eq!(
+            rules,
+            RuleSet {
+                gravity: true,
+                start_preset: StartPreset::Scattered,
+                board_size: Some((10, 12)),
+                ..RuleSet::classic()
+            }
+        );
+        assert_eq!(CustomRules::parse("()").unwrap().0, RuleSet::classic());
+    }
+
+    #[test]
+    fn custom_rules_with_a_field_that_doesnt_parse_are_refused() {
+        assert!(CustomRules::parse("(bonus_cells: \"many\")").is_none());
+        assert!(CustomRules::parse("(sprint_lines: -4)").is_none());
+        assert!(CustomRules::parse("(gravity: true, colors: 3)").is_none());
+        assert!(CustomRules::parse("gravity = true").is_none());
+        assert!(CustomRules::parse("[true]").is_none());
+    }
+
+    #[test]
+    fn overrides_from_the_arguments_add_to_every_mode() {
+        let overrides = RuleOverrides::from_args(["--hard", "--replay", "code"]);
+        assert_eq!(
+            overrides,
+            RuleOverrides {
+                gravity: false,
+                hard: true,
+            }
+        );
+
+        let custom = CustomRules::default();
+        let rules = RuleSet::for_mode(GameMode::Cascade, &custom, overrides);
+        assert!(rules.gravity && rules.hard);
+        let rules = RuleSet::for_mode(GameMode::Endless, &custom, RuleOverrides::default());
+        assert!(!rules.gravity && !rules.hard);
+    }
+}
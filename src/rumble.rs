@@ -0,0 +1,133 @@
+//! Gamepad rumble: a light tap on placements, a stronger pulse growing with the lines
+//! a clear removed and a long low rumble when the game ends, on every connected
+//! gamepad. Driven by the board's events alone, and scaled or turned off through
+//! [`FeedbackSettings`] in `feedback.txt`.
+
+use std::{fmt, time::Duration};
+
+use bevy::{
+    input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest},
+    prelude::*,
+};
+
+use crate::{
+    persistence::{PersistAppExt, Persisted},
+    GameOverEvent, LinesClearedEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+/// Weak motor strength and seconds of the tap of a placement.
+const PLACEMENT_RUMBLE: (f32, f32) = (0.2, 0.08);
+/// Strong motor strength each cleared line adds, up to [`MAX_CLEAR_STRENGTH`].
+const STRENGTH_PER_LINE: f32 = 0.3;
+const MAX_CLEAR_STRENGTH: f32 = 1.;
+/// Seconds of a clear's pulse, with [`SECONDS_PER_LINE`] more for every line after the
+/// first, up to [`MAX_CLEAR_SECONDS`].
+const CLEAR_SECONDS: f32 = 0.15;
+const SECONDS_PER_LINE: f32 = 0.05;
+const MAX_CLEAR_SECONDS: f32 = 0.4;
+/// Strong motor strength and seconds of the rumble at the end of a game.
+const GAME_OVER_RUMBLE: (f32, f32) = (0.5, 1.2);
+
+/// Haptic feedback preferences, kept apart from the general [`crate::settings::Settings`].
+#[derive(Resource)]
+pub struct FeedbackSettings {
+    pub rumble: bool,
+    /// Scales every rumble, from 0 to 1.
+    pub rumble_intensity: f32,
+}
+impl Default for FeedbackSettings {
+    fn default() -> Self {
+        Self {
+            rumble: true,
+            rumble_intensity: 1.,
+        }
+    }
+}
+impl fmt::Display for FeedbackSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rumble = {}", self.rumble)?;
+        writeln!(f, "rumble_intensity = {}", self.rumble_intensity)
+    }
+}
+impl Persisted for FeedbackSettings {
+    const PATH: &'static str = "feedback.txt";
+
+    /// Reads `key = value` lines, keys that are missing keep their default.
+    fn parse(text: &str) -> Option<Self> {
+        let mut settings = Self::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=')?;
+            match key.trim() {
+                "rumble" => settings.rumble = value.trim().parse().ok()?,
+                "rumble_intensity" => {
+                    settings.rumble_intensity = value.trim().parse::<f32>().ok()?.clamp(0., 1.)
+                }
+                key => warn!("unknown feedback setting {key}"),
+            }
+        }
+        Some(settings)
+    }
+}
+
+pub struct RumblePlugin;
+impl Plugin for RumblePlugin {
+    fn build(&self, app: &mut App) {
+        app.load_persisted::<FeedbackSettings>()
+            .add_systems(Update, rumble);
+    }
+}
+
+fn rumble(
+    settings: Res<FeedbackSettings>,
+    gamepads: Res<Gamepads>,
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    mut game_over: EventReader<GameOverEvent>,
+    mut requests: EventWriter<GamepadRumbleRequest>,
+    q_main_board: Query<(), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let placed = placements
+        .read()
+        .filter(|event| q_main_board.contains(event.board))
+        .count()
+        > 0;
+    let lines: usize = lines_cleared
+        .read()
+        .filter(|event| q_main_board.contains(event.board))
+        .map(|event| event.lines.rows.len() + event.lines.columns.len())
+        .sum();
+    let ended = game_over.read().count() > 0;
+    // Without a gamepad there's no one to tell
+    if !settings.rumble || gamepads.iter().next().is_none() {
+        return;
+    }
+
+    let mut rumbles = Vec::new();
+    if placed {
+        let (strength, seconds) = PLACEMENT_RUMBLE;
+        rumbles.push((GamepadRumbleIntensity::weak_motor(strength), seconds));
+    }
+    if lines > 0 {
+        let strength = (lines as f32 * STRENGTH_PER_LINE).min(MAX_CLEAR_STRENGTH);
+        let seconds =
+            (CLEAR_SECONDS + (lines - 1) as f32 * SECONDS_PER_LINE).min(MAX_CLEAR_SECONDS);
+        rumbles.push((GamepadRumbleIntensity::strong_motor(strength), seconds));
+    }
+    if ended {
+        let (strength, seconds) = GAME_OVER_RUMBLE;
+        rumbles.push((GamepadRumbleIntensity::strong_motor(strength), seconds));
+    }
+    for (intensity, seconds) in rumbles {
+        let intensity = GamepadRumbleIntensity {
+            strong_motor: intensity.strong_motor * settings.rumble_intensity,
+            weak_motor: intensity.weak_motor * settings.rumble_intensity,
+        };
+        for gamepad in gamepads.iter() {
+            requests.send(GamepadRumbleRequest::Add {
+                duration: Duration::from_secs_f32(seconds),
+                intensity,
+                gamepad,
+            });
+        }
+    }
+}
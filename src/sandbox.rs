@@ -0,0 +1,376 @@
+//! Sandbox: painting the main board freely, without scoring or game over, then exporting
+//! it or playing an endless game from it.
+//!
+//! Left click toggles a cell in the brush color, right click clears it, and either with
+//! Shift held paints the rectangle dragged over. Number keys pick the brush color.
+
+use bevy::prelude::*;
+use smallvec::smallvec;
+
+use crate::{
+    board::{Grid, TileColor},
+    click_guard::PointerCapture,
+    events::BoardCommand,
+    hover::CursorBoardPos,
+    menu::{GameMode, GameState},
+    palette::ColorPalette,
+    persistence::{stamp, PersistRequest, Versioned},
+    rules::{CustomRules, RuleOverrides, RuleSet},
+    save::SaveData,
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    systems::replace_selected_shape,
+    toast::spawn_toast,
+    BlocksSystems, Board, BoardChangedEvent, MainBoard, OverlayBoard, SelectedShape,
+};
+
+pub const SANDBOX_PATH: &str = "sandbox.txt";
+/// Brush colors in the order of the number keys.
+const PALETTE: [TileColor; 6] = [
+    TileColor::Gray,
+    TileColor::Red,
+    TileColor::Green,
+    TileColor::Blue,
+    TileColor::Stone,
+    TileColor::Cracked,
+];
+const PALETTE_KEYS: [KeyCode; 6] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+];
+const IDLE_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.5);
+
+#[derive(Resource)]
+struct Brush {
+    color: TileColor,
+    /// Cell and button a Shift-drag started with.
+    drag_start: Option<((usize, usize), MouseButton)>,
+}
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            color: TileColor::Gray,
+            drag_start: None,
+        }
+    }
+}
+
+/// Board and selected shape the endless game started by "Play from here" begins with.
+#[derive(Resource)]
+pub struct PlayFrom(SaveData);
+
+#[derive(Component)]
+struct SandboxRoot;
+
+#[derive(Component, Clone, Copy, PartialEq)]
+enum SandboxButton {
+    Color(TileColor),
+    Shape(ShapeId),
+    Export,
+    Play,
+}
+
+pub struct SandboxPlugin;
+impl Plugin for SandboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Brush>()
+            .add_systems(OnEnter(GameState::Sandbox), (spawn_palette, clear_board))
+            .add_systems(OnExit(GameState::Sandbox), despawn_palette)
+            .add_systems(
+                Update,
+                (
+                    press_buttons,
+                    highlight_buttons.after(press_buttons),
+                    paint
+                        .after(BlocksSystems::Preview)
+                        .before(BlocksSystems::Resolution),
+                )
+                    .run_if(in_state(GameState::Sandbox)),
+            )
+            .add_systems(
+                Update,
+                load_play_from
+                    .run_if(resource_exists::<PlayFrom>())
+                    .in_set(BlocksSystems::Preview),
+            );
+    }
+}
+
+fn button(commands: &mut ChildBuilder, button: SandboxButton, label: &str, color: Color) {
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::all(Val::Px(4.)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: IDLE_BUTTON.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|commands| {
+            commands.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 16.,
+                    color,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn spawn_palette(
+    mut commands: Commands,
+    registry: Res<ShapeRegistry>,
+    color_palette: Res<ColorPalette>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(8.),
+                    left: Val::Px(8.),
+                    width: Val::Px(120.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.),
+                    ..default()
+                },
+                ..default()
+            },
+            SandboxRoot,
+        ))
+        .with_children(|commands| {
+            for (index, color) in PALETTE.into_iter().enumerate() {
+                let label = format!("{} {}", index + 1, color_palette.name(color));
+                let display = color_palette.color(color);
+                button(commands, SandboxButton::Color(color), &label, display);
+            }
+            for color in color_palette.custom_colors() {
+                let display = color_palette.color(color);
+                let label = color_palette.name(color).to_owned();
+                button(commands, SandboxButton::Color(color), &label, display);
+            }
+            for (id, _) in registry.pool() {
+                let label = registry.name(id).to_owned();
+                button(commands, SandboxButton::Shape(id), &label, Color::WHITE);
+            }
+            button(commands, SandboxButton::Export, "Export", Color::WHITE);
+            button(
+                commands,
+                SandboxButton::Play,
+                "Play from here",
+                Color::WHITE,
+            );
+        });
+}
+
+fn despawn_palette(mut commands: Commands, q_root: Query<Entity, With<SandboxRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn clear_board(
+    mut q_board: Query<(&mut Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+) {
+    let Ok((mut board, entity)) = q_board.get_single_mut() else {
+        return;
+    };
+    board.grid.set_cells(Grid::default());
+    board_changed.send(BoardChangedEvent {
+        board: entity,
+        revision: board.grid.revision(),
+        cells: board.grid.positions().collect(),
+    });
+}
+
+/// Turns clicks on the board into fills of single cells or dragged rectangles.
+fn paint(
+    input_keys: Res<Input<KeyCode>>,
+    input_mb: Res<Input<MouseButton>>,
+    cursor: Res<CursorBoardPos>,
+    mut brush: ResMut<Brush>,
+    mut board_commands: EventWriter<BoardCommand>,
+    q_board: Query<(&Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    pointer: Res<PointerCapture>,
+) {
+    for (&key, color) in PALETTE_KEYS.iter().zip(PALETTE) {
+        if input_keys.just_pressed(key) {
+            brush.color = color;
+        }
+    }
+    // Clicks on the palette are not meant for the board
+    if pointer.captured() {
+        return;
+    }
+    let Ok((board, board_entity)) = q_board.get_single() else {
+        return;
+    };
+    let cell = cursor
+        .cell()
+        .filter(|&(entity, _)| entity == board_entity)
+        .map(|(_, cell)| cell);
+    let shift = input_keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    for button in [MouseButton::Left, MouseButton::Right] {
+        let color = (button == MouseButton::Left).then_some(brush.color);
+        if let Some((start, _)) = brush.drag_start.filter(|&(_, b)| b == button) {
+            if !input_mb.just_released(button) {
+                continue;
+            }
+            brush.drag_start = None;
+            let Some(end) = cell else {
+                continue;
+            };
+            let (xs, ys) = (
+                start.0.min(end.0)..=start.0.max(end.0),
+                start.1.min(end.1)..=start.1.max(end.1),
+            );
+            board_commands.send(BoardCommand::Fill {
+                board: board_entity,
+                cells: ys.flat_map(|y| xs.clone().map(move |x| (x, y))).collect(),
+                color,
+            });
+            continue;
+        }
+        let Some((x, y)) = cell.filter(|_| input_mb.just_pressed(button)) else {
+            continue;
+        };
+        if shift {
+            brush.drag_start = Some(((x, y), button));
+            continue;
+        }
+        // Clicking a cell already in the brush color empties it again
+        let toggled = color.filter(|&color| board.grid.cells.0[y][x] != Some(color));
+        board_commands.send(BoardCommand::Fill {
+            board: board_entity,
+            cells: smallvec![(x, y)],
+            color: toggled,
+        });
+    }
+}
+
+fn press_buttons(
+    mut commands: Commands,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    (custom_rules, overrides): (Res<CustomRules>, Res<RuleOverrides>),
+    mut brush: ResMut<Brush>,
+    mut mode: ResMut<GameMode>,
+    mut rules: ResMut<RuleSet>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut persist: EventWriter<PersistRequest>,
+    q_buttons: Query<(&Interaction, &SandboxButton), Changed<Interaction>>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_selected_shape: Query<
+        (Entity, &mut ShapeInstance, &Children, &Transform),
+        With<SelectedShape>,
+    >,
+    mut q_sprites: Query<&mut Sprite>,
+) {
+    let Some(&pressed) = q_buttons
+        .iter()
+        .find_map(|(interaction, button)| (*interaction == Interaction::Pressed).then_some(button))
+    else {
+        return;
+    };
+    let (Ok(board), Ok((entity, instance, children, &transform))) =
+        (q_board.get_single(), q_selected_shape.get_single_mut())
+    else {
+        return;
+    };
+    let data = SaveData {
+        cells: board.grid.cells,
+        selected: *instance,
+        play_time: 0.,
+        score: 0,
+        thumbnail: None,
+    };
+    match pressed {
+        SandboxButton::Color(color) => brush.color = color,
+        SandboxButton::Shape(id) => replace_selected_shape(
+            &mut commands,
+            (&registry, &palette),
+            (entity, instance, children),
+            &mut q_sprites,
+            ShapeInstance {
+                id,
+                rotation: 0,
+                color: brush.color,
+            },
+            transform,
+        ),
+        SandboxButton::Export => {
+            persist.send(PersistRequest {
+                path: SANDBOX_PATH.into(),
+                contents: stamp(SaveData::VERSION, data),
+            });
+            spawn_toast(&mut commands, format!("Exported to {SANDBOX_PATH}"));
+        }
+        SandboxButton::Play => {
+            *mode = GameMode::Endless;
+            *rules = RuleSet::for_mode(GameMode::Endless, &custom_rules, *overrides);
+            commands.insert_resource(PlayFrom(data));
+            next_state.set(GameState::Playing);
+        }
+    }
+}
+
+fn highlight_buttons(
+    brush: Res<Brush>,
+    mut q_buttons: Query<(&SandboxButton, &mut BackgroundColor)>,
+) {
+    for (button, mut background) in q_buttons.iter_mut() {
+        let selected = *button == SandboxButton::Color(brush.color);
+        *background = if selected {
+            SELECTED_BUTTON
+        } else {
+            IDLE_BUTTON
+        }
+        .into();
+    }
+}
+
+/// Puts the sandbox board and shape in place once the restart starting the game wiped
+/// them.
+fn load_play_from(
+    mut commands: Commands,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    play_from: Res<PlayFrom>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut q_board: Query<(&mut Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_selected_shape: Query<
+        (Entity, &mut ShapeInstance, &Children, &Transform),
+        With<SelectedShape>,
+    >,
+    mut q_sprites: Query<&mut Sprite>,
+) {
+    commands.remove_resource::<PlayFrom>();
+    let PlayFrom(data) = &*play_from;
+    if let Ok((mut board, entity)) = q_board.get_single_mut() {
+        board.grid.set_cells(data.cells);
+        board_changed.send(BoardChangedEvent {
+            board: entity,
+            revision: board.grid.revision(),
+            cells: board.grid.positions().collect(),
+        });
+    }
+    if let Ok((entity, instance, children, &transform)) = q_selected_shape.get_single_mut() {
+        replace_selected_shape(
+            &mut commands,
+            (&registry, &palette),
+            (entity, instance, children),
+            &mut q_sprites,
+            data.selected,
+            transform,
+        );
+    }
+}
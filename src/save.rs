@@ -0,0 +1,533 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bevy::prelude::*;
+
+use crate::{
+    board::{parse_cells, Grid, TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    palette::ColorPalette,
+    persistence::{load_versioned, stamp, DataDir, LoadError, PersistRequest, Versioned},
+    play_time::PlayTime,
+    scoring::Score,
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    snapshot::{decode_png, BoardSnapshot, SnapshotReadyEvent},
+    strings::{t, Strings},
+    systems::replace_selected_shape,
+    toast::spawn_toast,
+    Board, BoardChangedEvent, MainBoard, OverlayBoard, PlacementEvent, SelectedShape,
+};
+
+pub const AUTOSAVE_PATH: &str = "autosave.txt";
+/// Size of the autosave thumbnail on screen, in pixels.
+const THUMBNAIL_SIZE: f32 = 120.;
+
+/// Everything needed to resume a game.
+#[derive(Clone)]
+pub struct SaveData {
+    pub cells: Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>,
+    pub selected: ShapeInstance,
+    /// Seconds played, see [`PlayTime`].
+    pub play_time: f32,
+    /// The main board's [`Score`].
+    pub score: u64,
+    /// PNG snapshot of the board, for the resume prompt and the save slots. Saves
+    /// written without a renderer have none.
+    pub thumbnail: Option<Vec<u8>>,
+}
+impl Versioned for SaveData {
+    const VERSION: u32 = 2;
+
+    /// Parses the format written by `Display`. The thumbnail line is optional, so
+    /// saves from before thumbnails were kept parse too, and one that doesn't decode is
+    /// dropped rather than losing the game.
+    fn parse_current(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let mut data = Self::parse_v1(&mut lines)?;
+        data.score = lines.next()?.strip_prefix("score ")?.parse().ok()?;
+        if let Some(line) = lines.next() {
+            let thumbnail = line.strip_prefix("thumbnail ")?;
+            data.thumbnail = STANDARD.decode(thumbnail).ok();
+        }
+        Some(data)
+    }
+
+    fn migrate(version: u32, text: &str) -> Option<Self> {
+        match version {
+            0 => SaveDataV0::parse(text).map(Self::from),
+            // Saves from before the score was kept resume at 0 points
+            1 => Self::parse_v1(&mut text.lines()),
+            _ => None,
+        }
+    }
+}
+impl SaveData {
+    /// The lines of version 1, which later versions start with.
+    fn parse_v1<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Option<Self> {
+        let (selected, cells) = parse_shape_and_cells(lines)?;
+        let play_time = lines.next()?.strip_prefix("time ")?.parse().ok()?;
+        Some(Self {
+            cells,
+            selected,
+            play_time,
+            score: 0,
+            thumbnail: None,
+        })
+    }
+}
+
+/// Saves from before versions were stamped. The oldest of them lack the play time.
+struct SaveDataV0 {
+    cells: Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>,
+    selected: ShapeInstance,
+    play_time: Option<f32>,
+}
+impl SaveDataV0 {
+    fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let (selected, cells) = parse_shape_and_cells(&mut lines)?;
+        let play_time = match lines.next() {
+            Some(line) => Some(line.strip_prefix("time ")?.parse().ok()?),
+            None => None,
+        };
+        Some(Self {
+            cells,
+            selected,
+            play_time,
+        })
+    }
+}
+impl From<SaveDataV0> for SaveData {
+    fn from(old: SaveDataV0) -> Self {
+        Self {
+            cells: old.cells,
+            selected: old.selected,
+            play_time: old.play_time.unwrap_or(0.),
+            score: 0,
+            thumbnail: None,
+        }
+    }
+}
+
+/// The `shape` line and the board's rows every version starts with.
+fn parse_shape_and_cells<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+) -> Option<(
+    ShapeInstance,
+    Grid<Option<TileColor>, BOARD_WIDTH, BOARD_HEIGHT>,
+)> {
+    let mut header = lines.next()?.strip_prefix("shape ")?.split(' ');
+    let selected = ShapeInstance {
+        id: ShapeId(header.next()?.parse().ok()?),
+        rotation: header.next()?.parse().ok()?,
+        color: TileColor::from_char(header.next()?.chars().next()?)?,
+    };
+    Some((selected, parse_cells(lines)?))
+}
+
+/// The current version's format, without the version line, see [`stamp`].
+impl fmt::Display for SaveData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "shape {} {} {}",
+            self.selected.id.0,
+            self.selected.rotation,
+            self.selected.color.to_char()
+        )?;
+        for row in self.cells.0.iter() {
+            for cell in row {
+                write!(f, "{}", cell.map_or('.', TileColor::to_char))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "time {}", self.play_time)?;
+        writeln!(f, "score {}", self.score)?;
+        if let Some(thumbnail) = &self.thumbnail {
+            writeln!(f, "thumbnail {}", STANDARD.encode(thumbnail))?;
+        }
+        Ok(())
+    }
+}
+
+/// Restores a saved game on the main board.
+#[derive(Event)]
+pub struct LoadSaveEvent(pub SaveData);
+
+/// Reads a save of the data directory, `None` with the reason logged, and shown if the
+/// player can do something about it, if it can't be resumed.
+pub fn read_save(
+    commands: &mut Commands,
+    strings: &Strings,
+    registry: &ShapeRegistry,
+    dir: &DataDir,
+    path: &str,
+) -> Option<SaveData> {
+    let data = match std::fs::read_to_string(dir.path(path)) {
+        Ok(text) => load_versioned::<SaveData>(&text),
+        Err(err) => {
+            error!("failed to read {path}: {err}");
+            return None;
+        }
+    };
+    match data {
+        Ok(data) if registry.contains(data.selected.id) => Some(data),
+        Ok(_) | Err(LoadError::Malformed) => {
+            warn!("ignoring malformed {path}");
+            None
+        }
+        Err(err @ LoadError::Newer(_)) => {
+            warn!("ignoring {path}: {err}");
+            spawn_toast(commands, t!(strings, "toast.newer_file", path));
+            None
+        }
+    }
+}
+
+/// The thumbnail of a save of the data directory, `None` if it has none or can't be read.
+pub fn read_thumbnail(dir: &DataDir, path: &str) -> Option<Image> {
+    let text = std::fs::read_to_string(dir.path(path)).ok()?;
+    decode_png(&load_versioned::<SaveData>(&text).ok()?.thumbnail?)
+}
+
+/// Saves waiting for the snapshot of their board, written once it was read back.
+#[derive(Resource, Default)]
+pub struct PendingSaves(Vec<(Handle<Image>, String, SaveData)>);
+
+/// Writes a save of the board to the data directory, along with the board's thumbnail
+/// a few frames later if it can be captured. The handle of the capture, if any, shows
+/// the board in the meantime.
+pub fn persist_save(
+    (snapshot, pending, persist): (
+        &mut BoardSnapshot,
+        &mut PendingSaves,
+        &mut EventWriter<PersistRequest>,
+    ),
+    board: Entity,
+    path: String,
+    data: SaveData,
+) -> Option<Handle<Image>> {
+    let Some(image) = snapshot.capture(board) else {
+        persist.send(PersistRequest {
+            path: path.into(),
+            contents: stamp(SaveData::VERSION, data),
+        });
+        return None;
+    };
+    // A newer save of the same file replaces the waiting one
+    pending
+        .0
+        .retain(|(_, pending_path, _)| *pending_path != path);
+    pending.0.push((image.clone(), path, data));
+    Some(image)
+}
+
+pub fn write_pending_saves(
+    mut pending: ResMut<PendingSaves>,
+    mut ready: EventReader<SnapshotReadyEvent>,
+    mut persist: EventWriter<PersistRequest>,
+) {
+    for snapshot in ready.read() {
+        let Some(index) = pending
+            .0
+            .iter()
+            .position(|(image, ..)| *image == snapshot.image)
+        else {
+            continue;
+        };
+        let (_, path, mut data) = pending.0.swap_remove(index);
+        data.thumbnail = Some(snapshot.png.clone());
+        persist.send(PersistRequest {
+            path: path.into(),
+            contents: stamp(SaveData::VERSION, data),
+        });
+    }
+}
+
+#[derive(Resource)]
+pub struct Autosave {
+    pub every_placements: usize,
+    pub enabled: bool,
+}
+impl Default for Autosave {
+    fn default() -> Self {
+        Self {
+            every_placements: 5,
+            enabled: true,
+        }
+    }
+}
+
+/// Offers to resume the autosave left behind by the previous session.
+#[derive(Component)]
+pub struct ResumePrompt;
+
+/// The autosave's thumbnail next to the [`ResumePrompt`].
+#[derive(Component)]
+pub struct ResumeThumbnail;
+
+/// Writes the autosave every few placements, off the main thread.
+pub fn autosave(
+    autosave: Res<Autosave>,
+    play_time: Res<PlayTime>,
+    mut snapshot: BoardSnapshot,
+    mut pending: ResMut<PendingSaves>,
+    mut persist: EventWriter<PersistRequest>,
+    mut placements: EventReader<PlacementEvent>,
+    mut placed_count: Local<usize>,
+    q_board: Query<(&Board, &Score), (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+) {
+    let mut placed = 0;
+    let mut board = None;
+    for event in placements
+        .read()
+        .filter(|event| q_board.contains(event.board))
+    {
+        placed += 1;
+        board = Some(event.board);
+    }
+    let Some(board) = board.filter(|_| autosave.enabled) else {
+        return;
+    };
+    let every = autosave.every_placements.max(1);
+    let due = (*placed_count + placed) / every > *placed_count / every;
+    *placed_count += placed;
+    let (Ok((grid, score)), Ok(&selected)) = (q_board.get(board), q_selected_shape.get_single())
+    else {
+        return;
+    };
+    if !due {
+        return;
+    }
+
+    let data = SaveData {
+        cells: grid.grid.cells,
+        selected,
+        play_time: play_time.0,
+        score: score.0,
+        thumbnail: None,
+    };
+    persist_save(
+        (&mut snapshot, &mut pending, &mut persist),
+        board,
+        AUTOSAVE_PATH.into(),
+        data,
+    );
+}
+
+/// Offers to resume the autosave if there is one that can be resumed.
+pub fn spawn_resume_prompt(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    registry: Res<ShapeRegistry>,
+    dir: Res<DataDir>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if !dir.path(AUTOSAVE_PATH).exists() {
+        return;
+    }
+    let Some(data) = read_save(&mut commands, &strings, &registry, &dir, AUTOSAVE_PATH) else {
+        return;
+    };
+    if let Some(image) = data.thumbnail.as_deref().and_then(decode_png) {
+        commands.spawn((
+            ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(36.),
+                    left: Val::Px(8.),
+                    width: Val::Px(THUMBNAIL_SIZE),
+                    height: Val::Px(THUMBNAIL_SIZE),
+                    ..default()
+                },
+                image: UiImage::new(images.add(image)),
+                ..default()
+            },
+            ResumeThumbnail,
+        ));
+    }
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                strings.get("save.resume"),
+                TextStyle {
+                    font_size: 20.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.),
+                left: Val::Px(8.),
+                ..default()
+            },
+            ..default()
+        },
+        ResumePrompt,
+    ));
+}
+
+pub fn relabel_resume_prompt(
+    strings: Res<Strings>,
+    mut q_prompt: Query<&mut Text, With<ResumePrompt>>,
+) {
+    for mut text in q_prompt.iter_mut() {
+        text.sections[0].value = strings.get("save.resume").to_owned();
+    }
+}
+
+/// Restores the autosave on Enter, the prompt goes away once the player places a
+/// shape instead.
+pub fn resume_autosave(
+    mut commands: Commands,
+    input_keys: Res<Input<KeyCode>>,
+    strings: Res<Strings>,
+    registry: Res<ShapeRegistry>,
+    dir: Res<DataDir>,
+    mut placements: EventReader<PlacementEvent>,
+    mut loads: EventWriter<LoadSaveEvent>,
+    q_prompt: Query<Entity, With<ResumePrompt>>,
+    q_thumbnails: Query<Entity, With<ResumeThumbnail>>,
+) {
+    let Ok(prompt) = q_prompt.get_single() else {
+        return;
+    };
+    let placed = placements.read().count() > 0;
+    if !placed && !input_keys.just_pressed(KeyCode::Return) {
+        return;
+    }
+    for entity in std::iter::once(prompt).chain(q_thumbnails.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    if placed {
+        return;
+    }
+    if let Some(data) = read_save(&mut commands, &strings, &registry, &dir, AUTOSAVE_PATH) {
+        loads.send(LoadSaveEvent(data));
+    }
+}
+
+/// Puts a loaded save's board, shape, play time and score in place of the game's.
+pub fn apply_save(
+    mut commands: Commands,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    mut play_time: ResMut<PlayTime>,
+    mut loads: EventReader<LoadSaveEvent>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut q_board: Query<(&mut Board, &mut Score, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_selected_shape: Query<
+        (Entity, &mut ShapeInstance, &Children, &Transform),
+        With<SelectedShape>,
+    >,
+    mut q_sprites: Query<&mut Sprite>,
+) {
+    let Some(LoadSaveEvent(data)) = loads.read().last() else {
+        return;
+    };
+    let Ok((mut board, mut score, board_entity)) = q_board.get_single_mut() else {
+        return;
+    };
+    board.grid.set_cells(data.cells);
+    play_time.0 = data.play_time;
+    score.0 = data.score;
+    board_changed.send(BoardChangedEvent {
+        board: board_entity,
+        revision: board.grid.revision(),
+        cells: board.grid.positions().collect(),
+    });
+
+    if let Ok((entity, instance, children, &transform)) = q_selected_shape.get_single_mut() {
+        replace_selected_shape(
+            &mut commands,
+            (&registry, &palette),
+            (entity, instance, children),
+            &mut q_sprites,
+            data.selected,
+            transform,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saves of every format version, of the same game.
+    const V0: &str = include_str!("../tests/fixtures/save_v0.txt");
+    const V1: &str = include_str!("../tests/fixtures/save_v1.txt");
+    const V2: &str = include_str!("../tests/fixtures/save_v2.txt");
+
+    fn assert_fixture_board(data: &SaveData) {
+        let cells = &data.cells.0;
+        assert_eq!(cells[0][..4], [Some(TileColor::Red); 4]);
+        assert_eq!(
+            cells[0][16..],
+            [
+                Some(TileColor::Green),
+                Some(TileColor::Green),
+                None,
+                Some(TileColor::Stone)
+            ]
+        );
+        assert_eq!(cells[1][0], Some(TileColor::Blue));
+        assert_eq!(cells[1][19], Some(TileColor::Cracked));
+        assert_eq!(cells[19][19], Some(TileColor::Gray));
+        assert_eq!(cells.iter().flatten().flatten().count(), 10);
+        assert!(
+            data.selected
+                == ShapeInstance {
+                    id: ShapeId(2),
+                    rotation: 1,
+                    color: TileColor::Green,
+                }
+        );
+    }
+
+    #[test]
+    fn version_0_saves_migrate_without_time_or_score() {
+        let data = load_versioned::<SaveData>(V0).unwrap();
+        assert_fixture_board(&data);
+        assert_eq!((data.play_time, data.score), (0., 0));
+    }
+
+    #[test]
+    fn version_1_saves_migrate_without_score() {
+        let data = load_versioned::<SaveData>(V1).unwrap();
+        assert_fixture_board(&data);
+        assert_eq!((data.play_time, data.score), (83.5, 0));
+    }
+
+    #[test]
+    fn version_2_saves_load_and_write_back_the_same() {
+        let data = load_versioned::<SaveData>(V2).unwrap();
+        assert_fixture_board(&data);
+        assert_eq!((data.play_time, data.score), (83.5, 1240));
+        assert_eq!(stamp(SaveData::VERSION, data), V2);
+    }
+
+    #[test]
+    fn thumbnails_write_back_and_broken_ones_are_dropped() {
+        let mut data = load_versioned::<SaveData>(V2).unwrap();
+        data.thumbnail = Some(vec![0x89, b'P', b'N', b'G', 0, 255]);
+        let text = stamp(SaveData::VERSION, data.clone());
+        assert!(text.ends_with("thumbnail iVBORwD/\n"));
+        assert_eq!(
+            load_versioned::<SaveData>(&text).unwrap().thumbnail,
+            data.thumbnail
+        );
+
+        let broken = format!("{V2}thumbnail not base64!\n");
+        let data = load_versioned::<SaveData>(&broken).unwrap();
+        assert_fixture_board(&data);
+        assert_eq!(data.thumbnail, None);
+    }
+
+    #[test]
+    fn saves_of_a_newer_version_are_refused() {
+        let newer = V2.replacen("version 2", "version 3", 1);
+        assert!(matches!(
+            load_versioned::<SaveData>(&newer),
+            Err(LoadError::Newer(3))
+        ));
+    }
+}
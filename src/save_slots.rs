@@ -0,0 +1,545 @@
+//! Three save slots next to the autosave, on a screen opened with F10 while playing.
+//! Each slot shows a snapshot of its board, its mode, score, play time and when it was
+//! saved. Overwriting a filled slot and deleting one ask for confirmation first, F5
+//! saves to the slot used last without asking.
+//!
+//! Every slot is a save file of its own, see [`slot_path`]. What the screen writes of
+//! them is kept in the small `saves.txt` index, only the thumbnails are read from the
+//! slots' files, once the screen opens.
+
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{
+    click_guard::ModalUi,
+    menu::{GameMode, GameState},
+    persistence::{slot_path, DataDir, PersistAppExt, PersistRequest, Persisted},
+    play_time::PlayTime,
+    save::{persist_save, read_save, read_thumbnail, LoadSaveEvent, PendingSaves, SaveData},
+    scoring::Score,
+    shape::{ShapeInstance, ShapeRegistry},
+    snapshot::BoardSnapshot,
+    strings::{t, Strings},
+    toast::spawn_toast,
+    Board, MainBoard, OverlayBoard, SelectedShape,
+};
+
+pub const SLOTS: usize = 3;
+/// Slot files are `save_1.txt` and on.
+const SLOT_STEM: &str = "save";
+/// Size of a slot's thumbnail on screen, in pixels.
+const THUMBNAIL_SIZE: f32 = 96.;
+const SELECTED_COLOR: Color = Color::rgb(0.25, 0.25, 0.35);
+const EMPTY_THUMBNAIL_COLOR: Color = Color::rgb(0.1, 0.1, 0.1);
+
+/// What the screen shows of a filled slot.
+#[derive(Clone, Copy)]
+pub struct SlotInfo {
+    pub mode: GameMode,
+    pub score: u64,
+    /// Seconds played, see [`PlayTime`].
+    pub play_time: f32,
+    /// Seconds since the Unix epoch.
+    pub saved_at: u64,
+}
+
+/// The `saves.txt` index of the slots.
+#[derive(Resource, Default)]
+pub struct SlotIndex {
+    pub slots: [Option<SlotInfo>; SLOTS],
+    /// Slot saved to or loaded from last, the one quick saves go to.
+    pub last: Option<usize>,
+}
+impl SlotIndex {
+    /// The slot used last, before that the first empty one.
+    fn quick_save_slot(&self) -> usize {
+        self.last
+            .or_else(|| self.slots.iter().position(Option::is_none))
+            .unwrap_or(0)
+    }
+}
+impl Persisted for SlotIndex {
+    const PATH: &'static str = "saves.txt";
+
+    /// Reads an optional `last <slot>` line and a
+    /// `slot <slot> <mode> <score> <play time> <saved at>` line per filled slot, slots
+    /// counting from 1. The board rows older indexes followed them with are skipped.
+    fn parse(text: &str) -> Option<Self> {
+        let mut index = Self::default();
+        for line in text.lines() {
+            if let Some(last) = line.strip_prefix("last ") {
+                index.last = parse_slot(last);
+                continue;
+            }
+            let Some(fields) = line.strip_prefix("slot ") else {
+                continue;
+            };
+            let mut fields = fields.split(' ');
+            let slot = parse_slot(fields.next()?)?;
+            let info = SlotInfo {
+                mode: GameMode::parse(fields.next()?)?,
+                score: fields.next()?.parse().ok()?,
+                play_time: fields.next()?.parse().ok()?,
+                saved_at: fields.next()?.parse().ok()?,
+            };
+            index.slots[slot] = Some(info);
+        }
+        Some(index)
+    }
+}
+impl fmt::Display for SlotIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(last) = self.last {
+            writeln!(f, "last {}", last + 1)?;
+        }
+        for (slot, info) in self.slots.iter().enumerate() {
+            let Some(info) = info else {
+                continue;
+            };
+            writeln!(
+                f,
+                "slot {} {} {} {} {}",
+                slot + 1,
+                info.mode.name(),
+                info.score,
+                info.play_time,
+                info.saved_at
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A slot as written in files, counting from 1.
+fn parse_slot(text: &str) -> Option<usize> {
+    let slot = text.trim().parse::<usize>().ok()?.checked_sub(1)?;
+    (slot < SLOTS).then_some(slot)
+}
+
+/// What the screen's keys do to the selected slot.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SlotAction {
+    Save,
+    Load,
+    Delete,
+}
+
+#[derive(Resource, Default)]
+struct SlotScreen {
+    open: bool,
+    selected: usize,
+    /// Action waiting for Y, shown in place of the key hints.
+    confirm: Option<SlotAction>,
+}
+
+#[derive(Component)]
+struct SlotScreenRoot;
+
+#[derive(Component)]
+struct SlotRow(usize);
+
+#[derive(Component)]
+struct SlotImage(usize);
+
+#[derive(Component)]
+struct SlotText(usize);
+
+#[derive(Component)]
+struct SlotPrompt;
+
+pub struct SaveSlotsPlugin;
+impl Plugin for SaveSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.load_persisted::<SlotIndex>()
+            .init_resource::<SlotScreen>()
+            .add_systems(OnExit(GameState::Playing), close_screen)
+            // Before the game sees the keys the open screen takes
+            .add_systems(
+                PreUpdate,
+                slot_input
+                    .after(InputSystem)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(Update, (update_screen, load_thumbnails));
+    }
+}
+
+/// F5 quick saves and F10 opens the screen. While it's open, the screen takes every
+/// key and click, the game sees none.
+fn slot_input(
+    mut commands: Commands,
+    (strings, registry, mode, play_time, dir): (
+        Res<Strings>,
+        Res<ShapeRegistry>,
+        Res<GameMode>,
+        Res<PlayTime>,
+        Res<DataDir>,
+    ),
+    (mut input_keys, mut input_mb): (ResMut<Input<KeyCode>>, ResMut<Input<MouseButton>>),
+    (mut screen, mut index): (ResMut<SlotScreen>, ResMut<SlotIndex>),
+    (mut snapshot, mut pending): (BoardSnapshot, ResMut<PendingSaves>),
+    mut persist: EventWriter<PersistRequest>,
+    mut loads: EventWriter<LoadSaveEvent>,
+    q_board: Query<(Entity, &Board, &Score), (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+    q_root: Query<Entity, With<SlotScreenRoot>>,
+    mut q_images: Query<(&SlotImage, &mut UiImage, &mut BackgroundColor)>,
+) {
+    let current = || {
+        let (entity, board, score) = q_board.get_single().ok()?;
+        let data = SaveData {
+            cells: board.grid.cells,
+            selected: *q_selected_shape.get_single().ok()?,
+            play_time: play_time.0,
+            score: score.0,
+            thumbnail: None,
+        };
+        Some((entity, data))
+    };
+    if !screen.open {
+        if input_keys.just_pressed(KeyCode::F10) {
+            *screen = SlotScreen {
+                open: true,
+                selected: index.last.unwrap_or(0),
+                confirm: None,
+            };
+            spawn_screen(&mut commands);
+        } else if input_keys.just_pressed(KeyCode::F5) {
+            if let Some((board, data)) = current() {
+                let slot = index.quick_save_slot();
+                save_to_slot(
+                    &mut commands,
+                    &strings,
+                    (&mut snapshot, &mut pending, &mut persist),
+                    &mut index,
+                    slot,
+                    (board, data, *mode),
+                );
+            }
+        }
+        return;
+    }
+
+    let slot = screen.selected;
+    let filled = index.slots[slot].is_some();
+    let mut action = None;
+    if let Some(confirm) = screen.confirm {
+        if input_keys.just_pressed(KeyCode::Y) {
+            action = Some(confirm);
+            screen.confirm = None;
+        } else if input_keys.any_just_pressed([KeyCode::N, KeyCode::Escape]) {
+            screen.confirm = None;
+        }
+    } else if input_keys.any_just_pressed([KeyCode::Escape, KeyCode::F10]) {
+        screen.open = false;
+    } else if input_keys.just_pressed(KeyCode::Up) {
+        screen.selected = (slot + SLOTS - 1) % SLOTS;
+    } else if input_keys.just_pressed(KeyCode::Down) {
+        screen.selected = (slot + 1) % SLOTS;
+    } else if input_keys.just_pressed(KeyCode::S) {
+        if filled {
+            screen.confirm = Some(SlotAction::Save);
+        } else {
+            action = Some(SlotAction::Save);
+        }
+    } else if input_keys.any_just_pressed([KeyCode::L, KeyCode::Return]) && filled {
+        action = Some(SlotAction::Load);
+    } else if input_keys.just_pressed(KeyCode::Delete) && filled {
+        screen.confirm = Some(SlotAction::Delete);
+    }
+    input_keys.clear();
+    input_mb.clear();
+
+    match action {
+        Some(SlotAction::Save) => {
+            let Some((board, data)) = current() else {
+                return;
+            };
+            let image = save_to_slot(
+                &mut commands,
+                &strings,
+                (&mut snapshot, &mut pending, &mut persist),
+                &mut index,
+                slot,
+                (board, data, *mode),
+            );
+            // The capture shows the board as soon as it rendered
+            for (slot_image, mut ui_image, mut color) in q_images.iter_mut() {
+                if slot_image.0 == slot {
+                    match &image {
+                        Some(image) => {
+                            *ui_image = UiImage::new(image.clone());
+                            *color = Color::WHITE.into();
+                        }
+                        None => {
+                            *ui_image = UiImage::default();
+                            *color = EMPTY_THUMBNAIL_COLOR.into();
+                        }
+                    }
+                }
+            }
+        }
+        Some(SlotAction::Load) => {
+            let Some(info) = index.slots[slot] else {
+                return;
+            };
+            // Loading the board into a game of another mode would mix up their rules
+            if info.mode != *mode {
+                spawn_toast(
+                    &mut commands,
+                    t!(
+                        strings,
+                        "toast.slot_mode",
+                        slot + 1,
+                        strings.get(info.mode.label())
+                    ),
+                );
+                return;
+            }
+            let path = slot_path(SLOT_STEM, slot);
+            let Some(data) = read_save(&mut commands, &strings, &registry, &dir, &path) else {
+                return;
+            };
+            loads.send(LoadSaveEvent(data));
+            index.last = Some(slot);
+            persist.send(index.persist_request());
+            spawn_toast(&mut commands, t!(strings, "toast.slot_loaded", slot + 1));
+            screen.open = false;
+        }
+        Some(SlotAction::Delete) => {
+            let path = slot_path(SLOT_STEM, slot);
+            match std::fs::remove_file(dir.path(&path)) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => error!("failed to delete {path}: {err}"),
+            }
+            index.slots[slot] = None;
+            if index.last == Some(slot) {
+                index.last = None;
+            }
+            persist.send(index.persist_request());
+            spawn_toast(&mut commands, t!(strings, "toast.slot_deleted", slot + 1));
+        }
+        None => {}
+    }
+    if !screen.open {
+        despawn_screen(&mut commands, &q_root);
+    }
+}
+
+/// Writes the save to the slot's file, with the board's thumbnail once it was captured,
+/// and its entry to the index. The capture, if any, to show until then.
+fn save_to_slot(
+    commands: &mut Commands,
+    strings: &Strings,
+    (snapshot, pending, persist): (
+        &mut BoardSnapshot,
+        &mut PendingSaves,
+        &mut EventWriter<PersistRequest>,
+    ),
+    index: &mut SlotIndex,
+    slot: usize,
+    (board, data, mode): (Entity, SaveData, GameMode),
+) -> Option<Handle<Image>> {
+    index.slots[slot] = Some(SlotInfo {
+        mode,
+        score: data.score,
+        play_time: data.play_time,
+        saved_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+    });
+    let path = slot_path(SLOT_STEM, slot);
+    let image = persist_save((snapshot, pending, persist), board, path, data);
+    index.last = Some(slot);
+    persist.send(index.persist_request());
+    spawn_toast(commands, t!(strings, "toast.slot_saved", slot + 1));
+    image
+}
+
+fn spawn_screen(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.8).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+            SlotScreenRoot,
+            ModalUi,
+        ))
+        .with_children(|commands| {
+            commands.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.,
+                        ..default()
+                    },
+                ),
+                SlotPrompt,
+            ));
+            for slot in 0..SLOTS {
+                commands
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Px(560.),
+                                align_items: AlignItems::Center,
+                                column_gap: Val::Px(12.),
+                                padding: UiRect::all(Val::Px(8.)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        SlotRow(slot),
+                    ))
+                    .with_children(|commands| {
+                        commands.spawn((
+                            ImageBundle {
+                                style: Style {
+                                    width: Val::Px(THUMBNAIL_SIZE),
+                                    height: Val::Px(THUMBNAIL_SIZE),
+                                    ..default()
+                                },
+                                background_color: EMPTY_THUMBNAIL_COLOR.into(),
+                                ..default()
+                            },
+                            SlotImage(slot),
+                        ));
+                        commands.spawn((
+                            TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font_size: 20.,
+                                    ..default()
+                                },
+                            ),
+                            SlotText(slot),
+                        ));
+                    });
+            }
+        });
+}
+
+fn despawn_screen(commands: &mut Commands, q_root: &Query<Entity, With<SlotScreenRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Leaving the game, e.g. through the console, closes the screen.
+fn close_screen(
+    mut commands: Commands,
+    mut screen: ResMut<SlotScreen>,
+    q_root: Query<Entity, With<SlotScreenRoot>>,
+) {
+    *screen = SlotScreen::default();
+    despawn_screen(&mut commands, &q_root);
+}
+
+/// Rewrites the slots' texts and the prompt, and highlights the selected slot.
+fn update_screen(
+    strings: Res<Strings>,
+    (screen, index): (Res<SlotScreen>, Res<SlotIndex>),
+    mut q_rows: Query<(&SlotRow, &mut BackgroundColor), Without<SlotImage>>,
+    mut q_images: Query<(&SlotImage, &mut UiImage, &mut BackgroundColor), Without<SlotRow>>,
+    mut q_texts: Query<(&SlotText, &mut Text), Without<SlotPrompt>>,
+    mut q_prompt: Query<&mut Text, With<SlotPrompt>>,
+) {
+    if !screen.open || !(screen.is_changed() || index.is_changed() || strings.is_changed()) {
+        return;
+    }
+    for (row, mut color) in q_rows.iter_mut() {
+        *color = if row.0 == screen.selected {
+            SELECTED_COLOR.into()
+        } else {
+            Color::NONE.into()
+        };
+    }
+    // Filled slots get their thumbnail from `load_thumbnails`
+    for (image, mut ui_image, mut color) in q_images.iter_mut() {
+        if index.slots[image.0].is_none() {
+            *ui_image = UiImage::default();
+            *color = EMPTY_THUMBNAIL_COLOR.into();
+        }
+    }
+    for (text, mut section) in q_texts.iter_mut() {
+        let slot = text.0;
+        section.sections[0].value = match &index.slots[slot] {
+            Some(info) => t!(
+                strings,
+                "slots.entry",
+                slot + 1,
+                strings.get(info.mode.label()),
+                strings.number(info.score),
+                PlayTime(info.play_time).format(),
+                format_timestamp(info.saved_at),
+            ),
+            None => t!(strings, "slots.empty", slot + 1),
+        };
+    }
+    if let Ok(mut prompt) = q_prompt.get_single_mut() {
+        let slot = screen.selected + 1;
+        prompt.sections[0].value = match screen.confirm {
+            Some(SlotAction::Save) => t!(strings, "slots.confirm_overwrite", slot),
+            Some(SlotAction::Delete) => t!(strings, "slots.confirm_delete", slot),
+            Some(SlotAction::Load) | None => strings.get("slots.hint").to_owned(),
+        };
+    }
+}
+
+/// Shows the thumbnails kept in the filled slots' saves once the screen opened.
+fn load_thumbnails(
+    index: Res<SlotIndex>,
+    dir: Res<DataDir>,
+    mut images: ResMut<Assets<Image>>,
+    mut q_images: Query<(&SlotImage, &mut UiImage, &mut BackgroundColor), Added<SlotImage>>,
+) {
+    for (slot_image, mut ui_image, mut color) in q_images.iter_mut() {
+        let slot = slot_image.0;
+        if index.slots[slot].is_none() {
+            continue;
+        }
+        let Some(image) = read_thumbnail(&dir, &slot_path(SLOT_STEM, slot)) else {
+            continue;
+        };
+        *ui_image = UiImage::new(images.add(image));
+        *color = Color::WHITE.into();
+    }
+}
+
+/// `YYYY-MM-DD hh:mm` in UTC of the seconds since the Unix epoch.
+fn format_timestamp(seconds: u64) -> String {
+    let minutes = seconds % 86_400 / 60;
+    // Days to the civil calendar, counting in 400 year eras from 0000-03-01
+    let days = seconds / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months from March
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{year}-{month:02}-{day:02} {:02}:{:02}",
+        minutes / 60,
+        minutes % 60
+    )
+}
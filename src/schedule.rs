@@ -0,0 +1,233 @@
+//! Hard mode events, scheduled a few turns ahead and announced on the HUD before
+//! they reach the board through [`BoardCommand`]s.
+
+use bevy::prelude::*;
+use rand::{seq::IteratorRandom, Rng};
+use smallvec::smallvec;
+
+use crate::{
+    board::TileColor, events::BoardCommand, scoring::pick_garbage, shape::ShapeRegistry,
+    systems::GameRng, systems::HardMode, systems::FREEZE_PLACEMENTS, toast::spawn_toast,
+    BlocksSystems, Board, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+/// Turns between two garbage drops, and cells each drop fills.
+const GARBAGE_INTERVAL: u32 = 8;
+const GARBAGE_CELLS: usize = 4;
+/// Turns between two cell freezes.
+const FREEZE_INTERVAL: u32 = 6;
+/// Turns between two garbage rows pushing the board up.
+const GARBAGE_ROW_INTERVAL: u32 = 15;
+/// Turns between two quarter turns of the board.
+const ROTATE_INTERVAL: u32 = 20;
+
+/// Placements on the main board since the start of the game.
+#[derive(Resource, Default)]
+pub struct TurnCounter(pub u32);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledKind {
+    /// Fills this many empty cells with stone.
+    Garbage(usize),
+    /// Freezes one filled cell.
+    Freeze,
+    /// Pushes the board up by this many garbage rows.
+    GarbageRows(usize),
+    /// Turns the board a quarter clockwise.
+    Rotate,
+}
+impl ScheduledKind {
+    fn label(&self) -> &'static str {
+        match self {
+            ScheduledKind::Garbage(_) => "Garbage",
+            ScheduledKind::Freeze => "Freeze",
+            ScheduledKind::GarbageRows(_) => "Rising",
+            ScheduledKind::Rotate => "Rotating",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ScheduledEvent {
+    /// Turn the event fires on, once [`TurnCounter`] reached it.
+    pub turn: u32,
+    pub kind: ScheduledKind,
+}
+
+/// Events waiting for their turn, in no particular order.
+#[derive(Resource, Default)]
+pub struct UpcomingEvents(pub Vec<ScheduledEvent>);
+
+#[derive(Component)]
+struct UpcomingText;
+
+pub struct SchedulePlugin;
+impl Plugin for SchedulePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TurnCounter>()
+            .init_resource::<UpcomingEvents>()
+            .add_systems(Startup, spawn_upcoming_text)
+            .add_systems(
+                Update,
+                (
+                    count_turns,
+                    schedule_hard_mode_events,
+                    run_scheduled_events,
+                    update_upcoming_text,
+                )
+                    .chain()
+                    .after(BlocksSystems::Resolution),
+            );
+    }
+}
+
+fn count_turns(
+    mut turns: ResMut<TurnCounter>,
+    mut placements: EventReader<PlacementEvent>,
+    q_main_board: Query<(), With<MainBoard>>,
+) {
+    for event in placements.read() {
+        if q_main_board.contains(event.board) {
+            turns.0 += 1;
+        }
+    }
+}
+
+/// Keeps one of each event scheduled at all times in hard mode.
+fn schedule_hard_mode_events(
+    hard_mode: Res<HardMode>,
+    turns: Res<TurnCounter>,
+    mut upcoming: ResMut<UpcomingEvents>,
+) {
+    if !hard_mode.0 {
+        return;
+    }
+    let kinds = [
+        (ScheduledKind::Garbage(GARBAGE_CELLS), GARBAGE_INTERVAL),
+        (ScheduledKind::Freeze, FREEZE_INTERVAL),
+        (ScheduledKind::GarbageRows(1), GARBAGE_ROW_INTERVAL),
+        (ScheduledKind::Rotate, ROTATE_INTERVAL),
+    ];
+    for (kind, interval) in kinds {
+        if !upcoming.0.iter().any(|event| event.kind == kind) {
+            upcoming.0.push(ScheduledEvent {
+                turn: turns.0 + interval,
+                kind,
+            });
+        }
+    }
+}
+
+/// Fires the due events. One that can't apply without ending the game right away is
+/// deferred by a turn.
+fn run_scheduled_events(
+    mut commands: Commands,
+    turns: Res<TurnCounter>,
+    registry: Res<ShapeRegistry>,
+    mut rng: ResMut<GameRng>,
+    mut upcoming: ResMut<UpcomingEvents>,
+    mut board_commands: EventWriter<BoardCommand>,
+    q_main_board: Query<(&Board, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    if !turns.is_changed() {
+        return;
+    }
+    let Ok((board, board_entity)) = q_main_board.get_single() else {
+        return;
+    };
+    let turn = board.grid.turn();
+    for event in upcoming.0.iter_mut().filter(|event| event.turn <= turns.0) {
+        let command = match event.kind {
+            ScheduledKind::Garbage(count) => {
+                let cells = pick_garbage(&board.grid, &registry, &mut rng, count);
+                (cells.len() == count).then_some(BoardCommand::Fill {
+                    board: board_entity,
+                    cells,
+                    color: Some(TileColor::Stone),
+                })
+            }
+            ScheduledKind::Freeze => board
+                .grid
+                .positions()
+                .filter(|&(x, y)| {
+                    board.grid.cells.0[y][x].is_some() && !board.grid.meta.0[y][x].is_frozen(turn)
+                })
+                .choose(&mut rng.rng)
+                .map(|cell| BoardCommand::Freeze {
+                    board: board_entity,
+                    cells: smallvec![cell],
+                    placements: FREEZE_PLACEMENTS,
+                }),
+            ScheduledKind::GarbageRows(count) => {
+                let gaps: Vec<usize> = (0..count)
+                    .map(|_| rng.rng.gen_range(0..board.grid.width()))
+                    .collect();
+                let mut preview = board.grid;
+                preview
+                    .insert_garbage_rows(count, |row| gaps[row])
+                    .is_ok()
+                    .then_some(BoardCommand::GarbageRows {
+                        board: board_entity,
+                        gaps,
+                    })
+            }
+            ScheduledKind::Rotate => Some(BoardCommand::Rotate90 {
+                board: board_entity,
+            }),
+        };
+        match command {
+            Some(command) => board_commands.send(command),
+            None => {
+                event.turn = turns.0 + 1;
+                warn!("{} deferred by a turn", event.kind.label());
+                spawn_toast(
+                    &mut commands,
+                    format!("{} delayed, no room", event.kind.label()),
+                );
+            }
+        }
+    }
+    upcoming.0.retain(|event| event.turn > turns.0);
+}
+
+fn spawn_upcoming_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(32.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            ..default()
+        },
+        UpcomingText,
+    ));
+}
+
+/// Lists the upcoming events soonest first, e.g. "Garbage in 3".
+fn update_upcoming_text(
+    turns: Res<TurnCounter>,
+    upcoming: Res<UpcomingEvents>,
+    mut q_text: Query<&mut Text, With<UpcomingText>>,
+) {
+    if !upcoming.is_changed() && !turns.is_changed() {
+        return;
+    }
+    let mut events = upcoming.0.clone();
+    events.sort_by_key(|event| event.turn);
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value = events
+            .iter()
+            .map(|event| format!("{} in {}", event.kind.label(), event.turn - turns.0))
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
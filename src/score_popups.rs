@@ -0,0 +1,52 @@
+//! Floating "+points" text where a placement scored, rising and fading over a second.
+//! Totals of placements clearing several lines at once show larger.
+
+use bevy::prelude::*;
+
+use crate::{render_order, scoring::ScoreBreakdownEvent, strings::Strings, tween::Tween};
+
+const POPUP_SECONDS: f32 = 1.;
+/// World units the text rises while it fades.
+const RISE: f32 = 40.;
+const FONT_SIZE: f32 = 22.;
+/// Font size of the total of a multi-line clear.
+const MULTI_CLEAR_FONT_SIZE: f32 = 34.;
+
+pub struct ScorePopupsPlugin;
+impl Plugin for ScorePopupsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_score_popups);
+    }
+}
+
+fn spawn_score_popups(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    mut breakdowns: EventReader<ScoreBreakdownEvent>,
+) {
+    for breakdown in breakdowns.read() {
+        let font_size = if breakdown.lines >= 2 {
+            MULTI_CLEAR_FONT_SIZE
+        } else {
+            FONT_SIZE
+        };
+        let start = breakdown.world_anchor.extend(render_order::POPUPS);
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    format!("+{}", strings.number(breakdown.total)),
+                    TextStyle {
+                        font_size,
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_translation(start),
+                ..default()
+            },
+            Tween::new(POPUP_SECONDS)
+                .with_translation(start, start + Vec3::Y * RISE)
+                .with_alpha(1., 0.)
+                .despawn_on_finish(),
+        ));
+    }
+}
@@ -0,0 +1,412 @@
+//! Score and level progression. Every [`LINES_PER_LEVEL`] cleared lines raise the
+//! level, which multiplies the score, favors larger shapes and drops some stone
+//! obstacles. Bonus cells multiplying the clears through them appear at the start and
+//! every few clears.
+
+use bevy::prelude::*;
+use rand::{seq::IteratorRandom, Rng};
+use smallvec::smallvec;
+
+use crate::{
+    board::{Board as BoardGrid, CellMeta, TileColor},
+    events::BoardCommand,
+    layout::LayoutSlot,
+    rules::RuleSet,
+    shape::ShapeRegistry,
+    strings::{t, Strings},
+    systems::GameRng,
+    systems::ShapeWeights,
+    zones::ZoneMap,
+    BlocksSystems, Board, CellList, LinesClearedEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+pub const LINES_PER_LEVEL: u32 = 10;
+/// Points per cleared line, before the level multiplier.
+const LINE_POINTS: u64 = 10;
+/// Bonus points per destroyed obstacle, before the level multiplier.
+const OBSTACLE_POINTS: u64 = 25;
+/// Bonus points per cleared puzzle target, before the level multiplier.
+const TARGET_POINTS: u64 = 50;
+/// Points per zone of every cleared cell, before the multipliers.
+const ZONE_POINTS: u64 = 1;
+/// Clears between two drops of [`RuleSet::bonus_cells`].
+const BONUS_EVERY_CLEARS: u32 = 5;
+/// Highest multiplier several bonus cells in one clear add up to.
+const MAX_MULTIPLIER: u32 = 9;
+/// How much each level raises the exponent of a shape's cell count in its weight.
+const SIZE_BIAS_PER_LEVEL: f32 = 0.25;
+/// Garbage cells dropped on level-up per level above the first, at most.
+const GARBAGE_PER_LEVEL: usize = 2;
+
+/// Points of the game played on a board, kept on the board itself so boards played
+/// side by side, like the menu's demo or a versus opponent, score apart.
+#[derive(Component, Default)]
+pub struct Score(pub u64);
+
+/// Level reached on a board, on the board along with its [`Score`].
+#[derive(Component)]
+pub struct Level {
+    pub level: u32,
+    /// Lines cleared since the start of the game.
+    pub lines: u32,
+}
+impl Default for Level {
+    fn default() -> Self {
+        Self { level: 1, lines: 0 }
+    }
+}
+
+/// Placements in a row that cleared lines on a board, 0 once one didn't. Kept on the
+/// board along with its [`Score`].
+#[derive(Component, Default)]
+pub struct Combo(pub u32);
+
+/// What a placement on the main board earned, sent once its clears were scored, for
+/// showing the points where they were made.
+#[derive(Event, Clone, Debug)]
+pub struct ScoreBreakdownEvent {
+    pub placement_cells: CellList,
+    /// Points for the placed cells.
+    pub base_points: u64,
+    /// Points for the cleared lines and what they removed, before bonus multipliers.
+    pub line_points: u64,
+    /// Highest bonus multiplier a clear got, 1 without any.
+    pub combo_multiplier: u32,
+    /// Bonus cells among the cleared cells.
+    pub bonus_cells: usize,
+    /// Lines cleared, those of a gravity cascade included.
+    pub lines: usize,
+    /// Everything the placement added to the score.
+    pub total: u64,
+    /// World position of the middle of the placed cells.
+    pub world_anchor: Vec2,
+}
+
+/// Breakdown of this frame's placement, sent once its clears were scored.
+#[derive(Resource, Default)]
+struct PendingBreakdown(Option<ScoreBreakdownEvent>);
+
+#[derive(Component)]
+struct ScoreText;
+
+pub struct ScoringPlugin;
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingBreakdown>()
+            .add_event::<ScoreBreakdownEvent>()
+            .add_systems(Startup, spawn_score_text)
+            .add_systems(
+                Update,
+                (
+                    score_placements.after(BlocksSystems::Placement),
+                    score_lines
+                        .after(BlocksSystems::Resolution)
+                        .after(score_placements),
+                    update_combos.after(BlocksSystems::Resolution),
+                    (update_score_text, send_breakdown)
+                        .after(score_lines)
+                        .after(update_combos),
+                ),
+            );
+    }
+}
+
+/// Weights favoring shapes with more cells the higher the level, 0 for the ones out of
+/// the pool.
+pub fn level_weights(registry: &ShapeRegistry, level: u32) -> Vec<f32> {
+    let bias = (level - 1) as f32 * SIZE_BIAS_PER_LEVEL;
+    registry
+        .iter()
+        .map(|(id, rotations)| {
+            if !registry.in_pool(id) {
+                return 0.;
+            }
+            let cells = rotations[0].bits().count_ones() as f32;
+            rotations.len() as f32 * cells.powf(bias)
+        })
+        .collect()
+}
+
+/// Picks up to `count` empty cells to fill, fewer if filling them would complete a
+/// line or leave no room for any shape of the pool.
+pub fn pick_garbage(
+    board: &BoardGrid,
+    registry: &ShapeRegistry,
+    rng: &mut GameRng,
+    count: usize,
+) -> CellList {
+    let empty = board
+        .positions()
+        .filter(|&(x, y)| board.cells.0[y][x].is_none());
+    let candidates = empty.choose_multiple(&mut rng.rng, count);
+    for count in (1..=candidates.len()).rev() {
+        let cells = &candidates[..count];
+        let mut preview = *board;
+        preview.fill_cells(cells.iter().copied(), Some(TileColor::Stone));
+        let playable = preview.full_lines().is_empty()
+            && registry.pool().any(|(_, rotations)| {
+                rotations
+                    .iter()
+                    .any(|shape| preview.find_placement(shape).is_some())
+            });
+        if playable {
+            return cells.into();
+        }
+    }
+    CellList::new()
+}
+
+/// Picks up to `count` empty cells without a bonus yet, outside of `exclude`, each
+/// with a x2 or x3 multiplier.
+pub fn pick_bonus_cells(
+    board: &BoardGrid,
+    rng: &mut GameRng,
+    count: usize,
+    exclude: &[(usize, usize)],
+) -> Vec<((usize, usize), u8)> {
+    let cells = board
+        .positions()
+        .filter(|&(x, y)| {
+            board.cells.0[y][x].is_none()
+                && board.meta.0[y][x].bonus <= 1
+                && !exclude.contains(&(x, y))
+        })
+        .choose_multiple(&mut rng.rng, count);
+    cells
+        .into_iter()
+        .map(|cell| (cell, rng.rng.gen_range(2..=3)))
+        .collect()
+}
+
+pub fn send_bonus_cells(
+    board_commands: &mut EventWriter<BoardCommand>,
+    board: Entity,
+    cells: Vec<((usize, usize), u8)>,
+) {
+    for (cell, bonus) in cells {
+        board_commands.send(BoardCommand::SetMeta {
+            board,
+            cells: smallvec![cell],
+            meta: CellMeta { bonus, ..default() },
+        });
+    }
+}
+
+/// Scores placements on every board keeping a [`Score`], breaking down those on the
+/// main board.
+fn score_placements(
+    mut pending: ResMut<PendingBreakdown>,
+    mut placements: EventReader<PlacementEvent>,
+    mut q_boards: Query<
+        (&Board, &GlobalTransform, &Level, &mut Score, Has<MainBoard>),
+        Without<OverlayBoard>,
+    >,
+) {
+    for event in placements.read() {
+        let Ok((board, transform, level, mut score, main)) = q_boards.get_mut(event.board) else {
+            continue;
+        };
+        let points = event.cells.len() as u64 * level.level as u64;
+        score.0 += points;
+        if !main {
+            continue;
+        }
+        let world_anchor = event
+            .cells
+            .iter()
+            .map(|&cell| board.cell_to_world(transform, cell))
+            .sum::<Vec2>()
+            / event.cells.len().max(1) as f32;
+        pending.0 = Some(ScoreBreakdownEvent {
+            placement_cells: event.cells.clone(),
+            base_points: points,
+            line_points: 0,
+            combo_multiplier: 1,
+            bonus_cells: 0,
+            lines: 0,
+            total: points,
+            world_anchor,
+        });
+    }
+}
+
+/// Scores cleared lines, clearing several at once is worth more, and raises the level
+/// of the board. On the main board, levels also change the shapes drawn and drop
+/// garbage, and clears replenish bonus cells.
+fn score_lines(
+    registry: Res<ShapeRegistry>,
+    mut rng: ResMut<GameRng>,
+    mut weights: ResMut<ShapeWeights>,
+    zone_map: Res<ZoneMap>,
+    rules: Res<RuleSet>,
+    mut pending: ResMut<PendingBreakdown>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    mut board_commands: EventWriter<BoardCommand>,
+    mut clears: Local<u32>,
+    mut q_boards: Query<(&Board, &mut Level, &mut Score, Has<MainBoard>), Without<OverlayBoard>>,
+) {
+    for event in lines_cleared.read() {
+        let Ok((board, mut level, mut score, main)) = q_boards.get_mut(event.board) else {
+            continue;
+        };
+        let lines = event.lines.len() as u32;
+        let points = LINE_POINTS * (lines * lines) as u64
+            + OBSTACLE_POINTS * event.lines.destroyed_obstacles as u64
+            + TARGET_POINTS * event.lines.targets_cleared as u64
+            + ZONE_POINTS
+                * event
+                    .lines
+                    .cells()
+                    .map(|(x, y)| zone_map.0 .0[y][x] as u64)
+                    .sum::<u64>();
+        let multiplier = if rules.multipliers {
+            event.lines.multiplier.min(MAX_MULTIPLIER)
+        } else {
+            1
+        };
+        score.0 += points * multiplier as u64 * level.level as u64;
+        let reached = (level.lines + lines) / LINES_PER_LEVEL + 1;
+        level.lines += lines;
+        if !main {
+            level.level = level.level.max(reached);
+            continue;
+        }
+        if let Some(breakdown) = pending.0.as_mut() {
+            breakdown.line_points += points * level.level as u64;
+            breakdown.combo_multiplier = breakdown.combo_multiplier.max(multiplier);
+            breakdown.bonus_cells += event.lines.bonus_cells;
+            breakdown.lines += event.lines.len();
+            breakdown.total += points * multiplier as u64 * level.level as u64;
+        }
+        *clears += 1;
+
+        let mut garbage = CellList::new();
+        if reached > level.level {
+            level.level = reached;
+            weights.0 = level_weights(&registry, reached);
+            garbage = pick_garbage(
+                &board.grid,
+                &registry,
+                &mut rng,
+                (reached as usize - 1) * GARBAGE_PER_LEVEL,
+            );
+        }
+        let bonus = if clears.is_multiple_of(BONUS_EVERY_CLEARS) {
+            pick_bonus_cells(&board.grid, &mut rng, rules.bonus_cells, &garbage)
+        } else {
+            Vec::new()
+        };
+        if !garbage.is_empty() {
+            board_commands.send(BoardCommand::Fill {
+                board: event.board,
+                cells: garbage,
+                color: Some(TileColor::Stone),
+            });
+        }
+        send_bonus_cells(&mut board_commands, event.board, bonus);
+    }
+}
+
+/// Extends the combo of every board whose placement this frame cleared lines, and ends
+/// it on the others that took a placement.
+fn update_combos(
+    mut placements: EventReader<PlacementEvent>,
+    mut lines_cleared: EventReader<LinesClearedEvent>,
+    mut q_boards: Query<&mut Combo>,
+) {
+    let cleared: Vec<Entity> = lines_cleared.read().map(|event| event.board).collect();
+    for event in placements.read() {
+        if let Ok(mut combo) = q_boards.get_mut(event.board) {
+            combo.0 = if cleared.contains(&event.board) {
+                combo.0 + 1
+            } else {
+                0
+            };
+        }
+    }
+}
+
+fn send_breakdown(
+    mut pending: ResMut<PendingBreakdown>,
+    mut breakdowns: EventWriter<ScoreBreakdownEvent>,
+) {
+    if let Some(breakdown) = pending.0.take() {
+        breakdowns.send(breakdown);
+    }
+}
+
+fn spawn_score_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 20.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            ..default()
+        },
+        ScoreText,
+        LayoutSlot::Score,
+    ));
+}
+
+/// Shows the main board's score and level, and its combo from two clearing placements
+/// in a row.
+fn update_score_text(
+    strings: Res<Strings>,
+    q_main_board: Query<(Ref<Score>, Ref<Level>, Ref<Combo>), With<MainBoard>>,
+    mut q_text: Query<&mut Text, With<ScoreText>>,
+) {
+    let Ok((score, level, combo)) = q_main_board.get_single() else {
+        return;
+    };
+    if !level.is_changed() && !score.is_changed() && !combo.is_changed() && !strings.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value = t!(
+            strings,
+            "hud.score",
+            level.level,
+            strings.number(score.0),
+            level.lines
+        );
+        if combo.0 >= 2 {
+            text.sections[0].value += "  ";
+            text.sections[0].value += &t!(strings, "hud.combo", combo.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BOARD_WIDTH;
+
+    #[test]
+    fn bonus_cells_go_on_empty_cells_without_a_bonus() {
+        let mut board = BoardGrid::default();
+        // Every cell but the two bottom rows filled, one of them already a bonus cell
+        for row in board.cells.0[2..].iter_mut() {
+            row.fill(Some(TileColor::Green));
+        }
+        board.meta.0[0][0].bonus = 2;
+        let exclude = [(1, 0), (2, 0)];
+
+        let picked = pick_bonus_cells(&board, &mut GameRng::new(122), 100, &exclude);
+
+        assert_eq!(picked.len(), 2 * BOARD_WIDTH - 3);
+        for &((x, y), bonus) in picked.iter() {
+            assert!(y < 2 && (x, y) != (0, 0) && !exclude.contains(&(x, y)));
+            assert!((2..=3).contains(&bonus));
+        }
+    }
+}
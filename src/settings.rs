@@ -0,0 +1,164 @@
+//! Player settings, persisted in `settings.txt`.
+
+use bevy::prelude::*;
+use std::fmt;
+
+use crate::{
+    board::{parse_size, BOARD_HEIGHT, BOARD_WIDTH},
+    layout,
+    persistence::Persisted,
+};
+
+#[derive(Resource)]
+pub(crate) struct Settings {
+    /// Shake the board on big clears, off for motion-sensitive players.
+    pub(crate) screen_shake: bool,
+    /// Seconds the selected shape takes to catch up with the cursor, 0 to follow it
+    /// exactly.
+    pub(crate) shape_smoothing: f32,
+    /// Set once the tutorial was finished or skipped, so it only runs on the first game.
+    pub(crate) tutorial_completed: bool,
+    /// Grow the board's tiles in at the start of a game, off to restart without waiting.
+    pub(crate) intro_animation: bool,
+    /// Describe placements, clears and overtime in words on a caption strip.
+    pub(crate) announcements: bool,
+    /// Fade repainted tiles to their new color rather than snapping, see [`tile_fade`].
+    pub(crate) tile_fade: bool,
+    /// Vary the placement sound with the combo and where the shape landed, see
+    /// [`audio`].
+    pub(crate) dynamic_audio: bool,
+    /// Trail a fading ghost behind the preview when it jumps, see [`ghost_trail`].
+    pub(crate) ghost_trail: bool,
+    /// Trace an outline around the selected shape, see [`shape_outline`].
+    pub(crate) shape_outline: bool,
+    /// Where the HUD goes around the board, see [`layout`].
+    pub(crate) layout: layout::LayoutMode,
+    /// Seconds clicks don't place after the window regained focus or a menu closed, see
+    /// [`click_guard`].
+    pub(crate) click_grace: f32,
+    /// Lean the shapes drawn toward the player's recent performance, see [`assist`].
+    pub(crate) adaptive_assist: bool,
+    /// Cells across and up the main board, picked in the menu, see [`resize`].
+    pub(crate) board_size: (usize, usize),
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            screen_shake: true,
+            shape_smoothing: 0.,
+            tutorial_completed: false,
+            intro_animation: true,
+            announcements: false,
+            tile_fade: true,
+            dynamic_audio: true,
+            ghost_trail: true,
+            shape_outline: false,
+            layout: layout::LayoutMode::Landscape,
+            click_grace: 0.15,
+            adaptive_assist: false,
+            board_size: (BOARD_WIDTH, BOARD_HEIGHT),
+        }
+    }
+}
+impl fmt::Display for Settings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "screen_shake = {}", self.screen_shake)?;
+        writeln!(f, "shape_smoothing = {}", self.shape_smoothing)?;
+        writeln!(f, "tutorial_completed = {}", self.tutorial_completed)?;
+        writeln!(f, "intro_animation = {}", self.intro_animation)?;
+        writeln!(f, "announcements = {}", self.announcements)?;
+        writeln!(f, "tile_fade = {}", self.tile_fade)?;
+        writeln!(f, "dynamic_audio = {}", self.dynamic_audio)?;
+        writeln!(f, "ghost_trail = {}", self.ghost_trail)?;
+        writeln!(f, "shape_outline = {}", self.shape_outline)?;
+        writeln!(f, "layout = {}", self.layout)?;
+        writeln!(f, "click_grace = {}", self.click_grace)?;
+        writeln!(f, "adaptive_assist = {}", self.adaptive_assist)?;
+        writeln!(
+            f,
+            "board_size = {}x{}",
+            self.board_size.0, self.board_size.1
+        )
+    }
+}
+impl Settings {
+    /// Sets the setting of a `key = value` line, `None` if the value doesn't parse.
+    /// Unknown keys are warned about and skipped.
+    fn apply(&mut self, key: &str, value: &str) -> Option<()> {
+        match key {
+            "screen_shake" => self.screen_shake = value.parse().ok()?,
+            "shape_smoothing" => self.shape_smoothing = value.parse().ok()?,
+            "tutorial_completed" => self.tutorial_completed = value.parse().ok()?,
+            "intro_animation" => self.intro_animation = value.parse().ok()?,
+            "announcements" => self.announcements = value.parse().ok()?,
+            "tile_fade" => self.tile_fade = value.parse().ok()?,
+            "dynamic_audio" => self.dynamic_audio = value.parse().ok()?,
+            "ghost_trail" => self.ghost_trail = value.parse().ok()?,
+            "shape_outline" => self.shape_outline = value.parse().ok()?,
+            "layout" => self.layout = layout::LayoutMode::parse(value)?,
+            "adaptive_assist" => self.adaptive_assist = value.parse().ok()?,
+            "click_grace" => self.click_grace = value.parse::<f32>().ok()?.max(0.),
+            "board_size" => self.board_size = parse_size(value)?,
+            key => warn!("unknown setting {key}"),
+        }
+        Some(())
+    }
+}
+impl Persisted for Settings {
+    const PATH: &'static str = "settings.txt";
+
+    /// Reads `key = value` lines, keys that are missing keep their default. A malformed
+    /// line is skipped with a warning, keeping the settings of the other lines.
+    fn parse(text: &str) -> Option<Self> {
+        let mut settings = Self::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let applied = line
+                .split_once('=')
+                .and_then(|(key, value)| settings.apply(key.trim(), value.trim()));
+            if applied.is_none() {
+                warn!("ignoring malformed line in {}: {line}", Self::PATH);
+            }
+        }
+        Some(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_malformed_line_keeps_the_other_settings() {
+        let settings = Settings::parse(
+            "screen_shake = false\nshape_smoothing = fast\nno equals sign\nclick_grace = 0.5\n",
+        )
+        .unwrap();
+        assert!(!settings.screen_shake);
+        assert_eq!(
+            settings.shape_smoothing,
+            Settings::default().shape_smoothing
+        );
+        assert_eq!(settings.click_grace, 0.5);
+    }
+
+    #[test]
+    fn settings_read_back_what_they_write() {
+        let settings = Settings {
+            announcements: true,
+            layout: layout::LayoutMode::Portrait,
+            click_grace: 0.25,
+            board_size: (12, 9),
+            ..default()
+        };
+        let read = Settings::parse(&settings.to_string()).unwrap();
+        assert_eq!(read.to_string(), settings.to_string());
+    }
+
+    #[test]
+    fn unsupported_board_sizes_keep_the_default() {
+        for line in ["board_size = 2x2", "board_size = 21x20", "board_size = 10"] {
+            let settings = Settings::parse(line).unwrap();
+            assert_eq!(settings.board_size, (BOARD_WIDTH, BOARD_HEIGHT), "{line}");
+        }
+    }
+}
@@ -0,0 +1,411 @@
+//! Settings screen, opened from the menu. Up and Down pick a setting, Enter or a click
+//! flips it and keeps it in `settings.txt`, Escape goes back to the menu.
+//!
+//! Below the settings a swatch shows every shape color of the [`ColorPalette`] as a
+//! placed tile beside a preview tile, on an empty tile, to check the two apart.
+
+use bevy::prelude::*;
+
+use crate::{
+    board::TileColor,
+    click_guard::ModalUi,
+    layout::LayoutMode,
+    menu::GameState,
+    palette::ColorPalette,
+    persistence::{PersistRequest, Persisted},
+    settings::Settings,
+    strings::{t, Strings},
+};
+
+const IDLE_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const SELECTED_BUTTON: Color = Color::rgb(0.3, 0.3, 0.5);
+const HINT_TEXT: Color = Color::rgb(0.4, 0.4, 0.4);
+/// Side of a swatch tile, in pixels.
+const SWATCH_TILE: f32 = 24.;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+enum SettingsEntry {
+    ScreenShake,
+    IntroAnimation,
+    TileFade,
+    GhostTrail,
+    ShapeOutline,
+    DynamicAudio,
+    Announcements,
+    AdaptiveAssist,
+    Layout,
+    Back,
+}
+impl SettingsEntry {
+    const ALL: [SettingsEntry; 10] = [
+        SettingsEntry::ScreenShake,
+        SettingsEntry::IntroAnimation,
+        SettingsEntry::TileFade,
+        SettingsEntry::GhostTrail,
+        SettingsEntry::ShapeOutline,
+        SettingsEntry::DynamicAudio,
+        SettingsEntry::Announcements,
+        SettingsEntry::AdaptiveAssist,
+        SettingsEntry::Layout,
+        SettingsEntry::Back,
+    ];
+
+    /// Key of the entry's text in [`Strings`].
+    fn label(self) -> &'static str {
+        match self {
+            SettingsEntry::ScreenShake => "settings.screen_shake",
+            SettingsEntry::IntroAnimation => "settings.intro_animation",
+            SettingsEntry::TileFade => "settings.tile_fade",
+            SettingsEntry::GhostTrail => "settings.ghost_trail",
+            SettingsEntry::ShapeOutline => "settings.shape_outline",
+            SettingsEntry::DynamicAudio => "settings.dynamic_audio",
+            SettingsEntry::Announcements => "settings.announcements",
+            SettingsEntry::AdaptiveAssist => "settings.adaptive_assist",
+            SettingsEntry::Layout => "settings.layout",
+            SettingsEntry::Back => "settings.back",
+        }
+    }
+
+    /// The on/off setting the entry flips, `None` for the others.
+    fn flag(self, settings: &mut Settings) -> Option<&mut bool> {
+        Some(match self {
+            SettingsEntry::ScreenShake => &mut settings.screen_shake,
+            SettingsEntry::IntroAnimation => &mut settings.intro_animation,
+            SettingsEntry::TileFade => &mut settings.tile_fade,
+            SettingsEntry::GhostTrail => &mut settings.ghost_trail,
+            SettingsEntry::ShapeOutline => &mut settings.shape_outline,
+            SettingsEntry::DynamicAudio => &mut settings.dynamic_audio,
+            SettingsEntry::Announcements => &mut settings.announcements,
+            SettingsEntry::AdaptiveAssist => &mut settings.adaptive_assist,
+            SettingsEntry::Layout | SettingsEntry::Back => return None,
+        })
+    }
+
+    /// Whether the on/off setting of the entry is on, `None` for the others.
+    fn is_on(self, settings: &Settings) -> Option<bool> {
+        Some(match self {
+            SettingsEntry::ScreenShake => settings.screen_shake,
+            SettingsEntry::IntroAnimation => settings.intro_animation,
+            SettingsEntry::TileFade => settings.tile_fade,
+            SettingsEntry::GhostTrail => settings.ghost_trail,
+            SettingsEntry::ShapeOutline => settings.shape_outline,
+            SettingsEntry::DynamicAudio => settings.dynamic_audio,
+            SettingsEntry::Announcements => settings.announcements,
+            SettingsEntry::AdaptiveAssist => settings.adaptive_assist,
+            SettingsEntry::Layout | SettingsEntry::Back => return None,
+        })
+    }
+
+    /// Changes the setting of the entry, `false` for [`SettingsEntry::Back`].
+    fn toggle(self, settings: &mut Settings) -> bool {
+        if let Some(flag) = self.flag(settings) {
+            *flag = !*flag;
+            return true;
+        }
+        if self != SettingsEntry::Layout {
+            return false;
+        }
+        settings.layout = match settings.layout {
+            LayoutMode::Landscape => LayoutMode::Portrait,
+            LayoutMode::Portrait => LayoutMode::Landscape,
+        };
+        true
+    }
+
+    /// Text of the entry, with the current value of its setting.
+    fn text(self, strings: &Strings, settings: &Settings) -> String {
+        let value = match self.is_on(settings) {
+            Some(true) => "settings.on",
+            Some(false) => "settings.off",
+            None if self == SettingsEntry::Back => return strings.get(self.label()).to_owned(),
+            None => match settings.layout {
+                LayoutMode::Landscape => "settings.landscape",
+                LayoutMode::Portrait => "settings.portrait",
+            },
+        };
+        t!(strings, self.label(), strings.get(value))
+    }
+}
+
+#[derive(Component)]
+struct SettingsRoot;
+
+/// Tile of the preview swatch, showing the color placed or as the preview draws it.
+#[derive(Component)]
+struct SwatchTile {
+    color: TileColor,
+    preview: bool,
+}
+impl SwatchTile {
+    fn color(&self, palette: &ColorPalette) -> Color {
+        match self.preview {
+            true => palette.preview_color(self.color),
+            false => palette.color(self.color),
+        }
+    }
+}
+
+/// Index into [`SettingsEntry::ALL`] of the entry Enter activates.
+#[derive(Resource, Default)]
+struct SettingsSelection(usize);
+
+pub struct SettingsMenuPlugin;
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsSelection>()
+            .add_systems(OnEnter(GameState::Settings), spawn_settings_menu)
+            .add_systems(OnExit(GameState::Settings), despawn_settings_menu)
+            .add_systems(
+                Update,
+                (
+                    navigate_settings,
+                    highlight_entries,
+                    relabel_entries.run_if(
+                        resource_changed::<Settings>().or_else(resource_changed::<Strings>()),
+                    ),
+                    recolor_swatch.run_if(resource_changed::<ColorPalette>()),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Settings)),
+            );
+    }
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    strings: Res<Strings>,
+    settings: Res<Settings>,
+    palette: Res<ColorPalette>,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            SettingsRoot,
+            ModalUi,
+        ))
+        .with_children(|commands| {
+            commands.spawn(
+                TextBundle::from_section(
+                    strings.get("settings.title"),
+                    TextStyle {
+                        font_size: 48.,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+            for entry in SettingsEntry::ALL {
+                commands
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(320.),
+                                padding: UiRect::all(Val::Px(8.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: IDLE_BUTTON.into(),
+                            ..default()
+                        },
+                        entry,
+                    ))
+                    .with_children(|commands| {
+                        commands.spawn((
+                            TextBundle::from_section(
+                                entry.text(&strings, &settings),
+                                TextStyle {
+                                    font_size: 24.,
+                                    ..default()
+                                },
+                            ),
+                            entry,
+                        ));
+                    });
+            }
+            commands.spawn(TextBundle::from_section(
+                strings.get("settings.preview_swatch"),
+                TextStyle {
+                    font_size: 16.,
+                    color: HINT_TEXT,
+                    ..default()
+                },
+            ));
+            commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|commands| {
+                    for color in palette.shape_colors() {
+                        spawn_swatch_pair(commands, &palette, color);
+                    }
+                });
+            commands.spawn(TextBundle::from_section(
+                strings.get("settings.hint"),
+                TextStyle {
+                    font_size: 16.,
+                    color: HINT_TEXT,
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// A placed and a preview tile of the color, side by side on an empty tile.
+fn spawn_swatch_pair(commands: &mut ChildBuilder, palette: &ColorPalette, color: TileColor) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(4.)),
+                column_gap: Val::Px(4.),
+                ..default()
+            },
+            background_color: Color::from(TileColor::Gray).into(),
+            ..default()
+        })
+        .with_children(|commands| {
+            for preview in [false, true] {
+                let tile = SwatchTile { color, preview };
+                commands.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(SWATCH_TILE),
+                            height: Val::Px(SWATCH_TILE),
+                            ..default()
+                        },
+                        background_color: tile.color(palette).into(),
+                        ..default()
+                    },
+                    tile,
+                ));
+            }
+        });
+}
+
+fn despawn_settings_menu(mut commands: Commands, q_root: Query<Entity, With<SettingsRoot>>) {
+    for entity in q_root.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Up and Down move the selection, Enter or a click flips the setting, Escape or
+/// [`SettingsEntry::Back`] goes back to the menu.
+fn navigate_settings(
+    input_keys: Res<Input<KeyCode>>,
+    mut selection: ResMut<SettingsSelection>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut persist: EventWriter<PersistRequest>,
+    q_buttons: Query<(&Interaction, &SettingsEntry), (Changed<Interaction>, With<Button>)>,
+) {
+    if input_keys.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+        return;
+    }
+    let count = SettingsEntry::ALL.len();
+    if input_keys.just_pressed(KeyCode::Down) {
+        selection.0 = (selection.0 + 1) % count;
+    }
+    if input_keys.just_pressed(KeyCode::Up) {
+        selection.0 = (selection.0 + count - 1) % count;
+    }
+    let mut activated = None;
+    for (interaction, entry) in q_buttons.iter() {
+        let index = SettingsEntry::ALL.iter().position(|e| e == entry).unwrap();
+        match interaction {
+            Interaction::Hovered => selection.0 = index,
+            Interaction::Pressed => activated = Some(*entry),
+            Interaction::None => {}
+        }
+    }
+    if input_keys.just_pressed(KeyCode::Return) {
+        activated = Some(SettingsEntry::ALL[selection.0]);
+    }
+    let Some(entry) = activated else {
+        return;
+    };
+    if entry.toggle(&mut settings) {
+        persist.send(settings.persist_request());
+    } else {
+        next_state.set(GameState::Menu);
+    }
+}
+
+fn highlight_entries(
+    selection: Res<SettingsSelection>,
+    mut q_buttons: Query<(&SettingsEntry, &mut BackgroundColor), With<Button>>,
+) {
+    for (entry, mut background) in q_buttons.iter_mut() {
+        let selected = SettingsEntry::ALL[selection.0] == *entry;
+        *background = if selected {
+            SELECTED_BUTTON
+        } else {
+            IDLE_BUTTON
+        }
+        .into();
+    }
+}
+
+fn relabel_entries(
+    strings: Res<Strings>,
+    settings: Res<Settings>,
+    mut q_labels: Query<(&SettingsEntry, &mut Text)>,
+) {
+    for (entry, mut text) in q_labels.iter_mut() {
+        text.sections[0].value = entry.text(&strings, &settings);
+    }
+}
+
+fn recolor_swatch(
+    palette: Res<ColorPalette>,
+    mut q_tiles: Query<(&SwatchTile, &mut BackgroundColor)>,
+) {
+    for (tile, mut background) in q_tiles.iter_mut() {
+        background.0 = tile.color(&palette);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_entry_but_back_changes_a_setting() {
+        let mut settings = Settings::default();
+        for entry in SettingsEntry::ALL {
+            let before = settings.to_string();
+            let changed = entry.toggle(&mut settings);
+            assert_eq!(changed, entry != SettingsEntry::Back, "{entry:?}");
+            assert_eq!(settings.to_string() != before, changed, "{entry:?}");
+        }
+    }
+
+    #[test]
+    fn toggling_twice_restores_the_setting() {
+        let mut settings = Settings::default();
+        for entry in SettingsEntry::ALL {
+            entry.toggle(&mut settings);
+            entry.toggle(&mut settings);
+        }
+        assert_eq!(settings.to_string(), Settings::default().to_string());
+    }
+}
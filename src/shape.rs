@@ -0,0 +1,536 @@
+//! Shapes: their fields, the registry holding every base shape with its rotations and
+//! the [`ShapeInstance`]s entities carry, plus the pool the game starts with.
+
+use bevy::prelude::*;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use std::fmt;
+
+use crate::board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH};
+
+/// Base shapes to build a [`ShapeRegistry`] from, as [`default_shapes`] returns and
+/// [`crate::ShapePoolChanged`] carries.
+pub type ShapePool = Vec<NamedShape>;
+
+#[derive(Clone, Copy)]
+pub struct Shape {
+    pub fields: [[bool; 8]; 8],
+}
+impl PartialEq for Shape {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits() == other.bits()
+    }
+}
+impl Eq for Shape {}
+impl Shape {
+    /// Packs the 8x8 fields into a bitmask, bit `y * 8 + x` set for every filled field.
+    pub fn bits(&self) -> u64 {
+        self.fields
+            .iter()
+            .flatten()
+            .enumerate()
+            .fold(0, |acc, (i, &val)| if val { acc | 1 << i } else { acc })
+    }
+    /// One bitmask per row, bit `x` set for every filled field.
+    pub fn row_masks(&self) -> [u32; 8] {
+        self.fields.map(|row| {
+            row.iter()
+                .enumerate()
+                .fold(0, |acc, (x, &val)| if val { acc | 1 << x } else { acc })
+        })
+    }
+    /// Whether no field is filled. Such a shape has no extent, [`Shape::bounds`] being
+    /// `(0, 0)`, and is kept out of the [`ShapeRegistry`].
+    pub fn is_empty(&self) -> bool {
+        self.bits() == 0
+    }
+    /// Whether the shape's bounds fit within a board of the given size, as they do when
+    /// equal to it. Shapes that don't can never be placed on it and are kept out of the
+    /// [`ShapeRegistry`]'s pool.
+    pub fn fits_board(&self, (board_width, board_height): (usize, usize)) -> bool {
+        let (width, height) = self.bounds();
+        width <= board_width && height <= board_height
+    }
+    pub fn bounds(&self) -> (usize, usize) {
+        self.fields
+            .iter()
+            .enumerate()
+            .fold((0, 0), |acc, (i, row)| {
+                let max_x_in_row = row.iter().enumerate().fold(0, |max_x, (j, &val)| {
+                    if val {
+                        usize::max(max_x, j + 1)
+                    } else {
+                        max_x
+                    }
+                });
+                (
+                    usize::max(acc.0, max_x_in_row),
+                    usize::max(acc.1, if max_x_in_row > 0 { i + 1 } else { acc.1 }),
+                )
+            })
+    }
+    pub fn rotate_90(&self) -> Shape {
+        let mut new_fields = [[false; 8]; 8];
+        let (width, height) = self.bounds();
+
+        for i in 0..height {
+            for (j, row) in new_fields.iter_mut().enumerate().take(width) {
+                row[height - i - 1] = self.fields[i][j];
+            }
+        }
+
+        Shape { fields: new_fields }
+    }
+
+    pub fn equivalents(&self) -> Vec<Shape> {
+        let mut shapes = vec![*self];
+        let mut seen = vec![self.bits()];
+        let mut rotated = *self;
+        for _ in 0..3 {
+            rotated = rotated.rotate_90();
+            let bits = rotated.bits();
+            if !seen.contains(&bits) {
+                seen.push(bits);
+                shapes.push(rotated);
+            }
+        }
+        shapes
+    }
+
+    pub fn from_pattern(w: usize, h: usize, pat: &str) -> Self {
+        if pat.len() != w * h {
+            panic!("Pattern length does not match given dimensions");
+        }
+
+        let mut fields = [[false; 8]; 8];
+        for (i, c) in pat.chars().enumerate() {
+            let x = i % w; // x-coordinate (column)
+            let y = i / w; // y-coordinate (row)
+
+            // Ensure we don't go out of bounds if the shape's w or h is greater than 8
+            if x >= 8 || y >= 8 {
+                panic!("Pattern dimensions out of bounds");
+            }
+
+            fields[y][x] = match c {
+                '#' => true,
+                '.' => false,
+                _ => panic!("Invalid character in pattern: {}", c),
+            };
+        }
+
+        Self { fields }
+    }
+}
+
+/// A base shape along with its display name, generated from its size if missing.
+#[derive(Clone)]
+pub struct NamedShape {
+    pub name: Option<String>,
+    pub shape: Shape,
+}
+impl From<Shape> for NamedShape {
+    fn from(shape: Shape) -> Self {
+        Self { name: None, shape }
+    }
+}
+
+/// Index of a base shape in the [`ShapeRegistry`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ShapeId(pub u16);
+
+/// Holds every base shape of the game along with its distinct rotations, so entities
+/// only need to carry a [`ShapeInstance`] instead of a full copy of the fields.
+#[derive(Resource)]
+pub struct ShapeRegistry {
+    rotations: Vec<Vec<Shape>>,
+    /// Display names by [`ShapeId`], unique within the pool.
+    names: Vec<String>,
+    /// Shapes below this id were swapped out of the pool. They still resolve, for the
+    /// pieces already handed out, but are no longer drawn.
+    pool_start: usize,
+    /// Size of the main board the pool was checked against.
+    board_size: (usize, usize),
+    /// Shapes last handed to [`ShapeRegistry::swap_pool`], including those too large
+    /// for the board, for the pool to take them back in when it grows.
+    offered: ShapePool,
+}
+impl ShapeRegistry {
+    /// Shapes without a name are named after their size and id, like `3x2 #4`. A name
+    /// that is already taken gets the shape's id appended. Panics without a single
+    /// shape to draw.
+    pub fn new<S: Into<NamedShape>>(shapes: impl IntoIterator<Item = S>) -> Self {
+        Self::try_new(shapes).expect("a shape registry needs at least one placeable shape")
+    }
+
+    /// Like [`ShapeRegistry::new`], `None` without a single shape to draw.
+    pub fn try_new<S: Into<NamedShape>>(shapes: impl IntoIterator<Item = S>) -> Option<Self> {
+        Self::try_for_board(shapes, (BOARD_WIDTH, BOARD_HEIGHT))
+    }
+
+    /// Like [`ShapeRegistry::try_new`], for a main board of the given size rather than
+    /// the full one.
+    pub fn try_for_board<S: Into<NamedShape>>(
+        shapes: impl IntoIterator<Item = S>,
+        board_size: (usize, usize),
+    ) -> Option<Self> {
+        let mut registry = Self {
+            rotations: Vec::new(),
+            names: Vec::new(),
+            pool_start: 0,
+            board_size,
+            offered: Vec::new(),
+        };
+        registry.swap_pool(shapes).then_some(registry)
+    }
+
+    /// Replaces the pool with new shapes, named as by [`ShapeRegistry::new`]. They get
+    /// ids after every existing one, ids are never reused, so instances of the old
+    /// shapes stay valid.
+    ///
+    /// Empty shapes and those larger than the board are left out, before any gets an id.
+    /// Returns `false`, keeping the pool as it is, if that leaves no shape.
+    pub fn swap_pool<S: Into<NamedShape>>(&mut self, shapes: impl IntoIterator<Item = S>) -> bool {
+        let offered: ShapePool = shapes
+            .into_iter()
+            .map(Into::into)
+            .filter(|named| {
+                let empty = named.shape.is_empty();
+                if empty {
+                    let name = named.name.as_deref().unwrap_or("unnamed");
+                    warn!("leaving out the {name} shape, it has no filled field");
+                }
+                !empty
+            })
+            .collect();
+        let shapes = fitting(&offered, self.board_size);
+        if shapes.is_empty() {
+            return false;
+        }
+        self.register(shapes);
+        self.offered = offered;
+        true
+    }
+
+    /// Checks the pool against a new size of the main board: shapes larger than it
+    /// leave the pool, and those of the last pool offered that fit again come back.
+    /// Ids and names follow [`ShapeRegistry::swap_pool`] whenever the pool changes.
+    ///
+    /// Returns `false`, keeping the pool and size as they are, if no shape fits.
+    pub fn fit_board(&mut self, board_size: (usize, usize)) -> bool {
+        let fits = |size| {
+            self.offered
+                .iter()
+                .map(|named| named.shape.fits_board(size))
+                .collect::<Vec<_>>()
+        };
+        if fits(board_size) == fits(self.board_size) {
+            self.board_size = board_size;
+            return true;
+        }
+        let shapes = fitting(&self.offered, board_size);
+        if shapes.is_empty() {
+            return false;
+        }
+        self.board_size = board_size;
+        self.register(shapes);
+        true
+    }
+
+    /// Size of the main board the pool fits on.
+    pub fn board_size(&self) -> (usize, usize) {
+        self.board_size
+    }
+
+    /// Gives the shapes ids after every existing one, making them the pool.
+    fn register(&mut self, shapes: ShapePool) {
+        self.pool_start = self.rotations.len();
+        for NamedShape { name, shape } in shapes {
+            let id = self.rotations.len();
+            let (width, height) = shape.bounds();
+            let mut name = name.unwrap_or_else(|| format!("{width}x{height} #{id}"));
+            if self.names[self.pool_start..].contains(&name) {
+                warn!("shape name {name} is taken, naming shape {id} {name} #{id}");
+                name = format!("{name} #{id}");
+            }
+            self.names.push(name);
+            self.rotations.push(shape.equivalents());
+        }
+    }
+
+    /// Registers a shape outside the pool without the checks of
+    /// [`ShapeRegistry::swap_pool`], for tests of invalid shapes slipping through.
+    #[cfg(feature = "test-harness")]
+    pub fn push_unchecked(&mut self, shape: Shape) -> ShapeId {
+        let id = ShapeId(self.rotations.len() as u16);
+        self.names.push(format!("unchecked #{}", id.0));
+        self.rotations.push(vec![shape]);
+        id
+    }
+
+    /// Display name of a shape, e.g. for announcing placements.
+    pub fn name(&self, id: ShapeId) -> &str {
+        &self.names[id.0 as usize]
+    }
+
+    pub fn get(&self, id: ShapeId, rotation: u8) -> &Shape {
+        let rotations = &self.rotations[id.0 as usize];
+        &rotations[rotation as usize % rotations.len()]
+    }
+
+    pub fn rotation_count(&self, id: ShapeId) -> usize {
+        self.rotations[id.0 as usize].len()
+    }
+
+    pub fn contains(&self, id: ShapeId) -> bool {
+        (id.0 as usize) < self.rotations.len()
+    }
+
+    /// Every shape with all of its distinct rotations, including the ones swapped out
+    /// of the pool.
+    pub fn iter(&self) -> impl Iterator<Item = (ShapeId, &[Shape])> {
+        self.rotations
+            .iter()
+            .enumerate()
+            .map(|(id, rotations)| (ShapeId(id as u16), rotations.as_slice()))
+    }
+
+    /// The shapes currently drawn, see [`ShapeRegistry::swap_pool`].
+    pub fn pool(&self) -> impl Iterator<Item = (ShapeId, &[Shape])> {
+        self.iter().skip(self.pool_start)
+    }
+
+    pub fn in_pool(&self, id: ShapeId) -> bool {
+        (self.pool_start..self.rotations.len()).contains(&(id.0 as usize))
+    }
+
+    /// Weights making every rotation of every shape in the pool equally likely.
+    pub fn default_weights(&self) -> Vec<f32> {
+        self.iter()
+            .map(|(id, rotations)| {
+                if self.in_pool(id) {
+                    rotations.len() as f32
+                } else {
+                    0.
+                }
+            })
+            .collect()
+    }
+
+    /// Picks a shape with probability proportional to its weight, indexed by
+    /// [`ShapeId`], and one of its rotations uniformly.
+    pub fn random_weighted<R: Rng + ?Sized>(&self, rng: &mut R, weights: &[f32]) -> (ShapeId, u8) {
+        let id = match WeightedIndex::new(weights) {
+            Ok(index) => index.sample(rng),
+            Err(_) => rng.gen_range(self.pool_start..self.rotations.len()),
+        };
+        (
+            ShapeId(id as u16),
+            rng.gen_range(0..self.rotations[id].len()) as u8,
+        )
+    }
+}
+
+/// The shapes of a pool fitting on a board of the given size, warning with the names of
+/// those left out.
+fn fitting(shapes: &[NamedShape], (board_width, board_height): (usize, usize)) -> ShapePool {
+    let (fit, too_large): (ShapePool, ShapePool) = shapes
+        .iter()
+        .cloned()
+        .partition(|named| named.shape.fits_board((board_width, board_height)));
+    if !too_large.is_empty() {
+        let names = too_large
+            .iter()
+            .map(|named| {
+                let name = named.name.as_deref().unwrap_or("unnamed");
+                let (width, height) = named.shape.bounds();
+                format!("{name} ({width}x{height})")
+            })
+            .collect::<Vec<_>>();
+        warn!(
+            "leaving out shapes larger than the {board_width}x{board_height} board: {}",
+            names.join(", ")
+        );
+    }
+    fit
+}
+
+/// A colored, rotated reference to a shape in the [`ShapeRegistry`].
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct ShapeInstance {
+    pub id: ShapeId,
+    pub rotation: u8,
+    pub color: TileColor,
+}
+impl ShapeInstance {
+    pub fn resolve<'a>(&self, registry: &'a ShapeRegistry) -> &'a Shape {
+        registry.get(self.id, self.rotation)
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = self.bounds();
+
+        for i in 0..height {
+            for j in 0..width {
+                // Write '#' for true and '.' for false
+                let c = if self.fields[i][j] { '#' } else { '.' };
+                write!(f, "{}", c)?;
+            }
+            // After each row except the last one, add a newline
+            if i < height - 1 {
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+impl fmt::Debug for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[macro_export]
+macro_rules! shapes {
+    (@name) => {
+        None
+    };
+    (@name $name:literal) => {
+        Some(String::from($name))
+    };
+    // Match one or more shape definitions, each optionally named with `as "Name"`,
+    // separated by semicolons
+    ($(($x:expr, $y:expr) $pattern:literal $(as $name:literal)?);+ $(;)?) => {
+        // Only the base shapes are listed; rotations are derived by the registry
+        vec![$($crate::shape::NamedShape {
+            name: $crate::shapes!(@name $($name)?),
+            shape: $crate::shape::Shape::from_pattern($x, $y, $pattern),
+        }),+]
+    };
+}
+
+/// Shapes of the game, used unless a [`ShapeRegistry`] was inserted before adding the
+/// [`crate::BlocksPlugin`].
+pub fn default_shapes() -> ShapePool {
+    shapes! {
+        (2,2) "####" as "Square";
+        (4,1) "####" as "Line 4";
+        (3,1) "###" as "Line 3";
+        (2,2) "##.#" as "V-piece";
+        (3,2) "###..#" as "L-piece";
+        (1,1) "#" as "Dot";
+        (1,2) "##" as "Line 2";
+        (3,3) "#########" as "Big square";
+        (2,3) "######" as "Rectangle";
+        (3,2) "###.#." as "T-piece";
+        (3,2) "##..##" as "S-piece";
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn registry() -> ShapeRegistry {
+        ShapeRegistry::new(shapes![(1, 1) "#" as "Dot"; (2, 1) "##" as "Bar"])
+    }
+
+    #[test]
+    fn swapping_appends_shapes_under_new_ids() {
+        let mut registry = registry();
+        assert!(registry.swap_pool(shapes![(3, 1) "###" as "Line"; (1, 1) "#" as "Dot"]));
+
+        let ids = registry.iter().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(ids.len(), 4);
+        let pool = registry.pool().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(pool, [ShapeId(2), ShapeId(3)]);
+        assert_eq!(registry.name(ShapeId(2)), "Line");
+        // Its old Dot is out of the pool but still resolves, for pieces handed out
+        assert_eq!(registry.name(ShapeId(0)), "Dot");
+        assert!(!registry.in_pool(ShapeId(0)));
+        assert_eq!(
+            *registry.get(ShapeId(1), 1),
+            Shape::from_pattern(1, 2, "##")
+        );
+        assert_eq!(registry.default_weights(), [0., 0., 2., 1.]);
+    }
+
+    #[test]
+    fn swapped_out_shapes_are_never_drawn() {
+        let mut registry = registry();
+        registry.swap_pool(shapes![(3, 1) "###"]);
+        let mut rng = StdRng::seed_from_u64(160);
+        let weights = registry.default_weights();
+        for _ in 0..100 {
+            assert_eq!(registry.random_weighted(&mut rng, &weights).0, ShapeId(2));
+        }
+    }
+
+    #[test]
+    fn a_pool_without_placeable_shapes_is_refused() {
+        let mut registry = registry();
+        assert!(!registry.swap_pool(shapes![(2, 2) "...."]));
+        assert!(ShapeRegistry::try_new(shapes![(1, 1) "."]).is_none());
+        assert!(registry.in_pool(ShapeId(0)));
+        assert_eq!(registry.iter().count(), 2);
+    }
+
+    #[test]
+    fn shapes_as_large_as_the_board_stay_in_the_pool() {
+        let shapes = shapes![(5, 1) "#####" as "Line 5"; (6, 1) "######" as "Line 6"];
+        let registry = ShapeRegistry::try_for_board(shapes, (5, 5)).unwrap();
+        let names = registry
+            .pool()
+            .map(|(id, _)| registry.name(id))
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Line 5"]);
+        assert_eq!(registry.default_weights(), [2.]);
+        assert!(ShapeRegistry::try_for_board(shapes![(6, 1) "######"], (5, 5)).is_none());
+    }
+
+    #[test]
+    fn fitting_a_board_keeps_ids_and_weights_consistent() {
+        let shapes = shapes![(1, 1) "#" as "Dot"; (6, 1) "######" as "Line 6"];
+        let mut registry = ShapeRegistry::new(shapes);
+        assert_eq!(registry.default_weights(), [1., 2.]);
+
+        // Shrinking leaves the line out under new ids, the old ones still resolve
+        assert!(registry.fit_board((5, 5)));
+        assert_eq!(registry.board_size(), (5, 5));
+        assert_eq!(registry.name(ShapeId(2)), "Dot");
+        assert_eq!(registry.name(ShapeId(1)), "Line 6");
+        assert_eq!(registry.default_weights(), [0., 0., 1.]);
+
+        // A size leaving the same shapes in keeps the pool
+        assert!(registry.fit_board((4, 5)));
+        assert_eq!(registry.iter().count(), 3);
+
+        // Growing back takes the line in again
+        assert!(registry.fit_board((6, 6)));
+        assert_eq!(registry.default_weights(), [0., 0., 0., 1., 2.]);
+        assert_eq!(registry.name(ShapeId(4)), "Line 6");
+    }
+
+    #[test]
+    fn a_board_no_shape_fits_on_is_refused() {
+        let mut registry = ShapeRegistry::new(shapes![(6, 1) "######" as "Line 6"]);
+        assert!(!registry.fit_board((5, 5)));
+        assert_eq!(registry.board_size(), (BOARD_WIDTH, BOARD_HEIGHT));
+        assert!(registry.in_pool(ShapeId(0)));
+    }
+
+    #[test]
+    fn a_shape_without_fields_is_empty() {
+        let empty = Shape::from_pattern(3, 2, "......");
+        assert!(empty.is_empty());
+        assert_eq!(empty.bounds(), (0, 0));
+        assert!(!Shape::from_pattern(3, 2, ".....#").is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+//! An outline traced around the selected shape's silhouette, in the palette's outline
+//! color, so the floating piece reads as one piece rather than a blob of squares. Only
+//! the outer edges are drawn, never those between two of its cells.
+//!
+//! The outline is an entity of its own following the selected shape, so the shape's
+//! children stay its tiles. It's rebuilt whenever the shape or its rotation changes.
+//! The `shape_outline` setting turns it on.
+
+use bevy::prelude::*;
+
+use crate::{
+    palette::ColorPalette,
+    render_order,
+    settings::Settings,
+    shape::{Shape, ShapeId, ShapeInstance, ShapeRegistry},
+    BlocksSystems, SelectedShape, TILE_SIZE,
+};
+
+/// Pixels across the outline's lines.
+const OUTLINE_PIXELS: f32 = 2.;
+
+/// A straight stretch of the outline between two corners of the shape's fields, `(x, y)`
+/// counting fields from the shape's lower left corner.
+type Segment = ((usize, usize), (usize, usize));
+
+/// Outline of the selected shape, following it around.
+#[derive(Component)]
+struct ShapeOutline {
+    /// Shape entity, shape and rotation it was built for.
+    key: (Entity, ShapeId, u8),
+}
+
+pub struct ShapeOutlinePlugin;
+impl Plugin for ShapeOutlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_outline.in_set(BlocksSystems::Presentation));
+    }
+}
+
+/// Edges between a filled field and an empty one or the shape's border, collinear
+/// neighbors merged into one segment.
+fn outline_segments(shape: &Shape) -> Vec<Segment> {
+    let (width, height) = shape.bounds();
+    let filled = |x: usize, y: usize| x < width && y < height && shape.fields[y][x];
+    let mut segments = Vec::new();
+    // Horizontal edges along each row boundary, the rows below and above differing
+    for y in 0..=height {
+        let mut start = None;
+        for x in 0..=width {
+            let below = y > 0 && filled(x, y - 1);
+            let edge = x < width && below != filled(x, y);
+            match (edge, start) {
+                (true, None) => start = Some(x),
+                (false, Some(from)) => {
+                    segments.push(((from, y), (x, y)));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    // Vertical edges along each column boundary
+    for x in 0..=width {
+        let mut start = None;
+        for y in 0..=height {
+            let left = x > 0 && filled(x - 1, y);
+            let edge = y < height && left != filled(x, y);
+            match (edge, start) {
+                (true, None) => start = Some(y),
+                (false, Some(from)) => {
+                    segments.push(((x, from), (x, y)));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+    segments
+}
+
+/// Builds, rebuilds or removes the outline to match the selected shape, and moves it
+/// along with the shape.
+fn update_outline(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    q_selected_shape: Query<(Entity, &ShapeInstance, &Transform, &Visibility), With<SelectedShape>>,
+    mut q_outline: Query<
+        (Entity, &ShapeOutline, &mut Transform, &mut Visibility),
+        Without<SelectedShape>,
+    >,
+) {
+    let selected = q_selected_shape
+        .get_single()
+        .ok()
+        .filter(|_| settings.shape_outline);
+    let key = selected.map(|(entity, instance, ..)| (entity, instance.id, instance.rotation));
+    for (entity, outline, mut transform, mut visibility) in q_outline.iter_mut() {
+        match selected {
+            Some((_, _, &shape_transform, &shape_visibility)) if Some(outline.key) == key => {
+                *transform = shape_transform;
+                transform.translation.z = render_order::SHAPE_OUTLINE;
+                *visibility = shape_visibility;
+            }
+            _ => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    let Some((entity, instance, &shape_transform, &visibility)) = selected else {
+        return;
+    };
+    if q_outline
+        .iter()
+        .any(|(_, outline, ..)| Some(outline.key) == key)
+    {
+        return;
+    }
+
+    let shape = instance.resolve(&registry);
+    let (width, height) = shape.bounds();
+    // Lines are drawn in the shape's fields, scaled up to pixels by its transform
+    let thickness = OUTLINE_PIXELS / TILE_SIZE;
+    let center = Vec2::new(width as f32, height as f32) * 0.5;
+    let mut transform = shape_transform;
+    transform.translation.z = render_order::SHAPE_OUTLINE;
+    commands
+        .spawn((
+            SpatialBundle {
+                transform,
+                visibility,
+                ..default()
+            },
+            ShapeOutline {
+                key: (entity, instance.id, instance.rotation),
+            },
+        ))
+        .with_children(|commands| {
+            for (from, to) in outline_segments(shape) {
+                let from = Vec2::new(from.0 as f32, from.1 as f32);
+                let to = Vec2::new(to.0 as f32, to.1 as f32);
+                // Reaching over the corners, for the lines to meet
+                let size = (to - from).abs() + Vec2::splat(thickness);
+                commands.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: palette.outline,
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(((from + to) * 0.5 - center).extend(0.)),
+                    ..default()
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Segments and their summed length.
+    fn outline(shape: Shape) -> (Vec<Segment>, usize) {
+        let segments = outline_segments(&shape);
+        let length = segments
+            .iter()
+            .map(|&((x0, y0), (x1, y1))| x1.abs_diff(x0) + y1.abs_diff(y0))
+            .sum();
+        (segments, length)
+    }
+
+    #[test]
+    fn a_single_cell_has_four_unit_edges() {
+        let (mut segments, length) = outline(Shape::from_pattern(1, 1, "#"));
+        segments.sort_unstable();
+        assert_eq!(
+            segments,
+            [
+                ((0, 0), (0, 1)),
+                ((0, 0), (1, 0)),
+                ((0, 1), (1, 1)),
+                ((1, 0), (1, 1))
+            ]
+        );
+        assert_eq!(length, 4);
+    }
+
+    #[test]
+    fn edges_between_cells_are_left_out() {
+        let (segments, length) = outline(Shape::from_pattern(3, 1, "###"));
+        assert_eq!(segments.len(), 4);
+        assert_eq!(length, 8);
+    }
+
+    #[test]
+    fn an_l_has_six_straight_edges() {
+        let (segments, length) = outline(Shape::from_pattern(3, 3, "#..#..###"));
+        assert_eq!(segments.len(), 6);
+        assert_eq!(length, 12);
+        assert!(segments
+            .iter()
+            .all(|&((x0, y0), (x1, y1))| x0 == x1 || y0 == y1));
+    }
+
+    #[test]
+    fn a_donut_is_outlined_inside_and_out() {
+        let (segments, length) = outline(Shape::from_pattern(3, 3, "####.####"));
+        assert_eq!(segments.len(), 8);
+        assert_eq!(length, 12 + 4);
+        // The hole's edges, around field (1, 1)
+        for edge in [
+            ((1, 1), (2, 1)),
+            ((1, 2), (2, 2)),
+            ((1, 1), (1, 2)),
+            ((2, 1), (2, 2)),
+        ] {
+            assert!(segments.contains(&edge), "{edge:?}");
+        }
+    }
+}
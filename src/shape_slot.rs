@@ -0,0 +1,390 @@
+//! The tray: beside the board, the selected shape as a silhouette at the board's scale,
+//! to judge it against the room left, and below it the [`UPCOMING_SHAPES`] of the
+//! [`Tray`]. Hovering a slot names its shape. A badge in the selected shape's slot
+//! turns yellow when fewer than [`FEW_PLACEMENTS`] placements are left for the shape
+//! and red when none are, and hovering it tells how many.
+//!
+//! Placements are counted over every rotation of the shape with the bitboard scan,
+//! spread over frames within the [`ScanBudget`]. A board revision for the same shape
+//! only starts a recount when it could change the badge: a count of none stays none
+//! until a cell frees up, and a large count is lowered by what the newly blocked cells
+//! could take at most, the tooltip saying "at least" then. With placement constraints,
+//! which may look at anything of the board, every revision is recounted.
+
+use bevy::prelude::*;
+use smallvec::SmallVec;
+
+use crate::{
+    board::{anchors, Bitboard},
+    budget::{FrameBudget, ScanBudget},
+    constraints::PlacementConstraints,
+    layout::{LayoutConfig, SHAPE_SLOT_CELLS},
+    menu::GameState,
+    render_order,
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    strings::{t, Strings},
+    systems::{Tray, UPCOMING_SHAPES},
+    tooltip::{Tooltip, TooltipArea},
+    BlocksSystems, Board, MainBoard, OverlayBoard, SelectedShape, TILE_SIZE,
+};
+
+/// Placements left below which the badge turns yellow.
+pub(crate) const FEW_PLACEMENTS: usize = 5;
+/// Placements left from which a new board revision only lowers the count, see the
+/// module docs.
+const LARGE_PLACEMENTS: usize = 50;
+const PLENTY_COLOR: Color = Color::rgba(1., 1., 1., 0.3);
+const FEW_COLOR: Color = Color::rgb(0.95, 0.8, 0.2);
+const NONE_COLOR: Color = Color::rgb(0.9, 0.25, 0.2);
+/// Size of the badge, in cells.
+const BADGE_SIZE: f32 = 0.6;
+/// Cells between two slots of the tray.
+const TRAY_GAP: f32 = 0.5;
+
+/// Placements of the selected shape counted against the board.
+#[derive(Default)]
+struct PlacementCount {
+    shape: Option<ShapeId>,
+    /// Revision and blocked cells of the board counted against.
+    board: Option<(u64, Bitboard)>,
+    /// Anchors of every rotation checked so far, `None` once the count is done.
+    scanned: Option<usize>,
+    placements: usize,
+    /// Whether `placements` is a lower bound, kept from an earlier revision.
+    at_least: bool,
+}
+impl PlacementCount {
+    /// A count of the shape to start from scratch.
+    fn start(shape: ShapeId, revision: u64, bitboard: Bitboard) -> Self {
+        Self {
+            shape: Some(shape),
+            board: Some((revision, bitboard)),
+            scanned: Some(0),
+            placements: 0,
+            at_least: false,
+        }
+    }
+
+    /// Carries the finished count over to a new revision of the board without a
+    /// recount, `false` if the badge could change and it needs one.
+    fn carry_over(&mut self, revision: u64, bitboard: Bitboard, cells_per_block: usize) -> bool {
+        let Some((_, counted)) = self.board.filter(|_| self.scanned.is_none()) else {
+            return false;
+        };
+        let (freed, blocked) = counted.rows.iter().zip(&bitboard.rows).fold(
+            (false, 0),
+            |(freed, blocked), (&before, &after)| {
+                (
+                    freed || before & !after != 0,
+                    blocked + (after & !before).count_ones() as usize,
+                )
+            },
+        );
+        // Each newly blocked cell takes at most the placements covering it
+        let lowest = self.placements.saturating_sub(blocked * cells_per_block);
+        let unchanged = self.placements == 0 || lowest >= LARGE_PLACEMENTS;
+        if freed || !unchanged {
+            return false;
+        }
+        self.at_least |= lowest < self.placements;
+        self.placements = lowest;
+        self.board = Some((revision, bitboard));
+        true
+    }
+}
+
+/// Placements left for the selected shape on the main board and whether that's a lower
+/// bound, `None` until counted.
+#[derive(Resource, Default, PartialEq)]
+pub(crate) struct PlacementsLeft(pub Option<(usize, bool)>);
+
+/// A slot of the tray, 0 for the selected shape and the next ones after it.
+#[derive(Component)]
+struct ShapeSlot(usize);
+
+/// Background of a slot, naming its shape in its tooltip.
+#[derive(Component)]
+struct SlotBackground(usize);
+
+/// A field of a slot's silhouette, rebuilt when the slot's shape changes.
+#[derive(Component)]
+struct SlotTile(usize);
+
+#[derive(Component)]
+struct FitBadge;
+
+pub struct ShapeSlotPlugin;
+impl Plugin for ShapeSlotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlacementsLeft>()
+            .add_systems(Startup, spawn_shape_slots)
+            .add_systems(
+                Update,
+                (
+                    (place_shape_slots, draw_silhouettes),
+                    count_placements.pipe(show_badge),
+                )
+                    .in_set(BlocksSystems::Presentation),
+            );
+    }
+}
+
+fn spawn_shape_slots(mut commands: Commands) {
+    for index in 0..=UPCOMING_SHAPES {
+        commands
+            .spawn((
+                SpatialBundle {
+                    transform: Transform::from_xyz(0., 0., render_order::SHAPE_SLOT)
+                        .with_scale(Vec3::splat(TILE_SIZE)),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                ShapeSlot(index),
+            ))
+            .with_children(|commands| {
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(1., 1., 1., 0.05),
+                            custom_size: Some(Vec2::splat(SHAPE_SLOT_CELLS)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    Tooltip(String::new()),
+                    TooltipArea(Rect::from_center_size(
+                        Vec2::ZERO,
+                        Vec2::splat(SHAPE_SLOT_CELLS),
+                    )),
+                    SlotBackground(index),
+                ));
+                if index > 0 {
+                    return;
+                }
+                // In the upper right corner, over the silhouette
+                let corner = 0.5 * (SHAPE_SLOT_CELLS - BADGE_SIZE);
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: PLENTY_COLOR,
+                            custom_size: Some(Vec2::ONE),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(corner, corner, 0.02)
+                            .with_scale(Vec3::splat(BADGE_SIZE)),
+                        ..default()
+                    },
+                    Tooltip(String::new()),
+                    // The badge is a cell wide around its center before scaling
+                    TooltipArea(Rect::from_center_size(Vec2::ZERO, Vec2::ONE)),
+                    FitBadge,
+                ));
+            });
+    }
+}
+
+/// Moves the slots where the layout puts them, the next shapes' below the selected
+/// one's, and shows them while playing.
+fn place_shape_slots(
+    state: Res<State<GameState>>,
+    layout: Res<LayoutConfig>,
+    q_board: Query<&GlobalTransform, (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_slots: Query<(&ShapeSlot, &mut Transform, &mut Visibility)>,
+) {
+    let in_game = matches!(state.get(), GameState::Playing | GameState::Sandbox);
+    let board_center = q_board
+        .get_single()
+        .ok()
+        .map(|transform| transform.translation().truncate());
+    for (slot, mut transform, mut visibility) in q_slots.iter_mut() {
+        visibility.set_if_neq(if in_game {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+        let Some(board_center) = board_center else {
+            continue;
+        };
+        let offset = Vec2::NEG_Y * slot.0 as f32 * (SHAPE_SLOT_CELLS + TRAY_GAP);
+        let center = board_center + (layout.shape_slot + offset) * TILE_SIZE;
+        if transform.translation.truncate() != center {
+            transform.translation.x = center.x;
+            transform.translation.y = center.y;
+        }
+    }
+}
+
+/// Draws the silhouette of each slot's shape and names it in the slot's tooltip.
+fn draw_silhouettes(
+    mut commands: Commands,
+    registry: Res<ShapeRegistry>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+    q_tray: Query<&Tray, With<MainBoard>>,
+    q_slots: Query<(Entity, &ShapeSlot)>,
+    mut q_backgrounds: Query<(&SlotBackground, &mut Tooltip)>,
+    q_tiles: Query<(Entity, &SlotTile)>,
+    mut drawn: Local<[Option<(ShapeId, u8)>; UPCOMING_SHAPES + 1]>,
+) {
+    let (Ok(selected_shape), Ok(tray)) = (q_selected_shape.get_single(), q_tray.get_single())
+    else {
+        return;
+    };
+    let shapes = std::iter::once(selected_shape).chain(tray.0.iter().take(UPCOMING_SHAPES));
+    for (index, instance) in shapes.enumerate() {
+        let key = (instance.id, instance.rotation);
+        if drawn[index] == Some(key) && !registry.is_changed() {
+            continue;
+        }
+        drawn[index] = Some(key);
+        for (tile, _) in q_tiles.iter().filter(|(_, tile)| tile.0 == index) {
+            commands.entity(tile).despawn_recursive();
+        }
+        for (_, mut tooltip) in q_backgrounds
+            .iter_mut()
+            .filter(|(background, _)| background.0 == index)
+        {
+            tooltip.0 = registry.name(instance.id).to_owned();
+        }
+        let Some((slot, _)) = q_slots.iter().find(|(_, slot)| slot.0 == index) else {
+            continue;
+        };
+        // The next shapes are dimmer than the one to place
+        let alpha = if index == 0 { 0.35 } else { 0.2 };
+        let shape = instance.resolve(&registry);
+        let (width, height) = shape.bounds();
+        commands.entity(slot).with_children(|commands| {
+            for (y, row) in shape.fields.iter().enumerate().take(height) {
+                for (x, _) in row
+                    .iter()
+                    .enumerate()
+                    .take(width)
+                    .filter(|(_, &field)| field)
+                {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: Color::rgba(1., 1., 1., alpha),
+                                custom_size: Some(Vec2::splat(0.9)),
+                                ..default()
+                            },
+                            transform: Transform::from_xyz(
+                                x as f32 - 0.5 * width as f32 + 0.5,
+                                y as f32 - 0.5 * height as f32 + 0.5,
+                                0.01,
+                            ),
+                            ..default()
+                        },
+                        SlotTile(index),
+                    ));
+                }
+            }
+        });
+    }
+}
+
+/// Counts the placements of the selected shape on the main board, `Some` with the count
+/// and whether it's a lower bound once done.
+fn count_placements(
+    registry: Res<ShapeRegistry>,
+    constraints: Res<PlacementConstraints>,
+    scan_budget: Res<ScanBudget>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+    mut count: Local<PlacementCount>,
+) -> Option<(usize, bool)> {
+    let (Ok(board), Ok(selected_shape)) = (q_board.get_single(), q_selected_shape.get_single())
+    else {
+        return None;
+    };
+    let board = &board.grid;
+    let id = selected_shape.id;
+    let rotations: SmallVec<[_; 4]> = (0..registry.rotation_count(id))
+        .map(|rotation| registry.get(id, rotation as u8))
+        .collect();
+    let revision = board.revision();
+    if count.shape != Some(id) || registry.is_changed() || constraints.is_changed() {
+        *count = PlacementCount::start(id, revision, board.bitboard());
+    } else if count.board.is_some_and(|(counted, _)| counted != revision) {
+        let bitboard = board.bitboard();
+        let cells_per_block = rotations
+            .iter()
+            .map(|shape| shape.bits().count_ones() as usize)
+            .sum();
+        if !constraints.is_empty() || !count.carry_over(revision, bitboard, cells_per_block) {
+            *count = PlacementCount::start(id, revision, bitboard);
+        }
+    }
+
+    if let (Some(scanned), Some((_, bitboard))) = (count.scanned, count.board) {
+        let budget = FrameBudget::new(scan_budget.0);
+        let candidates = rotations.iter().flat_map(|&shape| {
+            let shape_rows = shape.row_masks();
+            anchors(shape).map(move |anchor| (shape, shape_rows, anchor))
+        });
+        let mut done = true;
+        for (index, (shape, shape_rows, (x, y))) in candidates.enumerate().skip(scanned) {
+            if !budget.check() {
+                count.scanned = Some(index);
+                done = false;
+                break;
+            }
+            if bitboard.fits_at(&shape_rows, (x, y))
+                && constraints
+                    .check(board, shape, (x as isize, y as isize))
+                    .is_none()
+            {
+                count.placements += 1;
+            }
+        }
+        if done {
+            count.scanned = None;
+        }
+    }
+    count
+        .scanned
+        .is_none()
+        .then_some((count.placements, count.at_least))
+}
+
+/// Colors the badge by the count and puts it in its tooltip, keeping the last ones
+/// while a recount is under way.
+fn show_badge(
+    In(count): In<Option<(usize, bool)>>,
+    strings: Res<Strings>,
+    mut left: ResMut<PlacementsLeft>,
+    mut q_badge: Query<(&mut Sprite, &mut Tooltip), With<FitBadge>>,
+    mut shown: Local<Option<(usize, bool)>>,
+) {
+    if count.is_some() {
+        left.set_if_neq(PlacementsLeft(count));
+    }
+    let (Some((placements, at_least)), Ok((mut sprite, mut tooltip))) =
+        (count, q_badge.get_single_mut())
+    else {
+        return;
+    };
+    let color = if placements == 0 {
+        NONE_COLOR
+    } else if placements < FEW_PLACEMENTS {
+        FEW_COLOR
+    } else {
+        PLENTY_COLOR
+    };
+    if sprite.color != color {
+        sprite.color = color;
+    }
+    // Only the texts of another count or language are worth allocating
+    if *shown == count && !strings.is_changed() {
+        return;
+    }
+    *shown = count;
+    let key = if at_least {
+        "hud.placements_at_least"
+    } else {
+        "hud.placements"
+    };
+    let text = t!(strings, key, placements);
+    if tooltip.0 != text {
+        tooltip.0 = text;
+    }
+}
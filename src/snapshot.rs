@@ -0,0 +1,348 @@
+//! Rendering a board into an offscreen image, e.g. for save thumbnails.
+//!
+//! A capture spawns a camera rendering to the image as a child of the board, seeing
+//! only the board's tiles through [`SNAPSHOT_LAYER`], and despawns it once it rendered.
+//! The render world copies the image back from the GPU meanwhile, and a task encodes it
+//! as PNG for a [`SnapshotReadyEvent`].
+
+use std::{
+    io::Cursor,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Mutex,
+    },
+};
+
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    ecs::system::SystemParam,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssets,
+        render_resource::{
+            BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Extent3d, ImageCopyBuffer,
+            ImageDataLayout, MapMode, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureUsages,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        texture::{CompressedImageFormats, ImageSampler, ImageType},
+        view::RenderLayers,
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    tasks::AsyncComputeTaskPool,
+};
+use image::ImageOutputFormat;
+
+use crate::Board;
+
+/// Render layer the snapshot camera sees, added to the tiles of the captured board.
+const SNAPSHOT_LAYER: u8 = 1;
+/// Image pixels per board cell.
+const SNAPSHOT_CELL_PIXELS: u32 = 8;
+/// Frames the camera is kept around for. The image is read back after the second render,
+/// the board's pipelines may not be ready for the first.
+const SNAPSHOT_FRAMES: u32 = 3;
+/// Frames left on the camera when the image it rendered is read back.
+const READBACK_FRAMES_LEFT: u32 = 1;
+
+#[derive(Component)]
+struct SnapshotCamera {
+    frames_left: u32,
+    /// Entities given the snapshot layer for the capture.
+    tiles: Vec<Entity>,
+    image: Handle<Image>,
+}
+
+/// A capture read back from the GPU, with the image encoded as PNG.
+#[derive(Event)]
+pub struct SnapshotReadyEvent {
+    /// The handle [`BoardSnapshot::capture`] returned.
+    pub image: Handle<Image>,
+    pub png: Vec<u8>,
+}
+
+/// Captures read back and encoded by the render world's tasks. Only there with a renderer.
+#[derive(Resource)]
+struct SnapshotReceiver(Mutex<Receiver<SnapshotReadyEvent>>);
+
+/// Render world side of [`SnapshotReceiver`].
+#[derive(Resource)]
+struct SnapshotSender(Sender<SnapshotReadyEvent>);
+
+/// Images of the cameras extracted on their readback frame, in the render world.
+#[derive(Resource, Default)]
+struct PendingReadbacks(Vec<Handle<Image>>);
+
+#[derive(SystemParam)]
+pub struct BoardSnapshot<'w, 's> {
+    commands: Commands<'w, 's>,
+    images: ResMut<'w, Assets<Image>>,
+    receiver: Option<Res<'w, SnapshotReceiver>>,
+    q_board: Query<'w, 's, &'static Board>,
+}
+impl BoardSnapshot<'_, '_> {
+    /// Starts rendering the board into a new image. The handle is usable right away,
+    /// the image holds the board once the frame was rendered, and a
+    /// [`SnapshotReadyEvent`] brings its PNG once it was read back. `None` if there is
+    /// no such board or nothing renders, as in headless runs.
+    pub fn capture(&mut self, board_entity: Entity) -> Option<Handle<Image>> {
+        self.receiver.as_ref()?;
+        let board = self.q_board.get(board_entity).ok()?;
+        let size = Extent3d {
+            width: board.grid.width() as u32 * SNAPSHOT_CELL_PIXELS,
+            height: board.grid.height() as u32 * SNAPSHOT_CELL_PIXELS,
+            depth_or_array_layers: 1,
+        };
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: None,
+                size,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Bgra8UnormSrgb,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::COPY_DST
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+            ..default()
+        };
+        image.resize(size);
+        let image = self.images.add(image);
+
+        let tiles = board.drawing_entities();
+        for &tile in &tiles {
+            self.commands
+                .entity(tile)
+                .insert(RenderLayers::from_layers(&[0, SNAPSHOT_LAYER]));
+        }
+        let camera = self
+            .commands
+            .spawn((
+                Camera2dBundle {
+                    camera: Camera {
+                        target: RenderTarget::Image(image.clone()),
+                        order: -1,
+                        ..default()
+                    },
+                    camera_2d: Camera2d {
+                        clear_color: ClearColorConfig::Custom(Color::BLACK),
+                    },
+                    // The camera inherits the board's scale, so this is in cells per pixel
+                    projection: OrthographicProjection {
+                        scale: 1. / SNAPSHOT_CELL_PIXELS as f32,
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(0., 0., 100.),
+                    ..default()
+                },
+                UiCameraConfig { show_ui: false },
+                RenderLayers::layer(SNAPSHOT_LAYER),
+                SnapshotCamera {
+                    frames_left: SNAPSHOT_FRAMES,
+                    tiles,
+                    image: image.clone(),
+                },
+            ))
+            .id();
+        self.commands.entity(board_entity).add_child(camera);
+        Some(image)
+    }
+}
+
+pub struct SnapshotPlugin;
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SnapshotReadyEvent>()
+            .add_systems(First, send_ready_snapshots)
+            .add_systems(Last, finish_snapshots);
+        let (sender, receiver) = channel();
+        let Ok(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(SnapshotSender(sender))
+            .init_resource::<PendingReadbacks>()
+            .add_systems(ExtractSchedule, extract_readbacks)
+            .add_systems(Render, read_back_snapshots.in_set(RenderSet::Cleanup));
+        app.insert_resource(SnapshotReceiver(Mutex::new(receiver)));
+    }
+}
+
+/// Decodes a PNG written for a [`SnapshotReadyEvent`], e.g. a thumbnail kept in a save.
+pub fn decode_png(png: &[u8]) -> Option<Image> {
+    Image::from_buffer(
+        png,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+    )
+    .map_err(|err| warn!("failed to decode a snapshot: {err}"))
+    .ok()
+}
+
+fn send_ready_snapshots(
+    receiver: Option<Res<SnapshotReceiver>>,
+    mut ready: EventWriter<SnapshotReadyEvent>,
+) {
+    let Some(receiver) = receiver else {
+        return;
+    };
+    ready.send_batch(receiver.0.lock().unwrap().try_iter());
+}
+
+fn extract_readbacks(
+    mut readbacks: ResMut<PendingReadbacks>,
+    q_cameras: Extract<Query<&SnapshotCamera>>,
+) {
+    readbacks.0.extend(
+        q_cameras
+            .iter()
+            .filter(|camera| camera.frames_left == READBACK_FRAMES_LEFT)
+            .map(|camera| camera.image.clone()),
+    );
+}
+
+/// Copies the rendered images into buffers once the frame was submitted, and encodes
+/// them once the buffers are mapped.
+fn read_back_snapshots(
+    mut readbacks: ResMut<PendingReadbacks>,
+    sender: Res<SnapshotSender>,
+    images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    for handle in readbacks.0.drain(..) {
+        let Some(gpu_image) = images.get(&handle) else {
+            continue;
+        };
+        let (width, height) = (gpu_image.size.x as u32, gpu_image.size.y as u32);
+        // Bgra8UnormSrgb, rows padded to the copy alignment
+        let row_bytes = width as usize * 4;
+        let padded_row_bytes = RenderDevice::align_copy_bytes_per_row(row_bytes);
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("snapshot_readback"),
+            size: (padded_row_bytes * height as usize) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = render_device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("snapshot_readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            gpu_image.texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes as u32),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        render_queue.submit([encoder.finish()]);
+
+        let sender = sender.0.clone();
+        let mapped = buffer.clone();
+        // Mapping is polled with every submission of the render queue
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            if let Err(err) = result {
+                error!("failed to read back a snapshot: {err}");
+                return;
+            }
+            let data = mapped
+                .slice(..)
+                .get_mapped_range()
+                .chunks(padded_row_bytes)
+                .flat_map(|row| &row[..row_bytes])
+                .copied()
+                .collect();
+            mapped.unmap();
+            AsyncComputeTaskPool::get()
+                .spawn(async move {
+                    let size = Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    };
+                    let image = Image::new(
+                        size,
+                        TextureDimension::D2,
+                        data,
+                        TextureFormat::Bgra8UnormSrgb,
+                    );
+                    let Some(png) = encode_png(image) else {
+                        return;
+                    };
+                    let ready = SnapshotReadyEvent { image: handle, png };
+                    // The receiver only goes away with the app
+                    let _ = sender.send(ready);
+                })
+                .detach();
+        });
+    }
+}
+
+fn encode_png(image: Image) -> Option<Vec<u8>> {
+    let image = image
+        .try_into_dynamic()
+        .map_err(|err| error!("failed to convert a snapshot: {err}"))
+        .ok()?;
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .map_err(|err| error!("failed to encode a snapshot: {err}"))
+        .ok()?;
+    Some(png)
+}
+
+/// Despawns the cameras of finished captures and takes the tiles off their layer.
+fn finish_snapshots(mut commands: Commands, mut q_cameras: Query<(Entity, &mut SnapshotCamera)>) {
+    for (entity, mut camera) in q_cameras.iter_mut() {
+        camera.frames_left -= 1;
+        if camera.frames_left > 0 {
+            continue;
+        }
+        for &tile in &camera.tiles {
+            if let Some(mut tile) = commands.get_entity(tile) {
+                tile.remove::<RenderLayers>();
+            }
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_back_pixels_encode_to_a_png_bevy_decodes() {
+        let size = Extent3d {
+            width: 2,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        // Blue then opaque red, in the camera target's channel order
+        let data = vec![255, 0, 0, 255, 0, 0, 255, 255];
+        let image = Image::new(
+            size,
+            TextureDimension::D2,
+            data,
+            TextureFormat::Bgra8UnormSrgb,
+        );
+
+        let decoded = decode_png(&encode_png(image).unwrap()).unwrap();
+        assert_eq!(decoded.texture_descriptor.size, size);
+        assert_eq!(decoded.data, [0, 0, 255, 255, 255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,245 @@
+//! Races against the clock: sprints clear [`RuleSet::sprint_lines`] lines, digs clear
+//! the [`RuleSet::dig_rows`] garbage rows the board starts with, where other lines
+//! don't count. The play time is the result, entered in the mode's leaderboard where
+//! lower is better. A board locking up before the goal ends the run without a time,
+//! showing how far it got instead. Either way the game-over screen shows the results,
+//! see [`results_text`].
+
+use bevy::prelude::*;
+
+use crate::{
+    leaderboard::{record_result, Entry, LatestPlace, Leaderboard},
+    menu::{GameMode, GameState},
+    persistence::PersistRequest,
+    play_time::PlayTime,
+    replay::Playback,
+    restart::RestartEvent,
+    rules::RuleSet,
+    scoring::Level,
+    strings::{t, Strings},
+    Board, GameOverEvent, MainBoard, OverlayBoard, PlacementEvent,
+};
+
+/// Whether the current race ended, by finishing or locking up, and how.
+#[derive(Resource, Default)]
+pub struct SprintProgress {
+    finished: bool,
+    result: Option<SprintEndedEvent>,
+}
+
+/// Run condition stopping placements and the clock once the race ended.
+pub fn sprint_unfinished(progress: Res<SprintProgress>) -> bool {
+    !progress.finished
+}
+
+/// Sent once the race ended.
+#[derive(Event, Clone, Copy)]
+pub struct SprintEndedEvent {
+    /// Milliseconds it took, `None` if the board locked up first.
+    pub time: Option<u64>,
+    /// Lines or garbage rows cleared.
+    pub reached: usize,
+}
+
+/// Lines or garbage rows a race has to clear, and how many it did.
+struct Goal {
+    target: usize,
+    reached: usize,
+}
+impl Goal {
+    /// The goal of the mode, if it's a race.
+    fn of(mode: GameMode, rules: &RuleSet, level: &Level, board: &Board) -> Option<Self> {
+        match mode {
+            GameMode::Sprint => Some(Self {
+                target: rules.sprint_lines,
+                reached: level.lines as usize,
+            }),
+            GameMode::Dig => Some(Self {
+                target: rules.dig_rows,
+                reached: rules.dig_rows.saturating_sub(board.grid.garbage_rows()),
+            }),
+            _ => None,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.target.saturating_sub(self.reached)
+    }
+}
+
+#[derive(Component)]
+struct GoalText;
+
+pub struct SprintPlugin;
+impl Plugin for SprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SprintProgress>()
+            .add_event::<SprintEndedEvent>()
+            .add_systems(Startup, spawn_goal_text)
+            .add_systems(Update, reset_sprint)
+            // Clears are only scored by the end of the frame
+            .add_systems(
+                PostUpdate,
+                (
+                    check_sprint.run_if(in_state(GameState::Playing)).run_if(
+                        |mode: Res<GameMode>| matches!(*mode, GameMode::Sprint | GameMode::Dig),
+                    ),
+                    update_goal_text,
+                ),
+            );
+    }
+}
+
+/// `m:ss.cc`, minutes going past 59 rather than adding hours.
+pub fn format_time(millis: u64) -> String {
+    let (seconds, centis) = (millis / 1000, millis % 1000 / 10);
+    format!("{}:{:02}.{centis:02}", seconds / 60, seconds % 60)
+}
+
+fn reset_sprint(mut progress: ResMut<SprintProgress>, mut restarts: EventReader<RestartEvent>) {
+    if restarts.read().count() > 0 {
+        *progress = SprintProgress::default();
+    }
+}
+
+/// Ends the race once a placement reached its goal, or when the game ended before it.
+/// Finished runs go on the leaderboard, unless they are a replay being watched.
+fn check_sprint(
+    (mode, rules): (Res<GameMode>, Res<RuleSet>),
+    (play_time, playback): (Res<PlayTime>, Option<Res<Playback>>),
+    mut progress: ResMut<SprintProgress>,
+    (mut leaderboard, mut latest): (ResMut<Leaderboard>, ResMut<LatestPlace>),
+    mut placements: EventReader<PlacementEvent>,
+    mut game_over: EventReader<GameOverEvent>,
+    mut ended: EventWriter<SprintEndedEvent>,
+    mut persist: EventWriter<PersistRequest>,
+    q_board: Query<(&Board, &Level, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    let locked_up = game_over.read().count() > 0;
+    let Ok((board, level, entity)) = q_board.get_single() else {
+        return;
+    };
+    // Before the first placement a dig's garbage may not be down yet
+    let placed = placements.read().any(|event| event.board == entity);
+    let Some(goal) = Goal::of(*mode, &rules, level, board) else {
+        return;
+    };
+    if progress.finished {
+        return;
+    }
+    let time = if placed && goal.remaining() == 0 {
+        let millis = (play_time.0 * 1000.).round() as u64;
+        let entry = Entry {
+            value: millis,
+            play_time: play_time.0,
+        };
+        if playback.is_none() {
+            record_result((&mut leaderboard, &mut latest), &mut persist, *mode, entry);
+        }
+        Some(millis)
+    } else if locked_up {
+        None
+    } else {
+        return;
+    };
+    let result = SprintEndedEvent {
+        time,
+        reached: goal.reached,
+    };
+    progress.finished = true;
+    progress.result = Some(result);
+    ended.send(result);
+}
+
+/// Title and detail line of the race's results once it ended, `None` before or outside
+/// races.
+pub fn results_text(
+    (mode, rules): (GameMode, &RuleSet),
+    strings: &Strings,
+    leaderboard: &Leaderboard,
+    progress: &SprintProgress,
+) -> Option<(String, String)> {
+    let result = progress.result?;
+    Some(match result.time {
+        Some(time) => (
+            t!(strings, "sprint.finished", format_time(time)),
+            t!(
+                strings,
+                "sprint.best",
+                format_time(leaderboard.best(mode).unwrap_or(time))
+            ),
+        ),
+        None if mode == GameMode::Dig => (
+            strings.get("sprint.dnf").to_owned(),
+            t!(strings, "dig.dnf_rows", result.reached, rules.dig_rows),
+        ),
+        None => (
+            strings.get("sprint.dnf").to_owned(),
+            t!(
+                strings,
+                "sprint.dnf_lines",
+                result.reached,
+                rules.sprint_lines
+            ),
+        ),
+    })
+}
+
+fn spawn_goal_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 40.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(56.),
+                left: Val::Percent(40.),
+                display: Display::None,
+                ..default()
+            },
+            ..default()
+        },
+        GoalText,
+    ));
+}
+
+/// Shows what's left to clear in races, hiding the text in other modes.
+fn update_goal_text(
+    (mode, rules): (Res<GameMode>, Res<RuleSet>),
+    strings: Res<Strings>,
+    state: Res<State<GameState>>,
+    q_board: Query<(&Board, &Level), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut q_text: Query<(&mut Text, &mut Style), With<GoalText>>,
+) {
+    let (Ok((board, level)), Ok((mut text, mut style))) =
+        (q_board.get_single(), q_text.get_single_mut())
+    else {
+        return;
+    };
+    let goal = Goal::of(*mode, &rules, level, board).filter(|_| *state.get() == GameState::Playing);
+    let display = if goal.is_some() {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    if style.display != display {
+        style.display = display;
+    }
+    let Some(goal) = goal else {
+        return;
+    };
+    let key = match *mode {
+        GameMode::Dig => "dig.remaining",
+        _ => "sprint.remaining",
+    };
+    // Only touched when it changes, the board is checked every frame
+    let value = t!(strings, key, goal.remaining());
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
@@ -0,0 +1,145 @@
+//! Totals over every game played, in `stats.txt`. A game counts once it ends: at game
+//! over, or when it's restarted or left for the menu halfway.
+
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::{
+    menu::GameState,
+    persistence::{PersistAppExt, PersistRequest, Persisted},
+    play_time::{reset_play_time, PlayTime},
+    restart::RestartEvent,
+    scoring::{Level, Score},
+    MainBoard,
+};
+
+#[derive(Resource, Default, Clone, PartialEq, Debug)]
+pub struct LifetimeStats {
+    pub games: u64,
+    /// Seconds, see [`PlayTime`].
+    pub play_time: f32,
+    pub lines: u64,
+    pub best_score: u64,
+}
+impl LifetimeStats {
+    /// Adds a game that was played for `play_time` seconds.
+    fn add_game(&mut self, play_time: f32, score: u64, lines: u32) {
+        self.games += 1;
+        self.play_time += play_time;
+        self.lines += u64::from(lines);
+        self.best_score = self.best_score.max(score);
+    }
+
+    /// `h:mm:ss` of the total play time.
+    pub fn format_play_time(&self) -> String {
+        let seconds = self.play_time as u64;
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+impl Persisted for LifetimeStats {
+    const PATH: &'static str = "stats.txt";
+
+    /// Reads `key = value` lines, keys that are missing stay at zero.
+    fn parse(text: &str) -> Option<Self> {
+        let mut stats = Self::default();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            match key.trim() {
+                "games" => stats.games = value.parse().ok()?,
+                "play_time" => stats.play_time = value.parse().ok()?,
+                "lines" => stats.lines = value.parse().ok()?,
+                "best_score" => stats.best_score = value.parse().ok()?,
+                key => warn!("unknown stat {key}"),
+            }
+        }
+        Some(stats)
+    }
+}
+impl fmt::Display for LifetimeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "games = {}", self.games)?;
+        writeln!(f, "play_time = {}", self.play_time)?;
+        writeln!(f, "lines = {}", self.lines)?;
+        writeln!(f, "best_score = {}", self.best_score)
+    }
+}
+
+/// Whether the current game was added to the [`LifetimeStats`] already.
+#[derive(Resource, Default)]
+pub(crate) struct Counted(bool);
+
+pub struct StatsPlugin;
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.load_persisted::<LifetimeStats>()
+            .init_resource::<Counted>()
+            .add_systems(OnEnter(GameState::GameOver), count_game)
+            .add_systems(OnEnter(GameState::Menu), count_game)
+            // Before the restart forgets the game's play time
+            .add_systems(Update, count_restarted_game.before(reset_play_time));
+    }
+}
+
+/// Adds the game just ended, unless it was added already or never played.
+pub(crate) fn count_game(
+    play_time: Res<PlayTime>,
+    mut counted: ResMut<Counted>,
+    mut stats: ResMut<LifetimeStats>,
+    mut persist: EventWriter<PersistRequest>,
+    q_board: Query<(&Score, &Level), With<MainBoard>>,
+) {
+    if counted.0 || play_time.0 <= 0. {
+        return;
+    }
+    let Ok((score, level)) = q_board.get_single() else {
+        return;
+    };
+    counted.0 = true;
+    stats.add_game(play_time.0, score.0, level.lines);
+    persist.send(stats.persist_request());
+}
+
+/// Adds the game a restart ends, the game starting then counting anew.
+fn count_restarted_game(
+    mut restarts: EventReader<RestartEvent>,
+    play_time: Res<PlayTime>,
+    mut counted: ResMut<Counted>,
+    mut stats: ResMut<LifetimeStats>,
+    mut persist: EventWriter<PersistRequest>,
+    q_board: Query<(&Score, &Level), With<MainBoard>>,
+) {
+    if restarts.read().count() == 0 {
+        return;
+    }
+    if !counted.0 && play_time.0 > 0. {
+        if let Ok((score, level)) = q_board.get_single() {
+            stats.add_game(play_time.0, score.0, level.lines);
+            persist.send(stats.persist_request());
+        }
+    }
+    counted.0 = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_read_back_what_they_write() {
+        let mut stats = LifetimeStats::default();
+        stats.add_game(65.5, 1200, 14);
+        stats.add_game(30., 800, 6);
+        let read = LifetimeStats::parse(&stats.to_string()).unwrap();
+        assert_eq!(read, stats);
+        assert_eq!(read.games, 2);
+        assert_eq!(read.best_score, 1200);
+        assert_eq!(read.format_play_time(), "0:01:35");
+    }
+}
@@ -0,0 +1,383 @@
+//! Translations of the user-facing text. English is built in, other languages are the
+//! `.ron` files of the `lang` directory named by language code, like `lang/de.ron`,
+//! each a RON map from keys to texts. `{}` in a text stands for an argument, filled in
+//! order.
+//!
+//! L in the menu switches to the next language and keeps it in `language.txt`. Systems
+//! showing text rebuild it when [`Strings`] changes.
+
+use std::{collections::HashMap, fmt, io};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, Task},
+};
+
+use crate::{
+    menu::GameState,
+    persistence::{read_text_files, DataDir, PersistAppExt, PersistRequest, Persisted},
+};
+
+const LANG_DIR: &str = "lang";
+const ENGLISH_CODE: &str = "en";
+
+/// Every key with its English text.
+const ENGLISH: &[(&str, &str)] = &[
+    ("language.name", "English"),
+    // Separator between groups of three digits
+    ("number.group", ","),
+    ("menu.title", "Blocks"),
+    ("menu.endless", "Endless"),
+    ("menu.timed", "Timed"),
+    ("menu.puzzle", "Puzzle"),
+    ("menu.daily", "Daily"),
+    ("menu.versus", "Versus"),
+    ("menu.cascade", "Cascade"),
+    ("menu.adjacency", "Adjacency"),
+    ("menu.blockudoku", "Blockudoku"),
+    ("menu.custom", "Custom"),
+    ("menu.sprint", "Sprint"),
+    ("menu.dig", "Dig"),
+    ("menu.start_empty", "Start: empty"),
+    ("menu.start_scattered", "Start: scattered"),
+    ("menu.board_size", "Board: {}x{}"),
+    ("menu.sandbox", "Sandbox"),
+    ("menu.watch_replay", "Watch replay"),
+    ("menu.settings", "Settings"),
+    ("menu.quit", "Quit"),
+    ("settings.title", "Settings"),
+    ("settings.on", "on"),
+    ("settings.off", "off"),
+    ("settings.screen_shake", "Screen shake: {}"),
+    ("settings.intro_animation", "Intro animation: {}"),
+    ("settings.tile_fade", "Tile fade: {}"),
+    ("settings.ghost_trail", "Ghost trail: {}"),
+    ("settings.shape_outline", "Shape outline: {}"),
+    ("settings.dynamic_audio", "Dynamic audio: {}"),
+    ("settings.announcements", "Announcements: {}"),
+    ("settings.adaptive_assist", "Adaptive assist: {}"),
+    ("settings.layout", "Layout: {}"),
+    ("settings.landscape", "landscape"),
+    ("settings.portrait", "portrait"),
+    ("settings.back", "Back"),
+    ("settings.preview_swatch", "Placed and preview tiles"),
+    ("settings.hint", "Up/Down choose  Enter change  Esc back"),
+    ("pause.title", "Paused"),
+    ("pause.resume", "Resume"),
+    ("pause.restart", "Restart"),
+    ("pause.menu", "Main menu"),
+    ("game_over.title", "Game over"),
+    ("game_over.stats", "Score {}  Lines {}  Level {}"),
+    ("game_over.time", "Time {}"),
+    ("game_over.best", "New best!"),
+    ("game_over.place", "#{} on the leaderboard"),
+    (
+        "game_over.lifetime",
+        "{} games played  {} in total  best score {}",
+    ),
+    ("game_over.play_again", "Play again"),
+    ("game_over.copy_replay", "Copy replay code"),
+    ("game_over.menu", "Main menu"),
+    ("replay.status", "Replay {}/{} ({})  Space: pause  F: speed"),
+    ("replay.playing", "playing"),
+    ("replay.paused", "paused, Right to step"),
+    ("replay.fast", "2x"),
+    ("replay.finished", "finished"),
+    (
+        "replay.paste_hint",
+        "Replay code, paste with Ctrl+V   Enter watch  Esc close",
+    ),
+    ("attract.score", "Demo  Score {}  Lines {}"),
+    ("attract.press_any_key", "Press any key"),
+    ("attract.clear_lines", "Clear lines to score!"),
+    ("attract.rotate", "Rotate shapes with R or a right click"),
+    ("attract.bonus", "Clear bonus cells to multiply your points"),
+    ("hud.score", "Level {}  Score {}  Lines {}"),
+    ("hud.combo", "Combo x{}"),
+    ("hud.click_again", "Click again to place"),
+    ("hud.assist", "Assist"),
+    (
+        "minimap.tooltip",
+        "Column {}, row {}. Click to look there, right click to look back",
+    ),
+    ("minimap.outside", "Beyond the board"),
+    ("hud.placements", "{} placements left"),
+    ("hud.placements_at_least", "At least {} placements left"),
+    ("overtime.countdown", "Overtime {}s"),
+    ("overtime.over", "Overtime over, Ctrl+R to restart"),
+    ("puzzle.solved", "Puzzle solved  {}"),
+    (
+        "puzzle.result",
+        "{} placements, par {}. Ctrl+R to play again",
+    ),
+    ("sprint.remaining", "{} lines to go"),
+    ("sprint.finished", "Sprint finished  {}"),
+    ("sprint.best", "Best {}. Ctrl+R to run again"),
+    ("sprint.dnf", "Did not finish"),
+    ("sprint.dnf_lines", "{} of {} lines. Ctrl+R to try again"),
+    ("dig.remaining", "{} garbage rows to go"),
+    ("dig.dnf_rows", "{} of {} garbage rows. Ctrl+R to try again"),
+    ("toast.shapes_updated", "Shape set updated"),
+    ("toast.topped_out", "Topped out!"),
+    (
+        "toast.opponent_disconnected",
+        "Opponent disconnected, playing on locally",
+    ),
+    ("toast.no_moves", "No moves left, Ctrl+R to restart"),
+    ("toast.tutorial_complete", "Tutorial complete, have fun!"),
+    ("toast.plan_overlaps", "Overlaps the plan"),
+    ("toast.plan_aborted", "Plan no longer fits, stopped"),
+    (
+        "toast.hook_failed",
+        "A script hook failed and was turned off",
+    ),
+    (
+        "toast.newer_file",
+        "{} was made by a newer version of the game",
+    ),
+    ("toast.replay_copied", "Replay code copied to the clipboard"),
+    ("toast.replay_logged", "Replay code printed to the log"),
+    (
+        "toast.replay_newer",
+        "This replay was recorded by a newer version of the game (format {})",
+    ),
+    ("toast.replay_malformed", "This replay code is malformed"),
+    (
+        "toast.replay_board_size",
+        "This replay was recorded on an unsupported {}x{} board",
+    ),
+    ("save.resume", "Resume autosave? Press Enter"),
+    ("toast.slot_saved", "Saved to slot {}"),
+    ("toast.slot_loaded", "Loaded slot {}"),
+    ("toast.slot_deleted", "Slot {} deleted"),
+    ("toast.slot_mode", "Slot {} holds a game of {}"),
+    (
+        "slots.hint",
+        "Save slots   Up/Down choose  S save  L load  Del delete  Esc close",
+    ),
+    ("slots.empty", "Slot {}  empty"),
+    (
+        "slots.entry",
+        "Slot {}  {}  Score {}  Time {}  Saved {} UTC",
+    ),
+    ("slots.confirm_overwrite", "Overwrite slot {}? Y/N"),
+    ("slots.confirm_delete", "Delete slot {}? Y/N"),
+    ("autoplay.on", "Autoplay on"),
+    ("autoplay.off", "Autoplay off"),
+    (
+        "autoplay.step_on",
+        "Autoplay step mode on, N makes the next move",
+    ),
+    ("autoplay.step_off", "Autoplay step mode off"),
+];
+
+/// Fills in the arguments of a translated text: `t!(strings, "key", arg, ...)`.
+macro_rules! t {
+    ($strings:expr, $key:expr $(, $arg:expr)* $(,)?) => {
+        $strings.format($key, &[$(&$arg as &dyn std::fmt::Display),*])
+    };
+}
+pub(crate) use t;
+
+/// Texts of the current language.
+#[derive(Resource)]
+pub struct Strings {
+    code: String,
+    texts: HashMap<String, String>,
+}
+impl Default for Strings {
+    fn default() -> Self {
+        Self {
+            code: ENGLISH_CODE.into(),
+            texts: HashMap::new(),
+        }
+    }
+}
+impl Strings {
+    /// Reads a language file, warning about the keys it misses.
+    fn parse(code: &str, text: &str) -> Self {
+        if code == ENGLISH_CODE {
+            return Self::default();
+        }
+        let texts: HashMap<String, String> = ron::from_str(text).unwrap_or_else(|err| {
+            warn!("ignoring malformed {LANG_DIR}/{code}.ron: {err}");
+            HashMap::new()
+        });
+        for (key, _) in ENGLISH {
+            if !texts.contains_key(*key) {
+                warn!("language {code} misses {key}, using English");
+            }
+        }
+        Self {
+            code: code.to_owned(),
+            texts,
+        }
+    }
+
+    /// Text of the key, in English if the language misses it.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        if let Some(text) = self.texts.get(key) {
+            return text;
+        }
+        match ENGLISH.iter().find(|(k, _)| *k == key) {
+            Some((_, text)) => text,
+            None => {
+                warn!("no text for {key}");
+                key
+            }
+        }
+    }
+
+    /// Text of the key with its `{}` replaced by the arguments, see [`t`].
+    pub fn format(&self, key: &str, args: &[&dyn fmt::Display]) -> String {
+        let mut parts = self.get(key).split("{}");
+        let mut text = parts.next().unwrap_or_default().to_owned();
+        let mut args = args.iter();
+        for part in parts {
+            if let Some(arg) = args.next() {
+                text += &arg.to_string();
+            }
+            text += part;
+        }
+        text
+    }
+
+    /// The number with its digits grouped by three.
+    pub fn number(&self, number: u64) -> String {
+        let digits = number.to_string();
+        let separator = self.get("number.group");
+        let mut grouped = String::new();
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped += separator;
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+}
+
+/// Code of the chosen language.
+#[derive(Resource)]
+struct LanguageSetting(String);
+impl Default for LanguageSetting {
+    fn default() -> Self {
+        Self(ENGLISH_CODE.into())
+    }
+}
+impl Persisted for LanguageSetting {
+    const PATH: &'static str = "language.txt";
+
+    fn parse(text: &str) -> Option<Self> {
+        let code = text.trim();
+        (!code.is_empty()).then(|| Self(code.to_owned()))
+    }
+}
+impl fmt::Display for LanguageSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Language files by code, English first with no file.
+#[derive(Resource)]
+struct Languages(Vec<(String, String)>);
+impl Default for Languages {
+    fn default() -> Self {
+        Self(vec![(ENGLISH_CODE.into(), String::new())])
+    }
+}
+
+/// Pending read of the language directory.
+#[derive(Resource)]
+struct LanguageScan(Task<io::Result<Vec<(String, String)>>>);
+
+pub struct StringsPlugin;
+impl Plugin for StringsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Strings>()
+            .init_resource::<Languages>()
+            .load_persisted::<LanguageSetting>()
+            .add_systems(Startup, start_scan)
+            .add_systems(
+                Update,
+                (
+                    poll_scan.run_if(resource_exists::<LanguageScan>()),
+                    cycle_language.run_if(in_state(GameState::Menu)),
+                    apply_language.run_if(
+                        resource_changed::<Languages>()
+                            .or_else(resource_changed::<LanguageSetting>()),
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn start_scan(mut commands: Commands, dir: Res<DataDir>) {
+    commands.insert_resource(LanguageScan(read_text_files(dir.path(LANG_DIR), "ron")));
+}
+
+fn poll_scan(
+    mut commands: Commands,
+    mut scan: ResMut<LanguageScan>,
+    mut languages: ResMut<Languages>,
+) {
+    if !scan.0.is_finished() {
+        return;
+    }
+    commands.remove_resource::<LanguageScan>();
+    match block_on(&mut scan.0) {
+        Ok(mut files) => {
+            files.retain(|(code, _)| code != ENGLISH_CODE);
+            files.sort();
+            languages.0.extend(files);
+        }
+        Err(err) => error!("failed to read {LANG_DIR}: {err}"),
+    }
+}
+
+/// L switches to the next language.
+fn cycle_language(
+    input_keys: Res<Input<KeyCode>>,
+    languages: Res<Languages>,
+    mut setting: ResMut<LanguageSetting>,
+    mut persist: EventWriter<PersistRequest>,
+) {
+    if !input_keys.just_pressed(KeyCode::L) {
+        return;
+    }
+    let current = languages.0.iter().position(|(code, _)| *code == setting.0);
+    let next = current.map_or(0, |index| (index + 1) % languages.0.len());
+    setting.0 = languages.0[next].0.clone();
+    persist.send(setting.persist_request());
+}
+
+fn apply_language(
+    languages: Res<Languages>,
+    setting: Res<LanguageSetting>,
+    mut strings: ResMut<Strings>,
+) {
+    if setting.0 == strings.code {
+        return;
+    }
+    let Some((code, text)) = languages.0.iter().find(|(code, _)| *code == setting.0) else {
+        // Not read yet, or gone
+        return;
+    };
+    *strings = Strings::parse(code, text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_german_file_translates_every_key() {
+        let strings = Strings::parse("de", include_str!("../lang/de.ron"));
+        for (key, _) in ENGLISH {
+            assert!(strings.texts.contains_key(*key), "{key}");
+        }
+        assert_eq!(strings.number(1_234_567), "1.234.567");
+    }
+}
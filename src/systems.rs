@@ -0,0 +1,1362 @@
+//! The game's core systems: previewing and placing the selected shape, clearing lines,
+//! applying board commands and drawing the next shape.
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use smallvec::SmallVec;
+use std::{borrow::Cow, collections::VecDeque};
+
+#[cfg(feature = "test-harness")]
+use crate::harness;
+use crate::{
+    assist, autoplay,
+    board::{Board as BoardGrid, ClearedLines, Superimposition, SuperimpositionState, TileColor},
+    budget, click_guard,
+    components::{tile_translation, Board, MainBoard, OverlayBoard, SelectedShape, TILE_SIZE},
+    constraints::PlacementConstraints,
+    events::{
+        BoardChangedEvent, BoardCommand, LinesClearedEvent, PlacementEvent, ShapePoolChanged,
+    },
+    highlight::{HighlightLayer, Highlights},
+    hover,
+    input::IdleTimer,
+    keyboard,
+    palette::ColorPalette,
+    planning, render_order, replay, rotation, rules, scoring,
+    settings::Settings,
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    strings,
+    tile_style::Tile,
+    toast,
+    tween::Tween,
+    ui::CascadeCapNote,
+};
+
+/// Seconds between the clear animations of two neighboring cells of a cleared line.
+pub(crate) const CLEAR_WAVE_STEP: f32 = 0.02;
+pub(crate) const CLEAR_DURATION: f32 = 0.25;
+/// Number of lines that must clear at once for the board to shake.
+pub(crate) const SHAKE_MIN_LINES: usize = 3;
+pub(crate) const SHAKE_DURATION: f32 = 0.25;
+pub(crate) const SHAKE_AMPLITUDE_PER_LINE: f32 = 2.;
+/// Seconds whole-board transforms take to flip the board over.
+pub(crate) const BOARD_FLIP_DURATION: f32 = 0.3;
+/// Seconds without input before the selected shape starts hinting at a placement.
+pub(crate) const IDLE_HINT_DELAY: f32 = 10.;
+/// Placements a frozen cell stays frozen for.
+pub(crate) const FREEZE_PLACEMENTS: u32 = 5;
+/// Clear steps a gravity cascade may take, in play and in the preview.
+pub(crate) const MAX_CASCADE_STEPS: usize = 8;
+/// Overlay tint of a shape that fits but is vetoed by a [`constraints::PlacementConstraint`].
+pub(crate) const BLOCKED_BY_RULE: Color = Color::rgba(0.6, 0.3, 0.9, 0.5);
+
+pub(crate) fn build_shape<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    registry: &ShapeRegistry,
+    palette: &ColorPalette,
+    instance: &ShapeInstance,
+) -> EntityCommands<'w, 's, 'a> {
+    let shape = instance.resolve(registry);
+    debug_assert!(
+        !shape.is_empty(),
+        "building the empty shape {:?}",
+        instance.id
+    );
+    let mut shape_entity = commands.spawn((
+        *instance,
+        SpatialBundle {
+            transform: Transform::from_xyz(0., 0., render_order::SHAPE)
+                .with_scale(Vec3::splat(TILE_SIZE)),
+            ..default()
+        },
+    ));
+    shape_entity.with_children(|commands| {
+        for y in 0..shape.bounds().1 {
+            for x in 0..shape.bounds().0 {
+                if shape.fields[y][x] {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: palette.color(instance.color),
+                                custom_size: Some(Vec2::ONE),
+                                ..default()
+                            },
+                            // Tiles stay in the band of the shape itself
+                            transform: Transform {
+                                translation: (Vec3::new(
+                                    (x as f32 - (shape.bounds().0 as f32) * 0.5) + 0.5,
+                                    (y as f32 - (shape.bounds().1 as f32) * 0.5) + 0.5,
+                                    0.,
+                                )),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        Tile,
+                    ));
+                }
+            }
+        }
+    });
+    shape_entity
+}
+
+/// Changes the color of a built shape, rewriting its tiles in place and keeping their
+/// alpha.
+pub(crate) fn recolor_shape(
+    instance: &mut ShapeInstance,
+    children: &Children,
+    q_sprites: &mut Query<&mut Sprite>,
+    palette: &ColorPalette,
+    color: TileColor,
+) {
+    instance.color = color;
+    for &child in children.iter() {
+        if let Ok(mut sprite) = q_sprites.get_mut(child) {
+            sprite.color = palette.color(color).with_a(sprite.color.a());
+        }
+    }
+}
+
+/// Makes the selected shape `new`, recoloring it when only the color differs and
+/// rebuilding it with `transform` otherwise.
+pub(crate) fn replace_selected_shape(
+    commands: &mut Commands,
+    (registry, palette): (&ShapeRegistry, &ColorPalette),
+    (entity, mut instance, children): (Entity, Mut<ShapeInstance>, &Children),
+    q_sprites: &mut Query<&mut Sprite>,
+    new: ShapeInstance,
+    transform: Transform,
+) {
+    if (instance.id, instance.rotation) == (new.id, new.rotation) {
+        recolor_shape(&mut instance, children, q_sprites, palette, new.color);
+        return;
+    }
+    commands.entity(entity).despawn_recursive();
+    build_shape(commands, registry, palette, &new)
+        .insert(SelectedShape)
+        .insert(transform);
+}
+
+/// Brings the selected shape back should something else have despawned it, drawing a
+/// new one, or rebuilds it should its tiles have been stripped. Either would leave the
+/// game without a piece to place.
+pub(crate) fn restore_selected_shape(
+    mut commands: Commands,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    mut rng: ResMut<GameRng>,
+    (rules, assist): (Res<rules::RuleSet>, Res<assist::Assist>),
+    (weights, mut drought): (Res<ShapeWeights>, ResMut<ShapeDrought>),
+    preview: Res<Preview>,
+    mut q_queue: Query<(&mut ShapeQueue, &mut Tray), With<MainBoard>>,
+    q_selected_shape: Query<
+        (Entity, &ShapeInstance, &Transform, Option<&Children>),
+        With<SelectedShape>,
+    >,
+) {
+    let Some((entity, &instance, &transform, children)) = q_selected_shape.iter().next() else {
+        let Ok((mut queue, mut tray)) = q_queue.get_single_mut() else {
+            return;
+        };
+        warn!("the selected shape is gone, drawing another");
+        let new_shape = tray.next(
+            (&mut queue.0, &mut drought),
+            &mut rng.rng,
+            (&weights.0, rules.drought_threshold, &assist),
+            (&registry, &palette),
+        );
+        build_shape(&mut commands, &registry, &palette, &new_shape)
+            .insert(SelectedShape)
+            .insert(Transform {
+                translation: preview.world_position.extend(render_order::SHAPE),
+                scale: Vec3::splat(TILE_SIZE),
+                ..default()
+            });
+        return;
+    };
+    let tiles = instance.resolve(&registry).bits().count_ones() as usize;
+    // An empty shape has nothing to rebuild, `replace_empty_shape` draws another one
+    if tiles == 0 || children.map_or(0, |children| children.len()) == tiles {
+        return;
+    }
+    warn!(
+        "the selected shape {:?} lost its tiles, rebuilding it",
+        instance.id
+    );
+    commands.entity(entity).despawn_recursive();
+    build_shape(&mut commands, &registry, &palette, &instance)
+        .insert(SelectedShape)
+        .insert(transform);
+}
+
+/// Draws a new selected shape should an empty one have slipped through, it could
+/// neither be seen nor placed.
+pub(crate) fn replace_empty_shape(
+    mut commands: Commands,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    mut rng: ResMut<GameRng>,
+    weights: Res<ShapeWeights>,
+    mut q_selected_shape: Query<
+        (Entity, &mut ShapeInstance, &Children, &Transform),
+        With<SelectedShape>,
+    >,
+    mut q_sprites: Query<&mut Sprite>,
+) {
+    let Ok((entity, instance, children, &transform)) = q_selected_shape.get_single_mut() else {
+        return;
+    };
+    if !instance.resolve(&registry).is_empty() {
+        return;
+    }
+    warn!(
+        "the selected shape {:?} is empty, drawing another",
+        instance.id
+    );
+    let (id, rotation) = registry.random_weighted(&mut rng.rng, &weights.0);
+    let color = palette.random(&mut rng.rng);
+    replace_selected_shape(
+        &mut commands,
+        (&registry, &palette),
+        (entity, instance, children),
+        &mut q_sprites,
+        ShapeInstance {
+            id,
+            rotation,
+            color,
+        },
+        transform,
+    );
+}
+
+/// How the board ends up once the previewed placement's cascade settled, kept while
+/// the board and the placement stay the same. Its buffer is reused for the next one.
+#[derive(Default)]
+pub(crate) struct CascadeHints {
+    key: Option<(u64, ShapeInstance, (isize, isize))>,
+    /// Cells to hint at with their color.
+    cells: Vec<((usize, usize), Color)>,
+    /// The cascade went on past [`MAX_CASCADE_STEPS`].
+    capped: bool,
+}
+
+/// Moves the selected shape with the cursor and highlights where it would land on the
+/// overlay board.
+pub(crate) fn preview(
+    cursor: Res<hover::CursorBoardPos>,
+    q_board: Query<(&Board, &GlobalTransform, Entity), (With<MainBoard>, Without<OverlayBoard>)>,
+    mut highlights: ResMut<Highlights>,
+    mut q_selected_shape: Query<(&ShapeInstance, &mut Transform), With<SelectedShape>>,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    settings: Res<Settings>,
+    gravity: Res<Gravity>,
+    input_keys: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut preview: ResMut<Preview>,
+    (constraints, kick): (Res<PlacementConstraints>, Res<rotation::Kick>),
+    mut q_cascade_note: Query<&mut Visibility, With<CascadeCapNote>>,
+    mut cascade_hints: Local<CascadeHints>,
+) {
+    // Resolve queries
+    let (board, board_transform, board_entity) = q_board.single();
+    preview.superimposition = None;
+    preview.vetoed = None;
+    preview.translation = None;
+    let mut cascade_note = q_cascade_note.single_mut();
+    *cascade_note = Visibility::Hidden;
+
+    highlights.clear(HighlightLayer::Preview);
+    // Highlight where placements are allowed
+    highlights.clear(HighlightLayer::Regions);
+    for region in constraints.regions() {
+        for y in region.min.y..region.max.y {
+            for x in region.min.x..region.max.x {
+                highlights.set(
+                    HighlightLayer::Regions,
+                    (x as usize, y as usize),
+                    Color::rgba(1., 1., 1., 0.15),
+                );
+            }
+        }
+    }
+
+    let Some(world_position) = cursor.world else {
+        return;
+    };
+    let Ok((selected_shape, mut selected_shape_transform)) = q_selected_shape.get_single_mut()
+    else {
+        return;
+    };
+
+    let position_on_board = board.world_to_local(board_transform, world_position);
+    let translation = (
+        position_on_board.x / board.grid.width() as f32,
+        position_on_board.y / board.grid.height() as f32,
+    );
+    // Move the selected shape towards the cursor, the superimposition keeps using the
+    // exact cursor position
+    selected_shape_transform.translation = smooth_follow(
+        selected_shape_transform.translation,
+        world_position.extend(render_order::SHAPE),
+        settings.shape_smoothing,
+        time.delta_seconds(),
+    );
+
+    let shape = selected_shape.resolve(&registry);
+    // Has no anchor, `replace_empty_shape` draws another one
+    if shape.is_empty() {
+        return;
+    }
+    let mut superimposition = board
+        .grid
+        .superimpose_at(shape, kick.apply(board.grid.anchor_at(shape, translation)));
+    if superimposition.success {
+        preview.vetoed = constraints.check(
+            &board.grid,
+            selected_shape.resolve(&registry),
+            superimposition.anchor,
+        );
+        superimposition.success = preview.vetoed.is_none();
+    }
+    let vetoed = preview.vetoed.is_some();
+    preview.board = Some(board_entity);
+    preview.world_position = world_position;
+    preview.translation = Some(translation);
+    let superimposition = &*preview.superimposition.insert(superimposition);
+
+    // Highlight the shape over the cursor
+    for &(x, y, state) in superimposition.cells.iter() {
+        let color = match state {
+            SuperimpositionState::Blank => continue,
+            SuperimpositionState::Fits if vetoed => BLOCKED_BY_RULE,
+            SuperimpositionState::Fits => palette.preview_color(selected_shape.color),
+            SuperimpositionState::Intersects => Color::from(TileColor::Red).with_a(0.5),
+        };
+        highlights.set(HighlightLayer::Preview, (x as usize, y as usize), color);
+    }
+
+    // Faintly show how the board ends up once the cascade settled
+    let alt = input_keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    if !gravity.0 || !alt || !superimposition.success {
+        return;
+    }
+    let key = (
+        board.grid.revision(),
+        *selected_shape,
+        superimposition.anchor,
+    );
+    if cascade_hints.key != Some(key) || palette.is_changed() {
+        cascade_hints.key = Some(key);
+        cascade_hints.capped = false;
+        cascade_hints.cells.clear();
+        match board.grid.predict(
+            selected_shape.resolve(&registry),
+            superimposition.anchor,
+            selected_shape.color,
+            true,
+            MAX_CASCADE_STEPS,
+        ) {
+            Some(predicted) => {
+                let placed = superimposition.to_grid();
+                for (y, (row, predicted_row)) in board
+                    .grid
+                    .cells
+                    .0
+                    .iter()
+                    .zip(predicted.cells.0.iter())
+                    .enumerate()
+                {
+                    for (x, (&cell, &predicted_cell)) in row.iter().zip(predicted_row).enumerate() {
+                        let placed = placed.0[y][x] == SuperimpositionState::Fits;
+                        let hint = match (cell, predicted_cell) {
+                            (None, Some(color)) if !placed => palette.color(color).with_a(0.25),
+                            (Some(_), None) => Color::rgba(1., 1., 1., 0.25),
+                            (None, None) if placed => Color::rgba(1., 1., 1., 0.25),
+                            _ => continue,
+                        };
+                        cascade_hints.cells.push(((x, y), hint));
+                    }
+                }
+            }
+            None => cascade_hints.capped = true,
+        }
+    }
+    if cascade_hints.capped {
+        *cascade_note = Visibility::Inherited;
+    }
+    for &(cell, hint) in cascade_hints.cells.iter() {
+        highlights.set(HighlightLayer::Preview, cell, hint);
+    }
+}
+
+/// Moves the registry over to a new pool and draws from it with the weights of the
+/// current level.
+pub(crate) fn swap_shape_pool(
+    mut commands: Commands,
+    (mut registry, palette): (ResMut<ShapeRegistry>, Res<ColorPalette>),
+    strings: Res<strings::Strings>,
+    mut rng: ResMut<GameRng>,
+    (rules, assist): (Res<rules::RuleSet>, Res<assist::Assist>),
+    (mut weights, mut drought): (ResMut<ShapeWeights>, ResMut<ShapeDrought>),
+    mut pool_changes: EventReader<ShapePoolChanged>,
+    mut q_main_board: Query<(&scoring::Level, &mut ShapeQueue, &mut Tray), With<MainBoard>>,
+) {
+    let Some(ShapePoolChanged(shapes)) = pool_changes.read().last() else {
+        return;
+    };
+    if !registry.swap_pool(shapes.iter().cloned()) {
+        warn!("ignoring a shape pool without shapes");
+        return;
+    }
+    let main_board = q_main_board.get_single_mut().ok();
+    let level = main_board.as_ref().map_or(1, |(level, ..)| level.level);
+    weights.0 = scoring::level_weights(&registry, level);
+    drought.0.resize(weights.0.len(), 0);
+    // Only the selected shape plays out, the tray is drawn anew from the new pool
+    if let Some((_, mut queue, mut tray)) = main_board {
+        tray.0.clear();
+        tray.top_up(
+            UPCOMING_SHAPES,
+            (&mut queue.0, &mut drought),
+            &mut rng.rng,
+            (&weights.0, rules.drought_threshold, &assist),
+            (&registry, &palette),
+        );
+    }
+    toast::spawn_toast(&mut commands, strings.get("toast.shapes_updated"));
+}
+
+/// Places the selected shape where the preview shows it fits and draws the next one.
+pub(crate) fn place(
+    mut commands: Commands,
+    (input_mb, input_keys, mut click_guard, pointer): (
+        Res<Input<MouseButton>>,
+        Res<Input<KeyCode>>,
+        ResMut<click_guard::ClickGuard>,
+        Res<click_guard::PointerCapture>,
+    ),
+    (input_kind, input_gamepad): (Res<keyboard::ActiveInputKind>, Res<Input<GamepadButton>>),
+    preview: Res<Preview>,
+    (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    mut rng: ResMut<GameRng>,
+    (rules, assist): (Res<rules::RuleSet>, Res<assist::Assist>),
+    (weights, mut drought): (Res<ShapeWeights>, ResMut<ShapeDrought>),
+    mut plan: ResMut<planning::PlannedPlacements>,
+    (playback, mut autoplay, strings): (
+        Option<Res<replay::Playback>>,
+        ResMut<autoplay::Autoplay>,
+        Res<strings::Strings>,
+    ),
+    mut q_board: Query<
+        (&mut Board, &mut ShapeQueue, &mut Tray),
+        (With<MainBoard>, Without<OverlayBoard>),
+    >,
+    mut q_selected_shape: Query<(Entity, &mut ShapeInstance, &Children), With<SelectedShape>>,
+    mut q_sprites: Query<&mut Sprite>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut placements: EventWriter<PlacementEvent>,
+    #[cfg(feature = "test-harness")] mut simulated_clicks: EventReader<harness::SimulatedClick>,
+) {
+    let mouse_clicked = input_mb.just_pressed(MouseButton::Left);
+    // Simulated clicks are mouse clicks, the HUD and the click guard keep them too
+    #[cfg(feature = "test-harness")]
+    let mouse_clicked = simulated_clicks.read().count() > 0 || mouse_clicked;
+    let clicked = mouse_clicked
+        || ((input_keys.just_pressed(KeyCode::Space)
+            || keyboard::gamepad_just_pressed(&input_gamepad, keyboard::PLACE_BUTTON))
+            && *input_kind != keyboard::ActiveInputKind::Mouse);
+    // Replays place their recorded shapes on their own
+    if playback.is_some() {
+        return;
+    }
+    // A plan being committed places its next step in place of a click, as does the
+    // autoplay its move
+    let step = plan.next_commit();
+    let autoplayed = autoplay.take_move();
+    let (board_entity, anchor) = match (&step, autoplayed) {
+        (Some(step), _) => (step.board, step.anchor),
+        (None, Some(autoplayed)) => autoplayed,
+        (None, None) => {
+            if !clicked || plan.staging() {
+                return;
+            }
+            let (Some(board_entity), Some(superimposition)) =
+                (preview.board, &preview.superimposition)
+            else {
+                return;
+            };
+            if mouse_clicked && pointer.captured() {
+                return;
+            }
+            // The click that focused the window or closed a menu wasn't aimed at the board
+            if mouse_clicked && click_guard.active() {
+                click_guard.swallow();
+                return;
+            }
+            if !superimposition.success {
+                if let Some(message) = &preview.vetoed {
+                    toast::spawn_toast(&mut commands, message.clone());
+                }
+                return;
+            }
+            (board_entity, superimposition.anchor)
+        }
+    };
+    let (Ok((mut board, mut queue, mut tray)), Ok(selected)) = (
+        q_board.get_mut(board_entity),
+        q_selected_shape.get_single_mut(),
+    ) else {
+        return;
+    };
+    let selected_shape = match &step {
+        // Another shape came up than was peeked
+        Some(step) if step.drawn != *selected.1 => {
+            plan.abort(&mut commands, &strings);
+            return;
+        }
+        Some(step) => step.shape,
+        None => *selected.1,
+    };
+    let Some(cells) = board.grid.try_place(
+        selected_shape.resolve(&registry),
+        anchor,
+        selected_shape.color,
+    ) else {
+        if step.is_some() {
+            plan.abort(&mut commands, &strings);
+        }
+        return;
+    };
+    board_changed.send(BoardChangedEvent {
+        board: board_entity,
+        revision: board.grid.revision(),
+        cells: cells.clone(),
+    });
+    placements.send(PlacementEvent {
+        board: board_entity,
+        shape: selected_shape,
+        anchor,
+        cells,
+    });
+
+    let new_shape = tray.next(
+        (&mut queue.0, &mut drought),
+        &mut rng.rng,
+        (&weights.0, rules.drought_threshold, &assist),
+        (&registry, &palette),
+    );
+    replace_selected_shape(
+        &mut commands,
+        (&registry, &palette),
+        selected,
+        &mut q_sprites,
+        new_shape,
+        Transform {
+            translation: preview.world_position.extend(render_order::SHAPE),
+            scale: Vec3::splat(TILE_SIZE),
+            ..default()
+        },
+    );
+}
+
+/// Clears the lines completed by placements, with their wave animation and shake.
+pub(crate) fn clear_lines(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    palette: Res<ColorPalette>,
+    gravity: Res<Gravity>,
+    mut placements: EventReader<PlacementEvent>,
+    mut q_board: Query<(&mut Board, Option<&Shake>), Without<OverlayBoard>>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+    mut lines_cleared: EventWriter<LinesClearedEvent>,
+) {
+    for event in placements.read() {
+        let Ok((mut board, shake)) = q_board.get_mut(event.board) else {
+            continue;
+        };
+        let colors = board.grid;
+        let cleared = board.grid.clear_full_lines();
+        if cleared.is_empty() {
+            continue;
+        }
+        board_changed.send(BoardChangedEvent {
+            board: event.board,
+            revision: board.grid.revision(),
+            cells: cleared.cells().collect(),
+        });
+        spawn_clear_wave(
+            &mut commands,
+            &palette,
+            event.board,
+            &colors,
+            &board.grid,
+            &cleared,
+            &event.cells,
+        );
+        if settings.screen_shake && cleared.len() >= SHAKE_MIN_LINES {
+            commands.entity(event.board).insert(Shake {
+                timer: Timer::from_seconds(SHAKE_DURATION, TimerMode::Once),
+                amplitude: cleared.len() as f32 * SHAKE_AMPLITUDE_PER_LINE,
+                // Keep the origin of a shake still in progress, the current
+                // translation is already offset by it
+                origin: shake.and_then(|shake| shake.origin),
+            });
+        }
+        lines_cleared.send(LinesClearedEvent {
+            board: event.board,
+            lines: cleared,
+        });
+
+        if !gravity.0 {
+            continue;
+        }
+        for _ in 0..MAX_CASCADE_STEPS {
+            let colors = board.grid;
+            let Some(cleared) = board.grid.cascade_step() else {
+                break;
+            };
+            spawn_clear_wave(
+                &mut commands,
+                &palette,
+                event.board,
+                &colors,
+                &board.grid,
+                &cleared,
+                &[],
+            );
+            lines_cleared.send(LinesClearedEvent {
+                board: event.board,
+                lines: cleared,
+            });
+        }
+        // Cells fell anywhere, repaint the whole board
+        board_changed.send(BoardChangedEvent {
+            board: event.board,
+            revision: board.grid.revision(),
+            cells: board.grid.positions().collect(),
+        });
+    }
+}
+
+/// Repaints the frozen cells whose countdown moved with a placement.
+pub(crate) fn repaint_frozen_cells(
+    mut placements: EventReader<PlacementEvent>,
+    q_board: Query<&Board, Without<OverlayBoard>>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+) {
+    for event in placements.read() {
+        let Ok(board) = q_board.get(event.board) else {
+            continue;
+        };
+        let turn = board.grid.turn();
+        // Cells thawing this turn need their pip removed too
+        let counting_down: SmallVec<_> = board
+            .grid
+            .positions()
+            .filter(|&(x, y)| board.grid.meta.0[y][x].frozen_until >= turn)
+            .collect();
+        if !counting_down.is_empty() {
+            board_changed.send(BoardChangedEvent {
+                board: event.board,
+                revision: board.grid.revision(),
+                cells: counting_down,
+            });
+        }
+    }
+}
+
+/// Applies board commands, sent by game rules rather than the player.
+pub(crate) fn apply_board_commands(
+    mut commands: Commands,
+    strings: Res<strings::Strings>,
+    mut board_commands: EventReader<BoardCommand>,
+    mut q_board: Query<(&mut Board, &Transform, Has<Tween>), Without<OverlayBoard>>,
+    mut q_clear_wave: Query<(&Parent, &mut Transform), (With<ClearWaveTile>, Without<Board>)>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+) {
+    for command in board_commands.read() {
+        let (BoardCommand::Fill { board, .. }
+        | BoardCommand::SetMeta { board, .. }
+        | BoardCommand::Freeze { board, .. }
+        | BoardCommand::GarbageRows { board, .. }
+        | BoardCommand::MirrorHorizontal { board }
+        | BoardCommand::MirrorVertical { board }
+        | BoardCommand::Rotate90 { board }) = command;
+        let Ok((mut board_model, transform, tweening)) = q_board.get_mut(*board) else {
+            continue;
+        };
+        let all_cells = |grid: &BoardGrid| grid.positions().collect();
+        let cells: SmallVec<_> = match command {
+            BoardCommand::Fill { cells, color, .. } => {
+                if let Some(color) = color.filter(|color| !color.is_placeable()) {
+                    warn!(
+                        "ignoring a fill with {}, cells can't hold it",
+                        color.to_char()
+                    );
+                    continue;
+                }
+                board_model.grid.fill_cells(cells.iter().copied(), *color);
+                cells.iter().copied().collect()
+            }
+            BoardCommand::SetMeta { cells, meta, .. } => {
+                board_model.grid.set_meta(cells.iter().copied(), *meta);
+                cells.iter().copied().collect()
+            }
+            BoardCommand::Freeze {
+                cells, placements, ..
+            } => {
+                board_model.grid.freeze(cells.iter().copied(), *placements);
+                cells.iter().copied().collect()
+            }
+            BoardCommand::GarbageRows { gaps, .. } => {
+                if board_model
+                    .grid
+                    .insert_garbage_rows(gaps.len(), |row| gaps[row])
+                    .is_err()
+                {
+                    warn!("garbage rows would push cells off the board");
+                    toast::spawn_toast(&mut commands, strings.get("toast.topped_out"));
+                    continue;
+                }
+                // Clear animations still running move up with their rows
+                for (parent, mut transform) in q_clear_wave.iter_mut() {
+                    if parent.get() == *board {
+                        transform.translation.y += gaps.len() as f32;
+                    }
+                }
+                all_cells(&board_model.grid)
+            }
+            BoardCommand::MirrorHorizontal { .. } => {
+                board_model.grid.mirror_horizontal();
+                all_cells(&board_model.grid)
+            }
+            BoardCommand::MirrorVertical { .. } => {
+                board_model.grid.mirror_vertical();
+                all_cells(&board_model.grid)
+            }
+            BoardCommand::Rotate90 { .. } => {
+                if board_model.grid.rotate_90().is_err() {
+                    warn!("ignoring a rotation of a board that isn't square");
+                    continue;
+                }
+                all_cells(&board_model.grid)
+            }
+        };
+        // Flip the board over while its cells swap, unless something else animates it
+        let flip_axis = match command {
+            BoardCommand::MirrorHorizontal { .. } | BoardCommand::Rotate90 { .. } => Some(Vec3::X),
+            BoardCommand::MirrorVertical { .. } => Some(Vec3::Y),
+            _ => None,
+        };
+        if let Some(axis) = flip_axis.filter(|_| !tweening) {
+            let scale = transform.scale;
+            commands.entity(*board).insert(
+                Tween::new(BOARD_FLIP_DURATION).with_scale(scale * (Vec3::ONE - 2. * axis), scale),
+            );
+        }
+        board_changed.send(BoardChangedEvent {
+            board: *board,
+            revision: board_model.grid.revision(),
+            cells,
+        });
+    }
+}
+
+/// Exponentially approaches `target` with `smoothing` as time constant in seconds,
+/// never lagging more than one tile behind.
+pub(crate) fn smooth_follow(current: Vec3, target: Vec3, smoothing: f32, delta: f32) -> Vec3 {
+    if smoothing <= 0. {
+        return target;
+    }
+    let lag = (current - target) * (-delta / smoothing).exp();
+    target + lag.clamp_length_max(TILE_SIZE)
+}
+
+/// Spawns a shrinking copy of every cleared tile, each starting slightly after its
+/// neighbor so the clear sweeps outward from where the line was completed.
+pub(crate) fn spawn_clear_wave(
+    commands: &mut Commands,
+    palette: &ColorPalette,
+    board_entity: Entity,
+    colors: &BoardGrid,
+    remaining: &BoardGrid,
+    cleared: &ClearedLines,
+    placed: &[(usize, usize)],
+) {
+    // Sweep origins: where a row and a column cleared together the sweep starts at their
+    // intersection, otherwise at a placed cell on the line.
+    let nearest = |candidates: &[usize], target: usize| {
+        candidates
+            .iter()
+            .copied()
+            .min_by_key(|&c| c.abs_diff(target))
+            .unwrap_or(target)
+    };
+    let row_origin = |y: usize| {
+        let placed_x = placed
+            .iter()
+            .find(|&&(_, py)| py == y)
+            .or(placed.first())
+            .map_or(0, |&(x, _)| x);
+        nearest(&cleared.columns, placed_x)
+    };
+    let column_origin = |x: usize| {
+        let placed_y = placed
+            .iter()
+            .find(|&&(px, _)| px == x)
+            .or(placed.first())
+            .map_or(0, |&(_, y)| y);
+        nearest(&cleared.rows, placed_y)
+    };
+
+    commands.entity(board_entity).with_children(|commands| {
+        for (x, y) in cleared.cells() {
+            // Cracked and frozen cells stay in place
+            let Some(color) = colors.cells.0[y][x].filter(|_| remaining.cells.0[y][x].is_none())
+            else {
+                continue;
+            };
+            let row_delay = cleared.rows.contains(&y).then(|| x.abs_diff(row_origin(y)));
+            let column_delay = cleared
+                .columns
+                .contains(&x)
+                .then(|| y.abs_diff(column_origin(x)));
+            let steps = row_delay.into_iter().chain(column_delay).min().unwrap_or(0);
+
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: palette.color(color),
+                        custom_size: Some(Vec2::ONE),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(
+                        tile_translation(x, y, (remaining.width(), remaining.height()))
+                            + Vec3::Z * render_order::in_board(render_order::PARTICLES),
+                    ),
+                    ..default()
+                },
+                ClearWaveTile { cell: (x, y) },
+                Tile,
+                Tween::new(CLEAR_DURATION)
+                    .with_delay(steps as f32 * CLEAR_WAVE_STEP)
+                    .with_scale(Vec3::ONE, Vec3::ZERO)
+                    .despawn_on_finish(),
+            ));
+        }
+    });
+}
+
+/// Jitters the board around its original translation with decaying amplitude,
+/// restoring the translation exactly once the shake is over.
+pub(crate) fn shake(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_shake: Query<(Entity, &mut Shake, &mut Transform)>,
+) {
+    let mut rng = rand::thread_rng();
+    for (entity, mut shake, mut transform) in q_shake.iter_mut() {
+        let origin = *shake.origin.get_or_insert(transform.translation);
+        shake.timer.tick(time.delta());
+        if shake.timer.finished() {
+            transform.translation = origin;
+            commands.entity(entity).remove::<Shake>();
+            continue;
+        }
+        let amplitude = shake.amplitude * shake.timer.percent_left();
+        let jitter = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)) * amplitude;
+        transform.translation = origin + jitter.extend(0.);
+    }
+}
+
+/// Progress of the idle hint's search for a placement, kept while the board and the
+/// selected shape stay the same.
+#[derive(Default)]
+pub(crate) struct HintSearch {
+    pub(crate) key: Option<(u64, ShapeInstance)>,
+    pub(crate) scanned: usize,
+    pub(crate) found: Option<Option<(isize, isize)>>,
+}
+
+/// After a while without input, pulses the selected shape and highlights one
+/// placement where it fits and the constraints allow. The search for it is spread
+/// over frames within the [`budget::ScanBudget`].
+pub(crate) fn idle_hint(
+    time: Res<Time>,
+    idle: Res<IdleTimer>,
+    registry: Res<ShapeRegistry>,
+    constraints: Res<PlacementConstraints>,
+    scan_budget: Res<budget::ScanBudget>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<(&ShapeInstance, &Children), With<SelectedShape>>,
+    mut q_sprites: Query<&mut Sprite>,
+    mut highlights: ResMut<Highlights>,
+    mut pulsing: Local<bool>,
+    mut search: Local<HintSearch>,
+) {
+    highlights.clear(HighlightLayer::Hint);
+    let Ok((selected_shape, children)) = q_selected_shape.get_single() else {
+        return;
+    };
+    let idle = idle.0 >= IDLE_HINT_DELAY;
+    if !idle {
+        // Stop the pulse as soon as input resumes
+        if *pulsing {
+            for &child in children.iter() {
+                if let Ok(mut sprite) = q_sprites.get_mut(child) {
+                    sprite.color.set_a(1.);
+                }
+            }
+            *pulsing = false;
+        }
+        return;
+    }
+    *pulsing = true;
+
+    let pulse = 0.5 + 0.5 * (time.elapsed_seconds() * std::f32::consts::TAU * 0.5).cos();
+    for &child in children.iter() {
+        if let Ok(mut sprite) = q_sprites.get_mut(child) {
+            sprite.color.set_a(0.5 + 0.5 * pulse);
+        }
+    }
+
+    let shape = selected_shape.resolve(&registry);
+    let board = &q_board.single().grid;
+    let key = (board.revision(), *selected_shape);
+    if search.key != Some(key) || constraints.is_changed() {
+        *search = HintSearch {
+            key: Some(key),
+            ..default()
+        };
+    }
+    if search.found.is_none() {
+        let budget = budget::FrameBudget::new(scan_budget.0);
+        search.found =
+            constraints.resume_find_placement(board, shape, &mut search.scanned, &budget);
+    }
+    let Some(Some(anchor)) = search.found else {
+        return;
+    };
+    for (y, row) in shape.fields.iter().enumerate() {
+        for (x, &field) in row.iter().enumerate() {
+            if field {
+                highlights.set(
+                    HighlightLayer::Hint,
+                    (anchor.0 as usize + x, anchor.1 as usize + y),
+                    Color::WHITE.with_a(0.15 + 0.15 * pulse),
+                );
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct Shake {
+    pub(crate) timer: Timer,
+    pub(crate) amplitude: f32,
+    /// Translation to restore, captured on the first shaken frame.
+    pub(crate) origin: Option<Vec3>,
+}
+
+/// Shrinking copy of a cleared tile, see [`spawn_clear_wave`].
+#[derive(Component)]
+pub(crate) struct ClearWaveTile {
+    /// Cell of the board it was cleared from.
+    pub(crate) cell: (usize, usize),
+}
+
+/// Where the selected shape would land on which board, written by [`preview`].
+#[derive(Resource, Default)]
+pub(crate) struct Preview {
+    pub(crate) board: Option<Entity>,
+    pub(crate) superimposition: Option<Superimposition>,
+    /// Message of the [`constraints::PlacementConstraint`] blocking a placement that would fit.
+    pub(crate) vetoed: Option<Cow<'static, str>>,
+    pub(crate) world_position: Vec2,
+    /// Cursor position relative to the main board's extents, if there is a cursor.
+    pub(crate) translation: Option<(f32, f32)>,
+}
+
+/// Selection weight of every shape in the [`ShapeRegistry`], indexed by [`ShapeId`].
+#[derive(Resource)]
+pub(crate) struct ShapeWeights(pub(crate) Vec<f32>);
+
+/// Shapes drawn since each shape in the [`ShapeRegistry`] last appeared, indexed by
+/// [`ShapeId`].
+#[derive(Resource, Clone)]
+pub(crate) struct ShapeDrought(pub(crate) Vec<u32>);
+impl ShapeDrought {
+    pub(crate) fn new(shape_count: usize) -> Self {
+        Self(vec![0; shape_count])
+    }
+
+    /// The weights with every shape whose drought is past the threshold doubled for
+    /// each shape drawn beyond it. A threshold of 0 turns the protection off.
+    pub(crate) fn boost(&self, weights: &[f32], threshold: u32) -> Vec<f32> {
+        if threshold == 0 {
+            return weights.to_vec();
+        }
+        weights
+            .iter()
+            .zip(&self.0)
+            .map(|(weight, &drought)| {
+                let excess = drought.saturating_sub(threshold).min(64);
+                weight * 2f32.powi(excess as i32)
+            })
+            .collect()
+    }
+
+    /// The shape to draw outright, for no drought to outlast the threshold by more than
+    /// the shapes in the registry: the longest drought past the threshold, once the
+    /// shapes past it need every draw left to each appear in time.
+    pub(crate) fn due(&self, weights: &[f32], threshold: u32) -> Option<ShapeId> {
+        if threshold == 0 {
+            return None;
+        }
+        let mut overdue: Vec<(u32, usize)> = self
+            .0
+            .iter()
+            .zip(weights)
+            .enumerate()
+            .filter(|&(_, (&drought, &weight))| drought >= threshold && weight > 0.)
+            .map(|(index, (&drought, _))| (drought, index))
+            .collect();
+        overdue.sort_unstable_by(|a, b| b.cmp(a));
+        // The longer droughts come first, the one in place i is drawn at i draws from now
+        let bound = threshold as usize + weights.len() - 1;
+        let tight = overdue
+            .iter()
+            .enumerate()
+            .any(|(place, &(drought, _))| drought as usize + place >= bound);
+        tight.then(|| ShapeId(overdue[0].1 as u16))
+    }
+
+    pub(crate) fn record(&mut self, id: ShapeId) {
+        for (index, drought) in self.0.iter_mut().enumerate() {
+            *drought = if index == id.0 as usize {
+                0
+            } else {
+                *drought + 1
+            };
+        }
+    }
+}
+
+/// Draws the shape following a placement: the next forced one, the one whose drought
+/// can't last longer, or a random one with the shapes in a drought boosted and the
+/// [`assist::Assist`]'s bias applied.
+pub(crate) fn draw_shape(
+    (forced, drought): (&mut VecDeque<ShapeInstance>, &mut ShapeDrought),
+    rng: &mut StdRng,
+    (weights, drought_threshold, assist): (&[f32], u32, &assist::Assist),
+    (registry, palette): (&ShapeRegistry, &ColorPalette),
+) -> ShapeInstance {
+    let shape = forced.pop_front().unwrap_or_else(|| {
+        let weights = match drought.due(weights, drought_threshold) {
+            Some(due) => (0..weights.len())
+                .map(|id| if id == due.0 as usize { 1. } else { 0. })
+                .collect(),
+            None => assist.apply(&drought.boost(weights, drought_threshold), registry),
+        };
+        let (id, rotation) = registry.random_weighted(rng, &weights);
+        ShapeInstance {
+            id,
+            rotation,
+            color: palette.random(rng),
+        }
+    });
+    drought.record(shape.id);
+    shape
+}
+
+/// Shapes handed out before any random one, e.g. by the tutorial. Kept on the main
+/// board, which draws them.
+#[derive(Component, Default)]
+pub(crate) struct ShapeQueue(pub(crate) VecDeque<ShapeInstance>);
+
+/// Shapes the [`Tray`] shows coming up after the selected one.
+pub const UPCOMING_SHAPES: usize = 2;
+
+/// The shapes drawn already that come up after the selected one, the next first. Kept
+/// on the main board and shown beside it.
+#[derive(Component, Default)]
+pub struct Tray(pub(crate) VecDeque<ShapeInstance>);
+impl Tray {
+    /// The shapes coming up, the next first.
+    pub fn iter(&self) -> impl Iterator<Item = &ShapeInstance> {
+        self.0.iter()
+    }
+
+    /// Draws shapes until the tray holds `len` of them.
+    pub(crate) fn top_up(
+        &mut self,
+        len: usize,
+        (forced, drought): (&mut VecDeque<ShapeInstance>, &mut ShapeDrought),
+        rng: &mut StdRng,
+        (weights, drought_threshold, assist): (&[f32], u32, &assist::Assist),
+        (registry, palette): (&ShapeRegistry, &ColorPalette),
+    ) {
+        while self.0.len() < len {
+            let shape = draw_shape(
+                (forced, drought),
+                rng,
+                (weights, drought_threshold, assist),
+                (registry, palette),
+            );
+            self.0.push_back(shape);
+        }
+    }
+
+    /// Takes the next shape out, drawing one in its place for the tray to still show
+    /// [`UPCOMING_SHAPES`].
+    pub(crate) fn next(
+        &mut self,
+        (forced, drought): (&mut VecDeque<ShapeInstance>, &mut ShapeDrought),
+        rng: &mut StdRng,
+        (weights, drought_threshold, assist): (&[f32], u32, &assist::Assist),
+        (registry, palette): (&ShapeRegistry, &ColorPalette),
+    ) -> ShapeInstance {
+        self.top_up(
+            UPCOMING_SHAPES + 1,
+            (forced, drought),
+            rng,
+            (weights, drought_threshold, assist),
+            (registry, palette),
+        );
+        self.0.pop_front().expect("the tray was just topped up")
+    }
+}
+
+/// Source of every random decision affecting the game, so a seed reproduces it.
+#[derive(Resource)]
+pub struct GameRng {
+    /// Seed the game started from, the same for everyone playing the same daily.
+    pub seed: u64,
+    pub(crate) rng: StdRng,
+}
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+/// Whether cells fall after a clear, possibly completing more lines. Set from the
+/// [`rules::RuleSet`].
+#[derive(Resource, Default)]
+pub(crate) struct Gravity(pub(crate) bool);
+
+/// Hard mode schedules garbage drops and cell freezes. Set from the [`rules::RuleSet`].
+#[derive(Resource, Default)]
+pub(crate) struct HardMode(pub(crate) bool);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::default_shapes;
+    use bevy::ecs::system::RunSystemOnce;
+
+    const DRAWS: usize = 100_000;
+    const DROUGHT_THRESHOLD: u32 = 15;
+
+    /// Ids of `DRAWS` shapes drawn from the default pool, with the given weights.
+    fn draw_ids(weights: &[f32], drought_threshold: u32) -> Vec<usize> {
+        let registry = ShapeRegistry::new(default_shapes());
+        let palette = ColorPalette::default();
+        let assist = assist::Assist::default();
+        let mut drought = ShapeDrought::new(weights.len());
+        let mut rng = StdRng::seed_from_u64(139);
+        (0..DRAWS)
+            .map(|_| {
+                let shape = draw_shape(
+                    (&mut VecDeque::new(), &mut drought),
+                    &mut rng,
+                    (weights, drought_threshold, &assist),
+                    (&registry, &palette),
+                );
+                shape.id.0 as usize
+            })
+            .collect()
+    }
+
+    /// Longest run of draws without each shape.
+    fn longest_gaps(ids: &[usize], shape_count: usize) -> Vec<usize> {
+        let mut last_seen = vec![0; shape_count];
+        let mut gaps = vec![0; shape_count];
+        for (draw, &id) in ids.iter().enumerate() {
+            gaps[id] = gaps[id].max(draw - last_seen[id]);
+            last_seen[id] = draw + 1;
+        }
+        gaps
+    }
+
+    #[test]
+    fn shapes_are_drawn_as_often_as_weighted() {
+        let weights: Vec<f32> = (1..=default_shapes().len()).map(|i| i as f32).collect();
+        let total: f32 = weights.iter().sum();
+        let mut counts = vec![0; weights.len()];
+        for id in draw_ids(&weights, 0) {
+            counts[id] += 1;
+        }
+        for (count, weight) in counts.iter().zip(&weights) {
+            let frequency = *count as f32 / DRAWS as f32;
+            let expected = weight / total;
+            assert!(
+                (frequency - expected).abs() < 0.005,
+                "drawn {frequency}, weighted {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn droughts_end_soon_after_the_threshold() {
+        // Rare shapes, with an eighth of the weight of the others
+        let shape_count = default_shapes().len();
+        let weights: Vec<f32> = (0..shape_count)
+            .map(|id| if id < 3 { 1. } else { 8. })
+            .collect();
+        let unprotected = longest_gaps(&draw_ids(&weights, 0), shape_count);
+        let protected = longest_gaps(&draw_ids(&weights, DROUGHT_THRESHOLD), shape_count);
+        for &gap in &unprotected[..3] {
+            assert!(gap > 2 * DROUGHT_THRESHOLD as usize);
+        }
+        for (id, &gap) in protected.iter().enumerate() {
+            assert!(
+                gap <= DROUGHT_THRESHOLD as usize + shape_count,
+                "shape {id} missing for {gap} draws"
+            );
+        }
+    }
+
+    #[test]
+    fn the_tray_hands_out_forced_shapes_first_and_stays_full() {
+        let registry = ShapeRegistry::new(default_shapes());
+        let palette = ColorPalette::default();
+        let weights = vec![1.; default_shapes().len()];
+        let mut drought = ShapeDrought::new(weights.len());
+        let mut rng = StdRng::seed_from_u64(139);
+        let forced: Vec<_> = (0..3)
+            .map(|id| ShapeInstance {
+                id: ShapeId(id),
+                rotation: 0,
+                color: TileColor::Red,
+            })
+            .collect();
+        let mut queue: VecDeque<_> = forced.iter().copied().collect();
+        let mut tray = Tray::default();
+
+        let drawn: Vec<_> = (0..3)
+            .map(|_| {
+                tray.next(
+                    (&mut queue, &mut drought),
+                    &mut rng,
+                    (&weights, 0, &assist::Assist::default()),
+                    (&registry, &palette),
+                )
+            })
+            .collect();
+
+        assert!(drawn == forced);
+        assert!(queue.is_empty());
+        assert_eq!(tray.0.len(), UPCOMING_SHAPES);
+    }
+
+    fn spawn_selected(
+        In(instance): In<ShapeInstance>,
+        mut commands: Commands,
+        (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+    ) {
+        build_shape(&mut commands, &registry, &palette, &instance).insert(SelectedShape);
+    }
+
+    fn replace_selected(
+        In(new): In<ShapeInstance>,
+        mut commands: Commands,
+        (registry, palette): (Res<ShapeRegistry>, Res<ColorPalette>),
+        mut q_selected: Query<(Entity, &mut ShapeInstance, &Children), With<SelectedShape>>,
+        mut q_sprites: Query<&mut Sprite>,
+    ) {
+        replace_selected_shape(
+            &mut commands,
+            (&registry, &palette),
+            q_selected.single_mut(),
+            &mut q_sprites,
+            new,
+            Transform::IDENTITY,
+        );
+    }
+
+    /// A world holding the selected shape, built from the default pool.
+    fn world_with_selected(instance: ShapeInstance) -> World {
+        let mut world = World::new();
+        world.insert_resource(ShapeRegistry::new(default_shapes()));
+        world.insert_resource(ColorPalette::default());
+        world.run_system_once_with(instance, spawn_selected);
+        world
+    }
+
+    /// Replaces the selected shape, returning it and its tiles after.
+    fn replace(world: &mut World, new: ShapeInstance) -> (Entity, Vec<Entity>) {
+        world.run_system_once_with(new, replace_selected);
+        tiles(world)
+    }
+
+    /// The selected shape and its tiles.
+    fn tiles(world: &mut World) -> (Entity, Vec<Entity>) {
+        let (entity, children) = world
+            .query_filtered::<(Entity, &Children), With<SelectedShape>>()
+            .single(world);
+        (entity, children.to_vec())
+    }
+
+    #[test]
+    fn recoloring_the_selected_shape_keeps_its_tiles() {
+        let shape = ShapeInstance {
+            id: ShapeId(4),
+            rotation: 1,
+            color: TileColor::Blue,
+        };
+        let mut world = world_with_selected(shape);
+        let before = tiles(&mut world);
+
+        let after = replace(
+            &mut world,
+            ShapeInstance {
+                color: TileColor::Red,
+                ..shape
+            },
+        );
+
+        assert_eq!(after, before);
+        let red = world.resource::<ColorPalette>().color(TileColor::Red);
+        for tile in after.1 {
+            assert_eq!(world.get::<Sprite>(tile).unwrap().color, red);
+        }
+    }
+
+    #[test]
+    fn rotating_the_selected_shape_rebuilds_it() {
+        let shape = ShapeInstance {
+            id: ShapeId(4),
+            rotation: 1,
+            color: TileColor::Blue,
+        };
+        let mut world = world_with_selected(shape);
+        let before = tiles(&mut world);
+
+        let after = replace(
+            &mut world,
+            ShapeInstance {
+                rotation: 2,
+                ..shape
+            },
+        );
+
+        assert_ne!(after.0, before.0);
+        assert!(before
+            .1
+            .iter()
+            .all(|&tile| world.get_entity(tile).is_none()));
+    }
+}
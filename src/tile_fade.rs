@@ -0,0 +1,63 @@
+//! Fading board tiles to their new color over [`FADE_SECONDS`] instead of snapping, so
+//! gravity and garbage rows read as sweeps. Repaints set the [`TargetTileColor`] and
+//! [`fade_tiles`] moves the sprite towards it; the `tile_fade` setting turns it off.
+//!
+//! Highlights are written to both and show at once. Tiles under a running clear
+//! animation snap, the animation already shows them going.
+
+use bevy::prelude::*;
+
+use crate::{settings::Settings, systems::ClearWaveTile, Board};
+
+/// Seconds a tile takes to fade between any two colors.
+const FADE_SECONDS: f32 = 0.1;
+
+/// Color a main board tile is fading to.
+#[derive(Component)]
+pub struct TargetTileColor(pub Color);
+
+/// Paints a tile at once, for highlights that must not fade.
+pub fn paint_now(sprite: &mut Sprite, target: Option<Mut<TargetTileColor>>, color: Color) {
+    sprite.color = color;
+    if let Some(mut target) = target {
+        target.0 = color;
+    }
+}
+
+pub fn fade_tiles(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    q_boards: Query<&Board>,
+    q_clear_wave: Query<(&Parent, &ClearWaveTile)>,
+    mut q_tiles: Query<(&mut Sprite, &TargetTileColor)>,
+) {
+    for (parent, wave) in q_clear_wave.iter() {
+        let Ok(board) = q_boards.get(parent.get()) else {
+            continue;
+        };
+        let (x, y) = wave.cell;
+        if let Ok((mut sprite, target)) = q_tiles.get_mut(board.entities[y][x]) {
+            if sprite.color != target.0 {
+                sprite.color = target.0;
+            }
+        }
+    }
+    let step = if settings.tile_fade {
+        time.delta_seconds() / FADE_SECONDS
+    } else {
+        1.
+    };
+    for (mut sprite, target) in q_tiles.iter_mut() {
+        if sprite.color == target.0 {
+            continue;
+        }
+        let current = Vec4::from(sprite.color.as_rgba_f32());
+        let goal = Vec4::from(target.0.as_rgba_f32());
+        let delta = goal - current;
+        sprite.color = if delta.abs().max_element() <= step {
+            target.0
+        } else {
+            Color::from(current + delta.clamp(Vec4::splat(-step), Vec4::splat(step)))
+        };
+    }
+}
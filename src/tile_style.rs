@@ -0,0 +1,176 @@
+//! Sizing of the tile sprites of boards and shapes, keeping the gap between neighboring
+//! tiles the same number of screen pixels at any zoom and window size, and rounding
+//! their corners with a generated texture.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    transform::TransformSystem,
+    window::PrimaryWindow,
+};
+
+use crate::MainCamera;
+
+#[derive(Resource)]
+pub struct TileStyle {
+    /// Gap between neighboring tiles, in screen pixels.
+    pub gap_px: f32,
+    /// Radius of the tiles' corners as a share of their side, from square at 0 to round
+    /// at 0.5.
+    pub corner_radius: f32,
+}
+impl Default for TileStyle {
+    fn default() -> Self {
+        Self {
+            gap_px: 1.,
+            corner_radius: 0.12,
+        }
+    }
+}
+
+/// Texels across the rounded square texture.
+const TEXTURE_SIZE: u32 = 32;
+
+/// White rounded square the tiles are drawn with and tinted by their colors, the
+/// default white texture while the corners are square.
+#[derive(Resource, Default)]
+struct TileTexture(Handle<Image>);
+
+/// Cell-sized sprite of a board or shape, spawned with a size of one cell and shrunk by
+/// the gap of the [`TileStyle`] for the scale it is drawn at.
+#[derive(Component)]
+pub struct Tile;
+
+pub struct TileStylePlugin;
+impl Plugin for TileStylePlugin {
+    fn build(&self, app: &mut App) {
+        // After the tiles of the frame got their global scale, before they are drawn
+        app.init_resource::<TileStyle>()
+            .init_resource::<TileTexture>()
+            .add_systems(
+                PostUpdate,
+                (
+                    size_tiles.after(TransformSystem::TransformPropagate),
+                    round_tiles,
+                ),
+            );
+    }
+}
+
+/// Sizes tiles that are new or rescaled, and every tile once the style or the camera's
+/// projection changed, the latter also happening on window resizes.
+fn size_tiles(
+    style: Res<TileStyle>,
+    q_windows: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<Ref<OrthographicProjection>, With<MainCamera>>,
+    mut q_tiles: Query<(Ref<Tile>, Ref<GlobalTransform>, &mut Sprite)>,
+) {
+    let (Ok(window), Ok(projection)) = (q_windows.get_single(), q_camera.get_single()) else {
+        return;
+    };
+    if window.width() <= 0. {
+        return;
+    }
+    let resized = style.is_changed() || projection.is_changed();
+    let world_per_pixel = projection.area.width() / window.width();
+    for (tile, transform, mut sprite) in q_tiles.iter_mut() {
+        if !(resized || tile.is_added() || transform.is_changed()) {
+            continue;
+        }
+        // World units per cell, `TILE_SIZE` for boards and shapes at their usual scale
+        let cell = transform.compute_transform().scale.x;
+        if cell <= 0. {
+            continue;
+        }
+        let size = Some(Vec2::splat(
+            (1. - style.gap_px * world_per_pixel / cell).max(0.),
+        ));
+        if sprite.custom_size != size {
+            sprite.custom_size = size;
+        }
+    }
+}
+
+/// Gives new tiles the rounded texture, and every tile a new one once the style changed.
+fn round_tiles(
+    style: Res<TileStyle>,
+    mut images: ResMut<Assets<Image>>,
+    mut texture: ResMut<TileTexture>,
+    mut q_tiles: Query<(Ref<Tile>, &mut Handle<Image>)>,
+) {
+    if style.is_changed() {
+        texture.0 = match style.corner_radius > 0. {
+            true => images.add(rounded_square(style.corner_radius)),
+            false => Handle::default(),
+        };
+    }
+    for (tile, mut handle) in q_tiles.iter_mut() {
+        if (style.is_changed() || tile.is_added()) && *handle != texture.0 {
+            *handle = texture.0.clone();
+        }
+    }
+}
+
+/// A white square whose corners are cut round with the radius, as a share of its side,
+/// edges blending over a texel.
+fn rounded_square(radius: f32) -> Image {
+    let size = TEXTURE_SIZE as f32;
+    let radius = radius.clamp(0., 0.5) * size;
+    let mut data = Vec::with_capacity((TEXTURE_SIZE * TEXTURE_SIZE * 4) as usize);
+    for y in 0..TEXTURE_SIZE {
+        for x in 0..TEXTURE_SIZE {
+            // From the texel to the center of the nearest corner's circle, zero away from
+            // the corners
+            let offset = |at: u32| {
+                let at = at as f32 + 0.5;
+                at.clamp(radius, size - radius) - at
+            };
+            let offset = Vec2::new(offset(x), offset(y));
+            let alpha = match offset == Vec2::ZERO {
+                true => 1.,
+                false => (radius + 0.5 - offset.length()).clamp(0., 1.),
+            };
+            data.extend([255, 255, 255, (alpha * 255.).round() as u8]);
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: TEXTURE_SIZE,
+            height: TEXTURE_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::linear();
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alpha(image: &Image, x: u32, y: u32) -> u8 {
+        image.data[((y * TEXTURE_SIZE + x) * 4 + 3) as usize]
+    }
+
+    #[test]
+    fn only_the_corners_are_cut() {
+        let last = TEXTURE_SIZE - 1;
+        let rounded = rounded_square(0.25);
+        for (x, y) in [(0, 0), (last, 0), (0, last), (last, last)] {
+            assert_eq!(alpha(&rounded, x, y), 0);
+        }
+        let middle = TEXTURE_SIZE / 2;
+        for (x, y) in [(middle, 0), (0, middle), (middle, middle), (last, middle)] {
+            assert_eq!(alpha(&rounded, x, y), 255);
+        }
+
+        let square = rounded_square(0.);
+        assert!(square.data.chunks(4).all(|texel| texel[3] == 255));
+    }
+}
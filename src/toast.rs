@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+
+use crate::layout::LayoutSlot;
+
+/// Seconds a toast stays on screen.
+const TOAST_DURATION: f32 = 2.5;
+
+/// Short message shown at the top of the screen, fading out on its own.
+#[derive(Component)]
+pub struct Toast {
+    timer: Timer,
+}
+
+pub fn spawn_toast(commands: &mut Commands, message: impl Into<String>) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                message,
+                TextStyle {
+                    font_size: 18.,
+                    color: Color::ORANGE,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(80.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            ..default()
+        },
+        Toast {
+            timer: Timer::from_seconds(TOAST_DURATION, TimerMode::Once),
+        },
+        LayoutSlot::Toasts,
+        Interaction::default(),
+    ));
+}
+
+pub fn fade_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_toasts: Query<(Entity, &mut Toast, &mut Text)>,
+) {
+    for (entity, mut toast, mut text) in q_toasts.iter_mut() {
+        toast.timer.tick(time.delta());
+        if toast.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = 1. - toast.timer.percent();
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
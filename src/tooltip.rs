@@ -0,0 +1,214 @@
+//! Tooltips: a text bubble near the cursor once it rested on an entity with a
+//! [`Tooltip`] for a moment, following the cursor until it leaves the entity.
+//!
+//! Entities are hit-tested through their [`TooltipArea`], the topmost one under the
+//! cursor winning. UI nodes with a [`RelativeCursorPosition`] instead of an area are
+//! over the world, and win over any entity. Over none of them, the bubble names the
+//! selected shape instead.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use crate::{
+    hover::CursorBoardPos,
+    input::IdleTimer,
+    menu::GameState,
+    shape::{ShapeInstance, ShapeRegistry},
+    BlocksSystems, Board, BoardChangedEvent, OverlayBoard, SelectedShape,
+};
+
+/// Offset of the bubble from the cursor, in pixels.
+const TOOLTIP_OFFSET: Vec2 = Vec2::new(16., 16.);
+
+/// Text shown while hovering the entity's [`TooltipArea`], or the UI node.
+#[derive(Component)]
+pub struct Tooltip(pub String);
+
+/// Rectangle in the entity's local space its [`Tooltip`] shows over.
+#[derive(Component)]
+pub struct TooltipArea(pub Rect);
+
+/// Seconds the cursor has to rest on an entity before its tooltip appears.
+#[derive(Resource)]
+pub struct TooltipDelay(pub f32);
+impl Default for TooltipDelay {
+    fn default() -> Self {
+        Self(0.5)
+    }
+}
+
+#[derive(Component)]
+struct TooltipBubble;
+
+pub struct TooltipPlugin;
+impl Plugin for TooltipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TooltipDelay>()
+            .add_systems(Startup, spawn_bubble)
+            .add_systems(
+                Update,
+                (
+                    describe_cells.in_set(BlocksSystems::Presentation),
+                    update_bubble.after(BlocksSystems::Presentation),
+                ),
+            );
+    }
+}
+
+fn spawn_bubble(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(2.)),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.7).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TooltipBubble,
+    ));
+}
+
+/// Explains the bonus, target and frozen cells of the changed cells on their tiles.
+fn describe_cells(
+    mut commands: Commands,
+    mut board_changed: EventReader<BoardChangedEvent>,
+    q_board: Query<&Board, Without<OverlayBoard>>,
+) {
+    for event in board_changed.read() {
+        let Ok(board) = q_board.get(event.board) else {
+            continue;
+        };
+        for &(x, y) in &event.cells {
+            let meta = board.grid.meta.0[y][x];
+            let frozen_for = meta.frozen_for(board.grid.turn());
+            let text = if frozen_for == 1 {
+                Some("Frozen until the next placement".to_owned())
+            } else if frozen_for > 0 {
+                Some(format!("Frozen for {frozen_for} more placements"))
+            } else if meta.target {
+                Some("Target, clear it".to_owned())
+            } else if meta.bonus > 1 {
+                Some(format!("Bonus, x{} points when cleared", meta.bonus))
+            } else {
+                None
+            };
+            // Boards drawn into a texture have no tiles to carry tooltips
+            let Some(mut tile) = commands.get_entity(board.entities[y][x]) else {
+                continue;
+            };
+            match text {
+                Some(text) => tile.insert((
+                    Tooltip(text),
+                    // Tiles are a cell wide around their center
+                    TooltipArea(Rect::from_center_size(Vec2::ZERO, Vec2::ONE)),
+                )),
+                None => tile.remove::<(Tooltip, TooltipArea)>(),
+            };
+        }
+    }
+}
+
+/// Shows the tooltip of the topmost entity under the cursor once the cursor rested on
+/// it long enough, or the name of the selected shape once it rested anywhere else.
+fn update_bubble(
+    state: Res<State<GameState>>,
+    time: Res<Time>,
+    delay: Res<TooltipDelay>,
+    idle: Res<IdleTimer>,
+    registry: Res<ShapeRegistry>,
+    mut hovered: Local<Option<(Entity, f32)>>,
+    cursor: Res<CursorBoardPos>,
+    q_tooltips: Query<(
+        Entity,
+        &Tooltip,
+        &TooltipArea,
+        &GlobalTransform,
+        &InheritedVisibility,
+    )>,
+    q_ui_tooltips: Query<
+        (
+            Entity,
+            &Tooltip,
+            &RelativeCursorPosition,
+            &InheritedVisibility,
+        ),
+        Without<TooltipArea>,
+    >,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+    mut q_bubble: Query<(&mut Text, &mut Style, &mut Visibility), With<TooltipBubble>>,
+) {
+    let Ok((mut text, mut style, mut visibility)) = q_bubble.get_single_mut() else {
+        return;
+    };
+    let in_game = matches!(state.get(), GameState::Playing | GameState::Sandbox);
+    let ui_target = q_ui_tooltips
+        .iter()
+        .find(|(_, _, node_cursor, visibility)| visibility.get() && node_cursor.mouse_over())
+        .map(|(entity, tooltip, ..)| (entity, tooltip));
+    let world_target = cursor.world.filter(|_| in_game).and_then(|world| {
+        q_tooltips
+            .iter()
+            .filter(|(_, _, area, transform, visibility)| {
+                let local = transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(world.extend(0.));
+                visibility.get() && area.0.contains(local.truncate())
+            })
+            .max_by(|(.., a, _), (.., b, _)| a.translation().z.total_cmp(&b.translation().z))
+            .map(|(entity, tooltip, ..)| (entity, tooltip))
+    });
+    let target = ui_target.filter(|_| in_game).or(world_target);
+
+    // Time on the same entity, restarting whenever the cursor moves onto another one
+    let rested = match (target, *hovered) {
+        (Some((entity, _)), Some((previous, since))) if entity == previous => {
+            time.elapsed_seconds() - since
+        }
+        (Some((entity, _)), _) => {
+            *hovered = Some((entity, time.elapsed_seconds()));
+            0.
+        }
+        (None, _) => {
+            *hovered = None;
+            0.
+        }
+    };
+    let shown = match target {
+        Some((_, tooltip)) => (rested >= delay.0).then_some(tooltip.0.as_str()),
+        None => q_selected_shape
+            .get_single()
+            .ok()
+            .filter(|_| idle.0 >= delay.0)
+            .map(|selected_shape| registry.name(selected_shape.id)),
+    };
+    let (Some(position), Some(shown)) = (cursor.viewport.filter(|_| in_game), shown) else {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+        return;
+    };
+    if text.sections[0].value != shown {
+        text.sections[0].value = shown.to_owned();
+    }
+    let (left, top) = (
+        Val::Px(position.x + TOOLTIP_OFFSET.x),
+        Val::Px(position.y + TOOLTIP_OFFSET.y),
+    );
+    if style.left != left || style.top != top {
+        style.left = left;
+        style.top = top;
+    }
+    if *visibility != Visibility::Inherited {
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,299 @@
+//! Scripted first game teaching the rules, run once until finished or skipped.
+
+use bevy::{math::URect, prelude::*};
+
+use crate::{
+    board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    constraints::{PlacementConstraint, PlacementConstraints},
+    events::BoardCommand,
+    menu::{not_resuming, GameMode, GameState},
+    persistence::{PersistRequest, Persisted},
+    replay::Playback,
+    rules::RuleSet,
+    sandbox::PlayFrom,
+    settings::Settings,
+    shape::{ShapeId, ShapeInstance},
+    strings::Strings,
+    systems::ShapeQueue,
+    toast::spawn_toast,
+    BlocksSystems, Board, MainBoard, PlacementEvent,
+};
+
+/// Name of the tutorial's [`PlacementConstraint`].
+const CONSTRAINT: &str = "tutorial";
+
+struct Step {
+    text: &'static str,
+    shape: ShapeInstance,
+    /// Region the shape has to be placed in, `max` excluded.
+    target: URect,
+    /// Cells filled before the step starts, setting up a clear.
+    prefill: Vec<(usize, usize)>,
+}
+
+fn steps() -> [Step; 3] {
+    [
+        Step {
+            text: "Drag the square onto the highlighted area and click to place it",
+            shape: ShapeInstance {
+                id: ShapeId(0),
+                rotation: 0,
+                color: TileColor::Blue,
+            },
+            target: URect::new(9, 9, 11, 11),
+            prefill: Vec::new(),
+        },
+        Step {
+            text: "Complete a row to clear it",
+            shape: ShapeInstance {
+                id: ShapeId(1),
+                rotation: 0,
+                color: TileColor::Green,
+            },
+            target: URect::new(8, 0, 12, 1),
+            prefill: (0..BOARD_WIDTH)
+                .filter(|x| !(8..12).contains(x))
+                .map(|x| (x, 0))
+                .collect(),
+        },
+        Step {
+            text: "Columns clear too",
+            shape: ShapeInstance {
+                id: ShapeId(2),
+                rotation: 1,
+                color: TileColor::Red,
+            },
+            target: URect::new(19, 17, 20, 20),
+            prefill: (0..17).map(|y| (19, y)).collect(),
+        },
+    ]
+}
+
+/// Present while the tutorial runs.
+#[derive(Resource)]
+pub struct Tutorial {
+    step: usize,
+}
+
+#[derive(Component)]
+struct TutorialBox;
+
+#[derive(Component)]
+struct TutorialText;
+
+#[derive(Component)]
+struct SkipButton;
+
+pub struct TutorialPlugin;
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            start_tutorial.run_if(not_resuming),
+        )
+        .add_systems(
+            Update,
+            (
+                advance_tutorial.after(BlocksSystems::Resolution),
+                skip_tutorial.run_if(resource_exists::<Tutorial>()),
+            ),
+        );
+    }
+}
+
+fn start_tutorial(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    (mode, rules): (Res<GameMode>, Res<RuleSet>),
+    playback: Option<Res<Playback>>,
+    play_from: Option<Res<PlayFrom>>,
+    mut constraints: ResMut<PlacementConstraints>,
+    mut board_commands: EventWriter<BoardCommand>,
+    mut q_main_board: Query<(Entity, &Board, &mut ShapeQueue), With<MainBoard>>,
+) {
+    if settings.tutorial_completed
+        || *mode != GameMode::Endless
+        || playback.is_some()
+        || play_from.is_some()
+    {
+        return;
+    }
+    let Ok((board, grid, mut queue)) = q_main_board.get_single_mut() else {
+        return;
+    };
+    // The steps are laid out on the full board, the rules resize it with the restart
+    let size = rules
+        .board_size
+        .unwrap_or((grid.grid.width(), grid.grid.height()));
+    if size != (BOARD_WIDTH, BOARD_HEIGHT) {
+        return;
+    }
+    let steps = steps();
+    // The restart following the menu hands out the first one
+    queue.0 = steps.iter().map(|step| step.shape).collect();
+    commands.insert_resource(Tutorial { step: 0 });
+    spawn_tutorial_box(&mut commands, steps[0].text);
+    apply_step(&steps[0], board, &mut constraints, &mut board_commands);
+}
+
+fn apply_step(
+    step: &Step,
+    board: Entity,
+    constraints: &mut PlacementConstraints,
+    board_commands: &mut EventWriter<BoardCommand>,
+) {
+    constraints.remove(CONSTRAINT);
+    constraints.add(PlacementConstraint::inside_rect(CONSTRAINT, step.target));
+    if !step.prefill.is_empty() {
+        board_commands.send(BoardCommand::Fill {
+            board,
+            cells: step.prefill.iter().copied().collect(),
+            color: Some(TileColor::Gray),
+        });
+    }
+}
+
+fn spawn_tutorial_box(commands: &mut Commands, text: &str) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(40.),
+                    left: Val::Percent(25.),
+                    width: Val::Percent(50.),
+                    padding: UiRect::all(Val::Px(12.)),
+                    column_gap: Val::Px(12.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                ..default()
+            },
+            TutorialBox,
+        ))
+        .with_children(|commands| {
+            commands.spawn((
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font_size: 20.,
+                        ..default()
+                    },
+                ),
+                TutorialText,
+            ));
+            commands
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::all(Val::Px(6.)),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.3, 0.3, 0.3).into(),
+                        ..default()
+                    },
+                    SkipButton,
+                ))
+                .with_children(|commands| {
+                    commands.spawn(TextBundle::from_section(
+                        "Skip",
+                        TextStyle {
+                            font_size: 18.,
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+/// Moves on to the next step once the player placed the step's shape.
+fn advance_tutorial(
+    mut commands: Commands,
+    tutorial: Option<ResMut<Tutorial>>,
+    strings: Res<Strings>,
+    mut settings: ResMut<Settings>,
+    mut constraints: ResMut<PlacementConstraints>,
+    mut placements: EventReader<PlacementEvent>,
+    mut board_commands: EventWriter<BoardCommand>,
+    mut persist: EventWriter<PersistRequest>,
+    q_main_board: Query<(), With<MainBoard>>,
+    q_box: Query<Entity, With<TutorialBox>>,
+    mut q_text: Query<&mut Text, With<TutorialText>>,
+) {
+    let Some(mut tutorial) = tutorial else {
+        placements.clear();
+        return;
+    };
+    let Some(board) = placements
+        .read()
+        .map(|event| event.board)
+        .filter(|&board| q_main_board.contains(board))
+        .last()
+    else {
+        return;
+    };
+
+    tutorial.step += 1;
+    let steps = steps();
+    let Some(step) = steps.get(tutorial.step) else {
+        spawn_toast(&mut commands, strings.get("toast.tutorial_complete"));
+        finish_tutorial(
+            &mut commands,
+            &mut settings,
+            &mut constraints,
+            &mut persist,
+            &q_box,
+        );
+        return;
+    };
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value = step.text.into();
+    }
+    apply_step(step, board, &mut constraints, &mut board_commands);
+}
+
+fn skip_tutorial(
+    mut commands: Commands,
+    mut settings: ResMut<Settings>,
+    mut constraints: ResMut<PlacementConstraints>,
+    mut q_queue: Query<&mut ShapeQueue, With<MainBoard>>,
+    mut persist: EventWriter<PersistRequest>,
+    q_box: Query<Entity, With<TutorialBox>>,
+    q_skip: Query<&Interaction, (Changed<Interaction>, With<SkipButton>)>,
+) {
+    if !q_skip
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    for mut queue in q_queue.iter_mut() {
+        queue.0.clear();
+    }
+    finish_tutorial(
+        &mut commands,
+        &mut settings,
+        &mut constraints,
+        &mut persist,
+        &q_box,
+    );
+}
+
+/// Lifts the tutorial's constraints and remembers it ran.
+fn finish_tutorial(
+    commands: &mut Commands,
+    settings: &mut Settings,
+    constraints: &mut PlacementConstraints,
+    persist: &mut EventWriter<PersistRequest>,
+    q_box: &Query<Entity, With<TutorialBox>>,
+) {
+    commands.remove_resource::<Tutorial>();
+    constraints.remove(CONSTRAINT);
+    for entity in q_box.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    settings.tutorial_completed = true;
+    persist.send(settings.persist_request());
+}
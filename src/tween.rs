@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+/// Interpolates an entity's transform and sprite or text alpha over time, optionally
+/// despawning it once finished.
+#[derive(Component, Clone)]
+pub struct Tween {
+    /// Seconds to wait before the interpolation starts, the start values are held meanwhile.
+    pub delay: f32,
+    pub duration: f32,
+    pub elapsed: f32,
+    pub scale: Option<(Vec3, Vec3)>,
+    pub translation: Option<(Vec3, Vec3)>,
+    pub alpha: Option<(f32, f32)>,
+    pub despawn: bool,
+}
+impl Tween {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            delay: 0.,
+            duration,
+            elapsed: 0.,
+            scale: None,
+            translation: None,
+            alpha: None,
+            despawn: false,
+        }
+    }
+
+    pub fn with_delay(mut self, delay: f32) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_scale(mut self, from: Vec3, to: Vec3) -> Self {
+        self.scale = Some((from, to));
+        self
+    }
+
+    pub fn with_translation(mut self, from: Vec3, to: Vec3) -> Self {
+        self.translation = Some((from, to));
+        self
+    }
+
+    pub fn with_alpha(mut self, from: f32, to: f32) -> Self {
+        self.alpha = Some((from, to));
+        self
+    }
+
+    pub fn despawn_on_finish(mut self) -> Self {
+        self.despawn = true;
+        self
+    }
+
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0. {
+            return if self.elapsed >= self.delay { 1. } else { 0. };
+        }
+        ((self.elapsed - self.delay) / self.duration).clamp(0., 1.)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.delay + self.duration
+    }
+}
+
+pub fn tween(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_tweens: Query<(
+        Entity,
+        &mut Tween,
+        Option<&mut Transform>,
+        Option<&mut Sprite>,
+        Option<&mut Text>,
+    )>,
+) {
+    for (entity, mut tween, transform, sprite, text) in q_tweens.iter_mut() {
+        tween.elapsed += time.delta_seconds();
+        let t = tween.progress();
+        if let Some(mut transform) = transform {
+            if let Some((from, to)) = tween.scale {
+                transform.scale = from.lerp(to, t);
+            }
+            if let Some((from, to)) = tween.translation {
+                transform.translation = from.lerp(to, t);
+            }
+        }
+        if let Some((from, to)) = tween.alpha {
+            let alpha = from + (to - from) * t;
+            if let Some(mut sprite) = sprite {
+                sprite.color.set_a(alpha);
+            }
+            if let Some(mut text) = text {
+                for section in text.sections.iter_mut() {
+                    section.style.color.set_a(alpha);
+                }
+            }
+        }
+        if tween.finished() {
+            if tween.despawn {
+                commands.entity(entity).despawn_recursive();
+            } else {
+                commands.entity(entity).remove::<Tween>();
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Presentation of the boards and the HUD around them: the camera, board repaints and
+//! the assist overlay.
+
+use bevy::prelude::*;
+
+use crate::{
+    board,
+    board::TileColor,
+    board_texture,
+    components::{Board, MainBoard, MainCamera, OverlayBoard, SelectedShape},
+    constraints::PlacementConstraints,
+    events::BoardChangedEvent,
+    palette::ColorPalette,
+    settings::Settings,
+    shape::{ShapeId, ShapeInstance, ShapeRegistry},
+    systems::FREEZE_PLACEMENTS,
+    tile_fade, zones,
+};
+
+/// Spawns the [`MainCamera`], zoomed to the configured tile size.
+pub(crate) fn spawn_camera(mut commands: Commands, zoom: Res<CameraZoom>) {
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scale = zoom.0;
+    commands.spawn((camera, MainCamera));
+}
+
+/// Spawns the HUD texts of the board: the assist overlay and the cascade cap note.
+pub(crate) fn spawn_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                left: Val::Px(8.),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        AssistOverlay,
+    ));
+
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "cascade too long to preview",
+                TextStyle {
+                    font_size: 14.,
+                    color: Color::rgba(1., 1., 1., 0.5),
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(8.),
+                left: Val::Percent(40.),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CascadeCapNote,
+    ));
+}
+
+/// Counts the placements left for the selected shape in any of its rotations, as the
+/// placement constraints allow them, recounting only when the board, the shape or the
+/// constraints changed.
+pub(crate) fn update_assist_overlay(
+    registry: Res<ShapeRegistry>,
+    constraints: Res<PlacementConstraints>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_selected_shape: Query<&ShapeInstance, With<SelectedShape>>,
+    mut q_overlay: Query<(&mut Text, &Visibility), With<AssistOverlay>>,
+    mut cache: Local<Option<(u64, ShapeId)>>,
+) {
+    let Ok((mut text, visibility)) = q_overlay.get_single_mut() else {
+        return;
+    };
+    let Ok(selected_shape) = q_selected_shape.get_single() else {
+        return;
+    };
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    let board = &q_board.single().grid;
+    let key = (board.revision(), selected_shape.id);
+    if *cache == Some(key) && !constraints.is_changed() {
+        return;
+    }
+    *cache = Some(key);
+
+    let bitboard = board.bitboard();
+    let (mut placements, mut clearing) = (0, 0);
+    for rotation in 0..registry.rotation_count(selected_shape.id) {
+        let shape = registry.get(selected_shape.id, rotation as u8);
+        let shape_rows = shape.row_masks();
+        for anchor in board::anchors(shape) {
+            let allowed = bitboard.fits_at(&shape_rows, anchor)
+                && constraints
+                    .check(board, shape, (anchor.0 as isize, anchor.1 as isize))
+                    .is_none();
+            if allowed {
+                placements += 1;
+                if bitboard.completes_line(&shape_rows, anchor) {
+                    clearing += 1;
+                }
+            }
+        }
+    }
+    text.sections[0].value = format!("Placements: {placements}\nClearing a line: {clearing}");
+}
+
+/// Color the board state paints a tile in, before any highlight.
+pub(crate) fn tile_color(
+    board: &Board,
+    zone_map: &zones::ZoneMap,
+    palette: &ColorPalette,
+    x: usize,
+    y: usize,
+) -> Color {
+    match board.grid.cells.0[y][x] {
+        Some(color) => palette.color(color),
+        None => zone_map.empty_tile_color(x, y),
+    }
+}
+
+/// Size and color of the badge of a cell, transparent on cells without metadata.
+pub(crate) fn badge(board: &Board, x: usize, y: usize) -> (f32, Color) {
+    let meta = board.grid.meta.0[y][x];
+    // Frozen cells get an ice pip shrinking as they thaw
+    let frozen_for = meta.frozen_for(board.grid.turn());
+    if frozen_for > 0 {
+        let size = 0.3 + 0.5 * frozen_for as f32 / FREEZE_PLACEMENTS as f32;
+        (size, Color::rgba(0.7, 0.9, 1., 0.8))
+    } else if meta.target {
+        (0.3, Color::WHITE)
+    } else if meta.bonus > 2 {
+        (0.3, Color::ORANGE)
+    } else if meta.bonus > 1 {
+        (0.3, Color::GOLD)
+    } else {
+        (0.3, TileColor::Transparent.into())
+    }
+}
+
+/// Repaints the tiles of the cells touched by board changes, or their texels on boards
+/// drawn by [`BoardRenderer::Texture`].
+pub(crate) fn update_board(
+    zone_map: Res<zones::ZoneMap>,
+    palette: Res<ColorPalette>,
+    mut board_changed: EventReader<BoardChangedEvent>,
+    q_board: Query<&Board, Without<OverlayBoard>>,
+    settings: Res<Settings>,
+    mut q_board_tiles: Query<(&mut Sprite, Option<&mut tile_fade::TargetTileColor>)>,
+    mut images: ResMut<Assets<Image>>,
+    mut sprite_mutations: ResMut<SpriteMutations>,
+) {
+    for event in board_changed.read() {
+        let Ok(board) = q_board.get(event.board) else {
+            continue;
+        };
+        trace!(
+            "repainting {} cells for board revision {}",
+            event.cells.len(),
+            event.revision
+        );
+        if let Some(texture) = &board.texture {
+            let Some(image) = images.get_mut(&texture.image) else {
+                continue;
+            };
+            for &(x, y) in &event.cells {
+                let color = tile_color(board, &zone_map, &palette, x, y);
+                board_texture::paint_cell(image, (x, y), color, badge(board, x, y).1);
+            }
+            continue;
+        }
+        for &(x, y) in &event.cells {
+            if let Ok((mut sprite, target)) = q_board_tiles.get_mut(board.entities[y][x]) {
+                let color = tile_color(board, &zone_map, &palette, x, y);
+                match target {
+                    // Fading is left to `tile_fade::fade_tiles`
+                    Some(mut target) if settings.tile_fade => target.0 = color,
+                    target => tile_fade::paint_now(&mut sprite, target, color),
+                }
+                sprite_mutations.0 += 1;
+            }
+            if let Ok((mut sprite, _)) = q_board_tiles.get_mut(board.badges[y][x]) {
+                let (size, color) = badge(board, x, y);
+                sprite.custom_size = Some(Vec2::splat(size));
+                sprite.color = color;
+                sprite_mutations.0 += 1;
+            }
+        }
+    }
+}
+
+pub(crate) fn reset_sprite_mutations(mut sprite_mutations: ResMut<SpriteMutations>) {
+    sprite_mutations.0 = 0;
+}
+
+/// Projection scale of the [`MainCamera`] the plugin spawns.
+#[derive(Resource)]
+pub struct CameraZoom(pub f32);
+
+/// Text listing the placements left for the selected shape, toggled with F3.
+#[derive(Component)]
+pub(crate) struct AssistOverlay;
+
+/// Shown instead of the cascade preview when the cascade takes too many steps.
+#[derive(Component)]
+pub(crate) struct CascadeCapNote;
+
+/// Number of tile sprites recolored by the board and overlay systems this frame.
+#[derive(Resource, Default)]
+pub struct SpriteMutations(pub u32);
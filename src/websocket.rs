@@ -0,0 +1,130 @@
+//! The [`Transport`] of versus play in the browser, which can't open TCP sockets. The
+//! page connects to the WebSocket given as `?connect=<url>`, e.g. a WebSocket to TCP
+//! bridge like websockify in front of a native `--host`. Messages are the same bytes
+//! TCP carries, sent as binary frames.
+//!
+//! [`Transport`]: crate::net::Transport
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use js_sys::{ArrayBuffer, Uint8Array};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{BinaryType, Event, MessageEvent, WebSocket};
+
+/// Bytes the browser may hold back unsent before writes report `WouldBlock`.
+const MAX_BUFFERED: u32 = 64 * 1024;
+
+/// A WebSocket as a non-blocking byte stream. Writes report `WouldBlock` until the
+/// socket opened, reads see the end of the stream once it closed.
+pub struct WebSocketTransport {
+    socket: WebSocket,
+    /// Filled by the socket's message callback.
+    incoming: Arc<Mutex<VecDeque<u8>>>,
+    closed: Arc<AtomicBool>,
+    // Kept alive for as long as the socket calls them
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(Event)>,
+}
+// The browser's objects stay on the one thread wasm32 runs the game on
+unsafe impl Send for WebSocketTransport {}
+unsafe impl Sync for WebSocketTransport {}
+
+impl WebSocketTransport {
+    pub fn connect(url: &str) -> io::Result<Self> {
+        let socket = WebSocket::new(url).map_err(js_error)?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let incoming = Arc::new(Mutex::new(VecDeque::new()));
+        let received = incoming.clone();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let data = event.data();
+            let mut received = received.lock().unwrap();
+            if let Ok(buffer) = data.dyn_into::<ArrayBuffer>() {
+                received.extend(Uint8Array::new(&buffer).to_vec());
+            } else if let Some(text) = event.data().as_string() {
+                received.extend(text.bytes());
+            }
+        });
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let closed = Arc::new(AtomicBool::new(false));
+        let closing = closed.clone();
+        let on_close = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            closing.store(true, Ordering::Relaxed);
+        });
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(on_close.as_ref().unchecked_ref()));
+
+        Ok(Self {
+            socket,
+            incoming,
+            closed,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+}
+impl Read for WebSocketTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut incoming = self.incoming.lock().unwrap();
+        if incoming.is_empty() {
+            return match self.closed.load(Ordering::Relaxed) {
+                true => Ok(0),
+                false => Err(io::ErrorKind::WouldBlock.into()),
+            };
+        }
+        let read = buf.len().min(incoming.len());
+        for (byte, received) in buf.iter_mut().zip(incoming.drain(..read)) {
+            *byte = received;
+        }
+        Ok(read)
+    }
+}
+impl Write for WebSocketTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.socket.ready_state() {
+            WebSocket::CONNECTING => return Err(io::ErrorKind::WouldBlock.into()),
+            WebSocket::OPEN => {}
+            _ => return Err(io::ErrorKind::BrokenPipe.into()),
+        }
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.socket.buffered_amount() > MAX_BUFFERED {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        self.socket.send_with_u8_array(buf).map_err(js_error)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        self.socket.set_onerror(None);
+        let _ = self.socket.close();
+    }
+}
+
+/// The WebSocket the page was opened to connect to, from its `connect` query parameter.
+pub fn url_from_page() -> Option<String> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get("connect")
+}
+
+fn js_error(err: wasm_bindgen::JsValue) -> io::Error {
+    io::Error::other(format!("{err:?}"))
+}
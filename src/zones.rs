@@ -0,0 +1,136 @@
+//! Optional scoring zones: cells in higher zones are worth more when cleared.
+//!
+//! Zones are configured in `zones.txt`, which holds `rings <n>`, `stripes <n>` or
+//! `custom` followed by one row of digits per board row.
+
+use bevy::prelude::*;
+
+use crate::{
+    board::{Grid, TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    persistence::{PersistAppExt, Persisted},
+    BlocksSystems, Board, BoardChangedEvent, MainBoard, OverlayBoard,
+};
+
+/// How much brighter an empty tile gets per zone.
+const ZONE_BRIGHTNESS: f32 = 0.02;
+
+pub type ZoneGrid = Grid<u8, BOARD_WIDTH, BOARD_HEIGHT>;
+
+#[derive(Resource, Default, Clone)]
+pub enum ZoneConfig {
+    /// Every cell in zone 0.
+    #[default]
+    Off,
+    /// Concentric rings, the center being the highest zone.
+    Rings(u8),
+    /// Vertical stripes, the middle ones being the highest zones.
+    Stripes(u8),
+    Custom(Box<ZoneGrid>),
+}
+impl ZoneConfig {
+    /// Zones of a `width` by `height` board. Custom zones are laid out for the full
+    /// board and cut to its bottom left corner.
+    pub fn generate(&self, (width, height): (usize, usize)) -> ZoneGrid {
+        let mut zones = ZoneGrid::default();
+        // Distance of a coordinate to the nearer edge, scaled to `count` bands
+        let band = |i: usize, len: usize, count: u8| {
+            let distance = i.min(len - 1 - i);
+            (distance * count as usize / len.div_ceil(2)) as u8
+        };
+        for (y, row) in zones.0.iter_mut().enumerate() {
+            for (x, zone) in row.iter_mut().enumerate() {
+                *zone = match self {
+                    ZoneConfig::Off => 0,
+                    _ if x >= width || y >= height => 0,
+                    ZoneConfig::Rings(rings) => band(x, width, *rings).min(band(y, height, *rings)),
+                    ZoneConfig::Stripes(stripes) => band(x, width, *stripes),
+                    ZoneConfig::Custom(custom) => custom.0[y][x],
+                };
+            }
+        }
+        zones
+    }
+}
+impl Persisted for ZoneConfig {
+    const PATH: &'static str = "zones.txt";
+
+    fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        let mut header = lines.next()?.split_whitespace();
+        match (header.next()?, header.next()) {
+            ("rings", Some(count)) => Some(ZoneConfig::Rings(count.parse().ok()?)),
+            ("stripes", Some(count)) => Some(ZoneConfig::Stripes(count.parse().ok()?)),
+            ("custom", None) => {
+                let mut custom = ZoneGrid::default();
+                for row in custom.0.iter_mut() {
+                    let line = lines.next()?;
+                    if line.chars().count() != BOARD_WIDTH {
+                        return None;
+                    }
+                    for (zone, c) in row.iter_mut().zip(line.chars()) {
+                        *zone = c.to_digit(10)? as u8;
+                    }
+                }
+                Some(ZoneConfig::Custom(Box::new(custom)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Zone of every cell, generated from the [`ZoneConfig`].
+#[derive(Resource, Default)]
+pub struct ZoneMap(pub ZoneGrid);
+impl ZoneMap {
+    /// Color of an empty tile in the given cell, slightly brighter in higher zones.
+    pub fn empty_tile_color(&self, x: usize, y: usize) -> Color {
+        let gray = Color::from(TileColor::Gray);
+        let brightness = self.0 .0[y][x] as f32 * ZONE_BRIGHTNESS;
+        Color::rgb(
+            gray.r() + brightness,
+            gray.g() + brightness,
+            gray.b() + brightness,
+        )
+    }
+}
+
+pub struct ZonesPlugin;
+impl Plugin for ZonesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ZoneMap>()
+            .load_persisted::<ZoneConfig>()
+            .add_systems(
+                Update,
+                regenerate_zone_map.before(BlocksSystems::Presentation),
+            );
+    }
+}
+
+/// Regenerates the zone map whenever the config or the main board's size changes and
+/// repaints the boards' empty tiles with it.
+fn regenerate_zone_map(
+    config: Res<ZoneConfig>,
+    mut zone_map: ResMut<ZoneMap>,
+    mut size: Local<(usize, usize)>,
+    q_main_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+    q_board: Query<(&Board, Entity), Without<OverlayBoard>>,
+    mut board_changed: EventWriter<BoardChangedEvent>,
+) {
+    let main_size = q_main_board
+        .get_single()
+        .map_or((BOARD_WIDTH, BOARD_HEIGHT), |board| {
+            (board.grid.width(), board.grid.height())
+        });
+    if !config.is_changed() && *size == main_size {
+        return;
+    }
+    *size = main_size;
+    zone_map.0 = config.generate(main_size);
+    for (board, entity) in q_board.iter() {
+        board_changed.send(BoardChangedEvent {
+            board: entity,
+            revision: board.grid.revision(),
+            cells: board.grid.positions().collect(),
+        });
+    }
+}
@@ -0,0 +1,157 @@
+//! Placing, previewing and predicting shapes doesn't allocate, nor do the game's idle
+//! frames, and the frames of a placement only a bounded number of times. Runs in its own
+//! binary to count the allocations of the test's thread with a global allocator.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+#[cfg(feature = "test-harness")]
+use bevy::{ecs::schedule::ExecutorKind, prelude::*, transform::TransformSystem};
+#[cfg(feature = "test-harness")]
+use blocks::harness;
+use blocks::{
+    board::{Board, TileColor, BOARD_WIDTH},
+    shape::Shape,
+};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Allocations `f` made on this thread.
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// Frames the game runs before its frames are counted, for its buffers, queries and event
+/// queues to have grown to their steady size.
+#[cfg(feature = "test-harness")]
+const WARM_UP_FRAMES: usize = 10;
+/// Allocations allowed to the frames of a placement, from reading the click to placing
+/// the shape and drawing the next one.
+#[cfg(feature = "test-harness")]
+const MAX_PLACEMENT_ALLOCATIONS: usize = 512;
+
+/// A started headless game, warmed up, whose systems run on the test's thread where their
+/// allocations are counted. Bevy's transform propagation is left out of the counted
+/// frames, its `ParamSet` clones its access every frame.
+#[cfg(feature = "test-harness")]
+fn started_game() -> App {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    for (_, schedule) in app.world.resource_mut::<Schedules>().iter_mut() {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+    for _ in 0..WARM_UP_FRAMES {
+        app.update();
+    }
+    app.configure_sets(
+        PostUpdate,
+        TransformSystem::TransformPropagate.run_if(|| false),
+    );
+    // Building the schedule again
+    app.update();
+    app
+}
+
+#[cfg(feature = "test-harness")]
+fn filled_cells(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<blocks::Board>(board).unwrap().grid();
+    grid.cells.0.iter().flatten().flatten().count()
+}
+
+/// A board with its bottom row full but for its last cell.
+fn almost_full_row() -> Board {
+    let mut board = Board::default();
+    for x in 0..BOARD_WIDTH - 1 {
+        board.cells.0[0][x] = Some(TileColor::Green);
+    }
+    board
+}
+
+#[test]
+fn placing_does_not_allocate() {
+    let square = Shape::from_pattern(2, 2, "####");
+    let mut board = Board::default();
+    let count = allocations(|| {
+        let placed = board.try_place(&square, (4, 4), TileColor::Blue);
+        assert_eq!(placed.map(|cells| cells.len()), Some(4));
+    });
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn previewing_does_not_allocate() {
+    let line = Shape::from_pattern(4, 1, "####");
+    let board = almost_full_row();
+    let count = allocations(|| {
+        for x in -2..BOARD_WIDTH as isize {
+            let superimposition = board.superimpose_at(&line, (x, 3));
+            assert_eq!(superimposition.success, (0..=16).contains(&x));
+        }
+    });
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn predicting_a_cascade_does_not_allocate() {
+    let dot = Shape::from_pattern(1, 1, "#");
+    let board = almost_full_row();
+    let count = allocations(|| {
+        let predicted = board.predict(&dot, (BOARD_WIDTH as isize - 1, 0), TileColor::Red, true, 8);
+        assert!(predicted.unwrap().cells.0[0].iter().all(Option::is_none));
+    });
+    assert_eq!(count, 0);
+}
+
+#[cfg(feature = "test-harness")]
+#[test]
+fn idle_frames_do_not_allocate() {
+    let mut app = started_game();
+    for _ in 0..5 {
+        assert_eq!(allocations(|| app.update()), 0);
+    }
+}
+
+#[cfg(feature = "test-harness")]
+#[test]
+fn placement_frames_allocate_a_bounded_number_of_times() {
+    let mut app = started_game();
+    // The first placement grows the buffers of everything reacting to one
+    harness::click_cell(&mut app, (4, 4));
+    for cell in [(10, 4), (16, 4), (4, 12), (10, 12), (16, 12)] {
+        let filled = filled_cells(&mut app);
+        let count = allocations(|| harness::click_cell(&mut app, cell));
+        assert!(filled_cells(&mut app) > filled);
+        assert!(
+            count <= MAX_PLACEMENT_ALLOCATIONS,
+            "placing at {cell:?} allocated {count} times"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! The assist overlay counts the placements the player could actually make.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{board::TileColor, harness, prelude::*};
+
+/// Text of the assist overlay, once F3 showed it.
+fn overlay(app: &mut App) -> String {
+    app.world
+        .query::<(&Text, &Visibility)>()
+        .iter(&app.world)
+        .filter(|(_, visibility)| **visibility != Visibility::Hidden)
+        .map(|(text, _)| text.sections[0].value.clone())
+        .find(|text| text.starts_with("Placements"))
+        .expect("no assist overlay")
+}
+
+#[test]
+fn every_rotation_counts() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let line = harness::named_shape(&app, "Line 4", TileColor::Blue);
+    harness::select_shape(&mut app, line);
+    harness::fill(&mut app, [(0, 0)], Some(TileColor::Stone));
+    harness::press_key(&mut app, KeyCode::F3);
+
+    // 17 anchors in each of the 20 rows and columns, less the one over the filled cell
+    assert!(overlay(&mut app).starts_with("Placements: 678\n"));
+}
+
+#[test]
+fn constraints_veto_placements() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let line = harness::named_shape(&app, "Line 4", TileColor::Blue);
+    harness::select_shape(&mut app, line);
+    harness::fill(&mut app, [(0, 0)], Some(TileColor::Stone));
+    app.world.resource_mut::<RuleSet>().adjacency = true;
+    harness::press_key(&mut app, KeyCode::F3);
+
+    // Lying or standing next to the filled cell, on either of its sides
+    assert!(overlay(&mut app).starts_with("Placements: 4\n"));
+}
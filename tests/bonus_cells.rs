@@ -0,0 +1,46 @@
+//! Clears through bonus cells score their multiplier.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{CellMeta, TileColor, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+/// Points of clearing the bottom row with a dot, its cells given the metadata.
+fn clear_bottom_row(meta: &[((usize, usize), CellMeta)]) -> u64 {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    // No bonus cells but the given ones, the game started with some
+    app.world.resource_mut::<RuleSet>().bonus_cells = 0;
+    harness::set_meta(&mut app, (0..BOARD_WIDTH).map(|x| (x, 0)), default());
+    harness::fill(
+        &mut app,
+        (0..BOARD_WIDTH - 1).map(|x| (x, 0)),
+        Some(TileColor::Green),
+    );
+    for &(cell, meta) in meta {
+        harness::set_meta(&mut app, [cell], meta);
+    }
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::select_shape(&mut app, dot);
+    app.update();
+
+    harness::click_cell(&mut app, (BOARD_WIDTH - 1, 0));
+
+    let board = harness::main_board(&mut app);
+    app.world.get::<Score>(board).unwrap().0
+}
+
+#[test]
+fn a_bonus_cell_multiplies_the_clear() {
+    let triple = CellMeta {
+        bonus: 3,
+        ..default()
+    };
+    let plain = clear_bottom_row(&[]);
+    let bonus = clear_bottom_row(&[((4, 0), triple)]);
+    // The placement's own point isn't multiplied
+    assert_eq!(bonus - 1, 3 * (plain - 1));
+}
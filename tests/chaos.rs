@@ -0,0 +1,78 @@
+//! Play goes on while the selected shape keeps being despawned or stripped of its
+//! tiles, see [`Chaos`].
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::BOARD_WIDTH,
+    harness::{self, Chaos, SimulatedClick, SimulatedCursor},
+    prelude::*,
+    shape::ShapeRegistry,
+};
+
+/// Filled cells of the main board.
+fn filled(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    grid.cells.0.iter().flatten().flatten().count()
+}
+
+/// Whether the selected shape exists with all of its tiles.
+fn whole_shape(app: &mut App) -> bool {
+    let Ok((shape, children)) = app
+        .world
+        .query_filtered::<(&ShapeInstance, &Children), With<SelectedShape>>()
+        .get_single(&app.world)
+    else {
+        return false;
+    };
+    let registry = app.world.resource::<ShapeRegistry>();
+    children.len() == shape.resolve(registry).bits().count_ones() as usize
+}
+
+/// Runs a frame, after which the selected shape is whole unless chaos struck at its
+/// end. Any damage from the frame before was repaired at its start.
+fn frame(app: &mut App) {
+    let strikes = app.world.resource::<Chaos>().strikes();
+    app.update();
+    let struck = app.world.resource::<Chaos>().strikes() != strikes;
+    assert!(struck || whole_shape(app));
+}
+
+#[test]
+fn play_recovers_from_losing_the_selected_shape() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    app.insert_resource(Chaos::every(3));
+    let sizes = {
+        let registry = app.world.resource::<ShapeRegistry>();
+        registry
+            .pool()
+            .map(|(_, rotations)| rotations[0].bits().count_ones() as usize)
+            .collect::<Vec<_>>()
+    };
+
+    let mut placements = 0;
+    for turn in 0..40 {
+        let before = filled(&mut app);
+        app.insert_resource(SimulatedCursor::Cell((
+            turn * 7 % BOARD_WIDTH,
+            turn * 3 % 20,
+        )));
+        frame(&mut app);
+        app.world.send_event(SimulatedClick);
+        frame(&mut app);
+
+        // Cells only ever come in whole shapes, lines clearing aside
+        let after = filled(&mut app);
+        if after > before {
+            assert!(
+                sizes.contains(&(after - before)),
+                "{before} to {after} cells"
+            );
+        }
+        placements += usize::from(after != before);
+    }
+    assert!(placements > 10);
+    assert!(app.world.resource::<Chaos>().strikes() > 10);
+}
@@ -0,0 +1,32 @@
+//! Clicks the HUD takes don't reach the board, simulated ones included.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::harness;
+
+fn filled_cells(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    let board = app.world.get::<blocks::Board>(board).unwrap();
+    board.grid().cells.0.iter().flatten().flatten().count()
+}
+
+#[test]
+fn click_on_a_button_over_the_board_places_nothing() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    // The pointer is over the button, as bevy_ui's focus system would find
+    let button = app
+        .world
+        .spawn(ButtonBundle {
+            interaction: Interaction::Hovered,
+            ..default()
+        })
+        .id();
+
+    harness::click_cell(&mut app, (10, 10));
+    assert_eq!(filled_cells(&mut app), 0);
+
+    app.world.despawn(button);
+    harness::click_cell(&mut app, (10, 10));
+    assert!(filled_cells(&mut app) > 0);
+}
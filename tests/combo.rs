@@ -0,0 +1,70 @@
+//! The main board's combo counts placements in a row that cleared lines and shows on
+//! the HUD from the second one.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+fn combo(app: &mut App) -> u32 {
+    let board = harness::main_board(app);
+    app.world.get::<Combo>(board).unwrap().0
+}
+
+fn hud_shows(app: &mut App, text: &str) -> bool {
+    app.world.query::<&Text>().iter(&app.world).any(|hud| {
+        hud.sections
+            .iter()
+            .any(|section| section.value.contains(text))
+    })
+}
+
+/// Places a dot at the end of `row`, completing it if the rest was filled.
+fn place_dot(app: &mut App, row: usize) {
+    let dot = harness::named_shape(app, "Dot", TileColor::Red);
+    harness::select_shape(app, dot);
+    app.update();
+    harness::click_cell(app, (BOARD_WIDTH - 1, row));
+}
+
+fn clear_row(app: &mut App, row: usize) {
+    harness::fill(
+        app,
+        (0..BOARD_WIDTH - 1).map(|x| (x, row)),
+        Some(TileColor::Green),
+    );
+    place_dot(app, row);
+}
+
+#[test]
+fn clears_in_a_row_build_the_combo_and_a_miss_ends_it() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+
+    clear_row(&mut app, 3);
+    assert_eq!(combo(&mut app), 1);
+    assert!(!hud_shows(&mut app, "Combo"));
+
+    clear_row(&mut app, 5);
+    assert_eq!(combo(&mut app), 2);
+    assert!(hud_shows(&mut app, "Combo x2"));
+
+    place_dot(&mut app, 8);
+    assert_eq!(combo(&mut app), 0);
+    assert!(!hud_shows(&mut app, "Combo"));
+}
+
+#[test]
+fn restarting_ends_the_combo() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    clear_row(&mut app, 3);
+    clear_row(&mut app, 5);
+
+    harness::press_keys(&mut app, &[KeyCode::ControlLeft, KeyCode::R]);
+
+    assert_eq!(combo(&mut app), 0);
+}
@@ -0,0 +1,68 @@
+//! Playing without the mouse: the arrow keys or a gamepad's D-pad steer the cursor, and
+//! rotations kick the shape back onto the board as they do under the mouse.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+fn filled_cells(app: &mut App) -> Vec<(usize, usize)> {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    grid.positions()
+        .filter(|&(x, y)| grid.cells.0[y][x].is_some())
+        .collect()
+}
+
+/// A vertical line of 4 at the right edge, whose horizontal rotation sticks out.
+fn select_vertical_line(app: &mut App) {
+    let line = harness::named_shape(app, "Line 4", TileColor::Blue);
+    harness::select_shape(
+        app,
+        ShapeInstance {
+            rotation: 1,
+            ..line
+        },
+    );
+    app.update();
+}
+
+/// The four rightmost cells of the middle row.
+fn kicked_line() -> Vec<(usize, usize)> {
+    (BOARD_WIDTH - 4..BOARD_WIDTH)
+        .map(|x| (x, BOARD_HEIGHT / 2))
+        .collect()
+}
+
+#[test]
+fn the_d_pad_rotation_kicks_the_shape_onto_the_board() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    select_vertical_line(&mut app);
+
+    for _ in 0..BOARD_WIDTH {
+        harness::press_button(&mut app, GamepadButtonType::DPadRight);
+    }
+    harness::press_button(&mut app, GamepadButtonType::East);
+    harness::press_button(&mut app, GamepadButtonType::South);
+
+    assert_eq!(filled_cells(&mut app), kicked_line());
+}
+
+#[test]
+fn the_arrow_key_rotation_kicks_the_shape_onto_the_board() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    select_vertical_line(&mut app);
+
+    for _ in 0..BOARD_WIDTH {
+        harness::press_key(&mut app, KeyCode::Right);
+    }
+    harness::press_key(&mut app, KeyCode::R);
+    harness::press_key(&mut app, KeyCode::Space);
+
+    assert_eq!(filled_cells(&mut app), kicked_line());
+}
@@ -0,0 +1,42 @@
+//! An empty selected shape, which could neither be seen nor placed, is replaced by
+//! one that can.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{board::TileColor, harness, prelude::*, shape::Shape, shape::ShapeRegistry};
+
+#[test]
+fn an_empty_selected_shape_is_replaced() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let empty = app
+        .world
+        .resource_mut::<ShapeRegistry>()
+        .push_unchecked(Shape::from_pattern(2, 2, "...."));
+    // Slipped through without its tiles being built
+    let mut selected = app
+        .world
+        .query_filtered::<&mut ShapeInstance, With<SelectedShape>>()
+        .single_mut(&mut app.world);
+    selected.id = empty;
+    selected.rotation = 0;
+    app.update();
+
+    let replaced = harness::selected_shape(&mut app);
+    assert_ne!(replaced.id, empty);
+    let registry = app.world.resource::<ShapeRegistry>();
+    assert!(registry.in_pool(replaced.id));
+    let fields = replaced.resolve(registry).bits().count_ones() as usize;
+    let tiles = app
+        .world
+        .query_filtered::<&Children, With<SelectedShape>>()
+        .single(&app.world)
+        .len();
+    assert_eq!(tiles, fields);
+
+    harness::fill(&mut app, [(0, 0)], Some(TileColor::Stone));
+    harness::click_cell(&mut app, (10, 10));
+    let board = harness::main_board(&mut app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    assert_eq!(grid.cells.0.iter().flatten().flatten().count(), 1 + fields);
+}
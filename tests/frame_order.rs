@@ -0,0 +1,57 @@
+//! Within a frame, the cursor is read before the preview, the preview before the
+//! placement, the placement before the clears it causes and the clears before the tiles
+//! are painted.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_WIDTH},
+    harness::{self, SimulatedClick, SimulatedCursor},
+    prelude::*,
+};
+
+fn cell(app: &mut App, cell: (usize, usize)) -> Option<TileColor> {
+    let board = harness::main_board(app);
+    app.world.get::<Board>(board).unwrap().grid().cells.0[cell.1][cell.0]
+}
+
+#[test]
+fn a_click_places_where_the_cursor_moved_that_frame() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::select_shape(&mut app, dot);
+    app.insert_resource(SimulatedCursor::Cell((2, 2)));
+    app.update();
+
+    app.insert_resource(SimulatedCursor::Cell((12, 7)));
+    app.world.send_event(SimulatedClick);
+    app.update();
+
+    assert_eq!(cell(&mut app, (12, 7)), Some(TileColor::Red));
+    assert_eq!(cell(&mut app, (2, 2)), None);
+    assert_eq!(harness::unpainted_tiles(&mut app), []);
+}
+
+#[test]
+fn a_placement_clears_its_lines_the_same_frame() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    harness::fill(
+        &mut app,
+        (0..BOARD_WIDTH - 1).map(|x| (x, 3)),
+        Some(TileColor::Green),
+    );
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::select_shape(&mut app, dot);
+    app.update();
+
+    app.insert_resource(SimulatedCursor::Cell((BOARD_WIDTH - 1, 3)));
+    app.world.send_event(SimulatedClick);
+    app.update();
+
+    assert_eq!(cell(&mut app, (0, 3)), None);
+    let board = harness::main_board(&mut app);
+    assert_eq!(app.world.get::<Level>(board).unwrap().lines, 1);
+    assert_eq!(harness::unpainted_tiles(&mut app), []);
+}
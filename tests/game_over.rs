@@ -0,0 +1,53 @@
+//! Escape pauses a game without ending it, and a game that's over waits on its results
+//! until played again.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{board::TileColor, harness, prelude::*};
+
+fn state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().get()
+}
+
+fn place_square(app: &mut App) -> Entity {
+    let square = harness::named_shape(app, "Square", TileColor::Blue);
+    harness::select_shape(app, square);
+    app.update();
+    harness::click_cell(app, (10, 10));
+    harness::main_board(app)
+}
+
+#[test]
+fn resuming_a_paused_game_keeps_it() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let board = place_square(&mut app);
+    let score = app.world.get::<Score>(board).unwrap().0;
+    assert!(score > 0);
+
+    harness::press_key(&mut app, KeyCode::Escape);
+    app.update();
+    assert_eq!(state(&app), GameState::Paused);
+
+    harness::press_key(&mut app, KeyCode::Escape);
+    app.update();
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(app.world.get::<Score>(board).unwrap().0, score);
+}
+
+#[test]
+fn playing_again_after_game_over_starts_a_new_game() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let board = place_square(&mut app);
+
+    app.world.send_event(GameOverEvent);
+    app.update();
+    app.update();
+    assert_eq!(state(&app), GameState::GameOver);
+
+    harness::press_key(&mut app, KeyCode::Return);
+    app.update();
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(app.world.get::<Score>(board).unwrap().0, 0);
+}
@@ -0,0 +1,128 @@
+//! Two games in one process playing versus over an in-memory channel.
+#![cfg(all(feature = "net", feature = "test-harness"))]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_WIDTH},
+    harness,
+    net::{self, Connection, GarbageAction, MirrorBoard},
+    prelude::*,
+};
+
+/// Small enough that a placement and its cells take several frames to get through.
+const CHANNEL_CAPACITY: usize = 16;
+/// Large enough for a clear and all of its cells to get through within a few frames.
+const WIDE_CHANNEL_CAPACITY: usize = 4096;
+/// Frames both games run for messages to arrive.
+const SYNC_FRAMES: usize = 60;
+
+fn versus_pair(capacity: usize) -> (App, App) {
+    let (host_end, guest_end) = net::memory_pair(capacity);
+    let mut host = harness::headless_app();
+    host.insert_resource(Connection::new(host_end))
+        .insert_resource(GameMode::Versus);
+    let mut guest = harness::headless_app();
+    guest
+        .insert_resource(Connection::new(guest_end))
+        .insert_resource(GameMode::Versus);
+    harness::start_game(&mut host);
+    harness::start_game(&mut guest);
+    (host, guest)
+}
+
+fn sync(host: &mut App, guest: &mut App) {
+    for _ in 0..SYNC_FRAMES {
+        host.update();
+        guest.update();
+    }
+}
+
+/// Cells and metadata of a board, whose revisions count differently on either side.
+fn cells(board: &Board) -> BoardSnapshot {
+    BoardSnapshot {
+        revision: 0,
+        ..board.grid().snapshot()
+    }
+}
+
+fn main_cells(app: &mut App) -> BoardSnapshot {
+    let board = harness::main_board(app);
+    cells(app.world.get::<Board>(board).unwrap())
+}
+
+fn mirror_cells(app: &mut App) -> Option<BoardSnapshot> {
+    app.world
+        .query_filtered::<&Board, With<MirrorBoard>>()
+        .get_single(&app.world)
+        .ok()
+        .map(cells)
+}
+
+fn filled(snapshot: &BoardSnapshot) -> usize {
+    snapshot.cells.0.iter().flatten().flatten().count()
+}
+
+fn place_square(app: &mut App, cell: (usize, usize)) {
+    let square = harness::named_shape(app, "Square", TileColor::Blue);
+    harness::select_shape(app, square);
+    app.update();
+    harness::click_cell(app, cell);
+}
+
+fn texts(app: &mut App) -> Vec<String> {
+    app.world
+        .query::<&Text>()
+        .iter(&app.world)
+        .map(|text| text.sections[0].value.clone())
+        .collect()
+}
+
+#[test]
+fn placements_show_on_the_opponents_mirror() {
+    let (mut host, mut guest) = versus_pair(CHANNEL_CAPACITY);
+
+    place_square(&mut host, (10, 10));
+    place_square(&mut guest, (3, 4));
+    place_square(&mut guest, (6, 4));
+    sync(&mut host, &mut guest);
+
+    assert_eq!(filled(&main_cells(&mut host)), 4);
+    assert!(mirror_cells(&mut guest) == Some(main_cells(&mut host)));
+    assert!(mirror_cells(&mut host) == Some(main_cells(&mut guest)));
+}
+
+#[test]
+fn clearing_several_lines_sends_the_opponent_garbage() {
+    let (mut host, mut guest) = versus_pair(WIDE_CHANNEL_CAPACITY);
+
+    // Two rows cleared at once
+    for x in (0..BOARD_WIDTH).step_by(2) {
+        place_square(&mut host, (x, 10));
+    }
+    sync(&mut host, &mut guest);
+
+    assert_eq!(filled(&main_cells(&mut host)), 0);
+    let garbage = GarbageAction::rows_for(2);
+    let board = harness::main_board(&mut guest);
+    let grid = guest.world.get::<Board>(board).unwrap().grid();
+    assert_eq!(grid.garbage_rows(), garbage);
+    assert_eq!(filled(&main_cells(&mut guest)), garbage * (BOARD_WIDTH - 1));
+    assert!(mirror_cells(&mut host) == Some(main_cells(&mut guest)));
+}
+
+#[test]
+fn losing_the_opponent_leaves_a_local_game() {
+    let (mut host, guest) = versus_pair(CHANNEL_CAPACITY);
+    drop(guest);
+
+    host.update();
+    host.update();
+
+    assert!(!host.world.contains_resource::<Connection>());
+    assert!(mirror_cells(&mut host).is_none());
+    assert!(texts(&mut host)
+        .iter()
+        .any(|text| text.starts_with("Opponent disconnected")));
+    place_square(&mut host, (10, 10));
+    assert_eq!(filled(&main_cells(&mut host)), 4);
+}
@@ -0,0 +1,74 @@
+//! Holding the right mouse button over the piece just placed lifts it back off the
+//! board, unless it cleared lines.
+#![cfg(feature = "test-harness")]
+
+use bevy::{
+    input::{mouse::MouseButtonInput, ButtonState},
+    prelude::*,
+};
+use blocks::{
+    board::{TileColor, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+fn filled_cells(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    grid.positions()
+        .filter(|&(x, y)| grid.cells.0[y][x].is_some())
+        .count()
+}
+
+/// Holds the right mouse button for half a second.
+fn hold_right_button(app: &mut App) {
+    for (state, frames) in [(ButtonState::Pressed, 30), (ButtonState::Released, 1)] {
+        app.world.send_event(MouseButtonInput {
+            button: MouseButton::Right,
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+        for _ in 0..frames {
+            app.update();
+        }
+    }
+}
+
+#[test]
+fn holding_the_right_button_lifts_the_last_piece() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let square = harness::named_shape(&app, "Square", TileColor::Blue);
+    harness::select_shape(&mut app, square);
+    app.update();
+    harness::click_cell(&mut app, (10, 10));
+    let board = harness::main_board(&mut app);
+    assert!(app.world.get::<Score>(board).unwrap().0 > 0);
+
+    hold_right_button(&mut app);
+
+    assert_eq!(filled_cells(&mut app), 0);
+    assert_eq!(app.world.get::<Score>(board).unwrap().0, 0);
+    assert_eq!(harness::selected_shape(&mut app).id, square.id);
+}
+
+#[test]
+fn a_piece_that_cleared_a_line_stays() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    harness::fill(
+        &mut app,
+        (2..BOARD_WIDTH).flat_map(|x| [(x, 0), (x, 1)]),
+        Some(TileColor::Green),
+    );
+    let square = harness::named_shape(&app, "Square", TileColor::Blue);
+    harness::select_shape(&mut app, square);
+    app.update();
+    harness::click_cell(&mut app, (0, 0));
+    app.update();
+    assert_eq!(filled_cells(&mut app), 0);
+
+    hold_right_button(&mut app);
+
+    assert_eq!(filled_cells(&mut app), 0);
+}
@@ -0,0 +1,94 @@
+//! Placing shapes on a headless game through the test harness's cursor and clicks.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+fn filled_cells(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    let board = app.world.get::<Board>(board).unwrap();
+    board.grid().cells.0.iter().flatten().flatten().count()
+}
+
+fn score(app: &mut App) -> u64 {
+    let board = harness::main_board(app);
+    app.world.get::<Score>(board).unwrap().0
+}
+
+/// Entities drawn on the main board: its tiles, the overlay board and animations.
+fn board_children(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    app.world.get::<Children>(board).unwrap().len()
+}
+
+fn selected_shapes(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<SelectedShape>>()
+        .iter(&app.world)
+        .count()
+}
+
+#[test]
+fn click_places_the_selected_shape() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let square = harness::named_shape(&app, "Square", TileColor::Blue);
+    harness::select_shape(&mut app, square);
+    app.update();
+
+    harness::click_cell(&mut app, (10, 10));
+
+    assert_eq!(filled_cells(&mut app), 4);
+    assert_eq!(score(&mut app), 4);
+    assert_eq!(selected_shapes(&mut app), 1);
+}
+
+#[test]
+fn click_on_filled_cells_places_nothing() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let square = harness::named_shape(&app, "Square", TileColor::Blue);
+    harness::select_shape(&mut app, square);
+    harness::fill(&mut app, [(10, 10)], Some(TileColor::Stone));
+    let selected = harness::selected_shape(&mut app);
+
+    harness::click_cell(&mut app, (10, 10));
+
+    assert_eq!(filled_cells(&mut app), 1);
+    assert_eq!(score(&mut app), 0);
+    assert!(harness::selected_shape(&mut app) == selected);
+}
+
+#[test]
+fn completing_a_row_clears_it_and_its_animation_cleans_up() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    harness::fill(
+        &mut app,
+        (0..BOARD_WIDTH - 1).map(|x| (x, 0)),
+        Some(TileColor::Green),
+    );
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::select_shape(&mut app, dot);
+    app.update();
+    let tiles = board_children(&mut app);
+
+    harness::click_cell(&mut app, (BOARD_WIDTH - 1, 0));
+
+    assert_eq!(filled_cells(&mut app), 0);
+    assert!(score(&mut app) > 1);
+    let board = harness::main_board(&mut app);
+    assert_eq!(app.world.get::<Level>(board).unwrap().lines, 1);
+    // A shrinking copy of every cleared cell, gone once the animation is over
+    app.update();
+    assert_eq!(board_children(&mut app), tiles + BOARD_WIDTH);
+    for _ in 0..120 {
+        app.update();
+    }
+    assert_eq!(board_children(&mut app), tiles);
+    assert_eq!(selected_shapes(&mut app), 1);
+}
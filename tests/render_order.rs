@@ -0,0 +1,57 @@
+//! The overlay board's preview tints draw above the main board's tiles and badges, and
+//! below the selected shape.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{harness, prelude::*};
+
+/// World z of every sprite under the entity, the entity's own included, leaving out
+/// the sprites under `except`.
+fn sprite_depths(app: &App, entity: Entity, except: Option<Entity>) -> Vec<f32> {
+    let mut depths = Vec::new();
+    let mut stack = vec![entity];
+    while let Some(entity) = stack.pop() {
+        if Some(entity) == except {
+            continue;
+        }
+        if app.world.get::<Sprite>(entity).is_some() {
+            let transform = app.world.get::<GlobalTransform>(entity).unwrap();
+            depths.push(transform.translation().z);
+        }
+        if let Some(children) = app.world.get::<Children>(entity) {
+            stack.extend(children.iter());
+        }
+    }
+    depths
+}
+
+fn max(depths: &[f32]) -> f32 {
+    depths.iter().copied().fold(f32::MIN, f32::max)
+}
+
+fn min(depths: &[f32]) -> f32 {
+    depths.iter().copied().fold(f32::MAX, f32::min)
+}
+
+#[test]
+fn the_overlay_draws_between_the_board_and_the_shape() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let overlay = app
+        .world
+        .query_filtered::<Entity, With<OverlayBoard>>()
+        .single(&app.world);
+    let selected = app
+        .world
+        .query_filtered::<Entity, With<SelectedShape>>()
+        .single(&app.world);
+    let main = harness::main_board(&mut app);
+
+    let overlay_depths = sprite_depths(&app, overlay, None);
+    let board_depths = sprite_depths(&app, main, Some(overlay));
+    let shape_depths = sprite_depths(&app, selected, None);
+
+    assert!(!overlay_depths.is_empty() && !board_depths.is_empty());
+    assert!(max(&board_depths) < min(&overlay_depths));
+    assert!(max(&overlay_depths) < min(&shape_depths));
+}
@@ -0,0 +1,134 @@
+//! A replay code watched in another game ends on the board it was recorded from, in the
+//! mode and under the rules it was recorded with.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+/// Cells and metadata of the main board, whose revision counts the restarts too.
+fn main_cells(app: &mut App) -> BoardSnapshot {
+    let board = harness::main_board(app);
+    BoardSnapshot {
+        revision: 0,
+        ..app.world.get::<Board>(board).unwrap().grid().snapshot()
+    }
+}
+
+fn filled(cells: &BoardSnapshot) -> usize {
+    cells.cells.0.iter().flatten().flatten().count()
+}
+
+fn score(app: &mut App) -> u64 {
+    let board = harness::main_board(app);
+    app.world.get::<Score>(board).unwrap().0
+}
+
+fn place(app: &mut App, name: &str, color: TileColor, cell: (usize, usize)) {
+    let shape = harness::named_shape(app, name, color);
+    harness::select_shape(app, shape);
+    app.update();
+    harness::click_cell(app, cell);
+}
+
+#[test]
+fn watching_a_replay_ends_on_the_recorded_board() {
+    let mut recorded = harness::headless_app();
+    harness::start_game(&mut recorded);
+    // Two rows cleared by squares, and squares left standing
+    for x in (0..BOARD_WIDTH).step_by(2) {
+        place(&mut recorded, "Square", TileColor::Blue, (x, 0));
+    }
+    place(&mut recorded, "Square", TileColor::Red, (4, 6));
+    place(&mut recorded, "Square", TileColor::Green, (11, 9));
+    let code = harness::replay_code(&mut recorded);
+
+    let mut watched = harness::headless_app();
+    harness::watch_replay(&mut watched, &code);
+
+    assert_eq!(filled(&main_cells(&mut recorded)), 8);
+    assert!(main_cells(&mut watched) == main_cells(&mut recorded));
+    assert_eq!(score(&mut watched), score(&mut recorded));
+}
+
+#[test]
+fn watching_a_dig_digs_the_recorded_garbage() {
+    let mut recorded = harness::headless_app();
+    *recorded.world.resource_mut::<GameMode>() = GameMode::Dig;
+    harness::start_game(&mut recorded);
+    place(&mut recorded, "Square", TileColor::Red, (4, 12));
+    place(&mut recorded, "Square", TileColor::Green, (10, 14));
+    let code = harness::replay_code(&mut recorded);
+
+    let mut watched = harness::headless_app();
+    harness::watch_replay(&mut watched, &code);
+
+    assert!(*watched.world.resource::<GameMode>() == GameMode::Dig);
+    let board = harness::main_board(&mut watched);
+    let garbage = watched
+        .world
+        .get::<Board>(board)
+        .unwrap()
+        .grid()
+        .garbage_rows();
+    assert!(garbage > 0);
+    assert!(main_cells(&mut watched) == main_cells(&mut recorded));
+}
+
+#[test]
+fn watching_a_scattered_opening_starts_from_the_recorded_clumps() {
+    let mut recorded = harness::headless_app();
+    recorded.insert_resource(RuleSet {
+        start_preset: StartPreset::Scattered,
+        start_density: 0.3,
+        ..RuleSet::classic()
+    });
+    harness::start_game(&mut recorded);
+    let opening = filled(&main_cells(&mut recorded));
+    assert!(opening > 0);
+    place(&mut recorded, "Square", TileColor::Red, (4, 6));
+    place(&mut recorded, "Square", TileColor::Green, (11, 9));
+    let code = harness::replay_code(&mut recorded);
+
+    let mut watched = harness::headless_app();
+    harness::watch_replay(&mut watched, &code);
+
+    assert_eq!(
+        watched.world.resource::<RuleSet>().start_preset,
+        StartPreset::Scattered
+    );
+    assert!(main_cells(&mut watched) == main_cells(&mut recorded));
+}
+
+#[test]
+fn watching_a_replay_scores_under_the_recorded_rules() {
+    let mut recorded = harness::headless_app();
+    *recorded.world.resource_mut::<GameMode>() = GameMode::Dig;
+    let rules = RuleSet {
+        dig_rows: 3,
+        bonus_cells: 12,
+        multipliers: true,
+        ..RuleSet::classic()
+    };
+    recorded.insert_resource(rules.clone());
+    harness::start_game(&mut recorded);
+    // Above the garbage
+    for x in (0..BOARD_WIDTH).step_by(2) {
+        place(&mut recorded, "Square", TileColor::Blue, (x, 3));
+    }
+    assert!(score(&mut recorded) > 0);
+    let code = harness::replay_code(&mut recorded);
+
+    let mut watched = harness::headless_app();
+    harness::watch_replay(&mut watched, &code);
+
+    // Watching pins the board to the recorded size
+    let mut watched_rules = watched.world.resource::<RuleSet>().clone();
+    watched_rules.board_size = None;
+    assert_eq!(watched_rules, rules);
+    assert!(main_cells(&mut watched) == main_cells(&mut recorded));
+    assert_eq!(score(&mut watched), score(&mut recorded));
+}
@@ -0,0 +1,224 @@
+//! A `ResizeBoardEvent` reconciles the tiles of the main and overlay boards with the new
+//! size, right away in the menu and with the next game once one started, and checks the
+//! shape pool against it.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    harness,
+    prelude::*,
+    shape::ShapeRegistry,
+    shapes,
+};
+
+const CTRL_R: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::R];
+
+fn resize(app: &mut App, width: usize, height: usize) {
+    app.world.send_event(ResizeBoardEvent { width, height });
+    app.update();
+}
+
+fn state(app: &App) -> GameState {
+    *app.world.resource::<State<GameState>>().get()
+}
+
+fn size(app: &mut App) -> (usize, usize) {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    (grid.width(), grid.height())
+}
+
+/// Tile sprites of a board, the sprites among its children.
+fn tiles(app: &mut App, board: Entity) -> usize {
+    let children = app.world.get::<Children>(board).unwrap().to_vec();
+    children
+        .into_iter()
+        .filter(|&child| app.world.get::<Sprite>(child).is_some())
+        .count()
+}
+
+fn overlay_board(app: &mut App) -> Entity {
+    app.world
+        .query_filtered::<Entity, With<OverlayBoard>>()
+        .single(&app.world)
+}
+
+/// Asserts every child lists an existing parent holding it, and every listed child
+/// exists and points back.
+fn assert_no_orphans(app: &mut App) {
+    let mut q_parents = app.world.query::<(Entity, &Parent)>();
+    for (child, parent) in q_parents.iter(&app.world) {
+        let children = app.world.get::<Children>(parent.get());
+        assert!(
+            children.is_some_and(|children| children.contains(&child)),
+            "{child:?} isn't a child of its parent"
+        );
+    }
+    let mut q_children = app.world.query::<(Entity, &Children)>();
+    for (parent, children) in q_children.iter(&app.world) {
+        for &child in children {
+            let back = app.world.get::<Parent>(child).map(Parent::get);
+            assert_eq!(back, Some(parent), "{child:?} is gone or has moved");
+        }
+    }
+}
+
+#[test]
+fn growing_and_shrinking_spawns_and_despawns_only_the_difference() {
+    let mut app = harness::headless_app();
+    app.update();
+    let (board, overlay) = (harness::main_board(&mut app), overlay_board(&mut app));
+    let entities = app.world.entities().len();
+    resize(&mut app, 10, 10);
+    assert_eq!(size(&mut app), (10, 10));
+    assert_eq!(tiles(&mut app, board), 100);
+    assert_eq!(tiles(&mut app, overlay), 100);
+    // A tile and its badge per cell left out, on both boards
+    let removed = 2 * 2 * (BOARD_WIDTH * BOARD_HEIGHT - 100) as u32;
+    assert_eq!(app.world.entities().len(), entities - removed);
+    assert_no_orphans(&mut app);
+    let corner = first_tile(&mut app, board);
+
+    resize(&mut app, BOARD_WIDTH, BOARD_HEIGHT);
+    assert_eq!(size(&mut app), (BOARD_WIDTH, BOARD_HEIGHT));
+    assert_eq!(tiles(&mut app, board), BOARD_WIDTH * BOARD_HEIGHT);
+    assert_eq!(tiles(&mut app, overlay), BOARD_WIDTH * BOARD_HEIGHT);
+    assert_eq!(app.world.entities().len(), entities);
+    assert_no_orphans(&mut app);
+    // Tiles that were there already are kept, only moved
+    assert!(app.world.get_entity(corner).is_some());
+
+    resize(&mut app, 10, 10);
+    assert_eq!(tiles(&mut app, board), 100);
+    assert_eq!(app.world.entities().len(), entities - removed);
+    assert_no_orphans(&mut app);
+}
+
+/// The tile of the main board in the bottom left corner.
+fn first_tile(app: &mut App, board: Entity) -> Entity {
+    let transform = *app.world.get::<GlobalTransform>(board).unwrap();
+    let corner = app
+        .world
+        .get::<Board>(board)
+        .unwrap()
+        .cell_to_world(&transform, (0, 0));
+    let children = app.world.get::<Children>(board).unwrap().to_vec();
+    children
+        .into_iter()
+        .find(|&child| {
+            app.world.get::<Sprite>(child).is_some()
+                && app
+                    .world
+                    .get::<GlobalTransform>(child)
+                    .is_some_and(|tile| tile.translation().truncate() == corner)
+        })
+        .unwrap()
+}
+
+#[test]
+fn resizing_keeps_the_board_centered() {
+    let mut app = harness::headless_app();
+    app.update();
+    resize(&mut app, 10, 6);
+    app.update();
+    let board = harness::main_board(&mut app);
+    let transform = *app.world.get::<GlobalTransform>(board).unwrap();
+    let origin = transform.translation().truncate();
+    let grid = app.world.get::<Board>(board).unwrap();
+    let corner = grid.cell_to_world(&transform, (0, 0));
+    assert_eq!(corner - origin, Vec2::new(-4.5, -2.5) * TILE_SIZE);
+    let beyond = corner + Vec2::new(10., 0.) * TILE_SIZE;
+    assert_eq!(grid.world_to_cell(&transform, beyond), None);
+}
+
+#[test]
+fn a_resize_during_a_game_waits_for_the_restart() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    resize(&mut app, 10, 10);
+    assert_eq!(size(&mut app), (BOARD_WIDTH, BOARD_HEIGHT));
+
+    harness::press_keys(&mut app, &CTRL_R);
+    assert_eq!(size(&mut app), (10, 10));
+
+    // Shapes go on the smaller board, and lines are its width long
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::fill(&mut app, (0..9).map(|x| (x, 9)), Some(TileColor::Green));
+    harness::select_shape(&mut app, dot);
+    app.update();
+    harness::click_cell(&mut app, (9, 9));
+    let board = harness::main_board(&mut app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    assert!(grid.cells.0[9].iter().all(Option::is_none));
+    assert!(app.world.get::<Score>(board).unwrap().0 > 0);
+}
+
+#[test]
+fn a_resize_while_paused_or_over_waits_for_the_next_game() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    harness::press_key(&mut app, KeyCode::Escape);
+    app.update();
+    assert_eq!(state(&app), GameState::Paused);
+    resize(&mut app, 10, 10);
+    assert_eq!(size(&mut app), (BOARD_WIDTH, BOARD_HEIGHT));
+
+    // Resuming keeps the game and its board
+    harness::press_key(&mut app, KeyCode::Escape);
+    app.update();
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(size(&mut app), (BOARD_WIDTH, BOARD_HEIGHT));
+
+    app.world.send_event(GameOverEvent);
+    app.update();
+    app.update();
+    assert_eq!(state(&app), GameState::GameOver);
+    resize(&mut app, 12, 8);
+    assert_eq!(size(&mut app), (BOARD_WIDTH, BOARD_HEIGHT));
+
+    harness::press_key(&mut app, KeyCode::Return);
+    app.update();
+    assert_eq!(state(&app), GameState::Playing);
+    assert_eq!(size(&mut app), (12, 8));
+}
+
+fn pool_names(app: &App) -> Vec<String> {
+    let registry = app.world.resource::<ShapeRegistry>();
+    registry
+        .pool()
+        .map(|(id, _)| registry.name(id).to_owned())
+        .collect()
+}
+
+#[test]
+fn shapes_larger_than_the_board_leave_the_pool_until_it_grows() {
+    let mut app = harness::headless_app();
+    app.update();
+    app.world.send_event(ShapePoolChanged(shapes![
+        (5, 1) "#####" as "Line 5";
+        (6, 1) "######" as "Line 6";
+    ]));
+    app.update();
+    assert_eq!(pool_names(&app), ["Line 5", "Line 6"]);
+
+    resize(&mut app, 5, 5);
+    assert_eq!(size(&mut app), (5, 5));
+    assert_eq!(pool_names(&app), ["Line 5"]);
+
+    resize(&mut app, 6, 5);
+    assert_eq!(pool_names(&app), ["Line 5", "Line 6"]);
+}
+
+#[test]
+fn a_size_no_shape_fits_on_is_refused() {
+    let mut app = harness::headless_app();
+    app.update();
+    app.world
+        .send_event(ShapePoolChanged(shapes![(6, 1) "######" as "Line 6"]));
+    app.update();
+
+    resize(&mut app, 5, 5);
+    assert_eq!(size(&mut app), (BOARD_WIDTH, BOARD_HEIGHT));
+    assert_eq!(pool_names(&app), ["Line 6"]);
+}
@@ -0,0 +1,96 @@
+//! Ctrl+R starts over in the mode being played.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+const CTRL_R: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::R];
+
+fn filled_cells(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    grid.cells.0.iter().flatten().flatten().count()
+}
+
+/// The selected shape and the tray before each of five placements of the drawn shapes,
+/// and after the last.
+fn first_draws(app: &mut App) -> Vec<(ShapeInstance, Vec<ShapeInstance>)> {
+    let mut draws = Vec::new();
+    for cell in [(4, 4), (10, 4), (16, 4), (4, 12), (10, 12)] {
+        draws.push((harness::selected_shape(app), harness::tray(app)));
+        let filled = filled_cells(app);
+        harness::click_cell(app, cell);
+        assert!(filled_cells(app) > filled, "nothing placed at {cell:?}");
+    }
+    draws.push((harness::selected_shape(app), harness::tray(app)));
+    draws
+}
+
+fn filled_rows(app: &mut App) -> usize {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    (0..BOARD_HEIGHT)
+        .filter(|&y| grid.cells.0[y].iter().any(Option::is_some))
+        .count()
+}
+
+#[test]
+fn restarting_clears_the_board_and_score() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let square = harness::named_shape(&app, "Square", TileColor::Blue);
+    harness::select_shape(&mut app, square);
+    app.update();
+    harness::click_cell(&mut app, (10, 10));
+    let board = harness::main_board(&mut app);
+    assert!(app.world.get::<Score>(board).unwrap().0 > 0);
+
+    harness::press_keys(&mut app, &CTRL_R);
+
+    assert_eq!(app.world.get::<Score>(board).unwrap().0, 0);
+    assert_eq!(app.world.get::<Level>(board).unwrap().lines, 0);
+    assert_eq!(filled_rows(&mut app), 0);
+    assert!(*app.world.resource::<GameMode>() == GameMode::Endless);
+}
+
+#[test]
+fn restarting_a_dig_digs_again() {
+    let mut app = harness::headless_app();
+    *app.world.resource_mut::<GameMode>() = GameMode::Dig;
+    harness::start_game(&mut app);
+    let rows = app
+        .world
+        .resource::<RuleSet>()
+        .dig_rows
+        .min(BOARD_HEIGHT - 1);
+    assert_eq!(filled_rows(&mut app), rows);
+    harness::fill(
+        &mut app,
+        (0..BOARD_WIDTH / 2).map(|x| (x, 15)),
+        Some(TileColor::Green),
+    );
+
+    harness::press_keys(&mut app, &CTRL_R);
+
+    assert!(*app.world.resource::<GameMode>() == GameMode::Dig);
+    assert_eq!(filled_rows(&mut app), rows);
+}
+
+#[test]
+fn restarting_a_daily_draws_the_same_shapes_again() {
+    let mut app = harness::headless_app();
+    *app.world.resource_mut::<GameMode>() = GameMode::Daily;
+    harness::start_game(&mut app);
+    let first = first_draws(&mut app);
+
+    // Twice, for the drought and bag state of a restarted game to be reset as well
+    for _ in 0..2 {
+        harness::press_keys(&mut app, &CTRL_R);
+        assert!(*app.world.resource::<GameMode>() == GameMode::Daily);
+        assert!(first_draws(&mut app) == first);
+    }
+}
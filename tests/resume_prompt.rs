@@ -0,0 +1,73 @@
+//! The resume prompt only shows for an autosave that can be resumed.
+#![cfg(feature = "test-harness")]
+
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bevy::prelude::*;
+use blocks::{
+    board::{BOARD_HEIGHT, BOARD_WIDTH},
+    harness, DataDir,
+};
+
+/// Texts on screen, the prompt's among them if it was spawned.
+fn texts(app: &mut App) -> Vec<String> {
+    app.world
+        .query::<&Text>()
+        .iter(&app.world)
+        .map(|text| text.sections[0].value.clone())
+        .collect()
+}
+
+/// Writes the autosave the app reads at startup.
+fn write_autosave(app: &App, contents: &str) {
+    let path = app.world.resource::<DataDir>().path("autosave.txt");
+    std::fs::write(path, contents).unwrap();
+}
+
+fn prompted(app: &mut App) -> bool {
+    texts(app).iter().any(|text| text.starts_with("Resume"))
+}
+
+#[test]
+fn only_an_autosave_that_parses_is_offered() {
+    let empty_row = ".".repeat(BOARD_WIDTH);
+    let rows = vec![empty_row; BOARD_HEIGHT].join("\n");
+    let valid = format!("version 2\nshape 0 0 B\n{rows}\ntime 12.5\nscore 40\n");
+
+    let mut app = harness::headless_app();
+    write_autosave(&app, "version 2\nshape what\n");
+    app.update();
+    assert!(!prompted(&mut app));
+
+    let mut app = harness::headless_app();
+    write_autosave(&app, &valid);
+    app.update();
+    assert!(prompted(&mut app));
+}
+
+#[test]
+fn the_thumbnail_kept_in_the_autosave_shows_next_to_the_prompt() {
+    let mut png = Vec::new();
+    image::RgbaImage::from_pixel(3, 2, image::Rgba([200, 40, 40, 255]))
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .unwrap();
+    let empty_row = ".".repeat(BOARD_WIDTH);
+    let rows = vec![empty_row; BOARD_HEIGHT].join("\n");
+    let thumbnail = STANDARD.encode(png);
+    let contents =
+        format!("version 2\nshape 0 0 B\n{rows}\ntime 12.5\nscore 40\nthumbnail {thumbnail}\n");
+
+    let mut app = harness::headless_app();
+    write_autosave(&app, &contents);
+    app.update();
+    assert!(prompted(&mut app));
+    let sizes: Vec<_> = app
+        .world
+        .query::<&UiImage>()
+        .iter(&app.world)
+        .filter_map(|image| app.world.resource::<Assets<Image>>().get(&image.texture))
+        .map(Image::size)
+        .collect();
+    assert!(sizes.contains(&UVec2::new(3, 2)));
+}
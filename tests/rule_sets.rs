@@ -0,0 +1,64 @@
+//! Rule sets bring their board size and clearing regions along: blockudoku plays on a
+//! 9x9 board clearing its 3x3 squares, and rules without a size go back to the menu's.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_HEIGHT, BOARD_WIDTH},
+    harness,
+    prelude::*,
+};
+
+const CTRL_R: [KeyCode; 2] = [KeyCode::ControlLeft, KeyCode::R];
+
+fn size(app: &mut App) -> (usize, usize) {
+    let board = harness::main_board(app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    (grid.width(), grid.height())
+}
+
+#[test]
+fn blockudoku_clears_full_squares() {
+    let mut app = harness::headless_app();
+    app.insert_resource(RuleSet::blockudoku());
+    harness::start_game(&mut app);
+    assert_eq!(size(&mut app), (9, 9));
+
+    let square = (3..6).flat_map(|y| (3..6).map(move |x| (x, y)));
+    harness::fill(
+        &mut app,
+        square.clone().filter(|&cell| cell != (4, 4)),
+        Some(TileColor::Green),
+    );
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::select_shape(&mut app, dot);
+    app.update();
+    harness::click_cell(&mut app, (4, 4));
+
+    let board = harness::main_board(&mut app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    assert!(square
+        .into_iter()
+        .all(|(x, y)| grid.cells.0[y][x].is_none()));
+    assert!(app.world.get::<Score>(board).unwrap().0 > 0);
+}
+
+#[test]
+fn rules_without_a_size_go_back_to_the_menus() {
+    let mut app = harness::headless_app();
+    app.insert_resource(RuleSet::blockudoku());
+    harness::start_game(&mut app);
+    assert_eq!(size(&mut app), (9, 9));
+
+    app.insert_resource(RuleSet::classic());
+    app.update();
+    // The game keeps its board until the restart
+    assert_eq!(size(&mut app), (9, 9));
+    harness::press_keys(&mut app, &CTRL_R);
+    assert_eq!(size(&mut app), (BOARD_WIDTH, BOARD_HEIGHT));
+
+    // Nor do its squares clear anymore
+    let board = harness::main_board(&mut app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    assert_eq!(grid.region_size(), 0);
+}
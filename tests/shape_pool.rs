@@ -0,0 +1,59 @@
+//! Swapping the shape pool mid-game: the selected shape plays out and the next one
+//! comes from the new pool.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{board::TileColor, harness, prelude::*, shape::ShapeRegistry, shapes};
+
+fn texts(app: &mut App) -> Vec<String> {
+    app.world
+        .query::<&Text>()
+        .iter(&app.world)
+        .map(|text| text.sections[0].value.clone())
+        .collect()
+}
+
+#[test]
+fn the_selected_shape_plays_out_and_the_next_comes_from_the_new_pool() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::select_shape(&mut app, dot);
+    let shapes_before = app.world.resource::<ShapeRegistry>().iter().count();
+
+    app.world
+        .send_event(ShapePoolChanged(shapes![(3, 1) "###" as "Bar"]));
+    app.update();
+
+    assert!(harness::selected_shape(&mut app) == dot);
+    assert!(texts(&mut app)
+        .iter()
+        .any(|text| text == "Shape set updated"));
+    let bar = harness::named_shape(&app, "Bar", TileColor::Red);
+    assert_eq!(bar.id.0 as usize, shapes_before);
+
+    for placed in 0..3 {
+        harness::click_cell(&mut app, (2 + 4 * placed, 10));
+        let selected = harness::selected_shape(&mut app);
+        assert_eq!(selected.id, bar.id);
+    }
+    let board = harness::main_board(&mut app);
+    let grid = app.world.get::<Board>(board).unwrap().grid();
+    assert_eq!(grid.cells.0[10][2], Some(TileColor::Red));
+    // The dot and two bars
+    assert_eq!(grid.cells.0.iter().flatten().flatten().count(), 7);
+}
+
+#[test]
+fn a_pool_without_shapes_is_ignored() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    let shapes_before = app.world.resource::<ShapeRegistry>().iter().count();
+
+    app.world.send_event(ShapePoolChanged(shapes![(2, 1) ".."]));
+    app.update();
+
+    let registry = app.world.resource::<ShapeRegistry>();
+    assert_eq!(registry.iter().count(), shapes_before);
+    assert!(registry.in_pool(blocks::shape::ShapeId(0)));
+}
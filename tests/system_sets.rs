@@ -0,0 +1,79 @@
+//! Systems of other plugins ordered against [`BlocksSystems`] see the frame's changes
+//! at the stages the sets promise.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{
+    board::{TileColor, BOARD_WIDTH},
+    harness::{self, SimulatedClick, SimulatedCursor},
+    prelude::*,
+};
+
+/// Filled cells of the main board's row 3, as seen by each stage of the last frame.
+#[derive(Resource, Default)]
+struct Observed {
+    after_placement: Option<usize>,
+    after_resolution: Option<usize>,
+    /// Whether the selected shape was replaced by the presentation stage.
+    new_shape_presented: bool,
+}
+
+fn row(q_board: &Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>) -> usize {
+    q_board.single().grid().cells.0[3].iter().flatten().count()
+}
+
+fn observe_placement(
+    mut observed: ResMut<Observed>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    observed.after_placement = Some(row(&q_board));
+}
+
+fn observe_resolution(
+    mut observed: ResMut<Observed>,
+    q_board: Query<&Board, (With<MainBoard>, Without<OverlayBoard>)>,
+) {
+    observed.after_resolution = Some(row(&q_board));
+}
+
+fn observe_presentation(
+    mut observed: ResMut<Observed>,
+    q_selected_shape: Query<(), Added<SelectedShape>>,
+) {
+    observed.new_shape_presented = !q_selected_shape.is_empty();
+}
+
+#[test]
+fn stages_see_the_placement_then_its_clears() {
+    let mut app = harness::headless_app();
+    app.init_resource::<Observed>().add_systems(
+        Update,
+        (
+            observe_placement
+                .after(BlocksSystems::Placement)
+                .before(BlocksSystems::Resolution),
+            observe_resolution
+                .after(BlocksSystems::Resolution)
+                .before(BlocksSystems::Presentation),
+            observe_presentation.in_set(BlocksSystems::Presentation),
+        ),
+    );
+    harness::start_game(&mut app);
+    harness::fill(
+        &mut app,
+        (0..BOARD_WIDTH - 1).map(|x| (x, 3)),
+        Some(TileColor::Green),
+    );
+    let dot = harness::named_shape(&app, "Dot", TileColor::Red);
+    harness::select_shape(&mut app, dot);
+    app.insert_resource(SimulatedCursor::Cell((BOARD_WIDTH - 1, 3)));
+    app.update();
+
+    app.world.send_event(SimulatedClick);
+    app.update();
+
+    let observed = app.world.resource::<Observed>();
+    assert_eq!(observed.after_placement, Some(BOARD_WIDTH));
+    assert_eq!(observed.after_resolution, Some(0));
+    assert!(observed.new_shape_presented);
+}
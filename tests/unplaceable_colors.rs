@@ -0,0 +1,36 @@
+//! Board commands can't write colors cells can't hold, which would show as empty
+//! cells blocking placements.
+#![cfg(feature = "test-harness")]
+
+use bevy::prelude::*;
+use blocks::{board::TileColor, harness, prelude::*};
+
+fn cell(app: &mut App, cell: (usize, usize)) -> Option<TileColor> {
+    let board = harness::main_board(app);
+    app.world.get::<Board>(board).unwrap().grid().cells.0[cell.1][cell.0]
+}
+
+#[test]
+fn transparent_fills_are_ignored() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    harness::fill(&mut app, [(4, 4)], Some(TileColor::Red));
+    harness::fill(&mut app, [(4, 4), (8, 8)], Some(TileColor::Transparent));
+
+    assert_eq!(cell(&mut app, (4, 4)), Some(TileColor::Red));
+    assert_eq!(cell(&mut app, (8, 8)), None);
+
+    // Nothing invisible blocks the cell the fill was meant for
+    let dot = harness::named_shape(&app, "Dot", TileColor::Blue);
+    harness::select_shape(&mut app, dot);
+    harness::click_cell(&mut app, (8, 8));
+    assert_eq!(cell(&mut app, (8, 8)), Some(TileColor::Blue));
+}
+
+#[test]
+fn gray_fills_are_kept() {
+    let mut app = harness::headless_app();
+    harness::start_game(&mut app);
+    harness::fill(&mut app, [(4, 4)], Some(TileColor::Gray));
+    assert_eq!(cell(&mut app, (4, 4)), Some(TileColor::Gray));
+}